@@ -0,0 +1,406 @@
+//! Account decoders and helpers for building instruction accounts.
+
+use anchor_lang::prelude::*;
+use cpamm::{
+    ConcentratedPool, DeniedMint, Factory, Observations, PoolCreator, Position, SwapInfo, SwapMeta,
+    Tick,
+};
+
+/// Decodes an Anchor account, checking its discriminator.
+pub fn decode<T: AccountDeserialize>(data: &[u8]) -> Result<T> {
+    T::try_deserialize(&mut &data[..])
+}
+
+/// Decodes a [Factory].
+pub fn decode_factory(data: &[u8]) -> Result<Factory> {
+    decode(data)
+}
+
+/// Decodes a [SwapInfo].
+pub fn decode_swap_info(data: &[u8]) -> Result<SwapInfo> {
+    decode(data)
+}
+
+/// Decodes a [SwapMeta].
+pub fn decode_swap_meta(data: &[u8]) -> Result<SwapMeta> {
+    decode(data)
+}
+
+/// Decodes an [Observations].
+pub fn decode_observations(data: &[u8]) -> Result<Observations> {
+    decode(data)
+}
+
+/// Decodes a [PoolCreator].
+pub fn decode_pool_creator(data: &[u8]) -> Result<PoolCreator> {
+    decode(data)
+}
+
+/// Decodes a [DeniedMint].
+pub fn decode_denied_mint(data: &[u8]) -> Result<DeniedMint> {
+    decode(data)
+}
+
+/// Decodes a [ConcentratedPool].
+pub fn decode_concentrated_pool(data: &[u8]) -> Result<ConcentratedPool> {
+    decode(data)
+}
+
+/// Decodes a [Tick].
+pub fn decode_tick(data: &[u8]) -> Result<Tick> {
+    decode(data)
+}
+
+/// Decodes a [Position].
+pub fn decode_position(data: &[u8]) -> Result<Position> {
+    decode(data)
+}
+
+/// Builds the [cpamm::accounts::SwapUserContext] of a user of a swap.
+pub fn user_context(swap: Pubkey, user_authority: Pubkey) -> cpamm::accounts::SwapUserContext {
+    cpamm::accounts::SwapUserContext {
+        token_program: anchor_spl::token::ID,
+        swap,
+        user_authority,
+    }
+}
+
+/// Builds the accounts of a [cpamm::cpamm::swap] of `input_mint` for the other token of the swap.
+///
+/// `token_programs` are the token programs of token 0 and token 1 of the swap.
+pub fn swap_accounts(
+    swap: Pubkey,
+    swap_info: &SwapInfo,
+    token_programs: [Pubkey; 2],
+    user_authority: Pubkey,
+    input_mint: Pubkey,
+    user_input: Pubkey,
+    user_output: Pubkey,
+) -> cpamm::accounts::Swap {
+    let [token_program_0, token_program_1] = token_programs;
+    let ((input, input_token_program), (output, output_token_program)) =
+        if input_mint == swap_info.token_0.mint {
+            (
+                (&swap_info.token_0, token_program_0),
+                (&swap_info.token_1, token_program_1),
+            )
+        } else {
+            (
+                (&swap_info.token_1, token_program_1),
+                (&swap_info.token_0, token_program_0),
+            )
+        };
+    cpamm::accounts::Swap {
+        user: user_context(swap, user_authority),
+        input: cpamm::accounts::SwapTokenWithFees {
+            user: user_input,
+            reserve: input.reserves,
+            fees: input.admin_fees,
+            mint: input.mint,
+            token_program: input_token_program,
+        },
+        output: cpamm::accounts::SwapTokenWithFees {
+            user: user_output,
+            reserve: output.reserves,
+            fees: output.admin_fees,
+            mint: output.mint,
+            token_program: output_token_program,
+        },
+    }
+}
+
+/// Builds the accounts of a [cpamm::cpamm::deposit].
+///
+/// `token_programs` are the token programs of token 0 and token 1 of the swap.
+pub fn deposit_accounts(
+    swap: Pubkey,
+    swap_info: &SwapInfo,
+    token_programs: [Pubkey; 2],
+    user_authority: Pubkey,
+    user_0: Pubkey,
+    user_1: Pubkey,
+    output_lp: Pubkey,
+) -> cpamm::accounts::Deposit {
+    cpamm::accounts::Deposit {
+        user: user_context(swap, user_authority),
+        input_0: cpamm::accounts::SwapToken {
+            user: user_0,
+            reserve: swap_info.token_0.reserves,
+            mint: swap_info.token_0.mint,
+            token_program: token_programs[0],
+        },
+        input_1: cpamm::accounts::SwapToken {
+            user: user_1,
+            reserve: swap_info.token_1.reserves,
+            mint: swap_info.token_1.mint,
+            token_program: token_programs[1],
+        },
+        pool_mint: swap_info.pool_mint,
+        output_lp,
+    }
+}
+
+/// Builds the accounts of a [cpamm::cpamm::withdraw].
+///
+/// `token_programs` are the token programs of token 0 and token 1 of the swap.
+pub fn withdraw_accounts(
+    swap: Pubkey,
+    swap_info: &SwapInfo,
+    token_programs: [Pubkey; 2],
+    user_authority: Pubkey,
+    input_lp: Pubkey,
+    user_0: Pubkey,
+    user_1: Pubkey,
+) -> cpamm::accounts::Withdraw {
+    cpamm::accounts::Withdraw {
+        user: user_context(swap, user_authority),
+        pool_mint: swap_info.pool_mint,
+        input_lp,
+        output_0: cpamm::accounts::SwapTokenWithFees {
+            user: user_0,
+            reserve: swap_info.token_0.reserves,
+            fees: swap_info.token_0.admin_fees,
+            mint: swap_info.token_0.mint,
+            token_program: token_programs[0],
+        },
+        output_1: cpamm::accounts::SwapTokenWithFees {
+            user: user_1,
+            reserve: swap_info.token_1.reserves,
+            fees: swap_info.token_1.admin_fees,
+            mint: swap_info.token_1.mint,
+            token_program: token_programs[1],
+        },
+    }
+}
+
+/// Builds the accounts of a [cpamm::cpamm::skim].
+///
+/// `token_programs` are the token programs of token 0 and token 1 of the swap.
+pub fn skim_accounts(
+    swap: Pubkey,
+    swap_info: &SwapInfo,
+    token_programs: [Pubkey; 2],
+) -> cpamm::accounts::Skim {
+    cpamm::accounts::Skim {
+        swap,
+        token_0: cpamm::accounts::SkimToken {
+            reserve: swap_info.token_0.reserves,
+            fees: swap_info.token_0.admin_fees,
+            mint: swap_info.token_0.mint,
+            token_program: token_programs[0],
+        },
+        token_1: cpamm::accounts::SkimToken {
+            reserve: swap_info.token_1.reserves,
+            fees: swap_info.token_1.admin_fees,
+            mint: swap_info.token_1.mint,
+            token_program: token_programs[1],
+        },
+    }
+}
+
+/// Builds the accounts of a [cpamm::cpamm::sync].
+pub fn sync_accounts(swap: Pubkey, swap_info: &SwapInfo) -> cpamm::accounts::SyncReserves {
+    cpamm::accounts::SyncReserves {
+        swap,
+        reserve_0: swap_info.token_0.reserves,
+        reserve_1: swap_info.token_1.reserves,
+    }
+}
+
+/// Builds the accounts of a [cpamm::cpamm::claim_admin_fees].
+///
+/// `token_programs` are the token programs of token 0 and token 1 of the swap,
+/// and `destinations` are the token accounts receiving the fees of each token.
+pub fn claim_admin_fees_accounts(
+    swap: Pubkey,
+    swap_info: &SwapInfo,
+    token_programs: [Pubkey; 2],
+    admin: Pubkey,
+    destinations: [Pubkey; 2],
+) -> cpamm::accounts::ClaimAdminFees {
+    cpamm::accounts::ClaimAdminFees {
+        swap_admin: cpamm::accounts::SwapAdmin { admin, swap },
+        token_0: cpamm::accounts::AdminFeesToken {
+            fees: swap_info.token_0.admin_fees,
+            destination: destinations[0],
+            mint: swap_info.token_0.mint,
+            token_program: token_programs[0],
+        },
+        token_1: cpamm::accounts::AdminFeesToken {
+            fees: swap_info.token_1.admin_fees,
+            destination: destinations[1],
+            mint: swap_info.token_1.mint,
+            token_program: token_programs[1],
+        },
+    }
+}
+
+/// Builds the accounts of a [cpamm::cpamm::close_swap].
+///
+/// `token_programs` are the token programs of token 0 and token 1 of the swap,
+/// and `destinations` are the token accounts receiving the remaining balances of each token.
+pub fn close_swap_accounts(
+    swap: Pubkey,
+    swap_info: &SwapInfo,
+    token_programs: [Pubkey; 2],
+    admin: Pubkey,
+    destinations: [Pubkey; 2],
+    rent_destination: Pubkey,
+) -> cpamm::accounts::CloseSwap {
+    cpamm::accounts::CloseSwap {
+        admin,
+        swap,
+        swap_meta: crate::pda::find_swap_meta_address(&swap_info.factory, swap_info.index).0,
+        observations: crate::pda::find_observations_address(&swap).0,
+        pool_mint: swap_info.pool_mint,
+        token_0: cpamm::accounts::CloseSwapToken {
+            reserve: swap_info.token_0.reserves,
+            fees: swap_info.token_0.admin_fees,
+            destination: destinations[0],
+            mint: swap_info.token_0.mint,
+            token_program: token_programs[0],
+        },
+        token_1: cpamm::accounts::CloseSwapToken {
+            reserve: swap_info.token_1.reserves,
+            fees: swap_info.token_1.admin_fees,
+            destination: destinations[1],
+            mint: swap_info.token_1.mint,
+            token_program: token_programs[1],
+        },
+        rent_destination,
+    }
+}
+
+/// Builds the accounts of a [cpamm::cpamm::set_admin_fees_account] of `mint`.
+pub fn set_admin_fees_account_accounts(
+    swap: Pubkey,
+    swap_info: &SwapInfo,
+    token_program: Pubkey,
+    admin: Pubkey,
+    mint: Pubkey,
+    new_fees: Pubkey,
+) -> cpamm::accounts::SetAdminFeesAccount {
+    let previous_fees = if mint == swap_info.token_0.mint {
+        swap_info.token_0.admin_fees
+    } else {
+        swap_info.token_1.admin_fees
+    };
+    cpamm::accounts::SetAdminFeesAccount {
+        swap_admin: cpamm::accounts::SwapAdmin { admin, swap },
+        previous_fees,
+        new_fees,
+        mint,
+        token_program,
+    }
+}
+
+/// Builds the accounts of a [cpamm::cpamm::increase_liquidity] of a [Position], whose new
+/// [cpamm::Tick]s are paid for by the `payer`.
+///
+/// `token_programs` are the token programs of token 0 and token 1 of the pool,
+/// and `user_tokens` are the owner's token accounts of each token.
+pub fn increase_liquidity_accounts(
+    pool_info: &ConcentratedPool,
+    position: Pubkey,
+    position_info: &Position,
+    token_programs: [Pubkey; 2],
+    user_tokens: [Pubkey; 2],
+    payer: Pubkey,
+) -> cpamm::accounts::IncreaseLiquidity {
+    let accounts = modify_liquidity_accounts(
+        pool_info,
+        position,
+        position_info,
+        token_programs,
+        user_tokens,
+    );
+    cpamm::accounts::IncreaseLiquidity {
+        pool: accounts.pool,
+        owner: accounts.owner,
+        position,
+        tick_lower: accounts.tick_lower,
+        tick_upper: accounts.tick_upper,
+        token_0: accounts.token_0,
+        token_1: accounts.token_1,
+        payer,
+        system_program: anchor_lang::solana_program::system_program::ID,
+    }
+}
+
+/// Builds the accounts of a [cpamm::cpamm::decrease_liquidity] or [cpamm::cpamm::collect_fees]
+/// of a [Position].
+///
+/// `token_programs` are the token programs of token 0 and token 1 of the pool,
+/// and `user_tokens` are the owner's token accounts of each token.
+pub fn modify_liquidity_accounts(
+    pool_info: &ConcentratedPool,
+    position: Pubkey,
+    position_info: &Position,
+    token_programs: [Pubkey; 2],
+    user_tokens: [Pubkey; 2],
+) -> cpamm::accounts::ModifyLiquidity {
+    let pool = position_info.pool;
+    cpamm::accounts::ModifyLiquidity {
+        pool,
+        owner: position_info.owner,
+        position,
+        tick_lower: crate::pda::find_tick_address(&pool, position_info.tick_lower).0,
+        tick_upper: crate::pda::find_tick_address(&pool, position_info.tick_upper).0,
+        token_0: cpamm::accounts::SwapToken {
+            user: user_tokens[0],
+            reserve: pool_info.token_0.reserves,
+            mint: pool_info.token_0.mint,
+            token_program: token_programs[0],
+        },
+        token_1: cpamm::accounts::SwapToken {
+            user: user_tokens[1],
+            reserve: pool_info.token_1.reserves,
+            mint: pool_info.token_1.mint,
+            token_program: token_programs[1],
+        },
+    }
+}
+
+/// Builds the accounts of a [cpamm::cpamm::swap_concentrated] of `input_mint` for the other
+/// token of the pool.
+///
+/// `token_programs` are the token programs of token 0 and token 1 of the pool.
+pub fn swap_concentrated_accounts(
+    pool: Pubkey,
+    pool_info: &ConcentratedPool,
+    token_programs: [Pubkey; 2],
+    user_authority: Pubkey,
+    input_mint: Pubkey,
+    user_input: Pubkey,
+    user_output: Pubkey,
+) -> cpamm::accounts::SwapConcentrated {
+    let [token_program_0, token_program_1] = token_programs;
+    let ((input, input_token_program), (output, output_token_program)) =
+        if input_mint == pool_info.token_0.mint {
+            (
+                (&pool_info.token_0, token_program_0),
+                (&pool_info.token_1, token_program_1),
+            )
+        } else {
+            (
+                (&pool_info.token_1, token_program_1),
+                (&pool_info.token_0, token_program_0),
+            )
+        };
+    cpamm::accounts::SwapConcentrated {
+        pool,
+        user_authority,
+        input: cpamm::accounts::SwapToken {
+            user: user_input,
+            reserve: input.reserves,
+            mint: input.mint,
+            token_program: input_token_program,
+        },
+        output: cpamm::accounts::SwapToken {
+            user: user_output,
+            reserve: output.reserves,
+            mint: output.mint,
+            token_program: output_token_program,
+        },
+    }
+}
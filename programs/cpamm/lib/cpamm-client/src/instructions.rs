@@ -0,0 +1,611 @@
+//! Instruction builders for every [cpamm::cpamm] instruction.
+//!
+//! Each builder takes the Anchor-generated accounts of the instruction along with
+//! its arguments. Optional remaining accounts, such as an [cpamm::Observations]
+//! account or a referrer, may be appended to [Instruction::accounts].
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::InstructionData;
+use cpamm::{accounts, instruction, FactoryPolicy, SwapFees};
+
+fn build(accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: cpamm::ID,
+        accounts: accounts.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+/// Builds a [cpamm::cpamm::new_factory] instruction.
+pub fn new_factory(accounts: accounts::NewFactory, bump: u8) -> Instruction {
+    build(accounts, instruction::NewFactory { _bump: bump })
+}
+
+/// Builds a [cpamm::cpamm::new_swap] instruction.
+pub fn new_swap(accounts: accounts::NewSwap, bump: u8, fee_tier_kbps: u32) -> Instruction {
+    build(
+        accounts,
+        instruction::NewSwap {
+            _bump: bump,
+            fee_tier_kbps,
+        },
+    )
+}
+
+/// Builds a [cpamm::cpamm::new_stable_swap] instruction.
+pub fn new_stable_swap(
+    accounts: accounts::NewSwap,
+    bump: u8,
+    fee_tier_kbps: u32,
+    amp_factor: u64,
+) -> Instruction {
+    build(
+        accounts,
+        instruction::NewStableSwap {
+            _bump: bump,
+            fee_tier_kbps,
+            amp_factor,
+        },
+    )
+}
+
+/// Builds a [cpamm::cpamm::new_weighted_swap] instruction.
+pub fn new_weighted_swap(
+    accounts: accounts::NewSwap,
+    bump: u8,
+    fee_tier_kbps: u32,
+    weight_0: u64,
+) -> Instruction {
+    build(
+        accounts,
+        instruction::NewWeightedSwap {
+            _bump: bump,
+            fee_tier_kbps,
+            weight_0,
+        },
+    )
+}
+
+/// Builds a [cpamm::cpamm::new_swap_meta] instruction.
+pub fn new_swap_meta(accounts: accounts::NewSwapMeta, bump: u8) -> Instruction {
+    build(accounts, instruction::NewSwapMeta { _bump: bump })
+}
+
+/// Builds a [cpamm::cpamm::swap] instruction.
+pub fn swap(accounts: accounts::Swap, amount_in: u64, minimum_amount_out: u64) -> Instruction {
+    build(
+        accounts,
+        instruction::Swap {
+            amount_in,
+            minimum_amount_out,
+        },
+    )
+}
+
+/// Builds a [cpamm::cpamm::swap_max] instruction.
+pub fn swap_max(accounts: accounts::Swap, minimum_amount_out: u64) -> Instruction {
+    build(accounts, instruction::SwapMax { minimum_amount_out })
+}
+
+/// Builds a [cpamm::cpamm::swap_exact_out] instruction.
+pub fn swap_exact_out(
+    accounts: accounts::Swap,
+    amount_out: u64,
+    maximum_amount_in: u64,
+) -> Instruction {
+    build(
+        accounts,
+        instruction::SwapExactOut {
+            amount_out,
+            maximum_amount_in,
+        },
+    )
+}
+
+/// Builds a [cpamm::cpamm::swap_with_deadline] instruction.
+pub fn swap_with_deadline(
+    accounts: accounts::Swap,
+    amount_in: u64,
+    minimum_amount_out: u64,
+    deadline: i64,
+) -> Instruction {
+    build(
+        accounts,
+        instruction::SwapWithDeadline {
+            amount_in,
+            minimum_amount_out,
+            deadline,
+        },
+    )
+}
+
+/// A hop of a [cpamm::cpamm::swap_route].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RouteHop {
+    /// The [cpamm::SwapInfo] of the hop.
+    pub swap: Pubkey,
+    /// The pool's reserves of the input token of the hop.
+    pub input_reserve: Pubkey,
+    /// The pool's reserves of the output token of the hop.
+    pub output_reserve: Pubkey,
+    /// The admin fees account of the output token of the hop.
+    pub output_fees: Pubkey,
+    /// The mint of the output token of the hop.
+    pub output_mint: Pubkey,
+    /// The token program of the output mint.
+    pub output_token_program: Pubkey,
+}
+
+/// Builds a [cpamm::cpamm::swap_route] instruction through the given hops.
+pub fn swap_route(
+    accounts: accounts::SwapRoute,
+    hops: &[RouteHop],
+    amount_in: u64,
+    minimum_amount_out: u64,
+) -> Instruction {
+    let mut ix = build(
+        accounts,
+        instruction::SwapRoute {
+            amount_in,
+            minimum_amount_out,
+        },
+    );
+    for hop in hops {
+        ix.accounts.extend([
+            AccountMeta::new(hop.swap, false),
+            AccountMeta::new(hop.input_reserve, false),
+            AccountMeta::new(hop.output_reserve, false),
+            AccountMeta::new(hop.output_fees, false),
+            AccountMeta::new_readonly(hop.output_mint, false),
+            AccountMeta::new_readonly(hop.output_token_program, false),
+        ]);
+    }
+    ix
+}
+
+/// Builds a [cpamm::cpamm::withdraw] instruction.
+pub fn withdraw(
+    accounts: accounts::Withdraw,
+    amount_in: u64,
+    minimum_amount_out_0: u64,
+    minimum_amount_out_1: u64,
+) -> Instruction {
+    build(
+        accounts,
+        instruction::Withdraw {
+            amount_in,
+            minimum_amount_out_0,
+            minimum_amount_out_1,
+        },
+    )
+}
+
+/// Builds a [cpamm::cpamm::withdraw_with_deadline] instruction.
+pub fn withdraw_with_deadline(
+    accounts: accounts::Withdraw,
+    amount_in: u64,
+    minimum_amount_out_0: u64,
+    minimum_amount_out_1: u64,
+    deadline: i64,
+) -> Instruction {
+    build(
+        accounts,
+        instruction::WithdrawWithDeadline {
+            amount_in,
+            minimum_amount_out_0,
+            minimum_amount_out_1,
+            deadline,
+        },
+    )
+}
+
+/// Builds a [cpamm::cpamm::withdraw_one] instruction.
+pub fn withdraw_one(
+    accounts: accounts::WithdrawOne,
+    pool_token_amount: u64,
+    minimum_amount_out: u64,
+) -> Instruction {
+    build(
+        accounts,
+        instruction::WithdrawOne {
+            pool_token_amount,
+            minimum_amount_out,
+        },
+    )
+}
+
+/// Builds a [cpamm::cpamm::deposit] instruction.
+pub fn deposit(
+    accounts: accounts::Deposit,
+    pool_token_amount: u64,
+    maximum_amount_in_0: u64,
+    maximum_amount_in_1: u64,
+) -> Instruction {
+    build(
+        accounts,
+        instruction::Deposit {
+            pool_token_amount,
+            maximum_amount_in_0,
+            maximum_amount_in_1,
+        },
+    )
+}
+
+/// Builds a [cpamm::cpamm::deposit_with_deadline] instruction.
+pub fn deposit_with_deadline(
+    accounts: accounts::Deposit,
+    pool_token_amount: u64,
+    maximum_amount_in_0: u64,
+    maximum_amount_in_1: u64,
+    deadline: i64,
+) -> Instruction {
+    build(
+        accounts,
+        instruction::DepositWithDeadline {
+            pool_token_amount,
+            maximum_amount_in_0,
+            maximum_amount_in_1,
+            deadline,
+        },
+    )
+}
+
+/// Builds a [cpamm::cpamm::deposit_single_token_exact_in] instruction.
+pub fn deposit_single_token_exact_in(
+    accounts: accounts::DepositSingle,
+    source_amount: u64,
+    minimum_pool_token_amount: u64,
+) -> Instruction {
+    build(
+        accounts,
+        instruction::DepositSingleTokenExactIn {
+            source_amount,
+            minimum_pool_token_amount,
+        },
+    )
+}
+
+/// Builds a [cpamm::cpamm::new_observations] instruction.
+pub fn new_observations(
+    accounts: accounts::NewObservations,
+    min_interval_seconds: u32,
+) -> Instruction {
+    build(
+        accounts,
+        instruction::NewObservations {
+            min_interval_seconds,
+        },
+    )
+}
+
+/// Builds a [cpamm::cpamm::record_observation] instruction.
+pub fn record_observation(accounts: accounts::RecordObservation) -> Instruction {
+    build(accounts, instruction::RecordObservation)
+}
+
+/// Builds a [cpamm::cpamm::consult] instruction.
+pub fn consult(accounts: accounts::Consult, window_seconds: u32) -> Instruction {
+    build(accounts, instruction::Consult { window_seconds })
+}
+
+/// Builds a [cpamm::cpamm::skim] instruction.
+pub fn skim(accounts: accounts::Skim) -> Instruction {
+    build(accounts, instruction::Skim)
+}
+
+/// Builds a [cpamm::cpamm::sync] instruction.
+pub fn sync(accounts: accounts::SyncReserves) -> Instruction {
+    build(accounts, instruction::Sync)
+}
+
+/// Builds a [cpamm::cpamm::flash_loan] instruction.
+pub fn flash_loan(accounts: accounts::FlashLoan, amount: u64) -> Instruction {
+    build(accounts, instruction::FlashLoan { amount })
+}
+
+/// Builds a [cpamm::cpamm::repay_flash_loan] instruction.
+pub fn repay_flash_loan(accounts: accounts::FlashLoan) -> Instruction {
+    build(accounts, instruction::RepayFlashLoan)
+}
+
+/// Builds a [cpamm::cpamm::set_flash_loan_fee] instruction.
+pub fn set_flash_loan_fee(accounts: accounts::SwapAdmin, fee_kbps: u64) -> Instruction {
+    build(accounts, instruction::SetFlashLoanFee { fee_kbps })
+}
+
+/// Builds a [cpamm::cpamm::set_fees] instruction.
+pub fn set_fees(accounts: accounts::SwapAdmin, fees: SwapFees) -> Instruction {
+    build(accounts, instruction::SetFees { fees })
+}
+
+/// Builds a [cpamm::cpamm::set_referral_fee] instruction.
+pub fn set_referral_fee(accounts: accounts::SwapAdmin, referral_fee_kbps: u32) -> Instruction {
+    build(accounts, instruction::SetReferralFee { referral_fee_kbps })
+}
+
+/// Builds a [cpamm::cpamm::set_dynamic_fee] instruction.
+pub fn set_dynamic_fee(
+    accounts: accounts::SwapAdmin,
+    max_trade_fee_kbps: u32,
+    volatility_factor_kbps: u32,
+) -> Instruction {
+    build(
+        accounts,
+        instruction::SetDynamicFee {
+            max_trade_fee_kbps,
+            volatility_factor_kbps,
+        },
+    )
+}
+
+/// Builds a [cpamm::cpamm::pause] instruction.
+pub fn pause(accounts: accounts::SwapAdmin) -> Instruction {
+    build(accounts, instruction::Pause)
+}
+
+/// Builds a [cpamm::cpamm::unpause] instruction.
+pub fn unpause(accounts: accounts::SwapAdmin) -> Instruction {
+    build(accounts, instruction::Unpause)
+}
+
+/// Builds a [cpamm::cpamm::begin_close_swap] instruction.
+pub fn begin_close_swap(accounts: accounts::SwapAdmin) -> Instruction {
+    build(accounts, instruction::BeginCloseSwap)
+}
+
+/// Builds a [cpamm::cpamm::close_swap] instruction.
+pub fn close_swap(accounts: accounts::CloseSwap) -> Instruction {
+    build(accounts, instruction::CloseSwap)
+}
+
+/// Builds a [cpamm::cpamm::claim_admin_fees] instruction.
+pub fn claim_admin_fees(accounts: accounts::ClaimAdminFees) -> Instruction {
+    build(accounts, instruction::ClaimAdminFees)
+}
+
+/// Builds a [cpamm::cpamm::set_admin_fees_account] instruction.
+pub fn set_admin_fees_account(accounts: accounts::SetAdminFeesAccount) -> Instruction {
+    build(accounts, instruction::SetAdminFeesAccount)
+}
+
+/// Builds a [cpamm::cpamm::commit_swap_admin] instruction.
+pub fn commit_swap_admin(accounts: accounts::SwapAdmin, new_admin: Pubkey) -> Instruction {
+    build(accounts, instruction::CommitSwapAdmin { new_admin })
+}
+
+/// Builds a [cpamm::cpamm::accept_swap_admin] instruction.
+pub fn accept_swap_admin(accounts: accounts::AcceptSwapAdmin) -> Instruction {
+    build(accounts, instruction::AcceptSwapAdmin)
+}
+
+/// Builds a [cpamm::cpamm::commit_factory_admin] instruction.
+pub fn commit_factory_admin(accounts: accounts::FactoryAdmin, new_admin: Pubkey) -> Instruction {
+    build(accounts, instruction::CommitFactoryAdmin { new_admin })
+}
+
+/// Builds a [cpamm::cpamm::set_default_fees] instruction.
+pub fn set_default_fees(accounts: accounts::FactoryAdmin, fees: SwapFees) -> Instruction {
+    build(accounts, instruction::SetDefaultFees { fees })
+}
+
+/// Builds a [cpamm::cpamm::set_fee_tiers] instruction.
+pub fn set_fee_tiers(accounts: accounts::FactoryAdmin, fee_tiers: Vec<u32>) -> Instruction {
+    build(accounts, instruction::SetFeeTiers { fee_tiers })
+}
+
+/// Builds a [cpamm::cpamm::set_factory_policy] instruction.
+pub fn set_factory_policy(accounts: accounts::FactoryAdmin, policy: FactoryPolicy) -> Instruction {
+    build(accounts, instruction::SetFactoryPolicy { policy })
+}
+
+/// Builds a [cpamm::cpamm::add_pool_creator] instruction.
+pub fn add_pool_creator(accounts: accounts::AddPoolCreator, bump: u8) -> Instruction {
+    build(accounts, instruction::AddPoolCreator { _bump: bump })
+}
+
+/// Builds a [cpamm::cpamm::remove_pool_creator] instruction.
+pub fn remove_pool_creator(accounts: accounts::RemovePoolCreator) -> Instruction {
+    build(accounts, instruction::RemovePoolCreator)
+}
+
+/// Builds a [cpamm::cpamm::deny_mint] instruction.
+pub fn deny_mint(accounts: accounts::DenyMint, bump: u8) -> Instruction {
+    build(accounts, instruction::DenyMint { _bump: bump })
+}
+
+/// Builds a [cpamm::cpamm::allow_mint] instruction.
+pub fn allow_mint(accounts: accounts::AllowMint) -> Instruction {
+    build(accounts, instruction::AllowMint)
+}
+
+/// Builds a [cpamm::cpamm::accept_factory_admin] instruction.
+pub fn accept_factory_admin(accounts: accounts::AcceptFactoryAdmin) -> Instruction {
+    build(accounts, instruction::AcceptFactoryAdmin)
+}
+
+/// Builds a [cpamm::cpamm::sync_swap_admin] instruction.
+pub fn sync_swap_admin(accounts: accounts::SyncSwapAdmin) -> Instruction {
+    build(accounts, instruction::SyncSwapAdmin)
+}
+
+/// Builds a [cpamm::cpamm::new_concentrated_pool] instruction.
+pub fn new_concentrated_pool(
+    accounts: accounts::NewConcentratedPool,
+    bump: u8,
+    tick_spacing: u16,
+    fee_tier_kbps: u32,
+    initial_sqrt_price_x64: u128,
+) -> Instruction {
+    build(
+        accounts,
+        instruction::NewConcentratedPool {
+            _bump: bump,
+            tick_spacing,
+            fee_tier_kbps,
+            initial_sqrt_price_x64,
+        },
+    )
+}
+
+/// Builds a [cpamm::cpamm::open_position] instruction.
+pub fn open_position(
+    accounts: accounts::OpenPosition,
+    bump: u8,
+    tick_lower: i32,
+    tick_upper: i32,
+) -> Instruction {
+    build(
+        accounts,
+        instruction::OpenPosition {
+            _bump: bump,
+            tick_lower,
+            tick_upper,
+        },
+    )
+}
+
+/// Builds a [cpamm::cpamm::increase_liquidity] instruction, given the linked [cpamm::Tick]s
+/// adjacent to the ticks of the position which are not linked yet.
+pub fn increase_liquidity(
+    accounts: accounts::IncreaseLiquidity,
+    adjacent_ticks: &[Pubkey],
+    liquidity: u128,
+    maximum_amount_in_0: u64,
+    maximum_amount_in_1: u64,
+) -> Instruction {
+    let mut ix = build(
+        accounts,
+        instruction::IncreaseLiquidity {
+            liquidity,
+            maximum_amount_in_0,
+            maximum_amount_in_1,
+        },
+    );
+    ix.accounts.extend(
+        adjacent_ticks
+            .iter()
+            .map(|tick| AccountMeta::new(*tick, false)),
+    );
+    ix
+}
+
+/// Builds a [cpamm::cpamm::decrease_liquidity] instruction, given the linked [cpamm::Tick]s
+/// adjacent to the ticks of the position which will no longer bound any liquidity.
+pub fn decrease_liquidity(
+    accounts: accounts::ModifyLiquidity,
+    adjacent_ticks: &[Pubkey],
+    liquidity: u128,
+    minimum_amount_out_0: u64,
+    minimum_amount_out_1: u64,
+) -> Instruction {
+    let mut ix = build(
+        accounts,
+        instruction::DecreaseLiquidity {
+            liquidity,
+            minimum_amount_out_0,
+            minimum_amount_out_1,
+        },
+    );
+    ix.accounts.extend(
+        adjacent_ticks
+            .iter()
+            .map(|tick| AccountMeta::new(*tick, false)),
+    );
+    ix
+}
+
+/// Builds a [cpamm::cpamm::collect_fees] instruction.
+pub fn collect_fees(accounts: accounts::ModifyLiquidity) -> Instruction {
+    build(accounts, instruction::CollectFees)
+}
+
+/// Builds a [cpamm::cpamm::close_position] instruction.
+pub fn close_position(accounts: accounts::ClosePosition) -> Instruction {
+    build(accounts, instruction::ClosePosition)
+}
+
+/// Builds a [cpamm::cpamm::swap_concentrated] instruction which may cross the given [cpamm::Tick]s,
+/// in order.
+pub fn swap_concentrated(
+    accounts: accounts::SwapConcentrated,
+    ticks: &[Pubkey],
+    amount_in: u64,
+    minimum_amount_out: u64,
+) -> Instruction {
+    let mut ix = build(
+        accounts,
+        instruction::SwapConcentrated {
+            amount_in,
+            minimum_amount_out,
+        },
+    );
+    ix.accounts
+        .extend(ticks.iter().map(|tick| AccountMeta::new(*tick, false)));
+    ix
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::solana_program::hash::hash;
+
+    #[test]
+    fn swap_data_has_sighash() {
+        let ix = swap(
+            accounts::Swap {
+                user: crate::accounts::user_context(Pubkey::new_unique(), Pubkey::new_unique()),
+                input: accounts::SwapTokenWithFees {
+                    user: Pubkey::new_unique(),
+                    reserve: Pubkey::new_unique(),
+                    fees: Pubkey::new_unique(),
+                    mint: Pubkey::new_unique(),
+                    token_program: anchor_spl::token::ID,
+                },
+                output: accounts::SwapTokenWithFees {
+                    user: Pubkey::new_unique(),
+                    reserve: Pubkey::new_unique(),
+                    fees: Pubkey::new_unique(),
+                    mint: Pubkey::new_unique(),
+                    token_program: anchor_spl::token::ID,
+                },
+            },
+            1,
+            2,
+        );
+        assert_eq!(ix.program_id, cpamm::ID);
+        assert_eq!(ix.accounts.len(), 13);
+        assert_eq!(ix.data[..8], hash(b"global:swap").to_bytes()[..8]);
+        assert_eq!(ix.data[8..16], 1_u64.to_le_bytes());
+        assert_eq!(ix.data[16..], 2_u64.to_le_bytes());
+    }
+
+    #[test]
+    fn swap_route_appends_hops() {
+        let hop = RouteHop {
+            swap: Pubkey::new_unique(),
+            input_reserve: Pubkey::new_unique(),
+            output_reserve: Pubkey::new_unique(),
+            output_fees: Pubkey::new_unique(),
+            output_mint: Pubkey::new_unique(),
+            output_token_program: anchor_spl::token::ID,
+        };
+        let ix = swap_route(
+            accounts::SwapRoute {
+                token_program: anchor_spl::token::ID,
+                user_authority: Pubkey::new_unique(),
+                input: Pubkey::new_unique(),
+                output: Pubkey::new_unique(),
+                input_mint: Pubkey::new_unique(),
+            },
+            &[hop, hop],
+            1,
+            1,
+        );
+        assert_eq!(ix.accounts.len(), 17);
+        assert!(ix.accounts[5..].chunks(6).all(|hop| {
+            hop[..4].iter().all(|meta| meta.is_writable)
+                && hop[4..].iter().all(|meta| !meta.is_writable)
+        }));
+    }
+}
@@ -0,0 +1,12 @@
+//! Rust client for the Sencha CP-AMM.
+#![deny(rustdoc::all)]
+#![allow(rustdoc::missing_doc_code_examples)]
+#![deny(missing_docs)]
+
+pub mod accounts;
+pub mod instructions;
+pub mod pda;
+pub mod quote;
+
+pub use cpamm;
+pub use cpamm::ID as PROGRAM_ID;
@@ -0,0 +1,115 @@
+//! Program-derived addresses of the CP-AMM.
+//!
+//! These mirror the seeds in the `cpamm` account structs.
+
+use anchor_lang::prelude::Pubkey;
+
+/// Finds the address of a [cpamm::Factory] created with the given base.
+pub fn find_factory_address(base: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"Factory", base.as_ref()], &cpamm::ID)
+}
+
+/// Finds the address of the [cpamm::SwapInfo] of a pair of sorted mints in a fee tier.
+///
+/// A `fee_tier_kbps` of zero finds the address of a swap created before fee tiers.
+pub fn find_swap_address(
+    factory: &Pubkey,
+    mint_0: &Pubkey,
+    mint_1: &Pubkey,
+    fee_tier_kbps: u32,
+) -> (Pubkey, u8) {
+    let fee_tier = fee_tier_kbps.to_le_bytes();
+    let fee_tier_seed: &[u8] = if fee_tier_kbps == 0 { &[] } else { &fee_tier };
+    Pubkey::find_program_address(
+        &[
+            b"SwapInfo",
+            factory.as_ref(),
+            mint_0.as_ref(),
+            mint_1.as_ref(),
+            fee_tier_seed,
+        ],
+        &cpamm::ID,
+    )
+}
+
+/// Finds the address of the [cpamm::SwapMeta] of the swap at `index` of a [cpamm::Factory].
+pub fn find_swap_meta_address(factory: &Pubkey, index: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"SwapMeta", factory.as_ref(), &index.to_le_bytes()],
+        &cpamm::ID,
+    )
+}
+
+/// Finds the address of the [cpamm::PoolCreator] of a key on a [cpamm::Factory].
+pub fn find_pool_creator_address(factory: &Pubkey, creator: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"PoolCreator", factory.as_ref(), creator.as_ref()],
+        &cpamm::ID,
+    )
+}
+
+/// Finds the address of the [cpamm::DeniedMint] of a mint on a [cpamm::Factory].
+/// A mint is denied if this account exists.
+pub fn find_denied_mint_address(factory: &Pubkey, mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"DeniedMint", factory.as_ref(), mint.as_ref()],
+        &cpamm::ID,
+    )
+}
+
+/// Finds the address of the [cpamm::Observations] of a [cpamm::SwapInfo].
+pub fn find_observations_address(swap: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"Observations", swap.as_ref()], &cpamm::ID)
+}
+
+/// Finds the address of the [cpamm::ConcentratedPool] of a pair of sorted mints and a tick spacing.
+pub fn find_concentrated_pool_address(
+    factory: &Pubkey,
+    mint_0: &Pubkey,
+    mint_1: &Pubkey,
+    tick_spacing: u16,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"ConcentratedPool",
+            factory.as_ref(),
+            mint_0.as_ref(),
+            mint_1.as_ref(),
+            &tick_spacing.to_le_bytes(),
+        ],
+        &cpamm::ID,
+    )
+}
+
+/// Finds the address of the [cpamm::Tick] at `index` of a [cpamm::ConcentratedPool].
+pub fn find_tick_address(pool: &Pubkey, index: i32) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"Tick", pool.as_ref(), &index.to_le_bytes()], &cpamm::ID)
+}
+
+/// Finds the address of the [cpamm::Position] of `owner` between two ticks of a [cpamm::ConcentratedPool].
+pub fn find_position_address(
+    pool: &Pubkey,
+    owner: &Pubkey,
+    tick_lower: i32,
+    tick_upper: i32,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"Position",
+            pool.as_ref(),
+            owner.as_ref(),
+            &tick_lower.to_le_bytes(),
+            &tick_upper.to_le_bytes(),
+        ],
+        &cpamm::ID,
+    )
+}
+
+/// Sorts two mints into the order required by [cpamm::SwapInfo].
+pub fn sort_mints(mint_a: Pubkey, mint_b: Pubkey) -> (Pubkey, Pubkey) {
+    if mint_a < mint_b {
+        (mint_a, mint_b)
+    } else {
+        (mint_b, mint_a)
+    }
+}
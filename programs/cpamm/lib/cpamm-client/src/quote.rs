@@ -0,0 +1,163 @@
+//! Quotes computed with the same math as the CP-AMM program, via [xyk]'s quotes.
+//!
+//! Quotes use the [SwapInfo::expected_reserves], which the program trades
+//! against instead of the balances of the reserve token accounts. Swaps
+//! created before their reserves were tracked cannot be quoted until their
+//! next instruction, e.g. a [cpamm::cpamm::sync].
+//!
+//! Swap quotes use the [SwapInfo::fees]. If dynamic fees are enabled, replace
+//! them with [SwapInfo::trade_fees_at] the reserves after the swap before quoting.
+
+use anchor_lang::prelude::Pubkey;
+use cpamm::{SwapInfo, SwapReserves};
+
+pub use xyk::SwapQuote;
+
+/// The [SwapInfo::expected_reserves], if they are tracked.
+fn expected_reserves(swap_info: &SwapInfo) -> Option<SwapReserves> {
+    let reserves = swap_info.expected_reserves;
+    if reserves.is_initialized() {
+        Some(reserves)
+    } else {
+        None
+    }
+}
+
+/// Quotes a [cpamm::cpamm::swap] of `amount_in` of `input_mint`.
+pub fn quote_swap(swap_info: &SwapInfo, input_mint: Pubkey, amount_in: u64) -> Option<SwapQuote> {
+    let is_input_token_0 = input_mint == swap_info.token_0.mint;
+    let reserves = expected_reserves(swap_info)?;
+    let curve = swap_info.curve.swap_calculator(is_input_token_0).ok()?;
+    xyk::quote_swap(
+        curve.as_ref(),
+        amount_in,
+        reserves.get(is_input_token_0),
+        reserves.get(!is_input_token_0),
+        &swap_info.fees.into(),
+    )
+}
+
+/// Quotes a [cpamm::cpamm::swap_exact_out] of `input_mint` for `amount_out`.
+///
+/// Only constant product swaps support exact output swaps.
+pub fn quote_swap_exact_out(
+    swap_info: &SwapInfo,
+    input_mint: Pubkey,
+    amount_out: u64,
+) -> Option<SwapQuote> {
+    if !swap_info.curve.is_constant_product() {
+        return None;
+    }
+    let is_input_token_0 = input_mint == swap_info.token_0.mint;
+    let reserves = expected_reserves(swap_info)?;
+    xyk::quote_swap_exact_out(
+        amount_out,
+        reserves.get(is_input_token_0),
+        reserves.get(!is_input_token_0),
+        &swap_info.fees.into(),
+    )
+}
+
+/// Quotes the amounts of each token required by a [cpamm::cpamm::deposit]
+/// of `pool_token_amount` LP tokens.
+///
+/// Deposits are proportional to the reserves on every curve.
+pub fn quote_deposit(
+    swap_info: &SwapInfo,
+    pool_mint_supply: u64,
+    pool_token_amount: u64,
+) -> Option<(u64, u64)> {
+    let reserves = expected_reserves(swap_info)?;
+    let result = xyk::quote_deposit(
+        pool_token_amount,
+        pool_mint_supply,
+        reserves.amount_0,
+        reserves.amount_1,
+    )?;
+    Some((result.token_a_amount, result.token_b_amount))
+}
+
+/// Quotes the amounts of each token received from a [cpamm::cpamm::withdraw]
+/// of `pool_token_amount` LP tokens.
+///
+/// Withdrawals are proportional to the reserves on every curve.
+pub fn quote_withdraw(
+    swap_info: &SwapInfo,
+    pool_mint_supply: u64,
+    pool_token_amount: u64,
+) -> Option<(u64, u64)> {
+    let reserves = expected_reserves(swap_info)?;
+    let result = xyk::quote_withdraw(
+        pool_token_amount,
+        pool_mint_supply,
+        reserves.amount_0,
+        reserves.amount_1,
+    )?;
+    Some((result.token_a_amount, result.token_b_amount))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cpamm::SwapCurve;
+
+    fn swap_info(amount_0: u64, amount_1: u64) -> SwapInfo {
+        SwapInfo {
+            fees: cpamm::fees::INITIAL,
+            curve: SwapCurve::constant_product(),
+            expected_reserves: SwapReserves { amount_0, amount_1 },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn quote_swap_takes_trade_fee_from_output() {
+        let quote =
+            quote_swap(&swap_info(1_000_000, 1_000_000), Pubkey::default(), 10_000).unwrap();
+        let result = xyk::swap(10_000, 1_000_000, 1_000_000).unwrap();
+        assert_eq!(quote.amount_in, result.source_amount_swapped);
+        assert_eq!(
+            quote.amount_out + quote.trade_fee(),
+            result.destination_amount_swapped
+        );
+        assert_eq!(quote.trade_fee(), 29);
+        assert_eq!(quote.admin_fee, 4);
+    }
+
+    #[test]
+    fn quote_swap_uses_expected_reserves() {
+        let tracked = SwapInfo {
+            token_0: cpamm::SwapTokenInfo {
+                mint: Pubkey::new_unique(),
+                ..Default::default()
+            },
+            ..swap_info(1_000_000, 2_000_000)
+        };
+        let quote_0 = quote_swap(&tracked, tracked.token_0.mint, 10_000).unwrap();
+        let quote_1 = quote_swap(&tracked, Pubkey::default(), 10_000).unwrap();
+        assert!(quote_0.amount_out > 10_000);
+        assert!(quote_1.amount_out < 10_000);
+
+        // untracked reserves cannot be quoted
+        assert!(quote_swap(&swap_info(0, 0), Pubkey::default(), 10_000).is_none());
+        assert!(quote_deposit(&swap_info(0, 0), 1_000_000, 1_000).is_none());
+    }
+
+    #[test]
+    fn quote_swap_exact_out_covers_amount_out() {
+        let swap_info = swap_info(1_000_000, 1_000_000);
+        let quote = quote_swap_exact_out(&swap_info, Pubkey::default(), 10_000).unwrap();
+        assert!(quote.amount_out >= 10_000);
+        let forward = quote_swap(&swap_info, Pubkey::default(), quote.amount_in).unwrap();
+        assert!(forward.amount_out >= 10_000);
+    }
+
+    #[test]
+    fn quote_deposit_rounds_against_user() {
+        let swap_info = swap_info(1_000_001, 3_000_001);
+        let deposit = quote_deposit(&swap_info, 1_000_000, 1_000).unwrap();
+        let withdraw = quote_withdraw(&swap_info, 1_000_000, 1_000).unwrap();
+        assert!(deposit.0 > withdraw.0);
+        assert!(deposit.1 > withdraw.1);
+    }
+}
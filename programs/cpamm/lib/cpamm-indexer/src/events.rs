@@ -0,0 +1,224 @@
+//! Decoding of the events emitted by the [cpamm] program.
+//!
+//! Events are logged by `emit!` as `Program log: <base64>` lines, where the
+//! data is the discriminator of the event followed by its Borsh encoding.
+//! Only lines logged while [cpamm::ID] is the executing program are decoded,
+//! so other programs cannot spoof events of the swap.
+//!
+//! Swap, deposit and withdraw events emitted before the program logged users, fees
+//! and reserves are decoded from their original layouts, with the fields missing
+//! from those layouts left at zero.
+
+use anchor_lang::prelude::*;
+use anchor_lang::{Discriminator, Event};
+use cpamm::{DepositEvent, NewPoolEvent, SwapEvent, WithdrawEvent};
+
+const PROGRAM_LOG: &str = "Program log: ";
+
+/// An event emitted by the [cpamm] program.
+pub enum CpammEvent {
+    /// A [NewPoolEvent].
+    NewPool(NewPoolEvent),
+    /// A [DepositEvent].
+    Deposit(DepositEvent),
+    /// A [WithdrawEvent].
+    Withdraw(WithdrawEvent),
+    /// A [SwapEvent].
+    Swap(SwapEvent),
+}
+
+impl CpammEvent {
+    /// Mint of the LP token of the pool which emitted the event.
+    pub fn lp_mint(&self) -> Pubkey {
+        match self {
+            CpammEvent::NewPool(event) => event.lp_mint,
+            CpammEvent::Deposit(event) => event.lp_mint,
+            CpammEvent::Withdraw(event) => event.lp_mint,
+            CpammEvent::Swap(event) => event.lp_mint,
+        }
+    }
+
+    /// Decodes the base64 data of a `Program log:` line, falling back to the [legacy] layouts.
+    /// Returns [None] if the data is not one of the supported events.
+    pub fn decode(data: &str) -> Option<CpammEvent> {
+        let data = base64::decode(data).ok()?;
+        if data.len() < 8 {
+            return None;
+        }
+        let (discriminator, mut body) = data.split_at(8);
+        let discriminator: [u8; 8] = discriminator.try_into().ok()?;
+        if discriminator == NewPoolEvent::discriminator() {
+            deserialize(&mut body).map(CpammEvent::NewPool)
+        } else if discriminator == DepositEvent::discriminator() {
+            deserialize(&mut body)
+                .or_else(|| {
+                    legacy::DepositEvent::try_from_slice(body)
+                        .ok()
+                        .map(Into::into)
+                })
+                .map(CpammEvent::Deposit)
+        } else if discriminator == WithdrawEvent::discriminator() {
+            deserialize(&mut body)
+                .or_else(|| {
+                    legacy::WithdrawEvent::try_from_slice(body)
+                        .ok()
+                        .map(Into::into)
+                })
+                .map(CpammEvent::Withdraw)
+        } else if discriminator == SwapEvent::discriminator() {
+            deserialize(&mut body)
+                .or_else(|| legacy::SwapEvent::try_from_slice(body).ok().map(Into::into))
+                .map(CpammEvent::Swap)
+        } else {
+            None
+        }
+    }
+}
+
+fn deserialize<E: Event>(body: &mut &[u8]) -> Option<E> {
+    // the body is copied, so that a failed attempt leaves it intact for the legacy layout
+    E::deserialize(&mut &body[..]).ok()
+}
+
+/// Layouts of the events emitted by the first release of the [cpamm] program.
+pub mod legacy {
+    use anchor_lang::prelude::*;
+
+    /// The original layout of a [cpamm::SwapEvent].
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+    pub struct SwapEvent {
+        /// Mint of the LP token.
+        pub lp_mint: Pubkey,
+        /// Mint of the input (source) token.
+        pub input_mint: Pubkey,
+        /// Mint of the output (destination) token.
+        pub output_mint: Pubkey,
+        /// Amount of source token swapped
+        pub source_amount_swapped: u64,
+        /// Amount of destination token swapped
+        pub destination_amount_swapped: u64,
+    }
+
+    /// The original layout of a [cpamm::DepositEvent].
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+    pub struct DepositEvent {
+        /// Mint of the LP token.
+        pub lp_mint: Pubkey,
+        /// Amount of pool tokens created.
+        pub pool_token_amount: u64,
+        /// Amount of token 0 deposited.
+        pub token_0_amount: u64,
+        /// Amount of token 1 deposited.
+        pub token_1_amount: u64,
+    }
+
+    /// The original layout of a [cpamm::WithdrawEvent].
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+    pub struct WithdrawEvent {
+        /// Mint of the LP token.
+        pub lp_mint: Pubkey,
+        /// Amount of pool tokens burned.
+        pub pool_token_amount: u64,
+        /// Amount of token 0 withdrawn.
+        pub token_0_amount: u64,
+        /// Amount of token 1 withdrawn.
+        pub token_1_amount: u64,
+    }
+
+    impl From<SwapEvent> for cpamm::SwapEvent {
+        fn from(event: SwapEvent) -> Self {
+            cpamm::SwapEvent {
+                lp_mint: event.lp_mint,
+                input_mint: event.input_mint,
+                output_mint: event.output_mint,
+                source_amount_swapped: event.source_amount_swapped,
+                destination_amount_swapped: event.destination_amount_swapped,
+                referrer: None,
+                referral_fee: 0,
+                trade_fee_kbps: 0,
+                user_authority: Pubkey::default(),
+                trade_fee: 0,
+                admin_trade_fee: 0,
+                reserve_0: 0,
+                reserve_1: 0,
+            }
+        }
+    }
+
+    impl From<DepositEvent> for cpamm::DepositEvent {
+        fn from(event: DepositEvent) -> Self {
+            cpamm::DepositEvent {
+                lp_mint: event.lp_mint,
+                pool_token_amount: event.pool_token_amount,
+                token_0_amount: event.token_0_amount,
+                token_1_amount: event.token_1_amount,
+                user_authority: Pubkey::default(),
+                trade_fee: 0,
+                admin_trade_fee: 0,
+                reserve_0: 0,
+                reserve_1: 0,
+                pool_token_supply: 0,
+            }
+        }
+    }
+
+    impl From<WithdrawEvent> for cpamm::WithdrawEvent {
+        fn from(event: WithdrawEvent) -> Self {
+            cpamm::WithdrawEvent {
+                lp_mint: event.lp_mint,
+                pool_token_amount: event.pool_token_amount,
+                token_0_amount: event.token_0_amount,
+                token_1_amount: event.token_1_amount,
+                user_authority: Pubkey::default(),
+                fee_0: 0,
+                fee_1: 0,
+                admin_fee_0: 0,
+                admin_fee_1: 0,
+                reserve_0: 0,
+                reserve_1: 0,
+                pool_token_supply: 0,
+            }
+        }
+    }
+}
+
+/// A [CpammEvent] and the position of its line in the logs of a transaction.
+pub struct LoggedEvent {
+    /// Index of the log line which contained the event.
+    pub log_index: usize,
+    /// The event.
+    pub event: CpammEvent,
+}
+
+/// Decodes the [CpammEvent]s from the log messages of a transaction.
+pub fn decode_logs<S: AsRef<str>>(logs: &[S]) -> Vec<LoggedEvent> {
+    // stack of the programs being invoked
+    let mut invocations: Vec<Pubkey> = vec![];
+    let mut events = vec![];
+    for (log_index, log) in logs.iter().enumerate() {
+        let log = log.as_ref();
+        if let Some(data) = log.strip_prefix(PROGRAM_LOG) {
+            if invocations.last() != Some(&cpamm::ID) {
+                continue;
+            }
+            if let Some(event) = CpammEvent::decode(data) {
+                events.push(LoggedEvent { log_index, event });
+            }
+        } else if let Some((program_id, status)) = parse_program_status(log) {
+            if status.starts_with("invoke [") {
+                invocations.push(program_id);
+            } else if (status == "success" || status.starts_with("failed"))
+                && invocations.last() == Some(&program_id)
+            {
+                invocations.pop();
+            }
+        }
+    }
+    events
+}
+
+/// Parses a `Program <id> <status>` line.
+fn parse_program_status(log: &str) -> Option<(Pubkey, &str)> {
+    let (program_id, status) = log.strip_prefix("Program ")?.split_once(' ')?;
+    Some((program_id.parse().ok()?, status))
+}
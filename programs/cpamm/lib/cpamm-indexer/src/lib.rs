@@ -0,0 +1,19 @@
+//! Decodes and stores the events of the Sencha CP-AMM.
+//!
+//! Transactions are indexed from their log messages:
+//!
+//! 1. [events::decode_logs] decodes the [events::CpammEvent]s of a transaction.
+//! 2. [records::Record] normalizes each event into a stable schema.
+//! 3. [store::Store] writes the records to SQLite, along with periodic
+//!    [records::StatsSnapshot]s of each pool's [cpamm::SwapCumulativeStats],
+//!    which are aggregated into OHLCV [records::Candle]s.
+#![deny(rustdoc::all)]
+#![allow(rustdoc::missing_doc_code_examples)]
+#![deny(missing_docs)]
+
+pub mod events;
+pub mod records;
+pub mod store;
+
+pub use cpamm;
+pub use cpamm::ID as PROGRAM_ID;
@@ -0,0 +1,385 @@
+//! Normalized records of [cpamm] events and swap statistics.
+//!
+//! Every record of an event is keyed by the signature of its transaction and
+//! the index of its log line, and pools are identified by their LP mint.
+//! Records of [crate::events::legacy] events have a default user, and zero fees and reserves.
+
+use crate::events::{decode_logs, CpammEvent};
+use anchor_lang::prelude::*;
+use cpamm::price_info::PRICE_SCALE;
+use cpamm::{SwapCumulativeStats, SwapCumulativeTokenStats, SwapCurve, SwapInfo};
+
+/// The transaction which emitted a set of events.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TransactionContext {
+    /// Signature of the transaction.
+    pub signature: String,
+    /// Slot of the transaction.
+    pub slot: u64,
+    /// Estimated production time of the block, if known.
+    pub block_time: Option<i64>,
+}
+
+/// Location of an event within its transaction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EventContext {
+    /// Signature of the transaction.
+    pub signature: String,
+    /// Slot of the transaction.
+    pub slot: u64,
+    /// Estimated production time of the block, if known.
+    pub block_time: Option<i64>,
+    /// Index of the log line of the event.
+    pub log_index: u32,
+}
+
+/// A newly created pool.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PoolRecord {
+    /// Location of the event.
+    pub context: EventContext,
+    /// Mint of the LP token.
+    pub lp_mint: Pubkey,
+    /// Mint of token 0.
+    pub mint_0: Pubkey,
+    /// Mint of token 1.
+    pub mint_1: Pubkey,
+    /// Initial number of LP tokens in the pool.
+    pub initial_liquidity: u64,
+}
+
+/// A swap.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SwapRecord {
+    /// Location of the event.
+    pub context: EventContext,
+    /// Mint of the LP token.
+    pub lp_mint: Pubkey,
+    /// The user which swapped the tokens.
+    pub user: Pubkey,
+    /// Mint of the input token.
+    pub input_mint: Pubkey,
+    /// Mint of the output token.
+    pub output_mint: Pubkey,
+    /// Amount of input tokens received by the pool.
+    pub amount_in: u64,
+    /// Amount of output tokens sent to the user, net of the trade fee.
+    pub amount_out: u64,
+    /// Trade fee, including the admin and referral fees.
+    pub trade_fee: u64,
+    /// Portion of the trade fee sent to the admin.
+    pub admin_trade_fee: u64,
+    /// Portion of the trade fee sent to the referrer.
+    pub referral_fee: u64,
+    /// Token account of the referrer, if any.
+    pub referrer: Option<Pubkey>,
+    /// Trade fee of the swap, in kbps.
+    pub trade_fee_kbps: u64,
+    /// Reserves of token 0 after the swap.
+    pub reserve_0: u64,
+    /// Reserves of token 1 after the swap.
+    pub reserve_1: u64,
+}
+
+/// A deposit of liquidity.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DepositRecord {
+    /// Location of the event.
+    pub context: EventContext,
+    /// Mint of the LP token.
+    pub lp_mint: Pubkey,
+    /// The user which deposited the tokens.
+    pub user: Pubkey,
+    /// Amount of LP tokens minted.
+    pub lp_amount: u64,
+    /// Amount of token 0 deposited.
+    pub amount_0: u64,
+    /// Amount of token 1 deposited.
+    pub amount_1: u64,
+    /// Trade fee of a single token deposit, including the admin trade fee.
+    pub trade_fee: u64,
+    /// Portion of the trade fee sent to the admin.
+    pub admin_trade_fee: u64,
+    /// Reserves of token 0 after the deposit.
+    pub reserve_0: u64,
+    /// Reserves of token 1 after the deposit.
+    pub reserve_1: u64,
+    /// Supply of the LP token after the deposit.
+    pub lp_supply: u64,
+}
+
+/// A withdrawal of liquidity.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WithdrawRecord {
+    /// Location of the event.
+    pub context: EventContext,
+    /// Mint of the LP token.
+    pub lp_mint: Pubkey,
+    /// The user which withdrew the tokens.
+    pub user: Pubkey,
+    /// Amount of LP tokens burned.
+    pub lp_amount: u64,
+    /// Amount of token 0 withdrawn.
+    pub amount_0: u64,
+    /// Amount of token 1 withdrawn.
+    pub amount_1: u64,
+    /// Fees charged on token 0, including the admin fees.
+    pub fee_0: u64,
+    /// Fees charged on token 1, including the admin fees.
+    pub fee_1: u64,
+    /// Portion of the fees on token 0 sent to the admin.
+    pub admin_fee_0: u64,
+    /// Portion of the fees on token 1 sent to the admin.
+    pub admin_fee_1: u64,
+    /// Reserves of token 0 after the withdrawal.
+    pub reserve_0: u64,
+    /// Reserves of token 1 after the withdrawal.
+    pub reserve_1: u64,
+    /// Supply of the LP token after the withdrawal.
+    pub lp_supply: u64,
+}
+
+/// A normalized [CpammEvent].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Record {
+    /// A [PoolRecord].
+    Pool(PoolRecord),
+    /// A [SwapRecord].
+    Swap(SwapRecord),
+    /// A [DepositRecord].
+    Deposit(DepositRecord),
+    /// A [WithdrawRecord].
+    Withdraw(WithdrawRecord),
+}
+
+impl Record {
+    /// Normalizes a [CpammEvent] logged at `log_index` of a transaction.
+    pub fn new(tx: &TransactionContext, log_index: u32, event: &CpammEvent) -> Record {
+        let context = EventContext {
+            signature: tx.signature.clone(),
+            slot: tx.slot,
+            block_time: tx.block_time,
+            log_index,
+        };
+        match event {
+            CpammEvent::NewPool(event) => Record::Pool(PoolRecord {
+                context,
+                lp_mint: event.lp_mint,
+                mint_0: event.mint_0,
+                mint_1: event.mint_1,
+                initial_liquidity: event.initial_liquidity,
+            }),
+            CpammEvent::Swap(event) => Record::Swap(SwapRecord {
+                context,
+                lp_mint: event.lp_mint,
+                user: event.user_authority,
+                input_mint: event.input_mint,
+                output_mint: event.output_mint,
+                amount_in: event.source_amount_swapped,
+                amount_out: event
+                    .destination_amount_swapped
+                    .saturating_sub(event.trade_fee),
+                trade_fee: event.trade_fee,
+                admin_trade_fee: event.admin_trade_fee,
+                referral_fee: event.referral_fee,
+                referrer: event.referrer,
+                trade_fee_kbps: event.trade_fee_kbps,
+                reserve_0: event.reserve_0,
+                reserve_1: event.reserve_1,
+            }),
+            CpammEvent::Deposit(event) => Record::Deposit(DepositRecord {
+                context,
+                lp_mint: event.lp_mint,
+                user: event.user_authority,
+                lp_amount: event.pool_token_amount,
+                amount_0: event.token_0_amount,
+                amount_1: event.token_1_amount,
+                trade_fee: event.trade_fee,
+                admin_trade_fee: event.admin_trade_fee,
+                reserve_0: event.reserve_0,
+                reserve_1: event.reserve_1,
+                lp_supply: event.pool_token_supply,
+            }),
+            CpammEvent::Withdraw(event) => Record::Withdraw(WithdrawRecord {
+                context,
+                lp_mint: event.lp_mint,
+                user: event.user_authority,
+                lp_amount: event.pool_token_amount,
+                amount_0: event.token_0_amount,
+                amount_1: event.token_1_amount,
+                fee_0: event.fee_0,
+                fee_1: event.fee_1,
+                admin_fee_0: event.admin_fee_0,
+                admin_fee_1: event.admin_fee_1,
+                reserve_0: event.reserve_0,
+                reserve_1: event.reserve_1,
+                lp_supply: event.pool_token_supply,
+            }),
+        }
+    }
+
+    /// Decodes and normalizes the events in the log messages of a transaction.
+    ///
+    /// Only the logs of successful transactions should be indexed,
+    /// since the events of failed transactions did not take effect.
+    pub fn from_logs<S: AsRef<str>>(tx: &TransactionContext, logs: &[S]) -> Vec<Record> {
+        decode_logs(logs)
+            .iter()
+            .map(|logged| Record::new(tx, logged.log_index as u32, &logged.event))
+            .collect()
+    }
+
+    /// Location of the event.
+    pub fn context(&self) -> &EventContext {
+        match self {
+            Record::Pool(record) => &record.context,
+            Record::Swap(record) => &record.context,
+            Record::Deposit(record) => &record.context,
+            Record::Withdraw(record) => &record.context,
+        }
+    }
+}
+
+/// The [SwapCumulativeStats] and reserves of a pool at a point in time.
+///
+/// Snapshots are taken periodically from the [SwapInfo] account, and are
+/// aggregated into [Candle]s by [candles].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StatsSnapshot {
+    /// Mint of the LP token.
+    pub lp_mint: Pubkey,
+    /// Slot at which the [SwapInfo] was read.
+    pub slot: u64,
+    /// Unix timestamp at which the [SwapInfo] was read.
+    pub timestamp: i64,
+    /// Cumulative statistics of the pool.
+    pub stats: SwapCumulativeStats,
+    /// Reserves of token 0.
+    pub reserve_0: u64,
+    /// Reserves of token 1.
+    pub reserve_1: u64,
+    /// Invariant of the pool.
+    pub curve: SwapCurve,
+}
+
+impl StatsSnapshot {
+    /// Takes a snapshot of a [SwapInfo].
+    pub fn new(swap_info: &SwapInfo, slot: u64, timestamp: i64) -> StatsSnapshot {
+        StatsSnapshot {
+            lp_mint: swap_info.pool_mint,
+            slot,
+            timestamp,
+            stats: swap_info.cumulative_stats,
+            reserve_0: swap_info.expected_reserves.amount_0,
+            reserve_1: swap_info.expected_reserves.amount_1,
+            curve: swap_info.curve,
+        }
+    }
+
+    /// Spot price of token 0 in raw units of token 1 on the [StatsSnapshot::curve].
+    /// Zero if the pool is missing the reserves of either token.
+    pub fn price(&self) -> f64 {
+        if self.reserve_0 == 0 || self.reserve_1 == 0 {
+            return 0.0;
+        }
+        match self.curve.spot_price(true, self.reserve_0, self.reserve_1) {
+            Some(price) => price as f64 / 10f64.powi(PRICE_SCALE.into()),
+            None => 0.0,
+        }
+    }
+}
+
+/// OHLCV and fees of a pool over an interval.
+///
+/// Prices are [StatsSnapshot::price]s. Volumes and fees are amounts in raw
+/// units of each token, where a swap counts towards the volume of both tokens.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Candle {
+    /// Mint of the LP token.
+    pub lp_mint: Pubkey,
+    /// Unix timestamp of the start of the interval.
+    pub start_ts: i64,
+    /// Price of the first snapshot of the interval.
+    pub open: f64,
+    /// Highest price of the interval.
+    pub high: f64,
+    /// Lowest price of the interval.
+    pub low: f64,
+    /// Price of the last snapshot of the interval.
+    pub close: f64,
+    /// Volume of token 0.
+    pub volume_0: u128,
+    /// Volume of token 1.
+    pub volume_1: u128,
+    /// Trade fees collected in token 0, including the admin fees.
+    pub trade_fees_0: u128,
+    /// Trade fees collected in token 1, including the admin fees.
+    pub trade_fees_1: u128,
+}
+
+/// Aggregates the [StatsSnapshot]s of a pool into [Candle]s of `interval` seconds.
+///
+/// Volumes and fees are the changes in [SwapCumulativeStats] since the
+/// previous snapshot, so the first snapshot only contributes its price.
+/// Snapshots must be sorted by timestamp, and intervals without snapshots are omitted.
+pub fn candles(snapshots: &[StatsSnapshot], interval: i64) -> Vec<Candle> {
+    assert!(interval > 0, "interval must be positive");
+    let mut candles: Vec<Candle> = vec![];
+    let mut previous: Option<&StatsSnapshot> = None;
+    for snapshot in snapshots {
+        let start_ts = snapshot.timestamp - snapshot.timestamp.rem_euclid(interval);
+        let price = snapshot.price();
+        let candle = match candles.last_mut() {
+            Some(candle) if candle.start_ts == start_ts => candle,
+            _ => {
+                candles.push(Candle {
+                    lp_mint: snapshot.lp_mint,
+                    start_ts,
+                    open: price,
+                    high: price,
+                    low: price,
+                    close: price,
+                    volume_0: 0,
+                    volume_1: 0,
+                    trade_fees_0: 0,
+                    trade_fees_1: 0,
+                });
+                candles.last_mut().unwrap()
+            }
+        };
+        candle.high = candle.high.max(price);
+        candle.low = candle.low.min(price);
+        candle.close = price;
+
+        if let Some(previous) = previous {
+            let (stats, previous) = (&snapshot.stats, &previous.stats);
+            candle.volume_0 += volume_delta(&stats.token_0, &previous.token_0);
+            candle.volume_1 += volume_delta(&stats.token_1, &previous.token_1);
+            candle.trade_fees_0 += u128::from(
+                stats
+                    .token_0
+                    .total_trade_fees
+                    .saturating_sub(previous.token_0.total_trade_fees),
+            );
+            candle.trade_fees_1 += u128::from(
+                stats
+                    .token_1
+                    .total_trade_fees
+                    .saturating_sub(previous.token_1.total_trade_fees),
+            );
+        }
+        previous = Some(snapshot);
+    }
+    candles
+}
+
+/// Swap volume of a token between two snapshots.
+fn volume_delta(stats: &SwapCumulativeTokenStats, previous: &SwapCumulativeTokenStats) -> u128 {
+    let input = stats
+        .total_input_volume
+        .saturating_sub(previous.total_input_volume);
+    let output = stats
+        .total_output_volume
+        .saturating_sub(previous.total_output_volume);
+    input.saturating_add(output)
+}
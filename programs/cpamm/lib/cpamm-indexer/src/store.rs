@@ -0,0 +1,471 @@
+//! SQLite store of [Record]s and [StatsSnapshot]s.
+//!
+//! Token amounts are stored as decimal `TEXT`, since they may not fit into
+//! SQLite's signed 64-bit integers. Public keys are stored as base58 `TEXT`.
+//! Inserting a record or snapshot which is already stored is a no-op,
+//! so transactions may safely be indexed more than once.
+
+use crate::records::*;
+use anchor_lang::prelude::Pubkey;
+use cpamm::{SwapCumulativeStats, SwapCumulativeTokenStats, SwapCurve};
+use rusqlite::types::Type;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use std::path::Path;
+use std::str::FromStr;
+
+pub use rusqlite::{Error, Result};
+
+/// Version of the schema of the [Store], recorded as the `user_version` of the database.
+pub const SCHEMA_VERSION: i64 = 1;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS pools (
+    lp_mint TEXT PRIMARY KEY,
+    signature TEXT NOT NULL,
+    slot INTEGER NOT NULL,
+    block_time INTEGER,
+    log_index INTEGER NOT NULL,
+    mint_0 TEXT NOT NULL,
+    mint_1 TEXT NOT NULL,
+    initial_liquidity TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS swaps (
+    signature TEXT NOT NULL,
+    log_index INTEGER NOT NULL,
+    slot INTEGER NOT NULL,
+    block_time INTEGER,
+    lp_mint TEXT NOT NULL,
+    user TEXT NOT NULL,
+    input_mint TEXT NOT NULL,
+    output_mint TEXT NOT NULL,
+    amount_in TEXT NOT NULL,
+    amount_out TEXT NOT NULL,
+    trade_fee TEXT NOT NULL,
+    admin_trade_fee TEXT NOT NULL,
+    referral_fee TEXT NOT NULL,
+    referrer TEXT,
+    trade_fee_kbps INTEGER NOT NULL,
+    reserve_0 TEXT NOT NULL,
+    reserve_1 TEXT NOT NULL,
+    PRIMARY KEY (signature, log_index)
+);
+CREATE INDEX IF NOT EXISTS swaps_lp_mint_slot ON swaps (lp_mint, slot);
+
+CREATE TABLE IF NOT EXISTS deposits (
+    signature TEXT NOT NULL,
+    log_index INTEGER NOT NULL,
+    slot INTEGER NOT NULL,
+    block_time INTEGER,
+    lp_mint TEXT NOT NULL,
+    user TEXT NOT NULL,
+    lp_amount TEXT NOT NULL,
+    amount_0 TEXT NOT NULL,
+    amount_1 TEXT NOT NULL,
+    trade_fee TEXT NOT NULL,
+    admin_trade_fee TEXT NOT NULL,
+    reserve_0 TEXT NOT NULL,
+    reserve_1 TEXT NOT NULL,
+    lp_supply TEXT NOT NULL,
+    PRIMARY KEY (signature, log_index)
+);
+CREATE INDEX IF NOT EXISTS deposits_lp_mint_slot ON deposits (lp_mint, slot);
+
+CREATE TABLE IF NOT EXISTS withdrawals (
+    signature TEXT NOT NULL,
+    log_index INTEGER NOT NULL,
+    slot INTEGER NOT NULL,
+    block_time INTEGER,
+    lp_mint TEXT NOT NULL,
+    user TEXT NOT NULL,
+    lp_amount TEXT NOT NULL,
+    amount_0 TEXT NOT NULL,
+    amount_1 TEXT NOT NULL,
+    fee_0 TEXT NOT NULL,
+    fee_1 TEXT NOT NULL,
+    admin_fee_0 TEXT NOT NULL,
+    admin_fee_1 TEXT NOT NULL,
+    reserve_0 TEXT NOT NULL,
+    reserve_1 TEXT NOT NULL,
+    lp_supply TEXT NOT NULL,
+    PRIMARY KEY (signature, log_index)
+);
+CREATE INDEX IF NOT EXISTS withdrawals_lp_mint_slot ON withdrawals (lp_mint, slot);
+
+CREATE TABLE IF NOT EXISTS stats_snapshots (
+    lp_mint TEXT NOT NULL,
+    slot INTEGER NOT NULL,
+    timestamp INTEGER NOT NULL,
+    input_volume_0 TEXT NOT NULL,
+    output_volume_0 TEXT NOT NULL,
+    deposit_volume_0 TEXT NOT NULL,
+    withdraw_volume_0 TEXT NOT NULL,
+    trade_fees_0 TEXT NOT NULL,
+    input_volume_1 TEXT NOT NULL,
+    output_volume_1 TEXT NOT NULL,
+    deposit_volume_1 TEXT NOT NULL,
+    withdraw_volume_1 TEXT NOT NULL,
+    trade_fees_1 TEXT NOT NULL,
+    total_lp_minted TEXT NOT NULL,
+    total_lp_redeemed TEXT NOT NULL,
+    reserve_0 TEXT NOT NULL,
+    reserve_1 TEXT NOT NULL,
+    curve_type INTEGER NOT NULL,
+    amp_factor TEXT NOT NULL,
+    weight_0 INTEGER NOT NULL,
+    PRIMARY KEY (lp_mint, slot)
+);
+";
+
+/// A SQLite database of [Record]s and [StatsSnapshot]s.
+pub struct Store {
+    conn: Connection,
+}
+
+impl Store {
+    /// Opens or creates the database at `path`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Store> {
+        Store::new(Connection::open(path)?)
+    }
+
+    /// Creates a database in memory.
+    pub fn open_in_memory() -> Result<Store> {
+        Store::new(Connection::open_in_memory()?)
+    }
+
+    /// Creates the schema of a database, if it does not exist yet.
+    pub fn new(conn: Connection) -> Result<Store> {
+        conn.execute_batch(SCHEMA)?;
+        conn.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+        Ok(Store { conn })
+    }
+
+    /// The underlying connection, for custom queries.
+    pub fn connection(&self) -> &Connection {
+        &self.conn
+    }
+
+    /// Decodes the events in the log messages of a transaction and stores their [Record]s.
+    /// Returns the number of new records.
+    pub fn index_transaction<S: AsRef<str>>(
+        &mut self,
+        tx: &TransactionContext,
+        logs: &[S],
+    ) -> Result<usize> {
+        self.insert_records(&Record::from_logs(tx, logs))
+    }
+
+    /// Stores [Record]s in a single database transaction.
+    /// Returns the number of new records.
+    pub fn insert_records(&mut self, records: &[Record]) -> Result<usize> {
+        let tx = self.conn.transaction()?;
+        let mut inserted = 0;
+        for record in records {
+            inserted += insert_record(&tx, record)?;
+        }
+        tx.commit()?;
+        Ok(inserted)
+    }
+
+    /// Stores a [StatsSnapshot]. Returns false if a snapshot of the pool
+    /// was already stored for the same slot.
+    pub fn insert_snapshot(&self, snapshot: &StatsSnapshot) -> Result<bool> {
+        let stats = &snapshot.stats;
+        let inserted = self.conn.execute(
+            "INSERT OR IGNORE INTO stats_snapshots VALUES
+             (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20)",
+            params![
+                snapshot.lp_mint.to_string(),
+                snapshot.slot,
+                snapshot.timestamp,
+                stats.token_0.total_input_volume.to_string(),
+                stats.token_0.total_output_volume.to_string(),
+                stats.token_0.total_deposit_volume.to_string(),
+                stats.token_0.total_withdraw_volume.to_string(),
+                stats.token_0.total_trade_fees.to_string(),
+                stats.token_1.total_input_volume.to_string(),
+                stats.token_1.total_output_volume.to_string(),
+                stats.token_1.total_deposit_volume.to_string(),
+                stats.token_1.total_withdraw_volume.to_string(),
+                stats.token_1.total_trade_fees.to_string(),
+                stats.total_lp_minted.to_string(),
+                stats.total_lp_redeemed.to_string(),
+                snapshot.reserve_0.to_string(),
+                snapshot.reserve_1.to_string(),
+                snapshot.curve.curve_type,
+                snapshot.curve.amp_factor.to_string(),
+                snapshot.curve.weight_0,
+            ],
+        )?;
+        Ok(inserted > 0)
+    }
+
+    /// Loads the [PoolRecord] of a pool.
+    pub fn pool(&self, lp_mint: &Pubkey) -> Result<Option<PoolRecord>> {
+        self.conn
+            .query_row(
+                "SELECT signature, slot, block_time, log_index,
+                 lp_mint, mint_0, mint_1, initial_liquidity
+                 FROM pools WHERE lp_mint = ?1",
+                [lp_mint.to_string()],
+                |row| {
+                    Ok(PoolRecord {
+                        context: event_context(row)?,
+                        lp_mint: parse(row, 4)?,
+                        mint_0: parse(row, 5)?,
+                        mint_1: parse(row, 6)?,
+                        initial_liquidity: parse(row, 7)?,
+                    })
+                },
+            )
+            .optional()
+    }
+
+    /// Loads the [SwapRecord]s of a pool, in order.
+    pub fn swaps(&self, lp_mint: &Pubkey) -> Result<Vec<SwapRecord>> {
+        let mut statement = self.conn.prepare(
+            "SELECT signature, slot, block_time, log_index,
+             lp_mint, user, input_mint, output_mint, amount_in, amount_out,
+             trade_fee, admin_trade_fee, referral_fee, referrer, trade_fee_kbps,
+             reserve_0, reserve_1
+             FROM swaps WHERE lp_mint = ?1 ORDER BY slot, signature, log_index",
+        )?;
+        let rows = statement.query_map([lp_mint.to_string()], |row| {
+            Ok(SwapRecord {
+                context: event_context(row)?,
+                lp_mint: parse(row, 4)?,
+                user: parse(row, 5)?,
+                input_mint: parse(row, 6)?,
+                output_mint: parse(row, 7)?,
+                amount_in: parse(row, 8)?,
+                amount_out: parse(row, 9)?,
+                trade_fee: parse(row, 10)?,
+                admin_trade_fee: parse(row, 11)?,
+                referral_fee: parse(row, 12)?,
+                referrer: parse_optional(row, 13)?,
+                trade_fee_kbps: row.get(14)?,
+                reserve_0: parse(row, 15)?,
+                reserve_1: parse(row, 16)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// Loads the [DepositRecord]s of a pool, in order.
+    pub fn deposits(&self, lp_mint: &Pubkey) -> Result<Vec<DepositRecord>> {
+        let mut statement = self.conn.prepare(
+            "SELECT signature, slot, block_time, log_index,
+             lp_mint, user, lp_amount, amount_0, amount_1,
+             trade_fee, admin_trade_fee, reserve_0, reserve_1, lp_supply
+             FROM deposits WHERE lp_mint = ?1 ORDER BY slot, signature, log_index",
+        )?;
+        let rows = statement.query_map([lp_mint.to_string()], |row| {
+            Ok(DepositRecord {
+                context: event_context(row)?,
+                lp_mint: parse(row, 4)?,
+                user: parse(row, 5)?,
+                lp_amount: parse(row, 6)?,
+                amount_0: parse(row, 7)?,
+                amount_1: parse(row, 8)?,
+                trade_fee: parse(row, 9)?,
+                admin_trade_fee: parse(row, 10)?,
+                reserve_0: parse(row, 11)?,
+                reserve_1: parse(row, 12)?,
+                lp_supply: parse(row, 13)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// Loads the [WithdrawRecord]s of a pool, in order.
+    pub fn withdrawals(&self, lp_mint: &Pubkey) -> Result<Vec<WithdrawRecord>> {
+        let mut statement = self.conn.prepare(
+            "SELECT signature, slot, block_time, log_index,
+             lp_mint, user, lp_amount, amount_0, amount_1,
+             fee_0, fee_1, admin_fee_0, admin_fee_1, reserve_0, reserve_1, lp_supply
+             FROM withdrawals WHERE lp_mint = ?1 ORDER BY slot, signature, log_index",
+        )?;
+        let rows = statement.query_map([lp_mint.to_string()], |row| {
+            Ok(WithdrawRecord {
+                context: event_context(row)?,
+                lp_mint: parse(row, 4)?,
+                user: parse(row, 5)?,
+                lp_amount: parse(row, 6)?,
+                amount_0: parse(row, 7)?,
+                amount_1: parse(row, 8)?,
+                fee_0: parse(row, 9)?,
+                fee_1: parse(row, 10)?,
+                admin_fee_0: parse(row, 11)?,
+                admin_fee_1: parse(row, 12)?,
+                reserve_0: parse(row, 13)?,
+                reserve_1: parse(row, 14)?,
+                lp_supply: parse(row, 15)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// Loads the [StatsSnapshot]s of a pool, sorted by timestamp.
+    pub fn snapshots(&self, lp_mint: &Pubkey) -> Result<Vec<StatsSnapshot>> {
+        let mut statement = self
+            .conn
+            .prepare("SELECT * FROM stats_snapshots WHERE lp_mint = ?1 ORDER BY timestamp, slot")?;
+        let rows = statement.query_map([lp_mint.to_string()], |row| {
+            Ok(StatsSnapshot {
+                lp_mint: parse(row, 0)?,
+                slot: row.get(1)?,
+                timestamp: row.get(2)?,
+                stats: SwapCumulativeStats {
+                    token_0: token_stats(row, 3)?,
+                    token_1: token_stats(row, 8)?,
+                    total_lp_minted: parse(row, 13)?,
+                    total_lp_redeemed: parse(row, 14)?,
+                },
+                reserve_0: parse(row, 15)?,
+                reserve_1: parse(row, 16)?,
+                curve: SwapCurve {
+                    curve_type: row.get(17)?,
+                    amp_factor: parse(row, 18)?,
+                    weight_0: row.get(19)?,
+                },
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// Aggregates the [StatsSnapshot]s of a pool into [Candle]s of `interval` seconds.
+    pub fn candles(&self, lp_mint: &Pubkey, interval: i64) -> Result<Vec<Candle>> {
+        Ok(candles(&self.snapshots(lp_mint)?, interval))
+    }
+}
+
+/// Inserts a [Record], returning the number of rows inserted.
+fn insert_record(conn: &Connection, record: &Record) -> Result<usize> {
+    let context = record.context();
+    match record {
+        Record::Pool(pool) => conn.execute(
+            "INSERT OR IGNORE INTO pools VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                pool.lp_mint.to_string(),
+                context.signature,
+                context.slot,
+                context.block_time,
+                context.log_index,
+                pool.mint_0.to_string(),
+                pool.mint_1.to_string(),
+                pool.initial_liquidity.to_string(),
+            ],
+        ),
+        Record::Swap(swap) => conn.execute(
+            "INSERT OR IGNORE INTO swaps VALUES
+             (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)",
+            params![
+                context.signature,
+                context.log_index,
+                context.slot,
+                context.block_time,
+                swap.lp_mint.to_string(),
+                swap.user.to_string(),
+                swap.input_mint.to_string(),
+                swap.output_mint.to_string(),
+                swap.amount_in.to_string(),
+                swap.amount_out.to_string(),
+                swap.trade_fee.to_string(),
+                swap.admin_trade_fee.to_string(),
+                swap.referral_fee.to_string(),
+                swap.referrer.map(|referrer| referrer.to_string()),
+                swap.trade_fee_kbps,
+                swap.reserve_0.to_string(),
+                swap.reserve_1.to_string(),
+            ],
+        ),
+        Record::Deposit(deposit) => conn.execute(
+            "INSERT OR IGNORE INTO deposits VALUES
+             (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+            params![
+                context.signature,
+                context.log_index,
+                context.slot,
+                context.block_time,
+                deposit.lp_mint.to_string(),
+                deposit.user.to_string(),
+                deposit.lp_amount.to_string(),
+                deposit.amount_0.to_string(),
+                deposit.amount_1.to_string(),
+                deposit.trade_fee.to_string(),
+                deposit.admin_trade_fee.to_string(),
+                deposit.reserve_0.to_string(),
+                deposit.reserve_1.to_string(),
+                deposit.lp_supply.to_string(),
+            ],
+        ),
+        Record::Withdraw(withdraw) => conn.execute(
+            "INSERT OR IGNORE INTO withdrawals VALUES
+             (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)",
+            params![
+                context.signature,
+                context.log_index,
+                context.slot,
+                context.block_time,
+                withdraw.lp_mint.to_string(),
+                withdraw.user.to_string(),
+                withdraw.lp_amount.to_string(),
+                withdraw.amount_0.to_string(),
+                withdraw.amount_1.to_string(),
+                withdraw.fee_0.to_string(),
+                withdraw.fee_1.to_string(),
+                withdraw.admin_fee_0.to_string(),
+                withdraw.admin_fee_1.to_string(),
+                withdraw.reserve_0.to_string(),
+                withdraw.reserve_1.to_string(),
+                withdraw.lp_supply.to_string(),
+            ],
+        ),
+    }
+}
+
+/// Reads the [EventContext] from the first four columns of a row.
+fn event_context(row: &Row) -> Result<EventContext> {
+    Ok(EventContext {
+        signature: row.get(0)?,
+        slot: row.get(1)?,
+        block_time: row.get(2)?,
+        log_index: row.get(3)?,
+    })
+}
+
+/// Reads the [SwapCumulativeTokenStats] from the five columns starting at `index`.
+fn token_stats(row: &Row, index: usize) -> Result<SwapCumulativeTokenStats> {
+    Ok(SwapCumulativeTokenStats {
+        total_input_volume: parse(row, index)?,
+        total_output_volume: parse(row, index + 1)?,
+        total_deposit_volume: parse(row, index + 2)?,
+        total_withdraw_volume: parse(row, index + 3)?,
+        total_trade_fees: parse(row, index + 4)?,
+    })
+}
+
+/// Parses a `TEXT` column.
+fn parse<T>(row: &Row, index: usize) -> Result<T>
+where
+    T: FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    let text: String = row.get(index)?;
+    text.parse()
+        .map_err(|err| Error::FromSqlConversionFailure(index, Type::Text, Box::new(err)))
+}
+
+/// Parses a nullable `TEXT` column.
+fn parse_optional<T>(row: &Row, index: usize) -> Result<Option<T>>
+where
+    T: FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    let text: Option<String> = row.get(index)?;
+    text.map(|text| {
+        text.parse()
+            .map_err(|err| Error::FromSqlConversionFailure(index, Type::Text, Box::new(err)))
+    })
+    .transpose()
+}
@@ -0,0 +1,361 @@
+use anchor_lang::prelude::{AnchorSerialize, Pubkey};
+use anchor_lang::Discriminator;
+use cpamm::{SwapCumulativeStats, SwapCumulativeTokenStats, SwapCurve};
+use cpamm_indexer::events::{decode_logs, legacy, CpammEvent};
+use cpamm_indexer::records::*;
+use cpamm_indexer::store::Store;
+use std::str::FromStr;
+
+const TOKEN_PROGRAM: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+
+/// Transactions recorded from a pool which was created, deposited into,
+/// swapped in both directions, and withdrawn from.
+fn fixtures() -> Vec<(TransactionContext, Vec<String>)> {
+    let txs: serde_json::Value =
+        serde_json::from_str(include_str!("fixtures/transactions.json")).unwrap();
+    txs.as_array()
+        .unwrap()
+        .iter()
+        .map(|tx| {
+            let context = TransactionContext {
+                signature: tx["signature"].as_str().unwrap().to_string(),
+                slot: tx["slot"].as_u64().unwrap(),
+                block_time: tx["blockTime"].as_i64(),
+            };
+            let logs = serde_json::from_value(tx["meta"]["logMessages"].clone()).unwrap();
+            (context, logs)
+        })
+        .collect()
+}
+
+fn lp_mint() -> Pubkey {
+    Pubkey::from_str("Fe5yqoRoM2KUx4SYGBPNFzdJKHq7AEDbHrYmNVEWMaZT").unwrap()
+}
+
+/// Returns the base64 data of the first event logged by a transaction.
+fn event_data(logs: &[String]) -> String {
+    let log_index = decode_logs(logs)[0].log_index;
+    logs[log_index]
+        .strip_prefix("Program log: ")
+        .unwrap()
+        .to_string()
+}
+
+#[test]
+fn test_decode_fixtures() {
+    let records: Vec<Record> = fixtures()
+        .iter()
+        .flat_map(|(tx, logs)| Record::from_logs(tx, logs))
+        .collect();
+    assert_eq!(records.len(), 5);
+
+    let pool = match &records[0] {
+        Record::Pool(pool) => pool,
+        record => panic!("unexpected record {:?}", record),
+    };
+    assert_eq!(pool.lp_mint, lp_mint());
+    assert_eq!(pool.initial_liquidity, 1_000_000_000);
+    assert_eq!(pool.context.slot, 100);
+    assert_eq!(pool.context.block_time, Some(1_660_000_000));
+
+    let deposit = match &records[1] {
+        Record::Deposit(deposit) => deposit,
+        record => panic!("unexpected record {:?}", record),
+    };
+    assert_eq!(deposit.amount_0, 10_000_000);
+    assert_eq!(deposit.amount_1, 10_000_000);
+    assert_eq!(deposit.reserve_0, 1_010_000_000);
+    assert_eq!(deposit.lp_supply, 1_010_000_000);
+
+    let swap = match &records[2] {
+        Record::Swap(swap) => swap,
+        record => panic!("unexpected record {:?}", record),
+    };
+    assert_eq!(swap.user, deposit.user);
+    assert_eq!(swap.input_mint, pool.mint_0);
+    assert_eq!(swap.output_mint, pool.mint_1);
+    assert_eq!(swap.amount_in, 5_000_000);
+    assert_eq!(swap.amount_out, 4_960_443);
+    assert_eq!(swap.trade_fee, 14_926);
+    assert_eq!(swap.admin_trade_fee, 2_388);
+    assert_eq!(swap.trade_fee_kbps, 30_000);
+    assert_eq!(swap.referrer, None);
+    // the reserves receive the input and send the output and the admin fee
+    assert_eq!(swap.reserve_0, deposit.reserve_0 + swap.amount_in);
+    assert_eq!(
+        swap.reserve_1,
+        deposit.reserve_1 - swap.amount_out - swap.admin_trade_fee
+    );
+
+    let withdraw = match &records[4] {
+        Record::Withdraw(withdraw) => withdraw,
+        record => panic!("unexpected record {:?}", record),
+    };
+    assert_eq!(withdraw.lp_amount, 4_000_000);
+    assert_eq!(withdraw.lp_supply, deposit.lp_supply - withdraw.lp_amount);
+}
+
+#[test]
+fn test_decode_ignores_other_programs() {
+    let fixtures = fixtures();
+    let (_, swap_logs) = &fixtures[2];
+    let data = event_data(swap_logs);
+
+    // an event logged by another program is not decoded
+    let spoofed = vec![
+        format!("Program {} invoke [1]", TOKEN_PROGRAM),
+        format!("Program log: {}", data),
+        format!("Program {} success", TOKEN_PROGRAM),
+    ];
+    assert!(decode_logs(&spoofed).is_empty());
+
+    // nor is an event logged by a program invoked by the swap
+    let nested = vec![
+        format!("Program {} invoke [1]", cpamm::ID),
+        format!("Program {} invoke [2]", TOKEN_PROGRAM),
+        format!("Program log: {}", data),
+        format!("Program {} success", TOKEN_PROGRAM),
+        format!("Program log: {}", data),
+        "Program log: not an event".to_string(),
+        format!("Program {} success", cpamm::ID),
+    ];
+    let events = decode_logs(&nested);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].log_index, 4);
+    assert!(matches!(&events[0].event, CpammEvent::Swap(swap) if swap.lp_mint == lp_mint()));
+}
+
+#[test]
+fn test_decode_legacy_events() {
+    let mint_0 = Pubkey::new_unique();
+    let mint_1 = Pubkey::new_unique();
+    let encode = |discriminator: [u8; 8], body: Vec<u8>| {
+        base64::encode([&discriminator[..], &body].concat())
+    };
+    let logs = vec![
+        format!("Program {} invoke [1]", cpamm::ID),
+        format!(
+            "Program log: {}",
+            encode(
+                cpamm::SwapEvent::discriminator(),
+                legacy::SwapEvent {
+                    lp_mint: lp_mint(),
+                    input_mint: mint_0,
+                    output_mint: mint_1,
+                    source_amount_swapped: 5_000_000,
+                    destination_amount_swapped: 4_975_369,
+                }
+                .try_to_vec()
+                .unwrap()
+            )
+        ),
+        format!(
+            "Program log: {}",
+            encode(
+                cpamm::DepositEvent::discriminator(),
+                legacy::DepositEvent {
+                    lp_mint: lp_mint(),
+                    pool_token_amount: 1_000,
+                    token_0_amount: 2_000,
+                    token_1_amount: 3_000,
+                }
+                .try_to_vec()
+                .unwrap()
+            )
+        ),
+        format!(
+            "Program log: {}",
+            encode(
+                cpamm::WithdrawEvent::discriminator(),
+                legacy::WithdrawEvent {
+                    lp_mint: lp_mint(),
+                    pool_token_amount: 1_000,
+                    token_0_amount: 2_000,
+                    token_1_amount: 3_000,
+                }
+                .try_to_vec()
+                .unwrap()
+            )
+        ),
+        format!("Program {} success", cpamm::ID),
+    ];
+
+    // events emitted before the upgrade are decoded from their original layouts
+    let tx = TransactionContext {
+        signature: "legacy".to_string(),
+        slot: 1,
+        block_time: None,
+    };
+    let records = Record::from_logs(&tx, &logs);
+    assert_eq!(records.len(), 3);
+    match &records[0] {
+        Record::Swap(swap) => {
+            assert_eq!(swap.lp_mint, lp_mint());
+            assert_eq!(swap.input_mint, mint_0);
+            assert_eq!(swap.output_mint, mint_1);
+            assert_eq!(swap.amount_in, 5_000_000);
+            assert_eq!(swap.amount_out, 4_975_369);
+            assert_eq!(swap.user, Pubkey::default());
+            assert_eq!(swap.reserve_0, 0);
+        }
+        record => panic!("unexpected record {:?}", record),
+    }
+    match &records[1] {
+        Record::Deposit(deposit) => {
+            assert_eq!(deposit.lp_amount, 1_000);
+            assert_eq!(deposit.amount_0, 2_000);
+            assert_eq!(deposit.amount_1, 3_000);
+        }
+        record => panic!("unexpected record {:?}", record),
+    }
+    match &records[2] {
+        Record::Withdraw(withdraw) => {
+            assert_eq!(withdraw.lp_amount, 1_000);
+            assert_eq!(withdraw.amount_0, 2_000);
+            assert_eq!(withdraw.amount_1, 3_000);
+        }
+        record => panic!("unexpected record {:?}", record),
+    }
+
+    // the current layouts still take precedence
+    let (_, swap_logs) = &fixtures()[2];
+    assert!(matches!(
+        CpammEvent::decode(&event_data(swap_logs)),
+        Some(CpammEvent::Swap(swap)) if swap.trade_fee == 14_926
+    ));
+}
+
+#[test]
+fn test_store_records() {
+    let mut store = Store::open_in_memory().unwrap();
+    for (tx, logs) in fixtures() {
+        assert_eq!(store.index_transaction(&tx, &logs).unwrap(), 1);
+    }
+    // indexing a transaction again is a no-op
+    for (tx, logs) in fixtures() {
+        assert_eq!(store.index_transaction(&tx, &logs).unwrap(), 0);
+    }
+
+    let records: Vec<Record> = fixtures()
+        .iter()
+        .flat_map(|(tx, logs)| Record::from_logs(tx, logs))
+        .collect();
+    let pool = store.pool(&lp_mint()).unwrap().unwrap();
+    assert_eq!(Record::Pool(pool), records[0]);
+    let deposits = store.deposits(&lp_mint()).unwrap();
+    assert_eq!(
+        deposits
+            .into_iter()
+            .map(Record::Deposit)
+            .collect::<Vec<_>>(),
+        records[1..2]
+    );
+    let swaps = store.swaps(&lp_mint()).unwrap();
+    assert_eq!(
+        swaps.into_iter().map(Record::Swap).collect::<Vec<_>>(),
+        records[2..4]
+    );
+    let withdrawals = store.withdrawals(&lp_mint()).unwrap();
+    assert_eq!(
+        withdrawals
+            .into_iter()
+            .map(Record::Withdraw)
+            .collect::<Vec<_>>(),
+        records[4..]
+    );
+
+    assert!(store.pool(&Pubkey::default()).unwrap().is_none());
+    assert!(store.swaps(&Pubkey::default()).unwrap().is_empty());
+}
+
+fn snapshot(timestamp: i64, volume_0: u128, fees_1: u64, reserve_1: u64) -> StatsSnapshot {
+    StatsSnapshot {
+        lp_mint: lp_mint(),
+        slot: timestamp as u64,
+        timestamp,
+        stats: SwapCumulativeStats {
+            token_0: SwapCumulativeTokenStats {
+                total_input_volume: volume_0,
+                ..Default::default()
+            },
+            token_1: SwapCumulativeTokenStats {
+                total_output_volume: volume_0 / 2,
+                total_trade_fees: fees_1,
+                ..Default::default()
+            },
+            total_lp_minted: u128::MAX,
+            total_lp_redeemed: 0,
+        },
+        reserve_0: 1_000,
+        reserve_1,
+        curve: SwapCurve::constant_product(),
+    }
+}
+
+#[test]
+fn test_candles() {
+    let snapshots = vec![
+        snapshot(3_550, 100, 10, 2_000),
+        snapshot(3_600, 200, 20, 3_000),
+        snapshot(3_700, 400, 30, 1_000),
+        snapshot(7_199, 400, 30, 2_500),
+        snapshot(10_800, 1_000, 50, 4_000),
+    ];
+
+    let store = Store::open_in_memory().unwrap();
+    for snapshot in &snapshots {
+        assert!(store.insert_snapshot(snapshot).unwrap());
+    }
+    assert!(!store.insert_snapshot(&snapshots[0]).unwrap());
+    assert_eq!(store.snapshots(&lp_mint()).unwrap(), snapshots);
+
+    let candles = store.candles(&lp_mint(), 3_600).unwrap();
+    assert_eq!(candles.len(), 3);
+
+    // the first snapshot only contributes its price
+    assert_eq!(candles[0].start_ts, 0);
+    assert_eq!(candles[0].open, 2.0);
+    assert_eq!(candles[0].close, 2.0);
+    assert_eq!(candles[0].volume_0, 0);
+    assert_eq!(candles[0].trade_fees_1, 0);
+
+    assert_eq!(candles[1].start_ts, 3_600);
+    assert_eq!(candles[1].open, 3.0);
+    assert_eq!(candles[1].high, 3.0);
+    assert_eq!(candles[1].low, 1.0);
+    assert_eq!(candles[1].close, 2.5);
+    assert_eq!(candles[1].volume_0, 300);
+    assert_eq!(candles[1].volume_1, 150);
+    assert_eq!(candles[1].trade_fees_0, 0);
+    assert_eq!(candles[1].trade_fees_1, 20);
+
+    // empty intervals are skipped
+    assert_eq!(candles[2].start_ts, 10_800);
+    assert_eq!(candles[2].open, 4.0);
+    assert_eq!(candles[2].volume_0, 600);
+    assert_eq!(candles[2].volume_1, 300);
+    assert_eq!(candles[2].trade_fees_1, 20);
+}
+
+#[test]
+fn test_snapshot_price_follows_curve() {
+    let stable = StatsSnapshot {
+        curve: SwapCurve::stable_swap(100),
+        ..snapshot(3_600, 0, 0, 2_000)
+    };
+    let weighted = StatsSnapshot {
+        slot: 3_601,
+        curve: SwapCurve::weighted_product(8_000),
+        ..snapshot(3_601, 0, 0, 1_000)
+    };
+    assert_eq!(snapshot(3_600, 0, 0, 2_000).price(), 2.0);
+    // much closer to 1:1 than the constant product price
+    assert!(stable.price() > 1.0 && stable.price() < 1.01);
+    // with equal reserves, the price is the ratio of the weights
+    assert_eq!(weighted.price(), 4.0);
+
+    let store = Store::open_in_memory().unwrap();
+    assert!(store.insert_snapshot(&stable).unwrap());
+    assert!(store.insert_snapshot(&weighted).unwrap());
+    assert_eq!(store.snapshots(&lp_mint()).unwrap(), vec![stable, weighted]);
+}
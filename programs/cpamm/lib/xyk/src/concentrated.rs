@@ -0,0 +1,585 @@
+//! Concentrated liquidity math, where liquidity is provided over price ranges
+//! bounded by ticks.
+//!
+//! The price of token 0 in terms of token 1 at tick `i` is `1.0001^i`.
+//! Prices are represented by their square roots in Q64.64 fixed point,
+//! so that the amounts of a range are linear in its liquidity `L`:
+//!
+//! - `amount_0 = L * (1 / sqrt(p_a) - 1 / sqrt(p_b))`
+//! - `amount_1 = L * (sqrt(p_b) - sqrt(p_a))`
+//!
+//! The tick math follows the Uniswap V3 implementation.
+
+use crate::KBPS_PER_WHOLE;
+use ::u128::mul_div_u64;
+use spl_math::uint::U256;
+
+/// The minimum tick, whose square root price is [MIN_SQRT_PRICE_X64].
+pub const MIN_TICK: i32 = -443_636;
+
+/// The maximum tick, whose square root price is [MAX_SQRT_PRICE_X64].
+pub const MAX_TICK: i32 = 443_636;
+
+/// Square root price of [MIN_TICK], Q64.64.
+pub const MIN_SQRT_PRICE_X64: u128 = 4_295_048_017;
+
+/// Square root price of [MAX_TICK], Q64.64.
+pub const MAX_SQRT_PRICE_X64: u128 = 79_226_673_515_401_279_992_447_579_062;
+
+/// `2^32 / log2(sqrt(1.0001))`, used to convert log2 prices into ticks.
+const LOG_B_2_X32: i128 = 59_543_866_431_248;
+
+/// Number of fractional bits of the log2 price computed by [sqrt_price_to_tick].
+const BIT_PRECISION: u32 = 14;
+
+/// Bound on the ticks under-estimated by [sqrt_price_to_tick], Q64.64.
+const LOG_B_P_ERR_MARGIN_LOWER_X64: i128 = 184_467_440_737_095_516;
+
+/// Bound on the ticks over-estimated by [sqrt_price_to_tick], Q64.64.
+const LOG_B_P_ERR_MARGIN_UPPER_X64: i128 = 15_793_534_762_490_258_745;
+
+/// `2^128 / sqrt(1.0001)^(2^i)` for each bit `i` of a tick, Q128.128.
+const TICK_RATIOS: [u128; 19] = [
+    0xfff97272373d413259a46990580e213a,
+    0xfff2e50f5f656932ef12357cf3c7fdcc,
+    0xffe5caca7e10e4e61c3624eaa0941cd0,
+    0xffcb9843d60f6159c9db58835c926644,
+    0xff973b41fa98c081472e6896dfb254c0,
+    0xff2ea16466c96a3843ec78b326b52861,
+    0xfe5dee046a99a2a811c461f1969c3053,
+    0xfcbe86c7900a88aedcffc83b479aa3a4,
+    0xf987a7253ac413176f2b074cf7815e54,
+    0xf3392b0822b70005940c7a398e4b70f3,
+    0xe7159475a2c29b7443b29c7fa6e889d9,
+    0xd097f3bdfd2022b8845ad8f792aa5825,
+    0xa9f746462d870fdf8a65dc1f90e061e5,
+    0x70d869a156d2a1b890bb3df62baf32f7,
+    0x31be135f97d08fd981231505542fcfa6,
+    0x9aa508b5b7a84e1c677de54f3e99bc9,
+    0x5d6af8dedb81196699c329225ee604,
+    0x2216e584f5fa1ea926041bedfe98,
+    0x48a170391f7dc42444e8fa2,
+];
+
+/// The result of a single step of a swap within a range of constant liquidity.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SwapStep {
+    /// Square root price after the step, Q64.64.
+    pub sqrt_price_x64: u128,
+    /// Amount of input tokens swapped, excluding the fee.
+    pub amount_in: u64,
+    /// Amount of output tokens swapped.
+    pub amount_out: u64,
+    /// Trade fee taken from the input tokens.
+    pub fee_amount: u64,
+}
+
+fn to_u64(value: U256) -> Option<u64> {
+    if value > U256::from(u64::MAX) {
+        None
+    } else {
+        Some(value.as_u64())
+    }
+}
+
+fn to_u128(value: U256) -> Option<u128> {
+    if value > U256::from(u128::MAX) {
+        None
+    } else {
+        Some(value.as_u128())
+    }
+}
+
+fn div_ceil(numerator: U256, denominator: U256) -> Option<U256> {
+    let quotient = numerator.checked_div(denominator)?;
+    if quotient.checked_mul(denominator)? == numerator {
+        Some(quotient)
+    } else {
+        quotient.checked_add(U256::one())
+    }
+}
+
+fn sorted(sqrt_price_a_x64: u128, sqrt_price_b_x64: u128) -> (u128, u128) {
+    if sqrt_price_a_x64 > sqrt_price_b_x64 {
+        (sqrt_price_b_x64, sqrt_price_a_x64)
+    } else {
+        (sqrt_price_a_x64, sqrt_price_b_x64)
+    }
+}
+
+/// The lowest tick usable by a pool with the given tick spacing.
+/// A tick spacing of zero is treated as one.
+pub fn min_tick(tick_spacing: u16) -> i32 {
+    let tick_spacing = i32::from(tick_spacing.max(1));
+    MIN_TICK / tick_spacing * tick_spacing
+}
+
+/// The highest tick usable by a pool with the given tick spacing.
+/// A tick spacing of zero is treated as one.
+pub fn max_tick(tick_spacing: u16) -> i32 {
+    let tick_spacing = i32::from(tick_spacing.max(1));
+    MAX_TICK / tick_spacing * tick_spacing
+}
+
+/// Computes the square root price at a tick, Q64.64.
+/// Returns [None] if the tick is not within [MIN_TICK] and [MAX_TICK].
+pub fn tick_to_sqrt_price(tick: i32) -> Option<u128> {
+    if !(MIN_TICK..=MAX_TICK).contains(&tick) {
+        return None;
+    }
+    let abs_tick = tick.unsigned_abs();
+    let mut ratio = if abs_tick & 1 != 0 {
+        U256::from(0xfffcb933bd6fad37aa2d162d1a594001_u128)
+    } else {
+        U256::one() << 128
+    };
+    for (i, tick_ratio) in TICK_RATIOS.iter().enumerate() {
+        if abs_tick & (2 << i) != 0 {
+            ratio = ratio.checked_mul(U256::from(*tick_ratio))? >> 128;
+        }
+    }
+    if tick > 0 {
+        ratio = U256::MAX / ratio;
+    }
+
+    // Q128.128 to Q64.64, rounding up
+    let remainder = ratio & U256::from(u64::MAX);
+    let sqrt_price = ratio >> 64;
+    if remainder.is_zero() {
+        to_u128(sqrt_price)
+    } else {
+        to_u128(sqrt_price.checked_add(U256::one())?)
+    }
+}
+
+/// Computes the greatest tick whose square root price is at most `sqrt_price_x64`.
+/// Returns [None] if the price is not within [MIN_SQRT_PRICE_X64] and [MAX_SQRT_PRICE_X64].
+pub fn sqrt_price_to_tick(sqrt_price_x64: u128) -> Option<i32> {
+    if !(MIN_SQRT_PRICE_X64..=MAX_SQRT_PRICE_X64).contains(&sqrt_price_x64) {
+        return None;
+    }
+
+    // integer part of the log2 of the price, Q32.32
+    let msb = 127 - sqrt_price_x64.leading_zeros();
+    let log2p_integer_x32 = (i128::from(msb) - 64) << 32;
+
+    // fractional part of the log2, computed by repeated squaring of the price
+    // normalized to [1, 2)
+    let mut r = if msb >= 64 {
+        sqrt_price_x64 >> (msb - 63)
+    } else {
+        sqrt_price_x64 << (63 - msb)
+    };
+    let mut bit: i128 = 1 << 63;
+    let mut log2p_fraction_x64: i128 = 0;
+    for _ in 0..BIT_PRECISION {
+        r = r.checked_mul(r)?;
+        let is_r_more_than_two = (r >> 127) as u32;
+        r >>= 63 + is_r_more_than_two;
+        log2p_fraction_x64 += bit * i128::from(is_r_more_than_two);
+        bit >>= 1;
+    }
+    let log2p_x32 = log2p_integer_x32 + (log2p_fraction_x64 >> 32);
+
+    // change the base of the log to sqrt(1.0001). The truncated log2 is
+    // within one tick of the actual tick, which is resolved by checking
+    // the price of the higher candidate.
+    let logbp_x64 = log2p_x32.checked_mul(LOG_B_2_X32)?;
+    let tick_low = ((logbp_x64 - LOG_B_P_ERR_MARGIN_LOWER_X64) >> 64) as i32;
+    let tick_high = ((logbp_x64 + LOG_B_P_ERR_MARGIN_UPPER_X64) >> 64) as i32;
+    if tick_low == tick_high {
+        return Some(tick_low);
+    }
+    match tick_to_sqrt_price(tick_high) {
+        Some(sqrt_price_high) if sqrt_price_high <= sqrt_price_x64 => Some(tick_high),
+        _ => Some(tick_low),
+    }
+}
+
+/// Computes the amount of token 0 in `liquidity` between two square root prices.
+/// Returns [None] if the amount does not fit into a [u64].
+pub fn amount_0_delta(
+    sqrt_price_a_x64: u128,
+    sqrt_price_b_x64: u128,
+    liquidity: u128,
+    round_up: bool,
+) -> Option<u64> {
+    let (lower, upper) = sorted(sqrt_price_a_x64, sqrt_price_b_x64);
+    if lower == 0 {
+        return None;
+    }
+    // L / sqrt(p_lower) - L / sqrt(p_upper), rounding each term away from the result
+    let numerator = U256::from(liquidity) << 64;
+    let amount = if round_up {
+        div_ceil(numerator, lower.into())?.checked_sub(numerator / U256::from(upper))?
+    } else {
+        (numerator / U256::from(lower)).saturating_sub(div_ceil(numerator, upper.into())?)
+    };
+    to_u64(amount)
+}
+
+/// Computes the amount of token 1 in `liquidity` between two square root prices.
+/// Returns [None] if the amount does not fit into a [u64].
+pub fn amount_1_delta(
+    sqrt_price_a_x64: u128,
+    sqrt_price_b_x64: u128,
+    liquidity: u128,
+    round_up: bool,
+) -> Option<u64> {
+    let (lower, upper) = sorted(sqrt_price_a_x64, sqrt_price_b_x64);
+    let product = U256::from(liquidity).checked_mul(U256::from(upper - lower))?;
+    let amount = if round_up {
+        div_ceil(product, U256::one() << 64)?
+    } else {
+        product >> 64
+    };
+    to_u64(amount)
+}
+
+/// Computes the amounts of token 0 and token 1 in `liquidity` over the range
+/// between `sqrt_price_lower_x64` and `sqrt_price_upper_x64`, at the current price.
+pub fn amounts_for_liquidity(
+    sqrt_price_x64: u128,
+    sqrt_price_lower_x64: u128,
+    sqrt_price_upper_x64: u128,
+    liquidity: u128,
+    round_up: bool,
+) -> Option<(u64, u64)> {
+    if sqrt_price_x64 <= sqrt_price_lower_x64 {
+        let amount_0 = amount_0_delta(
+            sqrt_price_lower_x64,
+            sqrt_price_upper_x64,
+            liquidity,
+            round_up,
+        )?;
+        Some((amount_0, 0))
+    } else if sqrt_price_x64 >= sqrt_price_upper_x64 {
+        let amount_1 = amount_1_delta(
+            sqrt_price_lower_x64,
+            sqrt_price_upper_x64,
+            liquidity,
+            round_up,
+        )?;
+        Some((0, amount_1))
+    } else {
+        let amount_0 = amount_0_delta(sqrt_price_x64, sqrt_price_upper_x64, liquidity, round_up)?;
+        let amount_1 = amount_1_delta(sqrt_price_lower_x64, sqrt_price_x64, liquidity, round_up)?;
+        Some((amount_0, amount_1))
+    }
+}
+
+/// Computes the maximum liquidity over the range between `sqrt_price_lower_x64` and
+/// `sqrt_price_upper_x64` which may be provided by `amount_0` and `amount_1` at the current price.
+pub fn liquidity_for_amounts(
+    sqrt_price_x64: u128,
+    sqrt_price_lower_x64: u128,
+    sqrt_price_upper_x64: u128,
+    amount_0: u64,
+    amount_1: u64,
+) -> Option<u128> {
+    let liquidity_0 = |lower: u128, upper: u128| -> Option<u128> {
+        // amount_0 * sqrt(p_lower) * sqrt(p_upper) / (sqrt(p_upper) - sqrt(p_lower))
+        let product = U256::from(lower).checked_mul(upper.into())? >> 64;
+        let numerator = product.checked_mul(amount_0.into())?;
+        to_u128(numerator.checked_div(U256::from(upper.checked_sub(lower)?))?)
+    };
+    let liquidity_1 = |lower: u128, upper: u128| -> Option<u128> {
+        // amount_1 / (sqrt(p_upper) - sqrt(p_lower))
+        let numerator = U256::from(amount_1) << 64;
+        to_u128(numerator.checked_div(U256::from(upper.checked_sub(lower)?))?)
+    };
+    if sqrt_price_x64 <= sqrt_price_lower_x64 {
+        liquidity_0(sqrt_price_lower_x64, sqrt_price_upper_x64)
+    } else if sqrt_price_x64 >= sqrt_price_upper_x64 {
+        liquidity_1(sqrt_price_lower_x64, sqrt_price_upper_x64)
+    } else {
+        let liquidity_0 = liquidity_0(sqrt_price_x64, sqrt_price_upper_x64)?;
+        let liquidity_1 = liquidity_1(sqrt_price_lower_x64, sqrt_price_x64)?;
+        Some(liquidity_0.min(liquidity_1))
+    }
+}
+
+/// Computes the square root price after `amount_in` is swapped into `liquidity`.
+///
+/// The price is rounded so that it moves less than the exact result,
+/// which favors the pool.
+pub fn next_sqrt_price_from_input(
+    sqrt_price_x64: u128,
+    liquidity: u128,
+    amount_in: u64,
+    zero_for_one: bool,
+) -> Option<u128> {
+    if amount_in == 0 {
+        return Some(sqrt_price_x64);
+    }
+    if liquidity == 0 || sqrt_price_x64 == 0 {
+        return None;
+    }
+    let liquidity_x64 = U256::from(liquidity) << 64;
+    if zero_for_one {
+        // L / (L / sqrt(p) + amount), rounding up
+        let denominator =
+            (liquidity_x64 / U256::from(sqrt_price_x64)).checked_add(U256::from(amount_in))?;
+        to_u128(div_ceil(liquidity_x64, denominator)?)
+    } else {
+        // sqrt(p) + amount / L, rounding down
+        let delta = to_u128((U256::from(amount_in) << 64) / U256::from(liquidity))?;
+        sqrt_price_x64.checked_add(delta)
+    }
+}
+
+/// Computes a step of a swap of up to `amount_remaining` from `sqrt_price_x64`
+/// towards `sqrt_price_target_x64`, within a range of constant `liquidity`.
+///
+/// The swap is from token 0 to token 1 if the target price is below the current price.
+/// A trade fee of `fee_kbps` is taken from the input.
+pub fn compute_swap_step(
+    sqrt_price_x64: u128,
+    sqrt_price_target_x64: u128,
+    liquidity: u128,
+    amount_remaining: u64,
+    fee_kbps: u64,
+) -> Option<SwapStep> {
+    let zero_for_one = sqrt_price_target_x64 <= sqrt_price_x64;
+    let fee_complement_kbps = KBPS_PER_WHOLE.checked_sub(fee_kbps)?;
+    let amount_remaining_less_fee =
+        mul_div_u64(amount_remaining, fee_complement_kbps, KBPS_PER_WHOLE)?;
+
+    let amount_in_to_target = if zero_for_one {
+        amount_0_delta(sqrt_price_target_x64, sqrt_price_x64, liquidity, true)
+    } else {
+        amount_1_delta(sqrt_price_x64, sqrt_price_target_x64, liquidity, true)
+    };
+
+    let (sqrt_price_next_x64, amount_in) = match amount_in_to_target {
+        Some(amount_in) if amount_in <= amount_remaining_less_fee => {
+            (sqrt_price_target_x64, amount_in)
+        }
+        // the target is out of reach, so the entire remaining amount is swapped
+        _ => (
+            next_sqrt_price_from_input(
+                sqrt_price_x64,
+                liquidity,
+                amount_remaining_less_fee,
+                zero_for_one,
+            )?,
+            amount_remaining_less_fee,
+        ),
+    };
+
+    let amount_out = if zero_for_one {
+        amount_1_delta(sqrt_price_next_x64, sqrt_price_x64, liquidity, false)?
+    } else {
+        amount_0_delta(sqrt_price_x64, sqrt_price_next_x64, liquidity, false)?
+    };
+
+    let fee_amount = if sqrt_price_next_x64 == sqrt_price_target_x64 {
+        if fee_complement_kbps == 0 {
+            return None;
+        }
+        let fee = div_ceil(
+            U256::from(amount_in).checked_mul(fee_kbps.into())?,
+            fee_complement_kbps.into(),
+        )?;
+        to_u64(fee)?.min(amount_remaining.checked_sub(amount_in)?)
+    } else {
+        amount_remaining.checked_sub(amount_in)?
+    };
+
+    Some(SwapStep {
+        sqrt_price_x64: sqrt_price_next_x64,
+        amount_in,
+        amount_out,
+        fee_amount,
+    })
+}
+
+/// Computes the fees earned per unit of `liquidity` from `fee_amount`, Q64.64.
+pub fn fee_growth(fee_amount: u64, liquidity: u128) -> Option<u128> {
+    if liquidity == 0 {
+        return Some(0);
+    }
+    to_u128((U256::from(fee_amount) << 64) / U256::from(liquidity))
+}
+
+/// Computes the fees earned by `liquidity` over a `fee_growth_delta_x64`.
+pub fn fees_earned(fee_growth_delta_x64: u128, liquidity: u128) -> Option<u64> {
+    to_u64(U256::from(fee_growth_delta_x64).checked_mul(liquidity.into())? >> 64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    const ONE_X64: u128 = 1 << 64;
+
+    #[test]
+    fn tick_to_sqrt_price_matches_float() {
+        for tick in [
+            MIN_TICK, -300_000, -50_000, -1_000, -1, 0, 1, 1_000, 50_000, 300_000, MAX_TICK,
+        ] {
+            let expected = 1.0001_f64.powf(f64::from(tick) / 2.0) * ONE_X64 as f64;
+            let actual = tick_to_sqrt_price(tick).unwrap() as f64;
+            assert!(
+                ((actual - expected) / expected).abs() < 1e-9,
+                "tick {}: {} != {}",
+                tick,
+                actual,
+                expected
+            );
+        }
+        assert_eq!(tick_to_sqrt_price(0), Some(ONE_X64));
+        assert_eq!(tick_to_sqrt_price(MIN_TICK), Some(MIN_SQRT_PRICE_X64));
+        assert_eq!(tick_to_sqrt_price(MAX_TICK), Some(MAX_SQRT_PRICE_X64));
+        assert_eq!(tick_to_sqrt_price(MIN_TICK - 1), None);
+        assert_eq!(tick_to_sqrt_price(MAX_TICK + 1), None);
+    }
+
+    #[test]
+    fn sqrt_price_to_tick_bounds() {
+        assert_eq!(sqrt_price_to_tick(ONE_X64), Some(0));
+        assert_eq!(sqrt_price_to_tick(ONE_X64 - 1), Some(-1));
+        assert_eq!(sqrt_price_to_tick(MIN_SQRT_PRICE_X64), Some(MIN_TICK));
+        assert_eq!(sqrt_price_to_tick(MAX_SQRT_PRICE_X64), Some(MAX_TICK));
+        assert_eq!(sqrt_price_to_tick(MIN_SQRT_PRICE_X64 - 1), None);
+        assert_eq!(sqrt_price_to_tick(MAX_SQRT_PRICE_X64 + 1), None);
+    }
+
+    #[test]
+    fn usable_ticks() {
+        assert_eq!(min_tick(1), MIN_TICK);
+        assert_eq!(max_tick(1), MAX_TICK);
+        assert_eq!(min_tick(64), -443_584);
+        assert_eq!(max_tick(64), 443_584);
+    }
+
+    #[test]
+    fn amounts_of_range() {
+        // a range of [1/4, 4] around a price of 1
+        let lower = ONE_X64 / 2;
+        let upper = ONE_X64 * 2;
+        let liquidity = 1_000_000;
+        assert_eq!(
+            amounts_for_liquidity(ONE_X64, lower, upper, liquidity, false),
+            Some((500_000, 500_000))
+        );
+        assert_eq!(
+            amounts_for_liquidity(lower, lower, upper, liquidity, false),
+            Some((1_500_000, 0))
+        );
+        assert_eq!(
+            amounts_for_liquidity(upper, lower, upper, liquidity, false),
+            Some((0, 1_500_000))
+        );
+        assert_eq!(
+            liquidity_for_amounts(ONE_X64, lower, upper, 500_000, 500_000),
+            Some(liquidity)
+        );
+    }
+
+    #[test]
+    fn swap_step_to_target() {
+        let liquidity = 1_000_000_000;
+        let target = tick_to_sqrt_price(-100).unwrap();
+        let step = compute_swap_step(ONE_X64, target, liquidity, u64::MAX / 2, 30_000).unwrap();
+        assert_eq!(step.sqrt_price_x64, target);
+        assert_eq!(
+            step.amount_in,
+            amount_0_delta(target, ONE_X64, liquidity, true).unwrap()
+        );
+        assert_eq!(step.fee_amount, (step.amount_in * 3 + 996) / 997);
+
+        // not enough to reach the target
+        let step = compute_swap_step(ONE_X64, target, liquidity, 1_000, 30_000).unwrap();
+        assert!(step.sqrt_price_x64 > target);
+        assert_eq!(step.amount_in + step.fee_amount, 1_000);
+        assert_eq!(step.fee_amount, 3);
+    }
+
+    proptest! {
+        #[test]
+        fn tick_to_sqrt_price_roundtrip(tick in MIN_TICK..=MAX_TICK) {
+            let sqrt_price = tick_to_sqrt_price(tick).unwrap();
+            prop_assert_eq!(sqrt_price_to_tick(sqrt_price), Some(tick));
+            if tick < MAX_TICK {
+                prop_assert!(sqrt_price < tick_to_sqrt_price(tick + 1).unwrap());
+            }
+        }
+
+        #[test]
+        fn sqrt_price_to_tick_is_floor(sqrt_price in MIN_SQRT_PRICE_X64..=MAX_SQRT_PRICE_X64) {
+            let tick = sqrt_price_to_tick(sqrt_price).unwrap();
+            prop_assert!(tick_to_sqrt_price(tick).unwrap() <= sqrt_price);
+            if tick < MAX_TICK {
+                prop_assert!(sqrt_price < tick_to_sqrt_price(tick + 1).unwrap());
+            }
+        }
+
+        #[test]
+        fn amounts_round_in_favor_of_pool(
+            tick_lower in -100_000..0,
+            width in 1..100_000,
+            tick in -100_000..100_000,
+            liquidity in 1..1_u128 << 40,
+        ) {
+            let lower = tick_to_sqrt_price(tick_lower).unwrap();
+            let upper = tick_to_sqrt_price(tick_lower + width).unwrap();
+            let sqrt_price = tick_to_sqrt_price(tick).unwrap();
+            let (up_0, up_1) = amounts_for_liquidity(sqrt_price, lower, upper, liquidity, true).unwrap();
+            let (down_0, down_1) = amounts_for_liquidity(sqrt_price, lower, upper, liquidity, false).unwrap();
+            prop_assert!(up_0 >= down_0 && up_1 >= down_1);
+            prop_assert!(up_0 - down_0 <= 2 && up_1 - down_1 <= 1);
+
+            // the liquidity provided by a pair of amounts never costs more than those amounts
+            let provided = liquidity_for_amounts(sqrt_price, lower, upper, down_0, down_1).unwrap();
+            let (cost_0, cost_1) = amounts_for_liquidity(sqrt_price, lower, upper, provided, false).unwrap();
+            prop_assert!(cost_0 <= down_0 && cost_1 <= down_1);
+        }
+
+        #[test]
+        fn swap_step_within_bounds(
+            tick in -100_000..100_000,
+            target_delta in -10_000..10_000,
+            liquidity in 1..1_u128 << 40,
+            amount_remaining in 0..u64::MAX / 2,
+            fee_kbps in 0..100_000_u64,
+        ) {
+            let sqrt_price = tick_to_sqrt_price(tick).unwrap();
+            let target = tick_to_sqrt_price(tick + target_delta).unwrap();
+            let step = compute_swap_step(sqrt_price, target, liquidity, amount_remaining, fee_kbps).unwrap();
+            prop_assert!(step.amount_in + step.fee_amount <= amount_remaining);
+
+            // the price moves towards the target, but not past it
+            let zero_for_one = target <= sqrt_price;
+            if zero_for_one {
+                prop_assert!(target <= step.sqrt_price_x64 && step.sqrt_price_x64 <= sqrt_price);
+            } else {
+                prop_assert!(sqrt_price <= step.sqrt_price_x64 && step.sqrt_price_x64 <= target);
+            }
+
+            // the input pays for the price movement, and the output is no more than it is worth
+            let (required_in, max_out) = if zero_for_one {
+                (
+                    amount_0_delta(step.sqrt_price_x64, sqrt_price, liquidity, true).unwrap(),
+                    amount_1_delta(step.sqrt_price_x64, sqrt_price, liquidity, false).unwrap(),
+                )
+            } else {
+                (
+                    amount_1_delta(sqrt_price, step.sqrt_price_x64, liquidity, true).unwrap(),
+                    amount_0_delta(sqrt_price, step.sqrt_price_x64, liquidity, false).unwrap(),
+                )
+            };
+            prop_assert!(step.amount_in + 2 >= required_in);
+            prop_assert!(step.amount_out <= max_out);
+        }
+
+        #[test]
+        fn fees_earned_at_most_fee(fee_amount in 0..u64::MAX, liquidity in 1..u128::MAX) {
+            let growth = fee_growth(fee_amount, liquidity);
+            prop_assume!(growth.is_some());
+            let growth = growth.unwrap();
+            prop_assert!(fees_earned(growth, liquidity).unwrap() <= fee_amount);
+        }
+    }
+}
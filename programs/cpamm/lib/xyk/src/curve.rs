@@ -0,0 +1,85 @@
+//! Swap curves.
+
+use spl_math::precise_number::PreciseNumber;
+
+use crate::{RoundDirection, SwapResult, TradingTokenResult};
+
+/// An invariant that a swap pool can be built on.
+pub trait Curve {
+    /// Calculates the amounts swapped when `source_amount` is swapped into the pool.
+    /// The result does not include any fees.
+    fn swap(
+        &self,
+        source_amount: u64,
+        swap_source_amount: u64,
+        swap_destination_amount: u64,
+    ) -> Option<SwapResult>;
+
+    /// Get the amount of trading tokens for the given amount of pool tokens,
+    /// provided the total trading tokens and supply of pool tokens.
+    ///
+    /// Deposits and withdrawals of both tokens are proportional to the reserves
+    /// for all invariants, so this defaults to [crate::pool_tokens_to_trading_tokens].
+    fn pool_tokens_to_trading_tokens(
+        &self,
+        pool_tokens: u64,
+        pool_token_supply: u64,
+        swap_token_a_amount: u64,
+        swap_token_b_amount: u64,
+        round_direction: RoundDirection,
+    ) -> Option<TradingTokenResult> {
+        crate::pool_tokens_to_trading_tokens(
+            pool_tokens,
+            pool_token_supply,
+            swap_token_a_amount,
+            swap_token_b_amount,
+            round_direction,
+        )
+    }
+
+    /// Initial amount of pool tokens for a new swap with the given reserves.
+    fn calculate_initial_swap_pool_amount(&self, amount_a: u64, amount_b: u64) -> Option<u64>;
+
+    /// Calculates the total normalized value of the curve given the liquidity
+    /// parameters. A swap should never decrease this value.
+    fn normalized_value(
+        &self,
+        swap_token_a_amount: u64,
+        swap_token_b_amount: u64,
+    ) -> Option<PreciseNumber>;
+
+    /// Computes the marginal price of token A in terms of token B at the given
+    /// reserves, scaled by 10^[crate::PRICE_SCALE].
+    fn spot_price(&self, swap_token_a_amount: u64, swap_token_b_amount: u64) -> Option<u128>;
+}
+
+/// The constant product invariant, `x * y = k`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ConstantProduct;
+
+impl Curve for ConstantProduct {
+    fn swap(
+        &self,
+        source_amount: u64,
+        swap_source_amount: u64,
+        swap_destination_amount: u64,
+    ) -> Option<SwapResult> {
+        crate::swap(source_amount, swap_source_amount, swap_destination_amount)
+    }
+
+    fn calculate_initial_swap_pool_amount(&self, amount_a: u64, amount_b: u64) -> Option<u64> {
+        crate::calculate_initial_swap_pool_amount(amount_a, amount_b)
+    }
+
+    fn normalized_value(
+        &self,
+        swap_token_a_amount: u64,
+        swap_token_b_amount: u64,
+    ) -> Option<PreciseNumber> {
+        crate::normalized_value(swap_token_a_amount, swap_token_b_amount)
+    }
+
+    fn spot_price(&self, swap_token_a_amount: u64, swap_token_b_amount: u64) -> Option<u128> {
+        crate::spot_price(swap_token_a_amount, swap_token_b_amount)
+    }
+}
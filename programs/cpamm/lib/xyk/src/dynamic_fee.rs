@@ -0,0 +1,142 @@
+//! Dynamic trade fees, which rise with short-term price volatility.
+//!
+//! Volatility is measured as the deviation of the spot price from a moving
+//! average of recent spot prices. Prices are scaled by 10^[crate::PRICE_SCALE],
+//! as returned by [crate::Curve::spot_price].
+
+use crate::KBPS_PER_WHOLE;
+use ::u128::mul_div_u64;
+use num_traits::ToPrimitive;
+use spl_math::uint::U256;
+
+fn abs_diff(a: u128, b: u128) -> u128 {
+    if a > b {
+        a - b
+    } else {
+        b - a
+    }
+}
+
+/// Moves a moving average of prices towards `price`, given that `price` has held
+/// for the last `time_elapsed` seconds.
+///
+/// The average catches up to the price entirely after `period` seconds.
+/// An `ema` of zero is uninitialized, and starts at `price`.
+pub fn update_price_ema(ema: u128, price: u128, time_elapsed: u64, period: u64) -> Option<u128> {
+    if ema == 0 || time_elapsed >= period {
+        return Some(price);
+    }
+    let delta = U256::from(abs_diff(price, ema))
+        .checked_mul(time_elapsed.into())?
+        .checked_div(period.into())?
+        .as_u128();
+    if price >= ema {
+        ema.checked_add(delta)
+    } else {
+        ema.checked_sub(delta)
+    }
+}
+
+/// Computes the deviation of `price` from the moving average `ema`, in kbps.
+/// Deviations above 100% are capped at [KBPS_PER_WHOLE].
+pub fn price_deviation_kbps(ema: u128, price: u128) -> Option<u64> {
+    if ema == 0 {
+        return Some(0);
+    }
+    let deviation = U256::from(abs_diff(price, ema))
+        .checked_mul(KBPS_PER_WHOLE.into())?
+        .checked_div(ema.into())?;
+    if deviation > U256::from(KBPS_PER_WHOLE) {
+        return Some(KBPS_PER_WHOLE);
+    }
+    deviation.as_u128().to_u64()
+}
+
+/// Computes the trade fee after a price deviation of `deviation_kbps`.
+///
+/// The fee rises from `base_fee_kbps` by `volatility_factor_kbps` of the deviation,
+/// up to `max_fee_kbps`. The fee is never lower than `base_fee_kbps`.
+pub fn dynamic_trade_fee_kbps(
+    base_fee_kbps: u64,
+    max_fee_kbps: u64,
+    volatility_factor_kbps: u64,
+    deviation_kbps: u64,
+) -> Option<u64> {
+    let increase = mul_div_u64(deviation_kbps, volatility_factor_kbps, KBPS_PER_WHOLE)?;
+    let fee = base_fee_kbps.checked_add(increase)?;
+    Some(fee.min(max_fee_kbps).max(base_fee_kbps))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    const PRICE: u128 = 1_000_000_000_000_000_000;
+
+    #[test]
+    fn update_price_ema_moves_linearly() {
+        assert_eq!(update_price_ema(0, PRICE, 0, 600), Some(PRICE));
+        assert_eq!(
+            update_price_ema(PRICE, 2 * PRICE, 300, 600),
+            Some(PRICE * 3 / 2)
+        );
+        assert_eq!(
+            update_price_ema(2 * PRICE, PRICE, 150, 600),
+            Some(PRICE * 7 / 4)
+        );
+        assert_eq!(
+            update_price_ema(PRICE, 2 * PRICE, 601, 600),
+            Some(2 * PRICE)
+        );
+    }
+
+    #[test]
+    fn dynamic_trade_fee_bounds() {
+        // 10% deviation, half of which is added to a 0.3% fee
+        let deviation = price_deviation_kbps(PRICE, PRICE * 11 / 10).unwrap();
+        assert_eq!(deviation, 1_000_000);
+        assert_eq!(
+            dynamic_trade_fee_kbps(30_000, 1_000_000, 5_000_000, deviation),
+            Some(530_000)
+        );
+        assert_eq!(
+            dynamic_trade_fee_kbps(30_000, 100_000, 5_000_000, deviation),
+            Some(100_000)
+        );
+        // a maximum below the base fee never lowers the fee
+        assert_eq!(
+            dynamic_trade_fee_kbps(30_000, 10_000, 5_000_000, deviation),
+            Some(30_000)
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn update_price_ema_between_ema_and_price(
+            ema in 1..u128::MAX,
+            price in 1..u128::MAX,
+            time_elapsed in 0..u64::MAX,
+            period in 1..u64::MAX,
+        ) {
+            let next = update_price_ema(ema, price, time_elapsed, period).unwrap();
+            prop_assert!(next >= ema.min(price));
+            prop_assert!(next <= ema.max(price));
+        }
+
+        #[test]
+        fn dynamic_trade_fee_within_bounds(
+            ema in 1..u128::MAX,
+            price in 0..u128::MAX,
+            base_fee_kbps in 0..KBPS_PER_WHOLE,
+            max_fee_kbps in 0..KBPS_PER_WHOLE,
+            volatility_factor_kbps in 0..u64::from(u32::MAX),
+        ) {
+            let deviation = price_deviation_kbps(ema, price).unwrap();
+            prop_assert!(deviation <= KBPS_PER_WHOLE);
+            let fee = dynamic_trade_fee_kbps(base_fee_kbps, max_fee_kbps, volatility_factor_kbps, deviation).unwrap();
+            prop_assert!(fee >= base_fee_kbps);
+            prop_assert!(fee <= base_fee_kbps.max(max_fee_kbps));
+        }
+    }
+}
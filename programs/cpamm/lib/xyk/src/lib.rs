@@ -1,12 +1,28 @@
 //! The constant product invariant calculator.
+//!
+//! Other invariants are available via the [Curve] trait.
 
 use ::u128::mul_div_u64;
 use num_traits::ToPrimitive;
 use spl_math::checked_ceil_div::CheckedCeilDiv;
-
+use spl_math::precise_number::PreciseNumber;
+use spl_math::uint::U256;
+
+mod concentrated;
+mod curve;
+mod dynamic_fee;
+mod quote;
+mod stable_swap;
 mod types;
+mod weighted;
 
+pub use concentrated::*;
+pub use curve::*;
+pub use dynamic_fee::*;
+pub use quote::*;
+pub use stable_swap::*;
 pub use types::*;
+pub use weighted::*;
 
 /// Initial amount of pool tokens for swap contract, calculated as the geometric mean of the two
 /// initial token liquidity amounts.
@@ -18,6 +34,22 @@ pub fn calculate_initial_swap_pool_amount(amount_a: u64, amount_b: u64) -> Optio
 // unless the pool dissolves
 pub const MINIMUM_LIQUIDITY: u64 = 1_000;
 
+/// Calculates the total normalized value of the curve given the liquidity
+/// parameters.
+///
+/// The constant product implementation for this function gives the square root of
+/// the Uniswap invariant.
+pub fn normalized_value(
+    swap_token_a_amount: u64,
+    swap_token_b_amount: u64,
+) -> Option<PreciseNumber> {
+    let swap_token_a_amount = PreciseNumber::new(swap_token_a_amount.into())?;
+    let swap_token_b_amount = PreciseNumber::new(swap_token_b_amount.into())?;
+    swap_token_a_amount
+        .checked_mul(&swap_token_b_amount)?
+        .sqrt()
+}
+
 /// Constant product swap ensures x * y = constant
 ///
 /// This is guaranteed to work for all values such that:
@@ -57,6 +89,69 @@ pub fn swap(
     })
 }
 
+/// Inverse of [swap]: computes the minimum amount of source tokens required
+/// to receive exactly `destination_amount` from a constant product swap.
+///
+/// The new invariant is always greater than or equal to the old invariant, and
+/// swapping one less source token would result in less than `destination_amount`.
+///
+/// This is guaranteed to work for all values such that:
+///  - 1 <= swap_source_amount * swap_destination_amount <= u128::MAX
+///  - 1 <= destination_amount < swap_destination_amount
+pub fn swap_exact_out(
+    destination_amount: u64,
+    swap_source_amount: u64,
+    swap_destination_amount: u64,
+) -> Option<SwapResult> {
+    // zero swap should not execute
+    if destination_amount == 0 {
+        return None;
+    }
+
+    let invariant = (swap_source_amount as u128).checked_mul(swap_destination_amount.into())?;
+
+    let new_swap_destination_amount = swap_destination_amount.checked_sub(destination_amount)?;
+    // the pool cannot be fully drained
+    if new_swap_destination_amount == 0 {
+        return None;
+    }
+
+    // round up so that the invariant never decreases
+    let (new_swap_source_amount, _) =
+        invariant.checked_ceil_div(new_swap_destination_amount.into())?;
+
+    let source_amount_swapped = new_swap_source_amount
+        .checked_sub(swap_source_amount.into())?
+        .to_u64()?;
+
+    Some(SwapResult {
+        source_amount_swapped,
+        destination_amount_swapped: destination_amount,
+    })
+}
+
+/// Computes the minimum amount that, after a fee of
+/// `floor(amount * fee_numerator / fee_denominator)` is taken out of it,
+/// leaves at least `amount_after_fee`.
+///
+/// Returns [None] if the fee is 100% or greater.
+pub fn amount_before_fee(
+    amount_after_fee: u64,
+    fee_numerator: u64,
+    fee_denominator: u64,
+) -> Option<u64> {
+    if amount_after_fee == 0 {
+        return Some(0);
+    }
+    let remaining_numerator = fee_denominator.checked_sub(fee_numerator)?;
+    if remaining_numerator == 0 {
+        return None;
+    }
+    // amount - floor(amount * f / d) = ceil(amount * (d - f) / d) >= amount_after_fee
+    // <=> amount * (d - f) > (amount_after_fee - 1) * d
+    mul_div_u64(amount_after_fee - 1, fee_denominator, remaining_numerator)?.checked_add(1)
+}
+
 /// Get the amount of trading tokens for the given amount of pool tokens,
 /// provided the total trading tokens and supply of pool tokens.
 ///
@@ -100,32 +195,80 @@ pub fn pool_tokens_to_trading_tokens(
     })
 }
 
+/// Get the amount of pool tokens for a deposit of only one of the trading tokens,
+/// provided the pool's reserves of that token and the supply of pool tokens.
+///
+/// Depositing into one side increases the square root of the invariant by
+/// a factor of `sqrt(1 + source_amount / swap_source_amount)`, so the pool tokens are:
+///
+/// `pool_token_supply * (sqrt(1 + source_amount / swap_source_amount) - 1)`
+///
+/// This is always rounded down, and does not include any fees.
+pub fn deposit_single_token_type(
+    source_amount: u64,
+    swap_source_amount: u64,
+    pool_token_supply: u64,
+) -> Option<u64> {
+    if swap_source_amount == 0 {
+        return None;
+    }
+
+    // new_pool_token_supply = floor(sqrt(pool_token_supply^2 * new_swap_source_amount / swap_source_amount))
+    let pool_token_supply_squared =
+        U256::from(pool_token_supply).checked_mul(U256::from(pool_token_supply))?;
+    let new_swap_source_amount = (swap_source_amount as u128).checked_add(source_amount.into())?;
+    let radicand = pool_token_supply_squared
+        .checked_mul(U256::from(new_swap_source_amount))?
+        .checked_div(U256::from(swap_source_amount))?;
+    if radicand > U256::from(u128::MAX) {
+        return None;
+    }
+    let new_pool_token_supply = spl_math::approximations::sqrt(radicand.as_u128())?;
+
+    new_pool_token_supply
+        .checked_sub(pool_token_supply.into())?
+        .to_u64()
+}
+
+/// Get the amount of one of the trading tokens for a withdrawal of only that token,
+/// provided the pool's reserves of that token and the supply of pool tokens.
+///
+/// This is the inverse of [deposit_single_token_type]:
+///
+/// `swap_destination_amount * (1 - ((pool_token_supply - pool_tokens) / pool_token_supply)^2)`
+///
+/// This is always rounded down, and does not include any fees.
+pub fn withdraw_single_token_type(
+    pool_tokens: u64,
+    swap_destination_amount: u64,
+    pool_token_supply: u64,
+) -> Option<u64> {
+    let new_pool_token_supply = pool_token_supply.checked_sub(pool_tokens)?;
+
+    // new_swap_destination_amount = ceil(swap_destination_amount * new_pool_token_supply^2 / pool_token_supply^2)
+    let numerator = U256::from(swap_destination_amount)
+        .checked_mul(U256::from(new_pool_token_supply))?
+        .checked_mul(U256::from(new_pool_token_supply))?;
+    let denominator = U256::from(pool_token_supply).checked_mul(U256::from(pool_token_supply))?;
+    let (new_swap_destination_amount, remainder) = numerator.div_mod(denominator);
+    let new_swap_destination_amount = if remainder.is_zero() {
+        new_swap_destination_amount
+    } else {
+        new_swap_destination_amount.checked_add(U256::one())?
+    };
+
+    // new_swap_destination_amount <= swap_destination_amount, so this fits in a u64
+    swap_destination_amount.checked_sub(new_swap_destination_amount.as_u64())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::types::RoundDirection;
     use proptest::prelude::*;
-    use spl_math::precise_number::PreciseNumber;
-    use spl_math::uint::U256;
 
     const MAX_SWAP_AMOUNT: u64 = u64::MAX >> 4;
 
-    /// Calculates the total normalized value of the curve given the liquidity
-    /// parameters.
-    ///
-    /// The constant product implementation for this function gives the square root of
-    /// the Uniswap invariant.
-    fn normalized_value(
-        swap_token_a_amount: u64,
-        swap_token_b_amount: u64,
-    ) -> Option<PreciseNumber> {
-        let swap_token_a_amount = PreciseNumber::new(swap_token_a_amount.into())?;
-        let swap_token_b_amount = PreciseNumber::new(swap_token_b_amount.into())?;
-        swap_token_a_amount
-            .checked_mul(&swap_token_b_amount)?
-            .sqrt()
-    }
-
     /// Test function checking that a swap never reduces the overall value of
     /// the pool.
     ///
@@ -169,6 +312,31 @@ mod tests {
         assert!(difference <= epsilon);
     }
 
+    /// Test function checking that an exact output swap never reduces the
+    /// overall value of the pool, and that the source amount is the minimum
+    /// required to receive the destination amount.
+    pub fn check_curve_value_from_swap_exact_out(
+        destination_token_amount: u64,
+        swap_source_amount: u64,
+        swap_destination_amount: u64,
+    ) {
+        let results = swap_exact_out(
+            destination_token_amount,
+            swap_source_amount,
+            swap_destination_amount,
+        )
+        .unwrap();
+        assert_eq!(results.destination_amount_swapped, destination_token_amount);
+
+        let invariant = (swap_source_amount as u128) * (swap_destination_amount as u128);
+        let new_swap_source_amount =
+            (swap_source_amount as u128) + (results.source_amount_swapped as u128);
+        let new_swap_destination_amount =
+            (swap_destination_amount - results.destination_amount_swapped) as u128;
+        assert!(new_swap_source_amount * new_swap_destination_amount >= invariant);
+        assert!((new_swap_source_amount - 1) * new_swap_destination_amount < invariant);
+    }
+
     /// Test function checking that a deposit never reduces the value of pool
     /// tokens.
     ///
@@ -218,6 +386,79 @@ mod tests {
         );
     }
 
+    /// Test function checking that a single-sided deposit never reduces the
+    /// value of pool tokens.
+    pub fn check_pool_value_from_deposit_single_token_type(
+        source_amount: u64,
+        swap_source_amount: u64,
+        pool_token_supply: u64,
+    ) {
+        let pool_token_amount =
+            deposit_single_token_type(source_amount, swap_source_amount, pool_token_supply)
+                .unwrap();
+        let new_swap_source_amount = swap_source_amount as u128 + source_amount as u128;
+        let new_pool_token_supply = pool_token_supply as u128 + pool_token_amount as u128;
+
+        // the other side of the pool is unchanged, so the following inequality must hold:
+        // sqrt(new_swap_source_amount * other) / new_pool_token_supply
+        //     >= sqrt(swap_source_amount * other) / pool_token_supply
+        // which reduces to:
+        // new_swap_source_amount * pool_token_supply^2 >= swap_source_amount * new_pool_token_supply^2
+        let pool_token_supply = U256::from(pool_token_supply);
+        let new_pool_token_supply = U256::from(new_pool_token_supply);
+        assert!(
+            U256::from(new_swap_source_amount) * pool_token_supply * pool_token_supply
+                >= U256::from(swap_source_amount) * new_pool_token_supply * new_pool_token_supply
+        );
+
+        // one more pool token would be too many
+        let too_many_pool_token_supply = new_pool_token_supply + 1;
+        assert!(
+            U256::from(new_swap_source_amount) * pool_token_supply * pool_token_supply
+                < U256::from(swap_source_amount)
+                    * too_many_pool_token_supply
+                    * too_many_pool_token_supply
+        );
+    }
+
+    /// Test function checking that a single-sided withdraw never reduces the
+    /// value of pool tokens.
+    pub fn check_pool_value_from_withdraw_single_token_type(
+        pool_token_amount: u64,
+        swap_destination_amount: u64,
+        pool_token_supply: u64,
+    ) {
+        let destination_amount = withdraw_single_token_type(
+            pool_token_amount,
+            swap_destination_amount,
+            pool_token_supply,
+        )
+        .unwrap();
+        let new_swap_destination_amount = swap_destination_amount - destination_amount;
+        let new_pool_token_supply = pool_token_supply - pool_token_amount;
+
+        // the other side of the pool is unchanged, so the following inequality must hold:
+        // new_swap_destination_amount * pool_token_supply^2 >= swap_destination_amount * new_pool_token_supply^2
+        let pool_token_supply = U256::from(pool_token_supply);
+        let new_pool_token_supply = U256::from(new_pool_token_supply);
+        assert!(
+            U256::from(new_swap_destination_amount) * pool_token_supply * pool_token_supply
+                >= U256::from(swap_destination_amount)
+                    * new_pool_token_supply
+                    * new_pool_token_supply
+        );
+
+        // one more token would be too many
+        if new_swap_destination_amount > 0 {
+            assert!(
+                U256::from(new_swap_destination_amount - 1) * pool_token_supply * pool_token_supply
+                    < U256::from(swap_destination_amount)
+                        * new_pool_token_supply
+                        * new_pool_token_supply
+            );
+        }
+    }
+
     /// Test function checking that a withdraw never reduces the value of pool
     /// tokens.
     ///
@@ -368,13 +609,98 @@ mod tests {
             swap_destination_amount in 1..MAX_SWAP_AMOUNT,
         ) {
             check_curve_value_from_swap(
-                source_token_amount as u64,
-                swap_source_amount as u64,
-                swap_destination_amount as u64,
+                source_token_amount,
+                swap_source_amount,
+                swap_destination_amount,
             );
         }
     }
 
+    #[test]
+    fn constant_product_swap_exact_out() {
+        // cannot drain the pool
+        assert!(swap_exact_out(10, 20_000, 10).is_none());
+        assert!(swap_exact_out(11, 20_000, 10).is_none());
+        // zero swap should not execute
+        assert!(swap_exact_out(0, 20_000, 30_000).is_none());
+
+        let tests: &[(u64, u64, u64, u64)] = &[
+            (14, 20_000, 30_000, 10), // 20_000 * 30_000 / 29_986 = 20_009.34
+            (15, 20_000, 30_000, 11), // 20_000 * 30_000 / 29_985 = 20_010.005
+            (49, 60_000, 30_000, 99), // 60_000 * 30_000 / 29_951 = 60_098.16
+            (6, 30_000 - 20, 10_000, 18),
+        ];
+        for (
+            destination_amount,
+            swap_source_amount,
+            swap_destination_amount,
+            expected_source_amount,
+        ) in tests.iter()
+        {
+            let result = swap_exact_out(
+                *destination_amount,
+                *swap_source_amount,
+                *swap_destination_amount,
+            )
+            .unwrap();
+            assert_eq!(result.source_amount_swapped, *expected_source_amount);
+            assert_eq!(result.destination_amount_swapped, *destination_amount);
+        }
+    }
+
+    #[test]
+    fn fee_inversion() {
+        assert_eq!(amount_before_fee(0, 30_000, 10_000_000), Some(0));
+        assert_eq!(amount_before_fee(1, 0, 10_000_000), Some(1));
+        // 999 - floor(999 * 0.003) = 997
+        assert_eq!(amount_before_fee(997, 30_000, 10_000_000), Some(999));
+        // 1_003 - floor(1_003 * 0.003) = 1_000
+        assert_eq!(amount_before_fee(1_000, 30_000, 10_000_000), Some(1_003));
+        assert_eq!(amount_before_fee(1, 10_000_000, 10_000_000), None);
+        assert_eq!(amount_before_fee(1, 10_000_001, 10_000_000), None);
+    }
+
+    proptest! {
+        #[test]
+        fn curve_value_does_not_decrease_from_swap_exact_out(
+            (swap_destination_amount, destination_token_amount) in total_and_intermediate(),
+            swap_source_amount in 1..MAX_SWAP_AMOUNT,
+        ) {
+            // the required source amount must fit in a u64
+            prop_assume!(
+                (swap_source_amount as u128) * (swap_destination_amount as u128)
+                    / ((swap_destination_amount - destination_token_amount) as u128)
+                    < u64::MAX as u128
+            );
+            check_curve_value_from_swap_exact_out(
+                destination_token_amount,
+                swap_source_amount,
+                swap_destination_amount,
+            );
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn amount_before_fee_is_minimal(
+            amount_after_fee in 1..=u64::MAX,
+            fee_numerator in 0..10_000_000u64,
+        ) {
+            let fee_denominator = 10_000_000;
+            let after_fee = |amount: u64| {
+                amount - mul_div_u64(amount, fee_numerator, fee_denominator).unwrap()
+            };
+            match amount_before_fee(amount_after_fee, fee_numerator, fee_denominator) {
+                // no amount leaves enough after the fee
+                None => prop_assert!(after_fee(u64::MAX) < amount_after_fee),
+                Some(amount) => {
+                    prop_assert_eq!(after_fee(amount), amount_after_fee);
+                    prop_assert!(after_fee(amount - 1) < amount_after_fee);
+                }
+            }
+        }
+    }
+
     proptest! {
         #[test]
         fn curve_value_does_not_decrease_from_deposit(
@@ -400,6 +726,64 @@ mod tests {
         }
     }
 
+    #[test]
+    fn single_token_type_deposit() {
+        // doubling the reserve mints sqrt(2) - 1 of the supply
+        assert_eq!(deposit_single_token_type(100, 100, 1_000), Some(414));
+        // quadrupling the reserve doubles the supply
+        assert_eq!(deposit_single_token_type(300, 100, 1_000), Some(1_000));
+        assert_eq!(deposit_single_token_type(0, 100, 1_000), Some(0));
+        assert_eq!(deposit_single_token_type(1, 0, 1_000), None);
+    }
+
+    proptest! {
+        #[test]
+        fn curve_value_does_not_decrease_from_deposit_single_token_type(
+            source_amount in 1..MAX_SWAP_AMOUNT,
+            swap_source_amount in 1..MAX_SWAP_AMOUNT,
+            pool_token_supply in 1..MAX_SWAP_AMOUNT,
+        ) {
+            // the new pool token supply must fit in a u64
+            prop_assume!(
+                U256::from(pool_token_supply) * U256::from(pool_token_supply)
+                    * U256::from(swap_source_amount as u128 + source_amount as u128)
+                    / U256::from(swap_source_amount)
+                    < U256::from(u64::MAX) * U256::from(u64::MAX)
+            );
+            check_pool_value_from_deposit_single_token_type(
+                source_amount,
+                swap_source_amount,
+                pool_token_supply,
+            );
+        }
+    }
+
+    #[test]
+    fn single_token_type_withdraw() {
+        // burning half of the supply withdraws 3/4 of the reserve
+        assert_eq!(withdraw_single_token_type(500, 1_000, 1_000), Some(750));
+        // burning 1 - 1 / sqrt(2) of the supply withdraws about half of the reserve
+        assert_eq!(withdraw_single_token_type(414, 1_000, 1_000), Some(656));
+        assert_eq!(withdraw_single_token_type(293, 1_000, 1_000), Some(500));
+        assert_eq!(withdraw_single_token_type(0, 1_000, 1_000), Some(0));
+        assert_eq!(withdraw_single_token_type(1_000, 1_000, 1_000), Some(1_000));
+        assert_eq!(withdraw_single_token_type(1_001, 1_000, 1_000), None);
+    }
+
+    proptest! {
+        #[test]
+        fn curve_value_does_not_decrease_from_withdraw_single_token_type(
+            (pool_token_supply, pool_token_amount) in total_and_intermediate(),
+            swap_destination_amount in 1..MAX_SWAP_AMOUNT,
+        ) {
+            check_pool_value_from_withdraw_single_token_type(
+                pool_token_amount,
+                swap_destination_amount,
+                pool_token_supply,
+            );
+        }
+    }
+
     proptest! {
         #[test]
         fn curve_value_does_not_decrease_from_withdraw(
@@ -407,10 +791,6 @@ mod tests {
             swap_token_a_amount in 1..MAX_SWAP_AMOUNT,
             swap_token_b_amount in 1..MAX_SWAP_AMOUNT,
         ) {
-            let pool_token_amount = pool_token_amount as u64;
-            let pool_token_supply = pool_token_supply as u64;
-            let swap_token_a_amount = swap_token_a_amount as u64;
-            let swap_token_b_amount = swap_token_b_amount as u64;
             // Make sure we will get at least one trading token out for each
             // side, otherwise the calculation fails
             prop_assume!((pool_token_amount as u128) * (swap_token_a_amount as u128) / (pool_token_supply as u128) >= 1);
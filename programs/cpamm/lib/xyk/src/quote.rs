@@ -0,0 +1,382 @@
+//! Quotes of pool operations, including fees.
+//!
+//! These share their math with the CP-AMM program, but do not include token
+//! transfer fees, and use the fees they are given rather than the dynamic
+//! trade fee of a pool at the time of the operation.
+
+use ::u128::mul_div_u64;
+use spl_math::uint::U256;
+
+use crate::{
+    deposit_single_token_type, pool_tokens_to_trading_tokens, swap_exact_out,
+    withdraw_single_token_type, ConstantProduct, Curve, RoundDirection, SwapResult,
+    TradingTokenResult,
+};
+
+/// Thousands of BPS in 100%.
+pub const KBPS_PER_WHOLE: u64 = 10_000_000;
+
+/// BPS in 100%.
+pub const BPS_PER_WHOLE: u64 = 10_000;
+
+/// Decimals of a spot price.
+pub const PRICE_SCALE: u8 = 18;
+
+/// Fees of a pool, in thousands of bps.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Fees {
+    /// Trade fee, thousands of bps
+    pub trade_fee_kbps: u64,
+    /// Withdraw fee, thousands of bps
+    pub withdraw_fee_kbps: u64,
+    /// Proportion of trade fee sent to the admin, thousands of bps
+    pub admin_trade_fee_kbps: u64,
+    /// Proportion of withdraw fee sent to the admin, thousands of bps
+    pub admin_withdraw_fee_kbps: u64,
+}
+
+impl Fees {
+    /// Computes the trade fee and the admin's share of it from the trade amount.
+    pub fn trade_fees(&self, trade_amount: u64) -> Option<(u64, u64)> {
+        let trade_fee = mul_div_u64(trade_amount, self.trade_fee_kbps, KBPS_PER_WHOLE)?;
+        let admin_trade_fee = mul_div_u64(trade_fee, self.admin_trade_fee_kbps, KBPS_PER_WHOLE)?;
+        Some((trade_fee, admin_trade_fee))
+    }
+
+    /// Computes the withdraw fee and the admin's share of it from the withdrawal amount.
+    pub fn withdraw_fees(&self, withdrawal_amount: u64) -> Option<(u64, u64)> {
+        let withdraw_fee = mul_div_u64(withdrawal_amount, self.withdraw_fee_kbps, KBPS_PER_WHOLE)?;
+        let admin_withdraw_fee =
+            mul_div_u64(withdraw_fee, self.admin_withdraw_fee_kbps, KBPS_PER_WHOLE)?;
+        Some((withdraw_fee, admin_withdraw_fee))
+    }
+
+    /// Computes the amount that must be swapped so that `amount_out` remains after the trade fee.
+    pub fn amount_before_trade_fee(&self, amount_out: u64) -> Option<u64> {
+        crate::amount_before_fee(amount_out, self.trade_fee_kbps, KBPS_PER_WHOLE)
+    }
+}
+
+/// Quote of a swap.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SwapQuote {
+    /// Amount of source tokens sent to the pool.
+    pub amount_in: u64,
+    /// Amount of destination tokens received, after fees.
+    pub amount_out: u64,
+    /// Portion of the trade fee which remains in the pool.
+    pub lp_fee: u64,
+    /// Portion of the trade fee sent to the admin.
+    pub admin_fee: u64,
+    /// Destination tokens per source token before the swap, scaled by 10^[PRICE_SCALE].
+    pub spot_price_before: u128,
+    /// Destination tokens per source token after the swap, scaled by 10^[PRICE_SCALE].
+    pub spot_price_after: u128,
+    /// Difference between the spot price and the execution price, excluding fees, in bps.
+    pub price_impact_bps: u64,
+}
+
+impl SwapQuote {
+    /// Total trade fee, including the admin fee.
+    pub fn trade_fee(&self) -> u64 {
+        self.lp_fee + self.admin_fee
+    }
+}
+
+/// Quote of a single-sided deposit or withdrawal.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SingleTokenQuote {
+    /// Amount of tokens deposited or received, after fees.
+    pub token_amount: u64,
+    /// Amount of pool tokens minted or burned.
+    pub pool_token_amount: u64,
+    /// Portion of the fees which remains in the pool.
+    pub lp_fee: u64,
+    /// Portion of the fees sent to the admin.
+    pub admin_fee: u64,
+}
+
+/// Computes destination tokens per source token, scaled by 10^[PRICE_SCALE].
+pub fn spot_price(swap_source_amount: u64, swap_destination_amount: u64) -> Option<u128> {
+    (swap_destination_amount as u128)
+        .checked_mul(10u128.checked_pow(PRICE_SCALE.into())?)?
+        .checked_div(swap_source_amount.into())
+}
+
+/// Computes how far below the spot price a swap executed, in bps, rounded down.
+fn price_impact_bps(swap_result: &SwapResult, spot_price: u128) -> Option<u64> {
+    // compare amount_out / amount_in against the spot price
+    let expected = U256::from(swap_result.source_amount_swapped).checked_mul(spot_price.into())?;
+    let actual = U256::from(swap_result.destination_amount_swapped)
+        .checked_mul(U256::from(10).checked_pow(PRICE_SCALE.into())?)?;
+    if actual >= expected {
+        return Some(0);
+    }
+    let impact = expected
+        .checked_sub(actual)?
+        .checked_mul(BPS_PER_WHOLE.into())?
+        .checked_div(expected)?;
+    Some(impact.as_u64())
+}
+
+fn quote_swap_result(
+    curve: &dyn Curve,
+    swap_result: SwapResult,
+    fees: &Fees,
+    swap_source_amount: u64,
+    swap_destination_amount: u64,
+) -> Option<SwapQuote> {
+    let (trade_fee, admin_fee) = fees.trade_fees(swap_result.destination_amount_swapped)?;
+    let amount_out = swap_result
+        .destination_amount_swapped
+        .checked_sub(trade_fee)?;
+
+    // the LP fee stays in the reserves, and the admin fee leaves the pool
+    let new_swap_source_amount =
+        swap_source_amount.checked_add(swap_result.source_amount_swapped)?;
+    let new_swap_destination_amount = swap_destination_amount
+        .checked_sub(amount_out)?
+        .checked_sub(admin_fee)?;
+
+    let spot_price_before = curve.spot_price(swap_source_amount, swap_destination_amount)?;
+    Some(SwapQuote {
+        amount_in: swap_result.source_amount_swapped,
+        amount_out,
+        lp_fee: trade_fee.checked_sub(admin_fee)?,
+        admin_fee,
+        spot_price_before,
+        spot_price_after: curve.spot_price(new_swap_source_amount, new_swap_destination_amount)?,
+        price_impact_bps: price_impact_bps(&swap_result, spot_price_before)?,
+    })
+}
+
+/// Quotes a swap of `source_amount` on the `curve`, as performed by `cpamm::swap`.
+pub fn quote_swap(
+    curve: &dyn Curve,
+    source_amount: u64,
+    swap_source_amount: u64,
+    swap_destination_amount: u64,
+    fees: &Fees,
+) -> Option<SwapQuote> {
+    let swap_result = curve.swap(source_amount, swap_source_amount, swap_destination_amount)?;
+    quote_swap_result(
+        curve,
+        swap_result,
+        fees,
+        swap_source_amount,
+        swap_destination_amount,
+    )
+}
+
+/// Quotes a swap receiving at least `amount_out` after fees, as performed by `cpamm::swap_exact_out`.
+///
+/// Only constant product pools support exact output swaps.
+pub fn quote_swap_exact_out(
+    amount_out: u64,
+    swap_source_amount: u64,
+    swap_destination_amount: u64,
+    fees: &Fees,
+) -> Option<SwapQuote> {
+    let destination_amount = fees.amount_before_trade_fee(amount_out)?;
+    if destination_amount >= swap_destination_amount {
+        return None;
+    }
+    let swap_result = swap_exact_out(
+        destination_amount,
+        swap_source_amount,
+        swap_destination_amount,
+    )?;
+    quote_swap_result(
+        &ConstantProduct,
+        swap_result,
+        fees,
+        swap_source_amount,
+        swap_destination_amount,
+    )
+}
+
+/// Quotes the amounts of each token required to mint `pool_token_amount`, as performed by `cpamm::deposit`.
+pub fn quote_deposit(
+    pool_token_amount: u64,
+    pool_token_supply: u64,
+    swap_token_a_amount: u64,
+    swap_token_b_amount: u64,
+) -> Option<TradingTokenResult> {
+    pool_tokens_to_trading_tokens(
+        pool_token_amount,
+        pool_token_supply,
+        swap_token_a_amount,
+        swap_token_b_amount,
+        RoundDirection::Ceiling,
+    )
+}
+
+/// Quotes the amounts of each token received for burning `pool_token_amount`, as performed by `cpamm::withdraw`.
+pub fn quote_withdraw(
+    pool_token_amount: u64,
+    pool_token_supply: u64,
+    swap_token_a_amount: u64,
+    swap_token_b_amount: u64,
+) -> Option<TradingTokenResult> {
+    let result = pool_tokens_to_trading_tokens(
+        pool_token_amount,
+        pool_token_supply,
+        swap_token_a_amount,
+        swap_token_b_amount,
+        RoundDirection::Floor,
+    )?;
+    Some(TradingTokenResult {
+        token_a_amount: std::cmp::min(swap_token_a_amount, result.token_a_amount),
+        token_b_amount: std::cmp::min(swap_token_b_amount, result.token_b_amount),
+    })
+}
+
+/// Half of `amount`, rounded up.
+fn half_ceil(amount: u64) -> Option<u64> {
+    amount.checked_div(2)?.checked_add(amount.checked_rem(2)?)
+}
+
+/// Quotes a deposit of `source_amount` of one token, as performed by `cpamm::deposit_single_token_exact_in`.
+///
+/// Half of the deposit is implicitly swapped into the other token, so it pays the trade fee.
+pub fn quote_deposit_single(
+    source_amount: u64,
+    swap_token_amount: u64,
+    pool_token_supply: u64,
+    fees: &Fees,
+) -> Option<SingleTokenQuote> {
+    let (trade_fee, admin_fee) = fees.trade_fees(half_ceil(source_amount)?)?;
+    let pool_token_amount = deposit_single_token_type(
+        source_amount.checked_sub(trade_fee)?,
+        swap_token_amount,
+        pool_token_supply,
+    )?;
+    Some(SingleTokenQuote {
+        token_amount: source_amount,
+        pool_token_amount,
+        lp_fee: trade_fee.checked_sub(admin_fee)?,
+        admin_fee,
+    })
+}
+
+/// Quotes a withdrawal of one token for `pool_token_amount`, as performed by `cpamm::withdraw_one`.
+///
+/// Half of the withdrawal is implicitly swapped from the other token, so it pays the trade fee,
+/// and then the withdraw fee is taken.
+pub fn quote_withdraw_one(
+    pool_token_amount: u64,
+    swap_token_amount: u64,
+    pool_token_supply: u64,
+    fees: &Fees,
+) -> Option<SingleTokenQuote> {
+    let destination_amount =
+        withdraw_single_token_type(pool_token_amount, swap_token_amount, pool_token_supply)?;
+    let (trade_fee, admin_trade_fee) = fees.trade_fees(half_ceil(destination_amount)?)?;
+    let token_amount = destination_amount.checked_sub(trade_fee)?;
+    let (withdraw_fee, admin_withdraw_fee) = fees.withdraw_fees(token_amount)?;
+
+    let admin_fee = admin_trade_fee.checked_add(admin_withdraw_fee)?;
+    let lp_fee = trade_fee
+        .checked_add(withdraw_fee)?
+        .checked_sub(admin_fee)?;
+    Some(SingleTokenQuote {
+        token_amount: token_amount.checked_sub(withdraw_fee)?,
+        pool_token_amount,
+        lp_fee,
+        admin_fee,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{swap, StableSwap};
+    use proptest::prelude::*;
+
+    const FEES: Fees = Fees {
+        trade_fee_kbps: 30_000,
+        withdraw_fee_kbps: 10_000,
+        admin_trade_fee_kbps: 1_600_000,
+        admin_withdraw_fee_kbps: 10_000_000,
+    };
+
+    #[test]
+    fn quote_swap_fees_and_prices() {
+        let quote = quote_swap(&ConstantProduct, 10_000, 1_000_000, 1_000_000, &FEES).unwrap();
+        let result = swap(10_000, 1_000_000, 1_000_000).unwrap();
+        assert_eq!(quote.amount_in, result.source_amount_swapped);
+        assert_eq!(
+            quote.amount_out + quote.lp_fee + quote.admin_fee,
+            result.destination_amount_swapped
+        );
+        assert_eq!(quote.lp_fee + quote.admin_fee, 29);
+        assert_eq!(quote.admin_fee, 4);
+        assert_eq!(quote.spot_price_before, 1_000_000_000_000_000_000);
+        assert!(quote.spot_price_after < quote.spot_price_before);
+        // 1% of the reserves moves the price by about 1%
+        assert_eq!(quote.price_impact_bps, 99);
+    }
+
+    #[test]
+    fn quote_swap_exact_out_covers_amount_out() {
+        let quote = quote_swap_exact_out(10_000, 1_000_000, 1_000_000, &FEES).unwrap();
+        assert!(quote.amount_out >= 10_000);
+        assert!(quote_swap_exact_out(1_000_000, 1_000_000, 1_000_000, &FEES).is_none());
+    }
+
+    #[test]
+    fn quote_swap_follows_curve() {
+        let stable = StableSwap::new(100).unwrap();
+        let quote = quote_swap(&stable, 10_000, 1_000_000, 1_000_000, &FEES).unwrap();
+        let result = stable.swap(10_000, 1_000_000, 1_000_000).unwrap();
+        assert_eq!(
+            quote.amount_out + quote.trade_fee(),
+            result.destination_amount_swapped
+        );
+        assert_eq!(quote.spot_price_before, 1_000_000_000_000_000_000);
+        // the price of a stable pool barely moves
+        assert!(quote.price_impact_bps < 10);
+    }
+
+    #[test]
+    fn quote_withdraw_one_fees() {
+        let quote = quote_withdraw_one(1_000, 1_000_000, 100_000, &FEES).unwrap();
+        let destination_amount = withdraw_single_token_type(1_000, 1_000_000, 100_000).unwrap();
+        assert_eq!(
+            quote.token_amount + quote.lp_fee + quote.admin_fee,
+            destination_amount
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn quote_swap_conserves_output(
+            source_amount in 1..u32::MAX as u64,
+            swap_source_amount in 1..u32::MAX as u64,
+            swap_destination_amount in 1..u32::MAX as u64,
+        ) {
+            if let Some(quote) = quote_swap(&ConstantProduct, source_amount, swap_source_amount, swap_destination_amount, &FEES) {
+                let result = swap(source_amount, swap_source_amount, swap_destination_amount).unwrap();
+                prop_assert_eq!(
+                    quote.amount_out + quote.lp_fee + quote.admin_fee,
+                    result.destination_amount_swapped
+                );
+                prop_assert!(quote.spot_price_after <= quote.spot_price_before);
+                prop_assert!(quote.price_impact_bps <= BPS_PER_WHOLE);
+            }
+        }
+
+        #[test]
+        fn quote_deposit_single_fees(
+            source_amount in 1..u32::MAX as u64,
+            swap_token_amount in 1..u32::MAX as u64,
+            pool_token_supply in 1..u32::MAX as u64,
+        ) {
+            if let Some(quote) = quote_deposit_single(source_amount, swap_token_amount, pool_token_supply, &FEES) {
+                let (trade_fee, _) = FEES.trade_fees(half_ceil(source_amount).unwrap()).unwrap();
+                prop_assert_eq!(quote.lp_fee + quote.admin_fee, trade_fee);
+                prop_assert_eq!(quote.token_amount, source_amount);
+            }
+        }
+    }
+}
@@ -0,0 +1,325 @@
+//! The StableSwap invariant calculator, based on the
+//! [Curve](https://curve.fi/files/stableswap-paper.pdf) whitepaper.
+//!
+//! The invariant is `A * n^n * sum(x_i) + D = A * D * n^n + D^(n + 1) / (n^n * prod(x_i))`,
+//! where `A` is the amplification coefficient and `n` is the number of tokens.
+
+use spl_math::precise_number::PreciseNumber;
+use spl_math::uint::U256;
+
+use crate::{Curve, SwapResult};
+
+/// Minimum amplification coefficient.
+pub const MIN_AMP: u64 = 1;
+
+/// Maximum amplification coefficient.
+pub const MAX_AMP: u64 = 1_000_000;
+
+/// Number of tokens in the pool.
+const N_COINS: u64 = 2;
+
+/// Maximum number of iterations of Newton's method.
+const MAX_ITERATIONS: usize = 256;
+
+/// The StableSwap invariant, which behaves like a constant sum near the
+/// balance point and like a constant product away from it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StableSwap {
+    /// Amplification coefficient, `A`.
+    pub amp_factor: u64,
+}
+
+fn to_u64(value: U256) -> Option<u64> {
+    if value > U256::from(u64::MAX) {
+        None
+    } else {
+        Some(value.as_u64())
+    }
+}
+
+fn abs_diff(a: U256, b: U256) -> U256 {
+    if a > b {
+        a - b
+    } else {
+        b - a
+    }
+}
+
+impl StableSwap {
+    /// Creates a new [StableSwap], returning [None] if the amplification coefficient
+    /// is not within [MIN_AMP] and [MAX_AMP].
+    pub fn new(amp_factor: u64) -> Option<Self> {
+        if (MIN_AMP..=MAX_AMP).contains(&amp_factor) {
+            Some(Self { amp_factor })
+        } else {
+            None
+        }
+    }
+
+    /// `A * n`, the leverage used in the Curve implementation.
+    fn ann(&self) -> Option<U256> {
+        U256::from(self.amp_factor).checked_mul(N_COINS.into())
+    }
+
+    /// Computes the invariant `D` for the given reserves via Newton's method.
+    pub fn compute_d(&self, amount_a: u64, amount_b: u64) -> Option<U256> {
+        let sum_x = U256::from(amount_a).checked_add(amount_b.into())?;
+        if sum_x.is_zero() {
+            return Some(U256::zero());
+        }
+
+        let ann = self.ann()?;
+        let amount_a_times_coins = U256::from(amount_a).checked_mul(N_COINS.into())?;
+        let amount_b_times_coins = U256::from(amount_b).checked_mul(N_COINS.into())?;
+
+        let mut d = sum_x;
+        for _ in 0..MAX_ITERATIONS {
+            // d_prod = D^(n + 1) / (n^n * prod(x_i))
+            let d_prod = d
+                .checked_mul(d)?
+                .checked_div(amount_a_times_coins)?
+                .checked_mul(d)?
+                .checked_div(amount_b_times_coins)?;
+            let d_prev = d;
+
+            // D = (Ann * S + D_P * n) * D / ((Ann - 1) * D + (n + 1) * D_P)
+            let numerator = ann
+                .checked_mul(sum_x)?
+                .checked_add(d_prod.checked_mul(N_COINS.into())?)?
+                .checked_mul(d)?;
+            let denominator = ann
+                .checked_sub(U256::one())?
+                .checked_mul(d)?
+                .checked_add(d_prod.checked_mul((N_COINS + 1).into())?)?;
+            d = numerator.checked_div(denominator)?;
+
+            if abs_diff(d, d_prev) <= U256::one() {
+                break;
+            }
+        }
+        Some(d)
+    }
+
+    /// Computes the reserve of the other token such that the invariant is `d`,
+    /// given the new reserve `x` of one token, via Newton's method.
+    pub fn compute_y(&self, x: U256, d: U256) -> Option<U256> {
+        let ann = self.ann()?;
+
+        // c = D^(n + 1) / (n^n * x * Ann)
+        let c = d
+            .checked_mul(d)?
+            .checked_div(x.checked_mul(N_COINS.into())?)?
+            .checked_mul(d)?
+            .checked_div(ann.checked_mul(N_COINS.into())?)?;
+        // b = x + D / Ann
+        let b = x.checked_add(d.checked_div(ann)?)?;
+
+        let mut y = d;
+        for _ in 0..MAX_ITERATIONS {
+            let y_prev = y;
+            // y = (y^2 + c) / (2y + b - D)
+            y = y
+                .checked_mul(y)?
+                .checked_add(c)?
+                .checked_div(y.checked_mul(2.into())?.checked_add(b)?.checked_sub(d)?)?;
+            if abs_diff(y, y_prev) <= U256::one() {
+                break;
+            }
+        }
+        Some(y)
+    }
+}
+
+impl Curve for StableSwap {
+    fn swap(
+        &self,
+        source_amount: u64,
+        swap_source_amount: u64,
+        swap_destination_amount: u64,
+    ) -> Option<SwapResult> {
+        // zero swap should not execute
+        if source_amount == 0 {
+            return None;
+        }
+
+        let d = self.compute_d(swap_source_amount, swap_destination_amount)?;
+        let new_swap_source_amount =
+            U256::from(swap_source_amount).checked_add(source_amount.into())?;
+        let new_swap_destination_amount = self.compute_y(new_swap_source_amount, d)?;
+
+        // subtract one to account for the truncation of Newton's method,
+        // so that the invariant never decreases
+        let destination_amount_swapped = to_u64(
+            U256::from(swap_destination_amount)
+                .checked_sub(new_swap_destination_amount)?
+                .checked_sub(U256::one())?,
+        )?;
+
+        // zero swap should not execute
+        if destination_amount_swapped == 0 {
+            return None;
+        }
+
+        Some(SwapResult {
+            source_amount_swapped: source_amount,
+            destination_amount_swapped,
+        })
+    }
+
+    fn calculate_initial_swap_pool_amount(&self, amount_a: u64, amount_b: u64) -> Option<u64> {
+        // D / n is the geometric mean of the reserves when the pool is balanced
+        to_u64(
+            self.compute_d(amount_a, amount_b)?
+                .checked_div(N_COINS.into())?,
+        )
+    }
+
+    fn normalized_value(
+        &self,
+        swap_token_a_amount: u64,
+        swap_token_b_amount: u64,
+    ) -> Option<PreciseNumber> {
+        // D is at most the sum of the reserves, so it always fits in a u128
+        let d = self.compute_d(swap_token_a_amount, swap_token_b_amount)?;
+        PreciseNumber::new(d.as_u128())?.checked_div(&PreciseNumber::new(N_COINS.into())?)
+    }
+
+    fn spot_price(&self, swap_token_a_amount: u64, swap_token_b_amount: u64) -> Option<u128> {
+        // the ratio of the partial derivatives of the invariant, multiplied through by n^n * x * y:
+        // (Ann * n^n * x * y + D^(n + 1) / x) / (Ann * n^n * x * y + D^(n + 1) / y)
+        let d = self.compute_d(swap_token_a_amount, swap_token_b_amount)?;
+        let d_pow = d.checked_mul(d)?.checked_mul(d)?;
+        let ann_xy = self
+            .ann()?
+            .checked_mul((N_COINS * N_COINS).into())?
+            .checked_mul(swap_token_a_amount.into())?
+            .checked_mul(swap_token_b_amount.into())?;
+        let numerator = ann_xy.checked_add(d_pow.checked_div(swap_token_a_amount.into())?)?;
+        let denominator = ann_xy.checked_add(d_pow.checked_div(swap_token_b_amount.into())?)?;
+        let price = numerator
+            .checked_mul(U256::from(10).checked_pow(crate::PRICE_SCALE.into())?)?
+            .checked_div(denominator)?;
+        if price > U256::from(u128::MAX) {
+            None
+        } else {
+            Some(price.as_u128())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    const MAX_SWAP_AMOUNT: u64 = u64::MAX >> 16;
+
+    /// Test function checking that a swap never reduces the invariant.
+    fn check_curve_value_from_swap(
+        curve: &StableSwap,
+        source_token_amount: u64,
+        swap_source_amount: u64,
+        swap_destination_amount: u64,
+    ) {
+        let results = match curve.swap(
+            source_token_amount,
+            swap_source_amount,
+            swap_destination_amount,
+        ) {
+            Some(results) => results,
+            // too small to swap
+            None => return,
+        };
+
+        let previous_d = curve
+            .compute_d(swap_source_amount, swap_destination_amount)
+            .unwrap();
+        let new_d = curve
+            .compute_d(
+                swap_source_amount + results.source_amount_swapped,
+                swap_destination_amount - results.destination_amount_swapped,
+            )
+            .unwrap();
+        assert!(new_d >= previous_d);
+    }
+
+    #[test]
+    fn amp_factor_bounds() {
+        assert!(StableSwap::new(0).is_none());
+        assert!(StableSwap::new(MIN_AMP).is_some());
+        assert!(StableSwap::new(MAX_AMP).is_some());
+        assert!(StableSwap::new(MAX_AMP + 1).is_none());
+    }
+
+    #[test]
+    fn balanced_invariant() {
+        let curve = StableSwap::new(100).unwrap();
+        assert_eq!(curve.compute_d(0, 0), Some(U256::zero()));
+        assert_eq!(
+            curve.compute_d(1_000_000, 1_000_000),
+            Some(U256::from(2_000_000))
+        );
+        assert_eq!(
+            curve.calculate_initial_swap_pool_amount(1_000_000, 1_000_000),
+            Some(1_000_000)
+        );
+    }
+
+    #[test]
+    fn stable_swap_is_flatter_than_constant_product() {
+        let stable = StableSwap::new(100).unwrap();
+        let source_amount = 10_000_000;
+        let reserve = 1_000_000_000;
+
+        let stable_result = stable.swap(source_amount, reserve, reserve).unwrap();
+        let constant_product_result = crate::swap(source_amount, reserve, reserve).unwrap();
+        assert!(
+            stable_result.destination_amount_swapped
+                > constant_product_result.destination_amount_swapped
+        );
+        // very close to 1:1
+        assert!(stable_result.destination_amount_swapped > source_amount * 999 / 1_000);
+        assert!(stable_result.destination_amount_swapped < source_amount);
+    }
+
+    #[test]
+    fn stable_swap_spot_price() {
+        let stable = StableSwap::new(100).unwrap();
+        let reserve = 1_000_000_000;
+        assert_eq!(
+            stable.spot_price(reserve, reserve),
+            Some(1_000_000_000_000_000_000)
+        );
+
+        // much closer to 1:1 than the constant product price when imbalanced
+        let price = stable.spot_price(reserve, reserve * 2).unwrap();
+        assert!(price > 1_000_000_000_000_000_000);
+        assert!(price < 1_010_000_000_000_000_000);
+        let reversed = stable.spot_price(reserve * 2, reserve).unwrap();
+        assert!(reversed < 1_000_000_000_000_000_000);
+
+        // the price of a small swap
+        let result = stable.swap(1_000_000, reserve, reserve * 2).unwrap();
+        let swap_price = u128::from(result.destination_amount_swapped) * 1_000_000_000_000;
+        assert!(swap_price <= price);
+        assert!(swap_price > price * 999 / 1_000);
+    }
+
+    proptest! {
+        #[test]
+        fn curve_value_does_not_decrease_from_swap(
+            amp_factor in MIN_AMP..=MAX_AMP,
+            source_token_amount in 1..MAX_SWAP_AMOUNT,
+            swap_source_amount in 1..MAX_SWAP_AMOUNT,
+            swap_destination_amount in 1..MAX_SWAP_AMOUNT,
+        ) {
+            let curve = StableSwap::new(amp_factor).unwrap();
+            check_curve_value_from_swap(
+                &curve,
+                source_token_amount,
+                swap_source_amount,
+                swap_destination_amount,
+            );
+        }
+    }
+}
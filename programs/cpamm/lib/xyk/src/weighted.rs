@@ -0,0 +1,505 @@
+//! The weighted product invariant calculator, based on the
+//! [Balancer](https://balancer.fi/whitepaper.pdf) whitepaper.
+//!
+//! The invariant is `x^w_x * y^w_y = k`, where the weights `w_x` and `w_y` sum to one.
+//! The constant product invariant is the special case where both weights are one half.
+//!
+//! Swapping `dx` into the pool pays out `dy = y * (1 - (x / (x + dx))^(w_x / w_y))`.
+//! The power is computed in Q64.64 fixed point by [pow_x64], whose error is
+//! bounded as documented there. Swaps round the power up by this bound so that
+//! the invariant never decreases.
+
+use std::convert::TryFrom;
+
+use spl_math::precise_number::PreciseNumber;
+use spl_math::uint::U256;
+
+use crate::{Curve, SwapResult};
+
+/// Denominator of the weights of a [WeightedProduct].
+pub const WEIGHT_DENOMINATOR: u64 = 10_000;
+
+/// Minimum weight of a token, i.e. 1%.
+pub const MIN_WEIGHT: u64 = 100;
+
+/// Maximum weight of a token, i.e. 99%.
+pub const MAX_WEIGHT: u64 = WEIGHT_DENOMINATOR - MIN_WEIGHT;
+
+/// The relative error of [pow_x64] is at most `2^-POW_ERROR_SHIFT`, for all
+/// exponents of at most [MAX_WEIGHT] / [MIN_WEIGHT].
+pub const POW_ERROR_SHIFT: u32 = 56;
+
+/// One, Q64.64.
+const ONE_X64: u128 = 1 << 64;
+
+/// `ln(2)`, Q1.127.
+const LN_2_X127: u128 = 117_932_881_612_756_647_068_972_071_382_077_242_199;
+
+/// The weighted product invariant.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WeightedProduct {
+    /// Weight of token A, in units of [WEIGHT_DENOMINATOR].
+    pub weight_a: u64,
+    /// Weight of token B, in units of [WEIGHT_DENOMINATOR].
+    pub weight_b: u64,
+}
+
+fn to_u64(value: U256) -> Option<u64> {
+    if value > U256::from(u64::MAX) {
+        None
+    } else {
+        Some(value.as_u64())
+    }
+}
+
+fn to_u128(value: U256) -> Option<u128> {
+    if value > U256::from(u128::MAX) {
+        None
+    } else {
+        Some(value.as_u128())
+    }
+}
+
+fn div_ceil(numerator: U256, denominator: U256) -> Option<U256> {
+    let quotient = numerator.checked_div(denominator)?;
+    if quotient.checked_mul(denominator)? == numerator {
+        Some(quotient)
+    } else {
+        quotient.checked_add(U256::one())
+    }
+}
+
+/// Computes `log2(x)`, Q64.64, returning [None] if `x` is zero.
+///
+/// The result is rounded down, and is less than `2^-63` below the exact value.
+pub fn log2_x64(x_x64: u128) -> Option<i128> {
+    if x_x64 == 0 {
+        return None;
+    }
+    let msb = 127 - x_x64.leading_zeros();
+    let integer = i128::from(msb) - 64;
+
+    // x / 2^integer is in [1, 2); find the bits of its logarithm by repeated squaring
+    let two_x127 = U256::one() << 128;
+    let mut y_x127 = U256::from(x_x64 << (127 - msb));
+    let mut fraction: i128 = 0;
+    for bit in (0..64).rev() {
+        y_x127 = y_x127.checked_mul(y_x127)? >> 127;
+        if y_x127 >= two_x127 {
+            y_x127 >>= 1;
+            fraction |= 1 << bit;
+        }
+    }
+    (integer << 64).checked_add(fraction)
+}
+
+/// Computes `2^z`, Q64.64, returning [None] if the result is `2^64` or greater.
+///
+/// The result is rounded down, and is less than `2^-120` of the exact value
+/// plus one unit in the last place below it.
+pub fn exp2_x64(z_x64: i128) -> Option<u128> {
+    let integer = z_x64 >> 64;
+    if integer >= 64 {
+        return None;
+    }
+    if integer < -128 {
+        return Some(0);
+    }
+    // fraction is in [0, 1)
+    let fraction = U256::from(z_x64.checked_sub(integer << 64)?.unsigned_abs());
+
+    // 2^fraction = e^(fraction * ln(2)), via its Taylor series
+    let t_x127 = fraction.checked_mul(LN_2_X127.into())? >> 64;
+    let mut term_x127 = U256::one() << 127;
+    let mut sum_x127 = term_x127;
+    let mut k = 1u64;
+    while !term_x127.is_zero() {
+        term_x127 = (term_x127.checked_mul(t_x127)? >> 127).checked_div(k.into())?;
+        sum_x127 = sum_x127.checked_add(term_x127)?;
+        k = k.checked_add(1)?;
+    }
+
+    // integer is in [-128, 64), so the shift is in (0, 191]
+    let shift = usize::try_from(63_i128.checked_sub(integer)?).ok()?;
+    to_u128(sum_x127 >> shift)
+}
+
+/// Computes `x^(numerator / denominator)`, Q64.64.
+///
+/// If the exponent is at most [MAX_WEIGHT] / [MIN_WEIGHT], the result is within
+/// `2^-POW_ERROR_SHIFT` of the exact value plus one unit in the last place:
+/// - [log2_x64] is less than `2^-63` from the exact logarithm, so multiplying it by
+///   the exponent and dividing it is less than `99 * 2^-63 + 2^-64 < 2^-56.4` from
+///   the exact exponent of two.
+/// - An error of `e` in the exponent of two is a relative error of at most
+///   `ln(2) * e < 2^-56.9` in the power, to which [exp2_x64] adds less than `2^-120`.
+pub fn pow_x64(x_x64: u128, numerator: u64, denominator: u64) -> Option<u128> {
+    if denominator == 0 {
+        return None;
+    }
+    if numerator == 0 {
+        return Some(ONE_X64);
+    }
+    if x_x64 == 0 {
+        return Some(0);
+    }
+    let z_x64 = log2_x64(x_x64)?
+        .checked_mul(numerator.into())?
+        .checked_div(denominator.into())?;
+    exp2_x64(z_x64)
+}
+
+/// Computes an upper bound on `x^(numerator / denominator)`, Q64.64, by adding
+/// the maximum error of [pow_x64] to it.
+pub fn pow_up_x64(x_x64: u128, numerator: u64, denominator: u64) -> Option<u128> {
+    let power = pow_x64(x_x64, numerator, denominator)?;
+    // if p >= x^e * (1 - 2^-56) - 1, then x^e <= p + p * 2^-55 + 1 for all p
+    power.checked_add((power >> (POW_ERROR_SHIFT - 1)).checked_add(2)?)
+}
+
+impl WeightedProduct {
+    /// Creates a new [WeightedProduct] where token A has `weight_a` and token B has
+    /// the rest of the weight, returning [None] if either weight is less than [MIN_WEIGHT].
+    pub fn new(weight_a: u64) -> Option<Self> {
+        if (MIN_WEIGHT..=MAX_WEIGHT).contains(&weight_a) {
+            Some(Self {
+                weight_a,
+                weight_b: WEIGHT_DENOMINATOR - weight_a,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// The same invariant with tokens A and B swapped.
+    pub fn reversed(&self) -> Self {
+        Self {
+            weight_a: self.weight_b,
+            weight_b: self.weight_a,
+        }
+    }
+
+    /// Computes the weighted geometric mean of the reserves, `a^w_a * b^w_b`, Q64.64.
+    pub fn compute_value_x64(&self, amount_a: u64, amount_b: u64) -> Option<u128> {
+        if amount_a == 0 || amount_b == 0 {
+            return Some(0);
+        }
+        let log_a = log2_x64(u128::from(amount_a) << 64)?;
+        let log_b = log2_x64(u128::from(amount_b) << 64)?;
+        let z_x64 = log_a
+            .checked_mul(self.weight_a.into())?
+            .checked_add(log_b.checked_mul(self.weight_b.into())?)?
+            .checked_div(WEIGHT_DENOMINATOR.into())?;
+        exp2_x64(z_x64)
+    }
+}
+
+impl Curve for WeightedProduct {
+    fn swap(
+        &self,
+        source_amount: u64,
+        swap_source_amount: u64,
+        swap_destination_amount: u64,
+    ) -> Option<SwapResult> {
+        // zero swap should not execute
+        if source_amount == 0 {
+            return None;
+        }
+
+        // x / (x + dx), rounded up
+        let new_swap_source_amount =
+            U256::from(swap_source_amount).checked_add(source_amount.into())?;
+        let ratio_x64 = to_u128(div_ceil(
+            U256::from(swap_source_amount) << 64,
+            new_swap_source_amount,
+        )?)?;
+
+        // rounding the power up rounds the new destination reserve up,
+        // so that the invariant never decreases
+        let power_x64 = pow_up_x64(ratio_x64, self.weight_a, self.weight_b)?.min(ONE_X64);
+        let new_swap_destination_amount = to_u64(div_ceil(
+            U256::from(swap_destination_amount).checked_mul(power_x64.into())?,
+            U256::from(ONE_X64),
+        )?)?;
+
+        let destination_amount_swapped =
+            swap_destination_amount.checked_sub(new_swap_destination_amount)?;
+
+        // zero swap should not execute
+        if destination_amount_swapped == 0 {
+            return None;
+        }
+
+        Some(SwapResult {
+            source_amount_swapped: source_amount,
+            destination_amount_swapped,
+        })
+    }
+
+    fn calculate_initial_swap_pool_amount(&self, amount_a: u64, amount_b: u64) -> Option<u64> {
+        // the weighted geometric mean is the geometric mean if the weights are equal
+        u64::try_from(self.compute_value_x64(amount_a, amount_b)? >> 64).ok()
+    }
+
+    fn normalized_value(
+        &self,
+        swap_token_a_amount: u64,
+        swap_token_b_amount: u64,
+    ) -> Option<PreciseNumber> {
+        PreciseNumber::new(self.compute_value_x64(swap_token_a_amount, swap_token_b_amount)?)?
+            .checked_div(&PreciseNumber::new(ONE_X64)?)
+    }
+
+    fn spot_price(&self, swap_token_a_amount: u64, swap_token_b_amount: u64) -> Option<u128> {
+        // (b / w_b) / (a / w_a)
+        to_u128(
+            U256::from(swap_token_b_amount)
+                .checked_mul(U256::from(10).checked_pow(crate::PRICE_SCALE.into())?)?
+                .checked_mul(self.weight_a.into())?
+                .checked_div(U256::from(swap_token_a_amount).checked_mul(self.weight_b.into())?)?,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    const MAX_SWAP_AMOUNT: u64 = 1 << 50;
+
+    /// Compares `x^k` with `y` exactly, where `x` and `y` are Q64.64 and `x < 2^64`.
+    fn cmp_int_pow(x_x64: u128, k: u32, y_x64: u128) -> std::cmp::Ordering {
+        let mut lhs = U256::one();
+        for _ in 0..k {
+            lhs *= U256::from(x_x64);
+        }
+        let rhs = U256::from(y_x64) << (64 * (k as usize - 1));
+        lhs.cmp(&rhs)
+    }
+
+    /// Lower bound of the error bound of [pow_x64], mirroring [pow_up_x64].
+    fn pow_down_x64(x_x64: u128, numerator: u64, denominator: u64) -> u128 {
+        let power = pow_x64(x_x64, numerator, denominator).unwrap();
+        power.saturating_sub((power >> (POW_ERROR_SHIFT - 1)) + 2)
+    }
+
+    /// Test function checking that a swap never reduces the invariant, for
+    /// weights whose ratio reduces to `a:b` where `a + b <= 5`.
+    ///
+    /// The invariant `x^a * y^b` is compared exactly, which fits in a U256 since
+    /// all amounts are below `2^51`.
+    fn check_curve_value_from_swap(
+        weight_source: u64,
+        source_token_amount: u64,
+        swap_source_amount: u64,
+        swap_destination_amount: u64,
+    ) {
+        let curve = WeightedProduct::new(weight_source).unwrap();
+        let results = match curve.swap(
+            source_token_amount,
+            swap_source_amount,
+            swap_destination_amount,
+        ) {
+            Some(results) => results,
+            // too small to swap
+            None => return,
+        };
+
+        let gcd = (1..=curve.weight_a.min(curve.weight_b))
+            .rev()
+            .find(|d| curve.weight_a % d == 0 && curve.weight_b % d == 0)
+            .unwrap();
+        let (a, b) = (curve.weight_a / gcd, curve.weight_b / gcd);
+        assert!(a + b <= 5);
+        let invariant =
+            |x: u64, y: u64| U256::from(x).pow(U256::from(a)) * U256::from(y).pow(U256::from(b));
+
+        let previous_value = invariant(swap_source_amount, swap_destination_amount);
+        let new_value = invariant(
+            swap_source_amount + results.source_amount_swapped,
+            swap_destination_amount - results.destination_amount_swapped,
+        );
+        assert!(new_value >= previous_value);
+    }
+
+    /// Test function checking that a swap pays out the exact amount of the
+    /// invariant for any weights, less rounding.
+    fn check_swap_amount(
+        weight_source: u64,
+        source_token_amount: u64,
+        swap_source_amount: u64,
+        swap_destination_amount: u64,
+    ) {
+        let curve = WeightedProduct::new(weight_source).unwrap();
+        let destination_amount_swapped = curve
+            .swap(
+                source_token_amount,
+                swap_source_amount,
+                swap_destination_amount,
+            )
+            .map_or(0, |results| results.destination_amount_swapped);
+
+        // dy = -y * expm1(w_x / w_y * ln(1 - dx / (x + dx)))
+        let exponent = curve.weight_a as f64 / curve.weight_b as f64;
+        let new_swap_source_amount = swap_source_amount as f64 + source_token_amount as f64;
+        let exact = -(swap_destination_amount as f64)
+            * (exponent * (-(source_token_amount as f64) / new_swap_source_amount).ln_1p())
+                .exp_m1();
+        let tolerance = 1e-12 * exact + 2.0;
+        assert!((destination_amount_swapped as f64) <= exact + tolerance);
+        assert!((destination_amount_swapped as f64) >= exact - tolerance);
+    }
+
+    #[test]
+    fn weight_bounds() {
+        assert!(WeightedProduct::new(0).is_none());
+        assert!(WeightedProduct::new(MIN_WEIGHT - 1).is_none());
+        assert!(WeightedProduct::new(MIN_WEIGHT).is_some());
+        assert!(WeightedProduct::new(MAX_WEIGHT).is_some());
+        assert!(WeightedProduct::new(MAX_WEIGHT + 1).is_none());
+        assert!(WeightedProduct::new(WEIGHT_DENOMINATOR).is_none());
+        assert_eq!(
+            WeightedProduct::new(8_000).unwrap().reversed(),
+            WeightedProduct::new(2_000).unwrap()
+        );
+    }
+
+    #[test]
+    fn log2_and_exp2() {
+        assert_eq!(log2_x64(0), None);
+        assert_eq!(log2_x64(ONE_X64), Some(0));
+        assert_eq!(log2_x64(ONE_X64 << 10), Some(10 << 64));
+        assert_eq!(log2_x64(ONE_X64 >> 10), Some(-10 << 64));
+        assert_eq!(log2_x64(1), Some(-64 << 64));
+
+        assert_eq!(exp2_x64(0), Some(ONE_X64));
+        assert_eq!(exp2_x64(10 << 64), Some(ONE_X64 << 10));
+        assert_eq!(exp2_x64(-10 << 64), Some(ONE_X64 >> 10));
+        assert_eq!(exp2_x64(63 << 64), Some(ONE_X64 << 63));
+        assert_eq!(exp2_x64(64 << 64), None);
+        assert_eq!(exp2_x64(-65 << 64), Some(0));
+        assert_eq!(exp2_x64(i128::MIN), Some(0));
+    }
+
+    #[test]
+    fn equal_weights_are_constant_product() {
+        let curve = WeightedProduct::new(WEIGHT_DENOMINATOR / 2).unwrap();
+        let reserve = 1_000_000_000;
+        for source_amount in [1_000, 1_000_000, 1_000_000_000] {
+            let weighted = curve.swap(source_amount, reserve, reserve).unwrap();
+            let constant_product = crate::swap(source_amount, reserve, reserve).unwrap();
+            assert!(
+                weighted.destination_amount_swapped <= constant_product.destination_amount_swapped
+            );
+            assert!(
+                weighted.destination_amount_swapped + 1
+                    >= constant_product.destination_amount_swapped
+            );
+        }
+        assert_eq!(
+            curve.calculate_initial_swap_pool_amount(1_000_000, 4_000_000),
+            Some(1_999_999)
+        );
+    }
+
+    #[test]
+    fn weights_set_the_spot_price() {
+        let reserve = 1_000_000_000_000;
+        // with equal reserves, token A is worth four times as much as token B
+        let curve = WeightedProduct::new(8_000).unwrap();
+        let results = curve.swap(1_000, reserve, reserve).unwrap();
+        assert_eq!(results.destination_amount_swapped, 3_999);
+        let results = curve.reversed().swap(4_000, reserve, reserve).unwrap();
+        assert_eq!(results.destination_amount_swapped, 999);
+
+        // the value of the pool is the weighted geometric mean of the reserves
+        assert_eq!(
+            curve.calculate_initial_swap_pool_amount(1 << 40, 1 << 20),
+            Some(1 << 36)
+        );
+
+        // with equal reserves, the spot price is the ratio of the weights
+        assert_eq!(
+            curve.spot_price(reserve, reserve),
+            Some(4_000_000_000_000_000_000)
+        );
+        assert_eq!(
+            curve.reversed().spot_price(reserve, reserve),
+            Some(250_000_000_000_000_000)
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn pow_integer_exponent_is_within_error_bound(
+            x_x64 in 1..(ONE_X64 >> 1),
+            k in 1..=4u32,
+            denominator in MIN_WEIGHT..=MAX_WEIGHT / 4,
+        ) {
+            let numerator = denominator * u64::from(k);
+            let upper = pow_up_x64(x_x64, numerator, denominator).unwrap();
+            let lower = pow_down_x64(x_x64, numerator, denominator);
+            prop_assert!(cmp_int_pow(x_x64, k, upper).is_le());
+            prop_assert!(cmp_int_pow(x_x64, k, lower).is_ge());
+        }
+
+        #[test]
+        fn pow_root_exponent_is_within_error_bound(
+            x_x64 in 1..(ONE_X64 >> 2),
+            k in 1..=4u32,
+            numerator in MIN_WEIGHT..=MAX_WEIGHT / 4,
+        ) {
+            // x^(1/k) is in [p_lower, p_upper] iff p_lower^k <= x <= p_upper^k
+            let denominator = numerator * u64::from(k);
+            let upper = pow_up_x64(x_x64, numerator, denominator).unwrap();
+            let lower = pow_down_x64(x_x64, numerator, denominator);
+            prop_assert!(cmp_int_pow(upper, k, x_x64).is_ge());
+            prop_assert!(cmp_int_pow(lower, k, x_x64).is_le());
+        }
+
+        #[test]
+        fn pow_is_close_to_floating_point(
+            x_x64 in 1..=ONE_X64,
+            numerator in MIN_WEIGHT..=MAX_WEIGHT,
+            denominator in MIN_WEIGHT..=MAX_WEIGHT,
+        ) {
+            let power = pow_x64(x_x64, numerator, denominator).unwrap();
+            let exponent = numerator as f64 / denominator as f64;
+            let exact = (x_x64 as f64 / ONE_X64 as f64).powf(exponent) * ONE_X64 as f64;
+            // the conversion of x to floating point has a relative error of 2^-53
+            let tolerance = exact * (exponent + 2.0) * f64::EPSILON + 2.0;
+            prop_assert!((power as f64 - exact).abs() <= tolerance);
+        }
+
+        #[test]
+        fn curve_value_does_not_decrease_from_swap(
+            weight_source in prop::sample::select(vec![2_000, 2_500, 4_000, 5_000, 6_000, 7_500, 8_000]),
+            source_token_amount in 1..MAX_SWAP_AMOUNT,
+            swap_source_amount in 1..MAX_SWAP_AMOUNT,
+            swap_destination_amount in 1..MAX_SWAP_AMOUNT,
+        ) {
+            check_curve_value_from_swap(
+                weight_source,
+                source_token_amount,
+                swap_source_amount,
+                swap_destination_amount,
+            );
+        }
+
+        #[test]
+        fn swap_amount_is_exact_less_rounding(
+            weight_source in MIN_WEIGHT..=MAX_WEIGHT,
+            source_token_amount in 1..MAX_SWAP_AMOUNT,
+            swap_source_amount in 1..MAX_SWAP_AMOUNT,
+            swap_destination_amount in 1..MAX_SWAP_AMOUNT,
+        ) {
+            check_swap_amount(
+                weight_source,
+                source_token_amount,
+                swap_source_amount,
+                swap_destination_amount,
+            );
+        }
+    }
+}
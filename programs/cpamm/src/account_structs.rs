@@ -31,6 +31,7 @@ pub struct NewFactory<'info> {
 
 /// Accounts for a [cpamm::new_swap] instruction.
 #[derive(Accounts)]
+#[instruction(_bump: u8, fee_tier_kbps: u32)]
 pub struct NewSwap<'info> {
     /// The [Factory].
     #[account(mut)]
@@ -43,7 +44,8 @@ pub struct NewSwap<'info> {
             b"SwapInfo".as_ref(),
             factory.key().to_bytes().as_ref(),
             token_0.mint.key().to_bytes().as_ref(),
-            token_1.mint.key().to_bytes().as_ref()
+            token_1.mint.key().to_bytes().as_ref(),
+            fee_tier_kbps.to_le_bytes().as_ref()
         ],
         bump,
         payer = payer
@@ -62,11 +64,13 @@ pub struct NewSwap<'info> {
     #[account(mut)]
     pub output_lp: Box<Account<'info, TokenAccount>>,
 
-    /// Payer.
+    /// Payer. This is the creator of the swap.
     #[account(mut)]
     pub payer: Signer<'info>,
+    /// The [PoolCreator] of the payer. Only checked if the [Factory] restricts pool creators.
+    pub pool_creator: UncheckedAccount<'info>,
 
-    /// [Token] program.
+    /// [Token] program of the pool mint.
     pub token_program: Program<'info, Token>,
 
     /// [System] program.
@@ -111,6 +115,27 @@ pub struct Swap<'info> {
     pub output: SwapTokenWithFees<'info>,
 }
 
+/// Accounts for a [cpamm::swap_route] instruction.
+///
+/// The pools of the route are passed as remaining accounts, six per hop:
+/// the [SwapInfo], the input reserve, the output reserve, the output admin fees account,
+/// the output mint, and the token program of the output mint.
+#[derive(Accounts)]
+pub struct SwapRoute<'info> {
+    /// The token program of the input mint.
+    pub token_program: UncheckedAccount<'info>,
+    /// The authority of the user.
+    pub user_authority: Signer<'info>,
+    /// The user's token account of the first input token of the route.
+    #[account(mut)]
+    pub input: UncheckedAccount<'info>,
+    /// The user's token account of the final output token of the route.
+    #[account(mut)]
+    pub output: UncheckedAccount<'info>,
+    /// The mint of the first input token of the route.
+    pub input_mint: UncheckedAccount<'info>,
+}
+
 /// Accounts for a [cpamm::withdraw] instruction.
 #[derive(Accounts)]
 pub struct Withdraw<'info> {
@@ -128,6 +153,23 @@ pub struct Withdraw<'info> {
     pub output_1: SwapTokenWithFees<'info>,
 }
 
+/// Accounts for a [cpamm::withdraw_one] instruction.
+#[derive(Accounts)]
+pub struct WithdrawOne<'info> {
+    /// The context of the user performing the withdraw.
+    pub user: SwapUserContext<'info>,
+    /// The pool mint of the swap.
+    #[account(mut)]
+    pub pool_mint: Account<'info, Mint>,
+    /// The input token of the withdraw.
+    #[account(mut)]
+    pub input_lp: Account<'info, TokenAccount>,
+    /// The output token of the withdraw.
+    pub output: SwapTokenWithFees<'info>,
+    /// The pool's reserves of the other token.
+    pub other_reserve: UncheckedAccount<'info>,
+}
+
 /// Accounts for a [cpamm::deposit] instruction.
 #[derive(Accounts)]
 pub struct Deposit<'info> {
@@ -145,25 +187,565 @@ pub struct Deposit<'info> {
     pub output_lp: Box<Account<'info, TokenAccount>>,
 }
 
+/// Accounts for a [cpamm::deposit_single_token_exact_in] instruction.
+#[derive(Accounts)]
+pub struct DepositSingle<'info> {
+    /// The context of the user performing the deposit.
+    pub user: SwapUserContext<'info>,
+    /// The input token of the deposit.
+    pub input: SwapTokenWithFees<'info>,
+    /// The pool's reserves of the other token.
+    pub other_reserve: UncheckedAccount<'info>,
+    /// The pool mint of the swap.
+    #[account(mut)]
+    pub pool_mint: Box<Account<'info, Mint>>,
+    /// The destination account for LP tokens.
+    #[account(mut)]
+    pub output_lp: Box<Account<'info, TokenAccount>>,
+}
+
+/// Accounts for a [cpamm::new_observations] instruction.
+#[derive(Accounts)]
+pub struct NewObservations<'info> {
+    /// The swap account
+    pub swap: Box<Account<'info, SwapInfo>>,
+    /// The admin of the swap.
+    pub admin: Signer<'info>,
+
+    /// The [Observations].
+    #[account(
+        init,
+        seeds = [
+            b"Observations".as_ref(),
+            swap.key().to_bytes().as_ref()
+        ],
+        bump,
+        payer = payer,
+        space = Observations::LEN
+    )]
+    pub observations: Box<Account<'info, Observations>>,
+
+    /// Payer.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// [System] program.
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for a [cpamm::record_observation] instruction.
+#[derive(Accounts)]
+pub struct RecordObservation<'info> {
+    /// The [SwapInfo] account.
+    #[account(mut)]
+    pub swap: Box<Account<'info, SwapInfo>>,
+    /// The pool's reserves of token 0.
+    pub reserve_0: UncheckedAccount<'info>,
+    /// The pool's reserves of token 1.
+    pub reserve_1: UncheckedAccount<'info>,
+    /// The [Observations] of the swap.
+    #[account(mut)]
+    pub observations: Box<Account<'info, Observations>>,
+}
+
+/// Accounts for a [cpamm::consult] instruction.
+#[derive(Accounts)]
+pub struct Consult<'info> {
+    /// The [SwapInfo] account.
+    pub swap: Box<Account<'info, SwapInfo>>,
+    /// The pool's reserves of token 0.
+    pub reserve_0: UncheckedAccount<'info>,
+    /// The pool's reserves of token 1.
+    pub reserve_1: UncheckedAccount<'info>,
+    /// The [Observations] of the swap.
+    pub observations: Box<Account<'info, Observations>>,
+}
+
+/// Accounts for a [cpamm::sync] instruction.
+#[derive(Accounts)]
+pub struct SyncReserves<'info> {
+    /// The [SwapInfo] account.
+    #[account(mut)]
+    pub swap: Box<Account<'info, SwapInfo>>,
+    /// The pool's reserves of token 0.
+    pub reserve_0: UncheckedAccount<'info>,
+    /// The pool's reserves of token 1.
+    pub reserve_1: UncheckedAccount<'info>,
+}
+
+/// Accounts for a [cpamm::skim] instruction.
+#[derive(Accounts)]
+pub struct Skim<'info> {
+    /// The [SwapInfo] account.
+    #[account(mut)]
+    pub swap: Box<Account<'info, SwapInfo>>,
+    /// Token 0 of the swap.
+    pub token_0: SkimToken<'info>,
+    /// Token 1 of the swap.
+    pub token_1: SkimToken<'info>,
+}
+
+/// Accounts for a [cpamm::flash_loan] or [cpamm::repay_flash_loan] instruction.
+#[derive(Accounts)]
+pub struct FlashLoan<'info> {
+    /// The context of the user performing the flash loan.
+    pub user: SwapUserContext<'info>,
+    /// The token being lent.
+    pub token: SwapTokenWithFees<'info>,
+    /// The pool's reserves of the other token.
+    pub other_reserve: UncheckedAccount<'info>,
+    /// The instructions sysvar.
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+}
+
+/// Accounts for admin instructions on a [SwapInfo].
+#[derive(Accounts)]
+pub struct SwapAdmin<'info> {
+    /// The admin of the [SwapInfo].
+    pub admin: Signer<'info>,
+    /// The [SwapInfo] account.
+    #[account(mut)]
+    pub swap: Box<Account<'info, SwapInfo>>,
+}
+
+/// Accounts for a [cpamm::claim_admin_fees] instruction.
+#[derive(Accounts)]
+pub struct ClaimAdminFees<'info> {
+    /// The admin of the [SwapInfo].
+    pub swap_admin: SwapAdmin<'info>,
+    /// Token 0 of the swap.
+    pub token_0: AdminFeesToken<'info>,
+    /// Token 1 of the swap.
+    pub token_1: AdminFeesToken<'info>,
+}
+
+/// Accounts for a [cpamm::close_swap] instruction.
+#[derive(Accounts)]
+pub struct CloseSwap<'info> {
+    /// The admin of the [SwapInfo].
+    pub admin: Signer<'info>,
+    /// The [SwapInfo] account.
+    #[account(mut, close = rent_destination)]
+    pub swap: Box<Account<'info, SwapInfo>>,
+    /// The [SwapMeta] address of the swap. Closed if it exists.
+    #[account(mut)]
+    pub swap_meta: UncheckedAccount<'info>,
+    /// The [Observations] address of the swap. Closed if it exists.
+    #[account(mut)]
+    pub observations: UncheckedAccount<'info>,
+    /// The pool mint of the swap.
+    pub pool_mint: Box<Account<'info, Mint>>,
+    /// Token 0 of the swap.
+    pub token_0: CloseSwapToken<'info>,
+    /// Token 1 of the swap.
+    pub token_1: CloseSwapToken<'info>,
+    /// Receives the rent of all closed accounts.
+    #[account(mut)]
+    pub rent_destination: UncheckedAccount<'info>,
+}
+
+/// Accounts for a [cpamm::set_admin_fees_account] instruction.
+#[derive(Accounts)]
+pub struct SetAdminFeesAccount<'info> {
+    /// The admin of the [SwapInfo].
+    pub swap_admin: SwapAdmin<'info>,
+    /// The current admin fees account of the token. Its balance is moved to `new_fees`.
+    #[account(mut)]
+    pub previous_fees: UncheckedAccount<'info>,
+    /// The new admin fees account of the token.
+    #[account(mut)]
+    pub new_fees: UncheckedAccount<'info>,
+    /// The mint of the token.
+    pub mint: UncheckedAccount<'info>,
+    /// The token program of the mint.
+    pub token_program: UncheckedAccount<'info>,
+}
+
+/// Accounts for a [cpamm::accept_swap_admin] instruction.
+#[derive(Accounts)]
+pub struct AcceptSwapAdmin<'info> {
+    /// The pending admin of the [SwapInfo].
+    pub pending_admin: Signer<'info>,
+    /// The [SwapInfo] account.
+    #[account(mut)]
+    pub swap: Box<Account<'info, SwapInfo>>,
+}
+
+/// Accounts for admin instructions on a [Factory].
+#[derive(Accounts)]
+pub struct FactoryAdmin<'info> {
+    /// The admin of the [Factory].
+    pub admin: Signer<'info>,
+    /// The [Factory].
+    #[account(mut)]
+    pub factory: Box<Account<'info, Factory>>,
+}
+
+/// Accounts for a [cpamm::accept_factory_admin] instruction.
+#[derive(Accounts)]
+pub struct AcceptFactoryAdmin<'info> {
+    /// The pending admin of the [Factory].
+    pub pending_admin: Signer<'info>,
+    /// The [Factory].
+    #[account(mut)]
+    pub factory: Box<Account<'info, Factory>>,
+}
+
+/// Accounts for a [cpamm::sync_swap_admin] instruction.
+#[derive(Accounts)]
+pub struct SyncSwapAdmin<'info> {
+    /// The [Factory] admin.
+    pub factory_admin: FactoryAdmin<'info>,
+    /// The [SwapInfo] account.
+    #[account(mut)]
+    pub swap: Box<Account<'info, SwapInfo>>,
+}
+
+/// Accounts for a [cpamm::add_pool_creator] instruction.
+#[derive(Accounts)]
+pub struct AddPoolCreator<'info> {
+    /// The [Factory] admin.
+    pub factory_admin: FactoryAdmin<'info>,
+    /// The key which may create swaps.
+    pub creator: UncheckedAccount<'info>,
+    /// The [PoolCreator].
+    #[account(
+        init,
+        seeds = [
+            b"PoolCreator".as_ref(),
+            factory_admin.factory.key().to_bytes().as_ref(),
+            creator.key().to_bytes().as_ref()
+        ],
+        bump,
+        payer = payer
+    )]
+    pub pool_creator: Box<Account<'info, PoolCreator>>,
+
+    /// Payer.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// [System] program.
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for a [cpamm::remove_pool_creator] instruction.
+#[derive(Accounts)]
+pub struct RemovePoolCreator<'info> {
+    /// The [Factory] admin.
+    pub factory_admin: FactoryAdmin<'info>,
+    /// The [PoolCreator].
+    #[account(mut, close = recipient)]
+    pub pool_creator: Box<Account<'info, PoolCreator>>,
+    /// Receives the rent of the [PoolCreator].
+    #[account(mut)]
+    pub recipient: UncheckedAccount<'info>,
+}
+
+/// Accounts for a [cpamm::deny_mint] instruction.
+#[derive(Accounts)]
+pub struct DenyMint<'info> {
+    /// The [Factory] admin.
+    pub factory_admin: FactoryAdmin<'info>,
+    /// The mint to deny.
+    pub mint: UncheckedAccount<'info>,
+    /// The [DeniedMint].
+    #[account(
+        init,
+        seeds = [
+            b"DeniedMint".as_ref(),
+            factory_admin.factory.key().to_bytes().as_ref(),
+            mint.key().to_bytes().as_ref()
+        ],
+        bump,
+        payer = payer
+    )]
+    pub denied_mint: Box<Account<'info, DeniedMint>>,
+
+    /// Payer.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// [System] program.
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for a [cpamm::allow_mint] instruction.
+#[derive(Accounts)]
+pub struct AllowMint<'info> {
+    /// The [Factory] admin.
+    pub factory_admin: FactoryAdmin<'info>,
+    /// The [DeniedMint].
+    #[account(mut, close = recipient)]
+    pub denied_mint: Box<Account<'info, DeniedMint>>,
+    /// Receives the rent of the [DeniedMint].
+    #[account(mut)]
+    pub recipient: UncheckedAccount<'info>,
+}
+
+/// Accounts for a [cpamm::new_concentrated_pool] instruction.
+#[derive(Accounts)]
+#[instruction(_bump: u8, tick_spacing: u16)]
+pub struct NewConcentratedPool<'info> {
+    /// The [Factory].
+    #[account(mut)]
+    pub factory: Box<Account<'info, Factory>>,
+
+    /// The [ConcentratedPool].
+    #[account(
+        init,
+        seeds = [
+            b"ConcentratedPool".as_ref(),
+            factory.key().to_bytes().as_ref(),
+            token_0.mint.key().to_bytes().as_ref(),
+            token_1.mint.key().to_bytes().as_ref(),
+            tick_spacing.to_le_bytes().as_ref()
+        ],
+        bump,
+        payer = payer
+    )]
+    pub pool: Box<Account<'info, ConcentratedPool>>,
+
+    /// The lowest [Tick] of the pool.
+    #[account(
+        init,
+        seeds = [
+            b"Tick".as_ref(),
+            pool.key().to_bytes().as_ref(),
+            xyk::min_tick(tick_spacing).to_le_bytes().as_ref()
+        ],
+        bump,
+        payer = payer
+    )]
+    pub tick_min: Box<Account<'info, Tick>>,
+
+    /// The highest [Tick] of the pool.
+    #[account(
+        init,
+        seeds = [
+            b"Tick".as_ref(),
+            pool.key().to_bytes().as_ref(),
+            xyk::max_tick(tick_spacing).to_le_bytes().as_ref()
+        ],
+        bump,
+        payer = payer
+    )]
+    pub tick_max: Box<Account<'info, Tick>>,
+
+    /// The first token of the pool.
+    pub token_0: InitConcentratedToken<'info>,
+    /// The second token of the pool. Its mint must be
+    /// after the first mint. using canonical ordering.
+    pub token_1: InitConcentratedToken<'info>,
+
+    /// Payer. This is the creator of the pool.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// The [PoolCreator] of the payer. Only checked if the [Factory] restricts pool creators.
+    pub pool_creator: UncheckedAccount<'info>,
+
+    /// [System] program.
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for a [cpamm::open_position] instruction.
+#[derive(Accounts)]
+#[instruction(_bump: u8, tick_lower: i32, tick_upper: i32)]
+pub struct OpenPosition<'info> {
+    /// The [ConcentratedPool].
+    pub pool: Box<Account<'info, ConcentratedPool>>,
+
+    /// Owner of the [Position].
+    pub owner: Signer<'info>,
+
+    /// The [Position].
+    #[account(
+        init,
+        seeds = [
+            b"Position".as_ref(),
+            pool.key().to_bytes().as_ref(),
+            owner.key().to_bytes().as_ref(),
+            tick_lower.to_le_bytes().as_ref(),
+            tick_upper.to_le_bytes().as_ref()
+        ],
+        bump,
+        payer = payer
+    )]
+    pub position: Box<Account<'info, Position>>,
+
+    /// Payer.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// [System] program.
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for a [cpamm::increase_liquidity] instruction.
+///
+/// The [Tick]s of the [Position] are created if they do not exist yet. The linked ticks
+/// adjacent to the ticks which do not bound any liquidity yet are passed as remaining accounts.
+#[derive(Accounts)]
+pub struct IncreaseLiquidity<'info> {
+    /// The [ConcentratedPool].
+    #[account(mut)]
+    pub pool: Box<Account<'info, ConcentratedPool>>,
+    /// Owner of the [Position].
+    pub owner: Signer<'info>,
+    /// The [Position].
+    #[account(mut)]
+    pub position: Box<Account<'info, Position>>,
+    /// The lower [Tick] of the position.
+    #[account(
+        init_if_needed,
+        seeds = [
+            b"Tick".as_ref(),
+            pool.key().to_bytes().as_ref(),
+            position.tick_lower.to_le_bytes().as_ref()
+        ],
+        bump,
+        payer = payer
+    )]
+    pub tick_lower: Box<Account<'info, Tick>>,
+    /// The upper [Tick] of the position.
+    #[account(
+        init_if_needed,
+        seeds = [
+            b"Tick".as_ref(),
+            pool.key().to_bytes().as_ref(),
+            position.tick_upper.to_le_bytes().as_ref()
+        ],
+        bump,
+        payer = payer
+    )]
+    pub tick_upper: Box<Account<'info, Tick>>,
+    /// Token 0 of the pool.
+    pub token_0: SwapToken<'info>,
+    /// Token 1 of the pool.
+    pub token_1: SwapToken<'info>,
+
+    /// Payer of the rent of new [Tick]s.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// [System] program.
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for a [cpamm::decrease_liquidity] or [cpamm::collect_fees] instruction.
+///
+/// When decreasing liquidity, the linked ticks adjacent to the ticks which no longer
+/// bound any liquidity are passed as remaining accounts.
+#[derive(Accounts)]
+pub struct ModifyLiquidity<'info> {
+    /// The [ConcentratedPool].
+    #[account(mut)]
+    pub pool: Box<Account<'info, ConcentratedPool>>,
+    /// Owner of the [Position].
+    pub owner: Signer<'info>,
+    /// The [Position].
+    #[account(mut)]
+    pub position: Box<Account<'info, Position>>,
+    /// The lower [Tick] of the position.
+    #[account(mut)]
+    pub tick_lower: Box<Account<'info, Tick>>,
+    /// The upper [Tick] of the position.
+    #[account(mut)]
+    pub tick_upper: Box<Account<'info, Tick>>,
+    /// Token 0 of the pool.
+    pub token_0: SwapToken<'info>,
+    /// Token 1 of the pool.
+    pub token_1: SwapToken<'info>,
+}
+
+/// Accounts for a [cpamm::close_position] instruction.
+#[derive(Accounts)]
+pub struct ClosePosition<'info> {
+    /// Owner of the [Position]. Receives the rent of the position.
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    /// The [Position].
+    #[account(mut, close = owner)]
+    pub position: Box<Account<'info, Position>>,
+}
+
+/// Accounts for a [cpamm::swap_concentrated] instruction.
+///
+/// The [Tick]s crossed by the swap are passed as remaining accounts, in the order
+/// in which they are crossed.
+#[derive(Accounts)]
+pub struct SwapConcentrated<'info> {
+    /// The [ConcentratedPool].
+    #[account(mut)]
+    pub pool: Box<Account<'info, ConcentratedPool>>,
+    /// The authority of the user.
+    pub user_authority: Signer<'info>,
+    /// The input token of the swap.
+    pub input: SwapToken<'info>,
+    /// The output token of the swap.
+    pub output: SwapToken<'info>,
+}
+
 // --------------------------------
 // Various accounts
 // --------------------------------
 
 /// Token accounts for the creation of a [SwapInfo].
+///
+/// The token may belong to either the SPL Token or the Token-2022 program.
+/// For more information, view [crate::token_interface].
 #[derive(Accounts)]
 pub struct InitSwapToken<'info> {
-    /// The [Mint] of the token.
-    pub mint: Box<Account<'info, Mint>>,
+    /// The mint of the token.
+    pub mint: UncheckedAccount<'info>,
     /// The taken account for the pool's reserves of this token.
-    pub reserve: Box<Account<'info, TokenAccount>>,
+    pub reserve: UncheckedAccount<'info>,
     /// The token account for the fees associated with the token.
-    pub fees: Box<Account<'info, TokenAccount>>,
+    pub fees: UncheckedAccount<'info>,
+    /// The [DeniedMint] address of the mint, which must not exist.
+    pub denied_mint: UncheckedAccount<'info>,
+}
+
+/// Token accounts of a [SwapInfo] which is being closed.
+///
+/// The token may belong to either the SPL Token or the Token-2022 program.
+/// For more information, view [crate::token_interface].
+#[derive(Accounts)]
+pub struct CloseSwapToken<'info> {
+    /// The token account for the pool's reserves of this token.
+    #[account(mut)]
+    pub reserve: UncheckedAccount<'info>,
+    /// The token account for the fees associated with the token.
+    #[account(mut)]
+    pub fees: UncheckedAccount<'info>,
+    /// The token account receiving the remaining balances of the reserve and the fees.
+    #[account(mut)]
+    pub destination: UncheckedAccount<'info>,
+    /// The mint of the token, which receives the transfer fees withheld in the closed accounts.
+    #[account(mut)]
+    pub mint: UncheckedAccount<'info>,
+    /// The token program of the mint.
+    pub token_program: UncheckedAccount<'info>,
+}
+
+/// Token accounts for the creation of a [ConcentratedPool].
+///
+/// The token may belong to either the SPL Token or the Token-2022 program.
+/// For more information, view [crate::token_interface].
+#[derive(Accounts)]
+pub struct InitConcentratedToken<'info> {
+    /// The mint of the token.
+    pub mint: UncheckedAccount<'info>,
+    /// The token account for the pool's reserves of this token.
+    pub reserve: UncheckedAccount<'info>,
+    /// The [DeniedMint] address of the mint, which must not exist.
+    pub denied_mint: UncheckedAccount<'info>,
 }
 
 #[derive(Accounts)]
 /// Context common to all router operations.
 pub struct SwapUserContext<'info> {
-    /// The [Token] program.
+    /// The [Token] program of the pool mint.
     pub token_program: Program<'info, Token>,
     /// The [SwapInfo] account.
     #[account(mut)]
@@ -173,27 +755,78 @@ pub struct SwapUserContext<'info> {
 }
 
 /// Token accounts for a 'swap' instruction.
+///
+/// The token may belong to either the SPL Token or the Token-2022 program.
+/// For more information, view [crate::token_interface].
 #[derive(Accounts)]
 pub struct SwapToken<'info> {
     /// The token account associated with the user.
     #[account(mut)]
-    pub user: Box<Account<'info, TokenAccount>>,
+    pub user: UncheckedAccount<'info>,
     /// The token account for the pool's reserves of this token.
     #[account(mut)]
-    pub reserve: Box<Account<'info, TokenAccount>>,
+    pub reserve: UncheckedAccount<'info>,
+    /// The mint of the token.
+    pub mint: UncheckedAccount<'info>,
+    /// The token program of the mint.
+    pub token_program: UncheckedAccount<'info>,
 }
 
 /// Token accounts for the destination of a swap instruction.
+///
+/// The token may belong to either the SPL Token or the Token-2022 program.
+/// For more information, view [crate::token_interface].
 #[derive(Accounts)]
 pub struct SwapTokenWithFees<'info> {
     /// TODO: init <> transfer instruction from user
     /// The token account associated with the user.
     #[account(mut)]
-    pub user: Box<Account<'info, TokenAccount>>,
+    pub user: UncheckedAccount<'info>,
     /// The taken account for the pool's reserves of this token.
     #[account(mut)]
-    pub reserve: Box<Account<'info, TokenAccount>>,
+    pub reserve: UncheckedAccount<'info>,
+    /// The token account for the fees associated with the token.
+    #[account(mut)]
+    pub fees: UncheckedAccount<'info>,
+    /// The mint of the token.
+    pub mint: UncheckedAccount<'info>,
+    /// The token program of the mint.
+    pub token_program: UncheckedAccount<'info>,
+}
+
+/// Token accounts for a [cpamm::skim] instruction.
+///
+/// The token may belong to either the SPL Token or the Token-2022 program.
+/// For more information, view [crate::token_interface].
+#[derive(Accounts)]
+pub struct SkimToken<'info> {
+    /// The token account for the pool's reserves of this token.
+    #[account(mut)]
+    pub reserve: UncheckedAccount<'info>,
     /// The token account for the fees associated with the token.
+    /// Skimmed tokens are sent here.
+    #[account(mut)]
+    pub fees: UncheckedAccount<'info>,
+    /// The mint of the token.
+    pub mint: UncheckedAccount<'info>,
+    /// The token program of the mint.
+    pub token_program: UncheckedAccount<'info>,
+}
+
+/// Token accounts for a [cpamm::claim_admin_fees] instruction.
+///
+/// The token may belong to either the SPL Token or the Token-2022 program.
+/// For more information, view [crate::token_interface].
+#[derive(Accounts)]
+pub struct AdminFeesToken<'info> {
+    /// The token account for the fees associated with the token.
+    #[account(mut)]
+    pub fees: UncheckedAccount<'info>,
+    /// The token account receiving the fees.
     #[account(mut)]
-    pub fees: Box<Account<'info, TokenAccount>>,
+    pub destination: UncheckedAccount<'info>,
+    /// The mint of the token.
+    pub mint: UncheckedAccount<'info>,
+    /// The token program of the mint.
+    pub token_program: UncheckedAccount<'info>,
 }
@@ -1,7 +1,13 @@
+use crate::token_interface::{InterfaceAccount, Mint, TokenAccount};
 use crate::*;
 use crate::{
-    Deposit, InitSwapToken, NewFactory, NewSwap, NewSwapMeta, Swap, SwapToken, SwapTokenInfo,
-    SwapTokenWithFees, SwapUserContext, Withdraw,
+    AcceptFactoryAdmin, AcceptSwapAdmin, AddPoolCreator, AdminFeesToken, AllowMint, ClaimAdminFees,
+    ClosePosition, ConcentratedTokenInfo, Consult, DenyMint, Deposit, DepositSingle, FactoryAdmin,
+    FlashLoan, IncreaseLiquidity, InitConcentratedToken, InitSwapToken, ModifyLiquidity,
+    NewConcentratedPool, NewFactory, NewObservations, NewSwap, NewSwapMeta, OpenPosition,
+    RecordObservation, RemovePoolCreator, SetAdminFeesAccount, Skim, SkimToken, Swap, SwapAdmin,
+    SwapConcentrated, SwapRoute, SwapToken, SwapTokenInfo, SwapTokenWithFees, SwapUserContext,
+    SyncReserves, SyncSwapAdmin, Withdraw, WithdrawOne,
 };
 
 // --------------------------------
@@ -16,7 +22,9 @@ impl<'info> Validate<'info> for NewFactory<'info> {
 
 impl<'info> Validate<'info> for NewSwap<'info> {
     fn validate(&self) -> Result<()> {
-        let pool_mint_decimals = self.token_0.mint.decimals.max(self.token_1.mint.decimals);
+        let mint_0: InterfaceAccount<Mint> = InterfaceAccount::try_from(&self.token_0.mint)?;
+        let mint_1: InterfaceAccount<Mint> = InterfaceAccount::try_from(&self.token_1.mint)?;
+        let pool_mint_decimals = mint_0.decimals.max(mint_1.decimals);
 
         // pool mint belongs to swap
         invariant!(
@@ -40,8 +48,10 @@ impl<'info> Validate<'info> for NewSwap<'info> {
         invariant!(token_0_mint.key() < token_1_mint.key(), SwapTokensNotSorted);
 
         let swap_key = self.swap.key();
-        self.token_0.validate_for_swap(swap_key)?;
-        self.token_1.validate_for_swap(swap_key)?;
+        self.token_0.validate_for_swap(&self.factory, swap_key)?;
+        self.token_1.validate_for_swap(&self.factory, swap_key)?;
+
+        validate_pool_creator(&self.factory, &self.pool_creator, self.payer.key())?;
 
         Ok(())
     }
@@ -59,6 +69,21 @@ impl<'info> Validate<'info> for SwapUserContext<'info> {
         // ensure no self-dealing
         assert_keys_neq!(self.user_authority, self.swap);
         invariant!(!self.swap.is_paused, Paused);
+        // the reserves are not accurate while a flash loan is in progress
+        invariant!(!self.swap.flash_loan.is_active(), FlashLoanInProgress);
+        Ok(())
+    }
+}
+
+impl<'info> SwapUserContext<'info> {
+    /// Validates the context of a withdrawal, which is allowed while the swap is closing.
+    fn validate_for_withdraw(&self) -> Result<()> {
+        if !self.swap.is_closing() {
+            return self.validate();
+        }
+        // ensure no self-dealing
+        assert_keys_neq!(self.user_authority, self.swap);
+        invariant!(!self.swap.flash_loan.is_active(), FlashLoanInProgress);
         Ok(())
     }
 }
@@ -75,7 +100,9 @@ impl<'info> Validate<'info> for Swap<'info> {
                 (&self.user.swap.token_1, &self.user.swap.token_0)
             };
 
-        assert_keys_eq!(self.output.user.owner, self.user.user_authority);
+        let output_user: InterfaceAccount<TokenAccount> =
+            InterfaceAccount::try_from(&self.output.user)?;
+        assert_keys_eq!(output_user.owner, self.user.user_authority);
 
         self.input.validate_for_swap(swap_input)?;
         self.output.validate_for_swap(swap_output)?;
@@ -84,9 +111,24 @@ impl<'info> Validate<'info> for Swap<'info> {
     }
 }
 
+impl<'info> Validate<'info> for SwapRoute<'info> {
+    fn validate(&self) -> Result<()> {
+        let input: InterfaceAccount<TokenAccount> = InterfaceAccount::try_from(&self.input)?;
+        let output: InterfaceAccount<TokenAccount> = InterfaceAccount::try_from(&self.output)?;
+        assert_keys_eq!(output.owner, self.user_authority);
+        assert_keys_neq!(self.input, self.output);
+
+        // loading the mint ensures that it is owned by a token program
+        let input_mint: InterfaceAccount<Mint> = InterfaceAccount::try_from(&self.input_mint)?;
+        assert_keys_eq!(input.mint, input_mint);
+        assert_keys_eq!(self.token_program, *self.input_mint.owner);
+        Ok(())
+    }
+}
+
 impl<'info> Validate<'info> for Withdraw<'info> {
     fn validate(&self) -> Result<()> {
-        self.user.validate()?;
+        self.user.validate_for_withdraw()?;
 
         assert_keys_eq!(self.pool_mint, self.user.swap.pool_mint);
         assert_keys_eq!(self.input_lp.mint, self.pool_mint);
@@ -98,6 +140,28 @@ impl<'info> Validate<'info> for Withdraw<'info> {
     }
 }
 
+impl<'info> Validate<'info> for WithdrawOne<'info> {
+    fn validate(&self) -> Result<()> {
+        self.user.validate()?;
+
+        assert_keys_eq!(self.pool_mint, self.user.swap.pool_mint);
+        assert_keys_eq!(self.input_lp.mint, self.pool_mint);
+
+        // inner validation will ensure that token destination mint equals respective reserve
+        let (swap_output, swap_other) =
+            if self.output.reserve.key() == self.user.swap.token_0.reserves {
+                (&self.user.swap.token_0, &self.user.swap.token_1)
+            } else {
+                (&self.user.swap.token_1, &self.user.swap.token_0)
+            };
+
+        self.output.validate_for_swap(swap_output)?;
+        assert_keys_eq!(self.other_reserve, swap_other.reserves);
+
+        Ok(())
+    }
+}
+
 impl<'info> Validate<'info> for Deposit<'info> {
     fn validate(&self) -> Result<()> {
         self.user.validate()?;
@@ -117,6 +181,326 @@ impl<'info> Validate<'info> for Deposit<'info> {
     }
 }
 
+impl<'info> Validate<'info> for DepositSingle<'info> {
+    fn validate(&self) -> Result<()> {
+        self.user.validate()?;
+
+        // inner validation will ensure that token source mint equals respective reserve
+        let (swap_input, swap_other) =
+            if self.input.reserve.key() == self.user.swap.token_0.reserves {
+                (&self.user.swap.token_0, &self.user.swap.token_1)
+            } else {
+                (&self.user.swap.token_1, &self.user.swap.token_0)
+            };
+
+        self.input.validate_for_swap(swap_input)?;
+        assert_keys_eq!(self.other_reserve, swap_other.reserves);
+
+        // should be same as swap
+        assert_keys_eq!(self.pool_mint, self.user.swap.pool_mint);
+
+        // lp output destination
+        assert_keys_eq!(self.output_lp.mint, self.user.swap.pool_mint);
+        assert_keys_neq!(self.output_lp.owner, self.user.swap);
+
+        Ok(())
+    }
+}
+
+impl<'info> Validate<'info> for NewObservations<'info> {
+    fn validate(&self) -> Result<()> {
+        assert_keys_eq!(self.admin, self.swap.admin_key, Unauthorized);
+        Ok(())
+    }
+}
+
+impl<'info> Validate<'info> for RecordObservation<'info> {
+    fn validate(&self) -> Result<()> {
+        assert_keys_eq!(self.reserve_0, self.swap.token_0.reserves);
+        assert_keys_eq!(self.reserve_1, self.swap.token_1.reserves);
+        assert_keys_eq!(self.observations.swap, self.swap);
+        Ok(())
+    }
+}
+
+impl<'info> Validate<'info> for Consult<'info> {
+    fn validate(&self) -> Result<()> {
+        assert_keys_eq!(self.reserve_0, self.swap.token_0.reserves);
+        assert_keys_eq!(self.reserve_1, self.swap.token_1.reserves);
+        assert_keys_eq!(self.observations.swap, self.swap);
+        Ok(())
+    }
+}
+
+impl<'info> Validate<'info> for SyncReserves<'info> {
+    fn validate(&self) -> Result<()> {
+        invariant!(!self.swap.is_paused, Paused);
+        invariant!(!self.swap.flash_loan.is_active(), FlashLoanInProgress);
+        assert_keys_eq!(self.reserve_0, self.swap.token_0.reserves);
+        assert_keys_eq!(self.reserve_1, self.swap.token_1.reserves);
+        Ok(())
+    }
+}
+
+impl<'info> Validate<'info> for Skim<'info> {
+    fn validate(&self) -> Result<()> {
+        invariant!(!self.swap.is_paused, Paused);
+        invariant!(!self.swap.flash_loan.is_active(), FlashLoanInProgress);
+        self.token_0.validate_for_swap(&self.swap.token_0)?;
+        self.token_1.validate_for_swap(&self.swap.token_1)?;
+        Ok(())
+    }
+}
+
+impl<'info> Validate<'info> for FlashLoan<'info> {
+    fn validate(&self) -> Result<()> {
+        // [SwapUserContext] is not validated here, since repayment must
+        // be possible while a flash loan is in progress.
+        assert_keys_neq!(self.user.user_authority, self.user.swap);
+
+        // inner validation will ensure that token mint equals respective reserve
+        let (swap_token, swap_other) =
+            if self.token.reserve.key() == self.user.swap.token_0.reserves {
+                (&self.user.swap.token_0, &self.user.swap.token_1)
+            } else {
+                (&self.user.swap.token_1, &self.user.swap.token_0)
+            };
+
+        self.token.validate_for_swap(swap_token)?;
+        assert_keys_eq!(self.other_reserve, swap_other.reserves);
+
+        Ok(())
+    }
+}
+
+impl<'info> Validate<'info> for SwapAdmin<'info> {
+    fn validate(&self) -> Result<()> {
+        assert_keys_eq!(self.admin, self.swap.admin_key, Unauthorized);
+        Ok(())
+    }
+}
+
+impl<'info> Validate<'info> for AcceptSwapAdmin<'info> {
+    fn validate(&self) -> Result<()> {
+        assert_keys_eq!(
+            self.pending_admin,
+            self.swap.pending_admin_key,
+            Unauthorized
+        );
+        Ok(())
+    }
+}
+
+impl<'info> Validate<'info> for FactoryAdmin<'info> {
+    fn validate(&self) -> Result<()> {
+        assert_keys_eq!(self.admin, self.factory.admin, Unauthorized);
+        Ok(())
+    }
+}
+
+impl<'info> Validate<'info> for AddPoolCreator<'info> {
+    fn validate(&self) -> Result<()> {
+        self.factory_admin.validate()
+    }
+}
+
+impl<'info> Validate<'info> for RemovePoolCreator<'info> {
+    fn validate(&self) -> Result<()> {
+        self.factory_admin.validate()?;
+        assert_keys_eq!(self.pool_creator.factory, self.factory_admin.factory);
+        Ok(())
+    }
+}
+
+impl<'info> Validate<'info> for DenyMint<'info> {
+    fn validate(&self) -> Result<()> {
+        self.factory_admin.validate()
+    }
+}
+
+impl<'info> Validate<'info> for AllowMint<'info> {
+    fn validate(&self) -> Result<()> {
+        self.factory_admin.validate()?;
+        assert_keys_eq!(self.denied_mint.factory, self.factory_admin.factory);
+        Ok(())
+    }
+}
+
+impl<'info> Validate<'info> for AcceptFactoryAdmin<'info> {
+    fn validate(&self) -> Result<()> {
+        assert_keys_eq!(self.pending_admin, self.factory.pending_admin, Unauthorized);
+        Ok(())
+    }
+}
+
+impl<'info> Validate<'info> for SyncSwapAdmin<'info> {
+    fn validate(&self) -> Result<()> {
+        self.factory_admin.validate()?;
+        assert_keys_eq!(self.swap.factory, self.factory_admin.factory);
+        Ok(())
+    }
+}
+
+impl<'info> Validate<'info> for ClaimAdminFees<'info> {
+    fn validate(&self) -> Result<()> {
+        self.swap_admin.validate()?;
+        let swap = &self.swap_admin.swap;
+        self.token_0.validate_for_swap(&swap.token_0)?;
+        self.token_1.validate_for_swap(&swap.token_1)?;
+        Ok(())
+    }
+}
+
+impl<'info> Validate<'info> for CloseSwap<'info> {
+    fn validate(&self) -> Result<()> {
+        assert_keys_eq!(self.admin, self.swap.admin_key, Unauthorized);
+        invariant!(self.swap.is_closing(), SwapNotClosing);
+        // all liquidity must have been withdrawn
+        assert_keys_eq!(self.pool_mint, self.swap.pool_mint);
+        invariant!(self.pool_mint.supply == 0, SwapPoolMintSupply);
+
+        let (swap_meta, _) = Pubkey::find_program_address(
+            &[
+                b"SwapMeta",
+                self.swap.factory.as_ref(),
+                &self.swap.index.to_le_bytes(),
+            ],
+            &crate::ID,
+        );
+        assert_keys_eq!(self.swap_meta, swap_meta);
+        let (observations, _) =
+            Pubkey::find_program_address(&[b"Observations", self.swap.key().as_ref()], &crate::ID);
+        assert_keys_eq!(self.observations, observations);
+
+        self.token_0.validate_for_swap(&self.swap.token_0)?;
+        self.token_1.validate_for_swap(&self.swap.token_1)?;
+        assert_keys_neq!(self.rent_destination, self.swap);
+        Ok(())
+    }
+}
+
+impl<'info> Validate<'info> for SetAdminFeesAccount<'info> {
+    fn validate(&self) -> Result<()> {
+        self.swap_admin.validate()?;
+        let swap = &self.swap_admin.swap;
+        let swap_token = if self.mint.key() == swap.token_0.mint {
+            &swap.token_0
+        } else {
+            assert_keys_eq!(self.mint, swap.token_1.mint);
+            &swap.token_1
+        };
+        assert_keys_eq!(self.previous_fees, swap_token.admin_fees);
+        // the mint of a swap is always owned by a token program
+        assert_keys_eq!(self.token_program, *self.mint.owner);
+
+        validate_admin_fees_account(&self.new_fees, swap_token.mint, swap.key())?;
+        assert_keys_neq!(self.new_fees, self.previous_fees);
+        // otherwise protocol fees would accrue to the LP holders
+        assert_keys_neq!(self.new_fees, swap_token.reserves);
+        Ok(())
+    }
+}
+
+impl<'info> Validate<'info> for NewConcentratedPool<'info> {
+    fn validate(&self) -> Result<()> {
+        let token_0_mint = &self.token_0.mint;
+        let token_1_mint = &self.token_1.mint;
+        assert_keys_neq!(
+            token_0_mint.key(),
+            token_1_mint.key(),
+            SwapTokensCannotBeEqual
+        );
+        invariant!(token_0_mint.key() < token_1_mint.key(), SwapTokensNotSorted);
+
+        let pool_key = self.pool.key();
+        self.token_0.validate_for_pool(&self.factory, pool_key)?;
+        self.token_1.validate_for_pool(&self.factory, pool_key)?;
+
+        validate_pool_creator(&self.factory, &self.pool_creator, self.payer.key())?;
+
+        Ok(())
+    }
+}
+
+impl<'info> Validate<'info> for OpenPosition<'info> {
+    fn validate(&self) -> Result<()> {
+        // the range of the position is validated by the instruction
+        Ok(())
+    }
+}
+
+impl<'info> Validate<'info> for IncreaseLiquidity<'info> {
+    fn validate(&self) -> Result<()> {
+        assert_keys_eq!(self.owner, self.position.owner, Unauthorized);
+        assert_keys_eq!(self.position.pool, self.pool);
+        // the ticks are derived from the position by their seeds
+
+        self.token_0.validate_for_pool(&self.pool.token_0)?;
+        self.token_1.validate_for_pool(&self.pool.token_1)?;
+
+        Ok(())
+    }
+}
+
+impl<'info> Validate<'info> for ModifyLiquidity<'info> {
+    fn validate(&self) -> Result<()> {
+        assert_keys_eq!(self.owner, self.position.owner, Unauthorized);
+        assert_keys_eq!(self.position.pool, self.pool);
+
+        assert_keys_eq!(self.tick_lower.pool, self.pool);
+        invariant!(
+            self.tick_lower.index == self.position.tick_lower,
+            InvalidTick
+        );
+        assert_keys_eq!(self.tick_upper.pool, self.pool);
+        invariant!(
+            self.tick_upper.index == self.position.tick_upper,
+            InvalidTick
+        );
+
+        self.token_0.validate_for_pool(&self.pool.token_0)?;
+        self.token_1.validate_for_pool(&self.pool.token_1)?;
+
+        Ok(())
+    }
+}
+
+impl<'info> Validate<'info> for ClosePosition<'info> {
+    fn validate(&self) -> Result<()> {
+        assert_keys_eq!(self.owner, self.position.owner, Unauthorized);
+        invariant!(
+            self.position.liquidity == 0
+                && self.position.fees_owed_0 == 0
+                && self.position.fees_owed_1 == 0,
+            PositionNotEmpty
+        );
+        Ok(())
+    }
+}
+
+impl<'info> Validate<'info> for SwapConcentrated<'info> {
+    fn validate(&self) -> Result<()> {
+        // ensure no self-dealing
+        assert_keys_neq!(self.user_authority, self.pool);
+
+        // inner validation will ensure that token source mint equals respective reserve
+        let (pool_input, pool_output) = if self.input.reserve.key() == self.pool.token_0.reserves {
+            (&self.pool.token_0, &self.pool.token_1)
+        } else {
+            (&self.pool.token_1, &self.pool.token_0)
+        };
+
+        let output_user: InterfaceAccount<TokenAccount> =
+            InterfaceAccount::try_from(&self.output.user)?;
+        assert_keys_eq!(output_user.owner, self.user_authority);
+
+        self.input.validate_for_pool(pool_input)?;
+        self.output.validate_for_pool(pool_output)?;
+
+        Ok(())
+    }
+}
+
 // --------------------------------
 // Account Structs
 // --------------------------------
@@ -124,21 +508,18 @@ impl<'info> Validate<'info> for Deposit<'info> {
 impl<'info> InitSwapToken<'info> {
     /// Validate the init swap.
     #[allow(deprecated)]
-    fn validate_for_swap(&self, swap: Pubkey) -> Result<()> {
-        // We could check token freeze authority presence
-        // This ensures the swap will always be functional, since a freeze
-        // would prevent the swap from working.
-        // We do not think this is necessary to add.
-
-        assert_keys_eq!(self.fees.mint, self.mint);
-        assert_keys_eq!(self.fees.owner, swap);
-        invariant!(self.fees.delegate.is_none());
-        invariant!(self.fees.close_authority.is_none());
-
-        assert_keys_eq!(self.reserve.mint, self.mint);
-        assert_keys_eq!(self.reserve.owner, swap);
-        invariant!(self.reserve.delegate.is_none());
-        invariant!(self.reserve.close_authority.is_none());
+    fn validate_for_swap(&self, factory: &Account<Factory>, swap: Pubkey) -> Result<()> {
+        let mint: InterfaceAccount<Mint> = InterfaceAccount::try_from(&self.mint)?;
+        invariant!(mint.has_supported_extensions(), UnsupportedMintExtension);
+        validate_mint_for_factory(factory, &mint, &self.denied_mint)?;
+
+        validate_admin_fees_account(&self.fees, self.mint.key(), swap)?;
+
+        let reserve: InterfaceAccount<TokenAccount> = InterfaceAccount::try_from(&self.reserve)?;
+        assert_keys_eq!(reserve.mint, self.mint);
+        assert_keys_eq!(reserve.owner, swap);
+        invariant!(reserve.delegate.is_none());
+        invariant!(reserve.close_authority.is_none());
 
         // ensure the fee and reserve accounts are different
         // otherwise protocol fees would accrue to the LP holders
@@ -147,10 +528,39 @@ impl<'info> InitSwapToken<'info> {
     }
 }
 
+impl<'info> InitConcentratedToken<'info> {
+    /// Validate the init pool.
+    fn validate_for_pool(&self, factory: &Account<Factory>, pool: Pubkey) -> Result<()> {
+        let mint: InterfaceAccount<Mint> = InterfaceAccount::try_from(&self.mint)?;
+        invariant!(mint.has_supported_extensions(), UnsupportedMintExtension);
+        validate_mint_for_factory(factory, &mint, &self.denied_mint)?;
+
+        let reserve: InterfaceAccount<TokenAccount> = InterfaceAccount::try_from(&self.reserve)?;
+        assert_keys_eq!(reserve.mint, self.mint);
+        assert_keys_eq!(reserve.owner, pool);
+        invariant!(reserve.delegate.is_none());
+        invariant!(reserve.close_authority.is_none());
+        Ok(())
+    }
+}
+
 impl<'info> SwapToken<'info> {
     fn validate_for_swap(&self, swap_info: &SwapTokenInfo) -> Result<()> {
-        assert_keys_eq!(self.reserve, swap_info.reserves);
-        assert_keys_eq!(self.user.mint, swap_info.mint);
+        self.validate_for_reserve(swap_info.reserves, swap_info.mint)
+    }
+
+    fn validate_for_pool(&self, token_info: &ConcentratedTokenInfo) -> Result<()> {
+        self.validate_for_reserve(token_info.reserves, token_info.mint)
+    }
+
+    fn validate_for_reserve(&self, reserve: Pubkey, mint: Pubkey) -> Result<()> {
+        assert_keys_eq!(self.reserve, reserve);
+        assert_keys_eq!(self.mint, mint);
+        // the mint of a swap is always owned by a token program
+        assert_keys_eq!(self.token_program, *self.mint.owner);
+
+        let user: InterfaceAccount<TokenAccount> = InterfaceAccount::try_from(&self.user)?;
+        assert_keys_eq!(user.mint, mint);
 
         // ensure no self-dealing
         assert_keys_neq!(self.reserve, self.user);
@@ -159,11 +569,59 @@ impl<'info> SwapToken<'info> {
     }
 }
 
+impl<'info> SkimToken<'info> {
+    fn validate_for_swap(&self, swap_info: &SwapTokenInfo) -> Result<()> {
+        assert_keys_eq!(self.fees, swap_info.admin_fees);
+        assert_keys_eq!(self.reserve, swap_info.reserves);
+        assert_keys_eq!(self.mint, swap_info.mint);
+        // the mint of a swap is always owned by a token program
+        assert_keys_eq!(self.token_program, *self.mint.owner);
+        Ok(())
+    }
+}
+
+impl<'info> CloseSwapToken<'info> {
+    fn validate_for_swap(&self, swap_info: &SwapTokenInfo) -> Result<()> {
+        assert_keys_eq!(self.reserve, swap_info.reserves);
+        assert_keys_eq!(self.fees, swap_info.admin_fees);
+        assert_keys_eq!(self.mint, swap_info.mint);
+        // the mint of a swap is always owned by a token program
+        assert_keys_eq!(self.token_program, *self.mint.owner);
+
+        let destination: InterfaceAccount<TokenAccount> =
+            InterfaceAccount::try_from(&self.destination)?;
+        assert_keys_eq!(destination.mint, swap_info.mint);
+        assert_keys_neq!(self.reserve, self.destination);
+        assert_keys_neq!(self.fees, self.destination);
+        Ok(())
+    }
+}
+
+impl<'info> AdminFeesToken<'info> {
+    fn validate_for_swap(&self, swap_info: &SwapTokenInfo) -> Result<()> {
+        assert_keys_eq!(self.fees, swap_info.admin_fees);
+        assert_keys_eq!(self.mint, swap_info.mint);
+        // the mint of a swap is always owned by a token program
+        assert_keys_eq!(self.token_program, *self.mint.owner);
+
+        let destination: InterfaceAccount<TokenAccount> =
+            InterfaceAccount::try_from(&self.destination)?;
+        assert_keys_eq!(destination.mint, swap_info.mint);
+        assert_keys_neq!(self.fees, self.destination);
+        Ok(())
+    }
+}
+
 impl<'info> SwapTokenWithFees<'info> {
     fn validate_for_swap(&self, swap_info: &SwapTokenInfo) -> Result<()> {
         assert_keys_eq!(self.fees, swap_info.admin_fees);
         assert_keys_eq!(self.reserve, swap_info.reserves);
-        assert_keys_eq!(self.user.mint, swap_info.mint);
+        assert_keys_eq!(self.mint, swap_info.mint);
+        // the mint of a swap is always owned by a token program
+        assert_keys_eq!(self.token_program, *self.mint.owner);
+
+        let user: InterfaceAccount<TokenAccount> = InterfaceAccount::try_from(&self.user)?;
+        assert_keys_eq!(user.mint, swap_info.mint);
 
         // ensure no self-dealing
         assert_keys_neq!(self.fees, self.user);
@@ -171,3 +629,51 @@ impl<'info> SwapTokenWithFees<'info> {
         Ok(())
     }
 }
+
+/// Validates an account which is to receive the admin fees of `mint` for the `swap`.
+fn validate_admin_fees_account(fees: &AccountInfo, mint: Pubkey, swap: Pubkey) -> Result<()> {
+    let fees: InterfaceAccount<TokenAccount> = InterfaceAccount::try_from(fees)?;
+    assert_keys_eq!(fees.mint, mint);
+    assert_keys_eq!(fees.owner, swap);
+    invariant!(fees.delegate.is_none());
+    invariant!(fees.close_authority.is_none());
+    Ok(())
+}
+
+/// Validates that the `creator` may create swaps and pools on the `factory`.
+/// The `pool_creator` is the [PoolCreator] address of the creator.
+fn validate_pool_creator<'info>(
+    factory: &Account<'info, Factory>,
+    pool_creator: &AccountInfo<'info>,
+    creator: Pubkey,
+) -> Result<()> {
+    if factory.policy.restrict_creators {
+        invariant!(*pool_creator.owner == crate::ID, PoolCreatorNotAllowed);
+        let pool_creator: Account<PoolCreator> = Account::try_from(pool_creator)?;
+        assert_keys_eq!(pool_creator.factory, factory.key(), PoolCreatorNotAllowed);
+        assert_keys_eq!(pool_creator.creator, creator, PoolCreatorNotAllowed);
+    }
+    Ok(())
+}
+
+/// Validates that the `mint` may be used by new swaps and pools of the `factory`.
+/// The `denied_mint` is the [DeniedMint] address of the mint.
+fn validate_mint_for_factory(
+    factory: &Account<Factory>,
+    mint: &InterfaceAccount<Mint>,
+    denied_mint: &AccountInfo,
+) -> Result<()> {
+    // a freeze of a reserve would prevent the swap from working
+    if factory.policy.require_no_freeze_authority {
+        invariant!(mint.freeze_authority.is_none(), MintHasFreezeAuthority);
+    }
+
+    // the mint is denied if its [DeniedMint] exists
+    let (expected_denied_mint, _) = Pubkey::find_program_address(
+        &[b"DeniedMint", factory.key().as_ref(), mint.key().as_ref()],
+        &crate::ID,
+    );
+    assert_keys_eq!(denied_mint.key(), expected_denied_mint);
+    invariant!(denied_mint.data_is_empty(), MintDenied);
+    Ok(())
+}
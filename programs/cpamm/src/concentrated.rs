@@ -0,0 +1,247 @@
+//! Concentrated liquidity pools.
+//!
+//! A [ConcentratedPool] prices swaps like a constant product pool whose liquidity
+//! changes whenever the price crosses an initialized [Tick]. Liquidity is provided
+//! by [Position]s over the range between two ticks, and only earns the trade fees
+//! of the swaps made while the price is within that range.
+//!
+//! Only the ends of the price range of the pool and the ticks which bound liquidity are
+//! linked into the list of ticks of the pool, so swaps only cross ticks which change the
+//! liquidity of the pool. For more information, view [TickList].
+//!
+//! Fees are tracked as in Uniswap V3: each tick stores the fee growth on the side of
+//! the tick opposite to the current price, from which the fee growth inside any range
+//! can be computed. Fee growths are allowed to overflow, so they are always compared
+//! using wrapping arithmetic.
+//!
+//! For the underlying math, view [xyk::compute_swap_step].
+#![deny(missing_docs)]
+
+use crate::*;
+
+/// Maximum [ConcentratedPool::tick_spacing].
+pub const MAX_TICK_SPACING: u16 = 16_384;
+
+impl ConcentratedPool {
+    /// Index of the lowest [Tick] of the pool, which is created with the pool.
+    pub fn min_tick(&self) -> i32 {
+        xyk::min_tick(self.tick_spacing)
+    }
+
+    /// Index of the highest [Tick] of the pool, which is created with the pool.
+    pub fn max_tick(&self) -> i32 {
+        xyk::max_tick(self.tick_spacing)
+    }
+
+    /// Returns true if `tick` is one of the ends of the pool, which are always linked.
+    pub fn is_end_tick(&self, tick: i32) -> bool {
+        tick == self.min_tick() || tick == self.max_tick()
+    }
+
+    /// Returns true if a [Tick] may be initialized at `tick`.
+    pub fn is_valid_tick(&self, tick: i32) -> bool {
+        tick % i32::from(self.tick_spacing) == 0
+            && (self.min_tick()..=self.max_tick()).contains(&tick)
+    }
+
+    /// Returns true if the liquidity of a range between two ticks is in use at the current price.
+    pub fn is_in_range(&self, tick_lower: i32, tick_upper: i32) -> bool {
+        tick_lower <= self.tick_current && self.tick_current < tick_upper
+    }
+
+    /// Computes the fee growth of both tokens inside the range between two ticks, Q64.64.
+    pub fn fee_growth_inside(&self, lower: &Tick, upper: &Tick) -> (u128, u128) {
+        let inside = |global: u128, lower_outside: u128, upper_outside: u128| {
+            let below = if self.tick_current >= lower.index {
+                lower_outside
+            } else {
+                global.wrapping_sub(lower_outside)
+            };
+            let above = if self.tick_current < upper.index {
+                upper_outside
+            } else {
+                global.wrapping_sub(upper_outside)
+            };
+            global.wrapping_sub(below).wrapping_sub(above)
+        };
+        (
+            inside(
+                self.fee_growth_global_0_x64,
+                lower.fee_growth_outside_0_x64,
+                upper.fee_growth_outside_0_x64,
+            ),
+            inside(
+                self.fee_growth_global_1_x64,
+                lower.fee_growth_outside_1_x64,
+                upper.fee_growth_outside_1_x64,
+            ),
+        )
+    }
+}
+
+impl Tick {
+    /// Initializes a [Tick] of the `pool` at `index` which is not linked.
+    pub fn init(&mut self, pool: Pubkey, bump: u8, index: i32) {
+        self.pool = pool;
+        self.bump = bump;
+        self.index = index;
+        self.prev = index;
+        self.next = index;
+        self.liquidity_gross = 0;
+        self.liquidity_net = 0;
+        self.fee_growth_outside_0_x64 = 0;
+        self.fee_growth_outside_1_x64 = 0;
+    }
+
+    /// Returns true if the tick is in the list of ticks of its pool.
+    /// For more information, view [TickList].
+    pub fn is_linked(&self) -> bool {
+        self.prev != self.index || self.next != self.index
+    }
+
+    /// Updates the liquidity of the tick when `liquidity_delta` is added to a [Position]
+    /// bounded by the tick.
+    pub fn update_liquidity(&mut self, liquidity_delta: i128, is_upper: bool) -> Option<()> {
+        self.liquidity_gross = add_liquidity_delta(self.liquidity_gross, liquidity_delta)?;
+        self.liquidity_net = if is_upper {
+            self.liquidity_net.checked_sub(liquidity_delta)?
+        } else {
+            self.liquidity_net.checked_add(liquidity_delta)?
+        };
+        Some(())
+    }
+
+    /// Crosses the tick, flipping its fee growth to the other side of the price.
+    /// Returns the [Tick::liquidity_net].
+    pub fn cross(&mut self, fee_growth_global_0_x64: u128, fee_growth_global_1_x64: u128) -> i128 {
+        self.fee_growth_outside_0_x64 =
+            fee_growth_global_0_x64.wrapping_sub(self.fee_growth_outside_0_x64);
+        self.fee_growth_outside_1_x64 =
+            fee_growth_global_1_x64.wrapping_sub(self.fee_growth_outside_1_x64);
+        self.liquidity_net
+    }
+}
+
+/// [Tick]s of a [ConcentratedPool] loaded by an instruction which links or unlinks ticks.
+///
+/// A tick is linked when liquidity is first added to it, and unlinked once it no longer
+/// bounds any liquidity. Unlinked ticks point to themselves. The adjacent linked ticks of
+/// the ticks being linked or unlinked must be loaded.
+pub struct TickList<'a> {
+    ticks: Vec<&'a mut Tick>,
+}
+
+impl<'a> TickList<'a> {
+    /// Creates a [TickList] of distinct [Tick]s.
+    pub fn new(ticks: Vec<&'a mut Tick>) -> Result<Self> {
+        for (i, tick) in ticks.iter().enumerate() {
+            invariant!(
+                ticks[..i].iter().all(|other| other.index != tick.index),
+                InvalidTick
+            );
+        }
+        Ok(Self { ticks })
+    }
+
+    fn find(&self, index: i32) -> Result<usize> {
+        Ok(unwrap_opt!(
+            self.ticks.iter().position(|tick| tick.index == index),
+            TicksNotAdjacent
+        ))
+    }
+
+    /// Links the unlinked [Tick] at `index` between the linked ticks around it.
+    pub fn link(&mut self, pool: &mut ConcentratedPool, index: i32) -> Result<()> {
+        // only the linked tick below `index` points past it
+        let prev = unwrap_opt!(
+            self.ticks
+                .iter()
+                .position(|tick| tick.index < index && index < tick.next),
+            TicksNotAdjacent
+        );
+        let prev_index = self.ticks[prev].index;
+        let next_index = self.ticks[prev].next;
+        let next = self.find(next_index)?;
+        let tick = self.find(index)?;
+
+        self.ticks[prev].next = index;
+        self.ticks[next].prev = index;
+        let tick = &mut self.ticks[tick];
+        tick.prev = prev_index;
+        tick.next = next_index;
+        // by convention, all fees so far were earned below the ticks at or below the price
+        if index <= pool.tick_current {
+            tick.fee_growth_outside_0_x64 = pool.fee_growth_global_0_x64;
+            tick.fee_growth_outside_1_x64 = pool.fee_growth_global_1_x64;
+        } else {
+            tick.fee_growth_outside_0_x64 = 0;
+            tick.fee_growth_outside_1_x64 = 0;
+        }
+
+        // the tick may be the new neighbor of the current price
+        if pool.tick_below == prev_index && pool.tick_above == next_index {
+            if index <= pool.tick_current {
+                pool.tick_below = index;
+            } else {
+                pool.tick_above = index;
+            }
+        }
+        Ok(())
+    }
+
+    /// Unlinks the linked [Tick] at `index` from its adjacent ticks.
+    pub fn unlink(&mut self, pool: &mut ConcentratedPool, index: i32) -> Result<()> {
+        let tick = self.find(index)?;
+        let prev_index = self.ticks[tick].prev;
+        let next_index = self.ticks[tick].next;
+        let prev = self.find(prev_index)?;
+        let next = self.find(next_index)?;
+
+        self.ticks[prev].next = next_index;
+        self.ticks[next].prev = prev_index;
+        let tick = &mut self.ticks[tick];
+        tick.prev = index;
+        tick.next = index;
+
+        if pool.tick_below == index {
+            pool.tick_below = prev_index;
+        }
+        if pool.tick_above == index {
+            pool.tick_above = next_index;
+        }
+        Ok(())
+    }
+}
+
+impl Position {
+    /// Accrues the fees earned by the position since they were last updated,
+    /// given the current fee growth inside its range.
+    pub fn update_fees(
+        &mut self,
+        fee_growth_inside_0_x64: u128,
+        fee_growth_inside_1_x64: u128,
+    ) -> Option<()> {
+        let earned_0 = xyk::fees_earned(
+            fee_growth_inside_0_x64.wrapping_sub(self.fee_growth_inside_0_last_x64),
+            self.liquidity,
+        )?;
+        let earned_1 = xyk::fees_earned(
+            fee_growth_inside_1_x64.wrapping_sub(self.fee_growth_inside_1_last_x64),
+            self.liquidity,
+        )?;
+        self.fees_owed_0 = self.fees_owed_0.checked_add(earned_0)?;
+        self.fees_owed_1 = self.fees_owed_1.checked_add(earned_1)?;
+        self.fee_growth_inside_0_last_x64 = fee_growth_inside_0_x64;
+        self.fee_growth_inside_1_last_x64 = fee_growth_inside_1_x64;
+        Some(())
+    }
+}
+
+/// Adds a signed `liquidity_delta` to `liquidity`.
+pub fn add_liquidity_delta(liquidity: u128, liquidity_delta: i128) -> Option<u128> {
+    if liquidity_delta >= 0 {
+        liquidity.checked_add(liquidity_delta.unsigned_abs())
+    } else {
+        liquidity.checked_sub(liquidity_delta.unsigned_abs())
+    }
+}
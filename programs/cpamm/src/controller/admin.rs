@@ -0,0 +1,104 @@
+//! Admin instruction processors.
+
+use crate::token_interface::{InterfaceAccount, Mint, TokenAccount};
+use crate::*;
+
+impl<'info> SwapAdmin<'info> {
+    /// Sets the paused state of the swap.
+    pub fn set_paused(&mut self, is_paused: bool) -> Result<()> {
+        let swap = &mut self.swap;
+        // a closing swap may have less than the minimum liquidity
+        invariant!(!swap.is_closing(), SwapClosing);
+        swap.is_paused = is_paused;
+
+        emit!(SetPausedEvent {
+            lp_mint: swap.pool_mint,
+            is_paused,
+        });
+
+        Ok(())
+    }
+}
+
+impl<'info> ClaimAdminFees<'info> {
+    /// Transfers the balances of the admin fees accounts to the destinations.
+    pub fn claim_admin_fees(&self) -> Result<()> {
+        let swap = &self.swap_admin.swap;
+        let amount_0 = self.token_0.claim(swap)?;
+        let amount_1 = self.token_1.claim(swap)?;
+
+        emit!(ClaimAdminFeesEvent {
+            lp_mint: swap.pool_mint,
+            destination_0: self.token_0.destination.key(),
+            amount_0,
+            destination_1: self.token_1.destination.key(),
+            amount_1,
+        });
+
+        Ok(())
+    }
+}
+
+impl<'info> AdminFeesToken<'info> {
+    /// Transfers the balance of the admin fees account to the destination.
+    /// Returns the amount claimed.
+    fn claim(&self, swap: &Account<'info, SwapInfo>) -> Result<u64> {
+        let fees: InterfaceAccount<TokenAccount> = InterfaceAccount::try_from(&self.fees)?;
+        let amount = fees.amount;
+        if amount == 0 {
+            return Ok(0);
+        }
+
+        let mint: InterfaceAccount<Mint> = InterfaceAccount::try_from(&self.mint)?;
+        let seeds = gen_swap_signer_seeds!(swap);
+        token_interface::transfer_checked(
+            &self.token_program,
+            &mint,
+            &self.fees,
+            &self.destination,
+            &swap.to_account_info(),
+            amount,
+            &[&seeds[..]],
+        )?;
+        Ok(amount)
+    }
+}
+
+impl<'info> SetAdminFeesAccount<'info> {
+    /// Replaces the admin fees account of a token, moving over its balance.
+    pub fn set_admin_fees_account(&mut self) -> Result<()> {
+        let previous_fees: InterfaceAccount<TokenAccount> =
+            InterfaceAccount::try_from(&self.previous_fees)?;
+        let mint: InterfaceAccount<Mint> = InterfaceAccount::try_from(&self.mint)?;
+        if previous_fees.amount != 0 {
+            let swap = &self.swap_admin.swap;
+            let seeds = gen_swap_signer_seeds!(swap);
+            token_interface::transfer_checked(
+                &self.token_program,
+                &mint,
+                &self.previous_fees,
+                &self.new_fees,
+                &swap.to_account_info(),
+                previous_fees.amount,
+                &[&seeds[..]],
+            )?;
+        }
+
+        let swap = &mut self.swap_admin.swap;
+        let swap_token = if mint.key() == swap.token_0.mint {
+            &mut swap.token_0
+        } else {
+            &mut swap.token_1
+        };
+        swap_token.admin_fees = self.new_fees.key();
+
+        emit!(SetAdminFeesAccountEvent {
+            lp_mint: swap.pool_mint,
+            mint: mint.key(),
+            previous_fees: self.previous_fees.key(),
+            fees: self.new_fees.key(),
+        });
+
+        Ok(())
+    }
+}
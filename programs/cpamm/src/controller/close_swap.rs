@@ -0,0 +1,107 @@
+//! [crate::cpamm::begin_close_swap] and [crate::cpamm::close_swap] instruction processors.
+
+use crate::token_interface::{self, InterfaceAccount, Mint, TokenAccount};
+use crate::*;
+use anchor_lang::AccountsClose;
+
+impl<'info> SwapAdmin<'info> {
+    /// Pauses the swap permanently, allowing its LPs to withdraw all of its liquidity.
+    pub fn begin_close_swap(&mut self) -> Result<()> {
+        let swap = &mut self.swap;
+        invariant!(!swap.is_closing(), SwapClosing);
+        swap.is_paused = true;
+        swap.flags |= SwapInfo::FLAG_CLOSING;
+
+        emit!(BeginCloseSwapEvent {
+            lp_mint: swap.pool_mint,
+        });
+
+        Ok(())
+    }
+}
+
+impl<'info> CloseSwap<'info> {
+    /// Sweeps and closes the token accounts of the swap, then closes its [SwapMeta]
+    /// and [Observations] if they exist.
+    pub fn close_swap(&self) -> Result<()> {
+        let swap = &self.swap;
+        let amount_0 = self.token_0.sweep_and_close(swap, &self.rent_destination)?;
+        let amount_1 = self.token_1.sweep_and_close(swap, &self.rent_destination)?;
+
+        close_if_exists::<SwapMeta>(&self.swap_meta, &self.rent_destination)?;
+        close_if_exists::<Observations>(&self.observations, &self.rent_destination)?;
+        // the swap itself is closed by the `close` constraint
+
+        emit!(CloseSwapEvent {
+            lp_mint: swap.pool_mint,
+            destination_0: self.token_0.destination.key(),
+            amount_0,
+            destination_1: self.token_1.destination.key(),
+            amount_1,
+            rent_destination: self.rent_destination.key(),
+        });
+
+        Ok(())
+    }
+}
+
+impl<'info> CloseSwapToken<'info> {
+    /// Transfers the balances of the reserve and admin fees accounts to the destination,
+    /// then closes both accounts. Returns the amount transferred.
+    ///
+    /// Transfer fees withheld in the accounts are harvested to the mint first,
+    /// since Token-2022 refuses to close accounts with withheld fees.
+    fn sweep_and_close(
+        &self,
+        swap: &Account<'info, SwapInfo>,
+        rent_destination: &AccountInfo<'info>,
+    ) -> Result<u64> {
+        let mint: InterfaceAccount<Mint> = InterfaceAccount::try_from(&self.mint)?;
+        token_interface::harvest_withheld_tokens_to_mint(
+            &self.token_program,
+            &mint,
+            &[&self.reserve, &self.fees],
+        )?;
+
+        let seeds = gen_swap_signer_seeds!(swap);
+        let mut amount: u64 = 0;
+        for account in [&self.reserve, &self.fees] {
+            let balance = InterfaceAccount::<TokenAccount>::try_from(account)?.amount;
+            if balance != 0 {
+                token_interface::transfer_checked(
+                    &self.token_program,
+                    &mint,
+                    account,
+                    &self.destination,
+                    &swap.to_account_info(),
+                    balance,
+                    &[&seeds[..]],
+                )?;
+                amount = unwrap_int!(amount.checked_add(balance));
+            }
+            token_interface::close_account(
+                &self.token_program,
+                account,
+                rent_destination,
+                &swap.to_account_info(),
+                &[&seeds[..]],
+            )?;
+        }
+        Ok(amount)
+    }
+}
+
+/// Closes an account of the program, if it exists.
+fn close_if_exists<'info, T>(
+    info: &AccountInfo<'info>,
+    rent_destination: &AccountInfo<'info>,
+) -> Result<()>
+where
+    T: AccountSerialize + AccountDeserialize + Owner + Clone,
+{
+    if *info.owner != crate::ID {
+        return Ok(());
+    }
+    let account: Account<T> = Account::try_from(info)?;
+    account.close(rent_destination.clone())
+}
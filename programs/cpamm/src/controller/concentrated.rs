@@ -0,0 +1,555 @@
+//! [crate::ConcentratedPool] instruction controllers.
+
+use crate::concentrated::{add_liquidity_delta, TickList, MAX_TICK_SPACING};
+use crate::token_interface::{self, InterfaceAccount, Mint};
+use crate::*;
+
+pub struct ModifyLiquidityArgs {
+    pub liquidity: u128,
+    /// Maximum amounts in for an increase, minimum amounts out for a decrease.
+    pub amount_limit_0: u64,
+    pub amount_limit_1: u64,
+}
+
+/// Creates a new concentrated liquidity pool.
+pub fn new_concentrated_pool(
+    ctx: Context<NewConcentratedPool>,
+    tick_spacing: u16,
+    fee_tier_kbps: u32,
+    initial_sqrt_price_x64: u128,
+) -> Result<()> {
+    invariant!(
+        tick_spacing != 0 && tick_spacing <= MAX_TICK_SPACING,
+        InvalidTickSpacing
+    );
+    invariant!(
+        ctx.accounts.factory.is_fee_tier_enabled(fee_tier_kbps),
+        InvalidFeeTier
+    );
+    let min_tick = xyk::min_tick(tick_spacing);
+    let max_tick = xyk::max_tick(tick_spacing);
+    // the price must be within the range of the ticks created with the pool
+    invariant!(
+        initial_sqrt_price_x64 >= unwrap_int!(xyk::tick_to_sqrt_price(min_tick))
+            && initial_sqrt_price_x64 < unwrap_int!(xyk::tick_to_sqrt_price(max_tick)),
+        InvalidSqrtPrice
+    );
+    let tick_current = unwrap_int!(xyk::sqrt_price_to_tick(initial_sqrt_price_x64));
+
+    // update factory index
+    let factory = &mut ctx.accounts.factory;
+    let index = factory.num_concentrated_pools;
+    factory.num_concentrated_pools = unwrap_int!(index.checked_add(1));
+
+    // init pool
+    let pool = &mut ctx.accounts.pool;
+    pool.factory = factory.key();
+    pool.bump = unwrap_bump!(ctx, "pool");
+    pool.index = index;
+    pool.token_0 = ConcentratedTokenInfo {
+        reserves: ctx.accounts.token_0.reserve.key(),
+        mint: ctx.accounts.token_0.mint.key(),
+    };
+    pool.token_1 = ConcentratedTokenInfo {
+        reserves: ctx.accounts.token_1.reserve.key(),
+        mint: ctx.accounts.token_1.mint.key(),
+    };
+    pool.tick_spacing = tick_spacing;
+    pool.trade_fee_kbps = fee_tier_kbps.into();
+    pool.sqrt_price_x64 = initial_sqrt_price_x64;
+    pool.tick_current = tick_current;
+    pool.liquidity = 0;
+    pool.tick_below = min_tick;
+    pool.tick_above = max_tick;
+    pool.fee_growth_global_0_x64 = 0;
+    pool.fee_growth_global_1_x64 = 0;
+
+    // init the ends of the list of ticks
+    let pool_key = pool.key();
+    let tick_min = &mut ctx.accounts.tick_min;
+    tick_min.pool = pool_key;
+    tick_min.bump = unwrap_bump!(ctx, "tick_min");
+    tick_min.index = min_tick;
+    tick_min.prev = min_tick;
+    tick_min.next = max_tick;
+
+    let tick_max = &mut ctx.accounts.tick_max;
+    tick_max.pool = pool_key;
+    tick_max.bump = unwrap_bump!(ctx, "tick_max");
+    tick_max.index = max_tick;
+    tick_max.prev = min_tick;
+    tick_max.next = max_tick;
+
+    emit!(NewConcentratedPoolEvent {
+        pool: pool_key,
+        mint_0: pool.token_0.mint,
+        mint_1: pool.token_1.mint,
+        tick_spacing,
+        trade_fee_kbps: pool.trade_fee_kbps,
+        sqrt_price_x64: initial_sqrt_price_x64,
+    });
+
+    Ok(())
+}
+
+/// Opens an empty position over the range between two ticks.
+pub fn open_position(ctx: Context<OpenPosition>, tick_lower: i32, tick_upper: i32) -> Result<()> {
+    let pool = &ctx.accounts.pool;
+    invariant!(
+        pool.is_valid_tick(tick_lower) && pool.is_valid_tick(tick_upper),
+        InvalidTick
+    );
+    invariant!(tick_lower < tick_upper, InvalidTickRange);
+
+    let position = &mut ctx.accounts.position;
+    position.pool = pool.key();
+    position.owner = ctx.accounts.owner.key();
+    position.bump = unwrap_bump!(ctx, "position");
+    position.tick_lower = tick_lower;
+    position.tick_upper = tick_upper;
+    position.liquidity = 0;
+    position.fee_growth_inside_0_last_x64 = 0;
+    position.fee_growth_inside_1_last_x64 = 0;
+    position.fees_owed_0 = 0;
+    position.fees_owed_1 = 0;
+    Ok(())
+}
+
+/// Adds liquidity to a position, creating its ticks and linking them if they had no liquidity.
+pub fn increase_liquidity<'info>(
+    ctx: Context<'_, '_, '_, 'info, IncreaseLiquidity<'info>>,
+    args: ModifyLiquidityArgs,
+) -> Result<()> {
+    let pool_key = ctx.accounts.pool.key();
+    let accounts = &mut *ctx.accounts;
+    if accounts.tick_lower.pool == Pubkey::default() {
+        let bump = unwrap_bump!(ctx, "tick_lower");
+        accounts
+            .tick_lower
+            .init(pool_key, bump, accounts.position.tick_lower);
+    }
+    if accounts.tick_upper.pool == Pubkey::default() {
+        let bump = unwrap_bump!(ctx, "tick_upper");
+        accounts
+            .tick_upper
+            .init(pool_key, bump, accounts.position.tick_upper);
+    }
+
+    update_tick_links(
+        &mut accounts.pool,
+        &mut accounts.tick_lower,
+        &mut accounts.tick_upper,
+        ctx.remaining_accounts,
+        true,
+    )?;
+
+    let liquidity_delta = unwrap_int!(i128::try_from(args.liquidity).ok());
+    update_position(
+        &mut accounts.pool,
+        &mut accounts.position,
+        &mut accounts.tick_lower,
+        &mut accounts.tick_upper,
+        liquidity_delta,
+    )?;
+
+    let (amount_0, amount_1) =
+        amounts_for_liquidity(&accounts.pool, &accounts.position, args.liquidity, true)?;
+    accounts
+        .token_0
+        .transfer_in(&accounts.owner, amount_0, args.amount_limit_0)?;
+    accounts
+        .token_1
+        .transfer_in(&accounts.owner, amount_1, args.amount_limit_1)?;
+
+    emit!(IncreaseLiquidityEvent {
+        pool: pool_key,
+        position: accounts.position.key(),
+        owner: accounts.owner.key(),
+        liquidity: args.liquidity,
+        token_0_amount: amount_0,
+        token_1_amount: amount_1,
+    });
+
+    Ok(())
+}
+
+/// Removes liquidity from a position, withdrawing the tokens of the liquidity.
+/// Ticks of the position which no longer bound any liquidity are unlinked.
+pub fn decrease_liquidity<'info>(
+    ctx: Context<'_, '_, '_, 'info, ModifyLiquidity<'info>>,
+    args: ModifyLiquidityArgs,
+) -> Result<()> {
+    invariant!(
+        args.liquidity <= ctx.accounts.position.liquidity,
+        InsufficientLiquidity
+    );
+    let liquidity_delta = unwrap_int!(i128::try_from(args.liquidity)
+        .ok()
+        .and_then(|v| v.checked_neg()));
+    ctx.accounts.update_position(liquidity_delta)?;
+
+    let accounts = &mut *ctx.accounts;
+    update_tick_links(
+        &mut accounts.pool,
+        &mut accounts.tick_lower,
+        &mut accounts.tick_upper,
+        ctx.remaining_accounts,
+        false,
+    )?;
+
+    let (amount_0, amount_1) = amounts_for_liquidity(
+        &ctx.accounts.pool,
+        &ctx.accounts.position,
+        args.liquidity,
+        false,
+    )?;
+    let received_0 = ctx.accounts.transfer_out(true, amount_0)?;
+    let received_1 = ctx.accounts.transfer_out(false, amount_1)?;
+    invariant!(
+        received_0 >= args.amount_limit_0 && received_1 >= args.amount_limit_1,
+        ExceededSlippage
+    );
+
+    emit!(DecreaseLiquidityEvent {
+        pool: ctx.accounts.pool.key(),
+        position: ctx.accounts.position.key(),
+        owner: ctx.accounts.owner.key(),
+        liquidity: args.liquidity,
+        token_0_amount: amount_0,
+        token_1_amount: amount_1,
+    });
+
+    Ok(())
+}
+
+/// Sends the fees earned by a position to its owner.
+pub fn collect_fees(ctx: Context<ModifyLiquidity>) -> Result<()> {
+    ctx.accounts.update_position(0)?;
+
+    let position = &mut ctx.accounts.position;
+    let fee_0 = position.fees_owed_0;
+    let fee_1 = position.fees_owed_1;
+    position.fees_owed_0 = 0;
+    position.fees_owed_1 = 0;
+
+    ctx.accounts.transfer_out(true, fee_0)?;
+    ctx.accounts.transfer_out(false, fee_1)?;
+
+    emit!(CollectFeesEvent {
+        pool: ctx.accounts.pool.key(),
+        position: ctx.accounts.position.key(),
+        owner: ctx.accounts.owner.key(),
+        fee_0,
+        fee_1,
+    });
+
+    Ok(())
+}
+
+impl<'info> ModifyLiquidity<'info> {
+    /// Accrues the fees of the position, then adds `liquidity_delta` to it.
+    fn update_position(&mut self, liquidity_delta: i128) -> Result<()> {
+        update_position(
+            &mut self.pool,
+            &mut self.position,
+            &mut self.tick_lower,
+            &mut self.tick_upper,
+            liquidity_delta,
+        )
+    }
+
+    /// Sends `amount` of a token from the reserves to the user.
+    /// Returns the amount received by the user, net of the transfer fee.
+    fn transfer_out(&self, is_token_0: bool, amount: u64) -> Result<u64> {
+        let token = if is_token_0 {
+            &self.token_0
+        } else {
+            &self.token_1
+        };
+        token.transfer_out(&self.pool, amount)
+    }
+}
+
+/// Accrues the fees of the position, then adds `liquidity_delta` to the position,
+/// its ticks, and the pool if the position is in range.
+fn update_position(
+    pool: &mut ConcentratedPool,
+    position: &mut Position,
+    tick_lower: &mut Tick,
+    tick_upper: &mut Tick,
+    liquidity_delta: i128,
+) -> Result<()> {
+    let (fee_growth_inside_0_x64, fee_growth_inside_1_x64) =
+        pool.fee_growth_inside(tick_lower, tick_upper);
+    unwrap_int!(position.update_fees(fee_growth_inside_0_x64, fee_growth_inside_1_x64));
+    if liquidity_delta == 0 {
+        return Ok(());
+    }
+
+    position.liquidity = unwrap_int!(add_liquidity_delta(position.liquidity, liquidity_delta));
+    unwrap_int!(tick_lower.update_liquidity(liquidity_delta, false));
+    unwrap_int!(tick_upper.update_liquidity(liquidity_delta, true));
+    if pool.is_in_range(tick_lower.index, tick_upper.index) {
+        pool.liquidity = unwrap_int!(add_liquidity_delta(pool.liquidity, liquidity_delta));
+    }
+    Ok(())
+}
+
+/// Computes the amounts of both tokens of `liquidity` in the range of the position.
+fn amounts_for_liquidity(
+    pool: &ConcentratedPool,
+    position: &Position,
+    liquidity: u128,
+    round_up: bool,
+) -> Result<(u64, u64)> {
+    Ok(unwrap_int!(xyk::amounts_for_liquidity(
+        pool.sqrt_price_x64,
+        unwrap_int!(xyk::tick_to_sqrt_price(position.tick_lower)),
+        unwrap_int!(xyk::tick_to_sqrt_price(position.tick_upper)),
+        liquidity,
+        round_up,
+    )))
+}
+
+/// If `link`, links the ticks of a position which are not linked. Otherwise, unlinks the
+/// ticks of the position which no longer bound any liquidity.
+///
+/// The linked ticks adjacent to the ticks being linked or unlinked are `adjacent_tick_infos`.
+fn update_tick_links(
+    pool: &mut ConcentratedPool,
+    tick_lower: &mut Tick,
+    tick_upper: &mut Tick,
+    adjacent_tick_infos: &[AccountInfo],
+    link: bool,
+) -> Result<()> {
+    let indices: Vec<i32> = [&*tick_lower, &*tick_upper]
+        .into_iter()
+        .filter(|tick| {
+            if link {
+                !tick.is_linked()
+            } else {
+                tick.is_linked() && tick.liquidity_gross == 0 && !pool.is_end_tick(tick.index)
+            }
+        })
+        .map(|tick| tick.index)
+        .collect();
+    if indices.is_empty() {
+        return Ok(());
+    }
+
+    let pool_key = tick_lower.pool;
+    let mut adjacent_ticks = adjacent_tick_infos
+        .iter()
+        .map(|tick_info| {
+            let tick: Account<Tick> = Account::try_from(tick_info)?;
+            assert_keys_eq!(tick.pool, pool_key);
+            Ok(tick)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut ticks = vec![tick_lower, tick_upper];
+    ticks.extend(adjacent_ticks.iter_mut().map(|tick| &mut **tick));
+    let mut ticks = TickList::new(ticks)?;
+    for index in indices {
+        if link {
+            ticks.link(pool, index)?;
+        } else {
+            ticks.unlink(pool, index)?;
+        }
+    }
+    drop(ticks);
+
+    for tick in adjacent_ticks.iter() {
+        tick.exit(&crate::ID)?;
+    }
+    Ok(())
+}
+
+impl<'info> SwapToken<'info> {
+    /// Sends enough tokens from the user for the reserves to receive `amount`,
+    /// failing if the user would have to send more than `maximum_amount_in`.
+    fn transfer_in(
+        &self,
+        authority: &AccountInfo<'info>,
+        amount: u64,
+        maximum_amount_in: u64,
+    ) -> Result<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+        let mint: InterfaceAccount<Mint> = InterfaceAccount::try_from(&self.mint)?;
+        let amount_in = mint.amount_before_transfer_fee(amount)?;
+        invariant!(amount_in <= maximum_amount_in, ExceededSlippage);
+        token_interface::transfer_checked(
+            &self.token_program,
+            &mint,
+            &self.user,
+            &self.reserve,
+            authority,
+            amount_in,
+            &[],
+        )
+    }
+
+    /// Sends `amount` from the reserves of the pool to the user.
+    /// Returns the amount received by the user, net of the transfer fee.
+    fn transfer_out(&self, pool: &Account<'info, ConcentratedPool>, amount: u64) -> Result<u64> {
+        if amount == 0 {
+            return Ok(0);
+        }
+        let mint: InterfaceAccount<Mint> = InterfaceAccount::try_from(&self.mint)?;
+        let seeds = gen_concentrated_pool_signer_seeds!(pool);
+        let signer_seeds = &[&seeds[..]];
+        token_interface::transfer_checked(
+            &self.token_program,
+            &mint,
+            &self.reserve,
+            &self.user,
+            &pool.to_account_info(),
+            amount,
+            signer_seeds,
+        )?;
+        Ok(unwrap_int!(amount.checked_sub(mint.transfer_fee(amount)?)))
+    }
+}
+
+/// Swaps along the price curve of the pool, crossing the [Tick]s passed as remaining accounts.
+///
+/// The swap ends early if the price reaches a tick which is not passed, or the end of the
+/// range of the pool, in which case only part of `amount_in` is spent.
+pub fn swap_concentrated<'info>(
+    ctx: Context<'_, '_, '_, 'info, SwapConcentrated<'info>>,
+    args: controller::swap::SwapArgs,
+) -> Result<()> {
+    let input_mint: InterfaceAccount<Mint> = InterfaceAccount::try_from(&ctx.accounts.input.mint)?;
+    let output_mint: InterfaceAccount<Mint> =
+        InterfaceAccount::try_from(&ctx.accounts.output.mint)?;
+
+    // the pool only receives the input net of the transfer fee
+    let amount_in = input_mint.amount_after_transfer_fee(args.amount_in)?;
+
+    let pool = &mut ctx.accounts.pool;
+    let zero_for_one = input_mint.as_ref().key() == pool.token_0.mint;
+    let mut ticks = ctx.remaining_accounts.iter();
+
+    let mut amount_remaining = amount_in;
+    let mut amount_out: u64 = 0;
+    let mut trade_fee: u64 = 0;
+    while amount_remaining > 0 {
+        let target_tick = if zero_for_one {
+            pool.tick_below
+        } else {
+            pool.tick_above
+        };
+        let sqrt_price_target_x64 = unwrap_int!(xyk::tick_to_sqrt_price(target_tick));
+        let step = unwrap_int!(xyk::compute_swap_step(
+            pool.sqrt_price_x64,
+            sqrt_price_target_x64,
+            pool.liquidity,
+            amount_remaining,
+            pool.trade_fee_kbps,
+        ));
+
+        amount_remaining = unwrap_int!(amount_remaining
+            .checked_sub(step.amount_in)
+            .and_then(|v| v.checked_sub(step.fee_amount)));
+        amount_out = unwrap_int!(amount_out.checked_add(step.amount_out));
+        trade_fee = unwrap_int!(trade_fee.checked_add(step.fee_amount));
+
+        // the trade fee is earned by the liquidity in range
+        let fee_growth = unwrap_int!(xyk::fee_growth(step.fee_amount, pool.liquidity));
+        if zero_for_one {
+            pool.fee_growth_global_0_x64 = pool.fee_growth_global_0_x64.wrapping_add(fee_growth);
+        } else {
+            pool.fee_growth_global_1_x64 = pool.fee_growth_global_1_x64.wrapping_add(fee_growth);
+        }
+
+        if step.sqrt_price_x64 != sqrt_price_target_x64 {
+            // the input was exhausted before reaching the tick
+            if step.sqrt_price_x64 != pool.sqrt_price_x64 {
+                pool.sqrt_price_x64 = step.sqrt_price_x64;
+                pool.tick_current = unwrap_int!(xyk::sqrt_price_to_tick(step.sqrt_price_x64));
+            }
+            break;
+        }
+        pool.sqrt_price_x64 = step.sqrt_price_x64;
+
+        // the price is at the tick, which has not been crossed yet
+        pool.tick_current = if zero_for_one {
+            target_tick
+        } else {
+            unwrap_int!(target_tick.checked_sub(1))
+        };
+        if target_tick == pool.min_tick() || target_tick == pool.max_tick() {
+            // there is no liquidity past the ends of the pool
+            break;
+        }
+        let tick_info = match ticks.next() {
+            Some(tick_info) => tick_info,
+            None => break,
+        };
+
+        let mut tick: Account<Tick> = Account::try_from(tick_info)?;
+        assert_keys_eq!(tick.pool, pool.key());
+        invariant!(tick.index == target_tick, InvalidTick);
+        let liquidity_net = tick.cross(pool.fee_growth_global_0_x64, pool.fee_growth_global_1_x64);
+        if zero_for_one {
+            pool.liquidity = unwrap_int!(liquidity_net
+                .checked_neg()
+                .and_then(|delta| add_liquidity_delta(pool.liquidity, delta)));
+            pool.tick_current = unwrap_int!(target_tick.checked_sub(1));
+            pool.tick_above = target_tick;
+            pool.tick_below = tick.prev;
+        } else {
+            pool.liquidity = unwrap_int!(add_liquidity_delta(pool.liquidity, liquidity_net));
+            pool.tick_current = target_tick;
+            pool.tick_below = target_tick;
+            pool.tick_above = tick.next;
+        }
+        tick.exit(&crate::ID)?;
+    }
+
+    let amount_swapped = unwrap_int!(amount_in.checked_sub(amount_remaining));
+    if amount_swapped == 0 {
+        // skip the transfers if nothing is being swapped
+        return Ok(());
+    }
+
+    // the transfer fee of the output mint is withheld from the user
+    let output_transfer_fee = output_mint.transfer_fee(amount_out)?;
+    invariant!(
+        unwrap_int!(amount_out.checked_sub(output_transfer_fee)) >= args.minimum_amount_out,
+        ExceededSlippage
+    );
+
+    // Transfer user's tokens to the pool.
+    token_interface::transfer_checked(
+        &ctx.accounts.input.token_program,
+        &input_mint,
+        &ctx.accounts.input.user,
+        &ctx.accounts.input.reserve,
+        &ctx.accounts.user_authority,
+        input_mint.amount_before_transfer_fee(amount_swapped)?,
+        &[],
+    )?;
+    // Transfer pool's tokens to the user.
+    ctx.accounts
+        .output
+        .transfer_out(&ctx.accounts.pool, amount_out)?;
+
+    let pool = &ctx.accounts.pool;
+    emit!(ConcentratedSwapEvent {
+        pool: pool.key(),
+        user_authority: ctx.accounts.user_authority.key(),
+        input_mint: input_mint.as_ref().key(),
+        output_mint: output_mint.as_ref().key(),
+        source_amount_swapped: amount_swapped,
+        destination_amount_swapped: amount_out,
+        trade_fee,
+        sqrt_price_x64: pool.sqrt_price_x64,
+        tick_current: pool.tick_current,
+        liquidity: pool.liquidity,
+    });
+
+    Ok(())
+}
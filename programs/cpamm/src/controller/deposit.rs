@@ -1,14 +1,25 @@
 //! [crate::cpamm::deposit] instruction controller.
 
+use crate::token_interface::{self, InterfaceAccount, Mint, TokenAccount};
 use crate::*;
 use anchor_spl::token;
 
 /// Deposit
 pub fn deposit(ctx: Context<Deposit>, args: DepositArgs) -> Result<()> {
+    let reserve_0: InterfaceAccount<TokenAccount> =
+        InterfaceAccount::try_from(&ctx.accounts.input_0.reserve)?;
+    let reserve_1: InterfaceAccount<TokenAccount> =
+        InterfaceAccount::try_from(&ctx.accounts.input_1.reserve)?;
+    ctx.accounts
+        .user
+        .swap
+        .init_expected_reserves(reserve_0.amount, reserve_1.amount);
+
     // update cumulative price info.
     // we call this before the short circuit
     // so the numbers are accurate.
     ctx.accounts.update_cumulative_price_info()?;
+    price_info::record_observation(&ctx.accounts.user.swap, ctx.remaining_accounts)?;
 
     let pool_mint_supply = ctx.accounts.pool_mint.supply;
     invariant!(pool_mint_supply > 0, "pool_mint.supply cannot be 0");
@@ -20,39 +31,52 @@ pub fn deposit(ctx: Context<Deposit>, args: DepositArgs) -> Result<()> {
     invariant!(args.maximum_amount_in_0 > 0, "args.maximum_amount_in_a > 0");
     invariant!(args.maximum_amount_in_1 > 0, "args.maximum_amount_in_b > 0");
 
-    let result = unwrap_int!(xyk::pool_tokens_to_trading_tokens(
+    let curve = ctx.accounts.user.swap.curve.calculator()?;
+    let reserves = ctx.accounts.user.swap.expected_reserves;
+    let result = unwrap_int!(curve.pool_tokens_to_trading_tokens(
         pool_token_amount,
         pool_mint_supply,
-        ctx.accounts.input_0.reserve.amount,
-        ctx.accounts.input_1.reserve.amount,
+        reserves.amount_0,
+        reserves.amount_1,
         xyk::RoundDirection::Ceiling,
     ));
 
-    invariant!(
-        result.token_a_amount <= args.maximum_amount_in_0,
-        ExceededSlippage
-    );
+    // the reserves must receive the computed amounts after the transfer fees
+    let mint_0: InterfaceAccount<Mint> = InterfaceAccount::try_from(&ctx.accounts.input_0.mint)?;
+    let mint_1: InterfaceAccount<Mint> = InterfaceAccount::try_from(&ctx.accounts.input_1.mint)?;
+    let amount_in_0 = mint_0.amount_before_transfer_fee(result.token_a_amount)?;
+    let amount_in_1 = mint_1.amount_before_transfer_fee(result.token_b_amount)?;
+
+    invariant!(amount_in_0 <= args.maximum_amount_in_0, ExceededSlippage);
     invariant!(result.token_a_amount > 0, InsufficientLiquidity);
-    invariant!(
-        result.token_b_amount <= args.maximum_amount_in_1,
-        ExceededSlippage
-    );
+    invariant!(amount_in_1 <= args.maximum_amount_in_1, ExceededSlippage);
     invariant!(result.token_b_amount > 0, InsufficientLiquidity);
 
     // Transfer user's tokens to the reserve.
     ctx.accounts
-        .transfer_user_to_reserve(&ctx.accounts.input_0, result.token_a_amount)?;
+        .transfer_user_to_reserve(&ctx.accounts.input_0, &mint_0, amount_in_0)?;
     ctx.accounts
-        .transfer_user_to_reserve(&ctx.accounts.input_1, result.token_b_amount)?;
+        .transfer_user_to_reserve(&ctx.accounts.input_1, &mint_1, amount_in_1)?;
+
+    let expected_reserves = &mut ctx.accounts.user.swap.expected_reserves;
+    unwrap_int!(expected_reserves.credit(true, result.token_a_amount));
+    unwrap_int!(expected_reserves.credit(false, result.token_b_amount));
 
     // Mint lp tokens to user
     ctx.accounts.mint_lp_to_user(pool_token_amount)?;
 
+    let reserves = ctx.accounts.user.swap.expected_reserves;
     emit!(DepositEvent {
         lp_mint: ctx.accounts.user.swap.pool_mint,
         pool_token_amount,
         token_0_amount: result.token_a_amount,
         token_1_amount: result.token_b_amount,
+        user_authority: ctx.accounts.user.user_authority.key(),
+        trade_fee: 0,
+        admin_trade_fee: 0,
+        reserve_0: reserves.amount_0,
+        reserve_1: reserves.amount_1,
+        pool_token_supply: unwrap_int!(pool_mint_supply.checked_add(pool_token_amount)),
     });
 
     ctx.accounts.track_cumulative_deposit_volume(
@@ -70,19 +94,149 @@ pub struct DepositArgs {
     pub maximum_amount_in_1: u64,
 }
 
+/// Deposit a single token
+pub fn deposit_single_token_exact_in(
+    ctx: Context<DepositSingle>,
+    args: DepositSingleArgs,
+) -> Result<()> {
+    let reserve: InterfaceAccount<TokenAccount> =
+        InterfaceAccount::try_from(&ctx.accounts.input.reserve)?;
+    let other_reserve: InterfaceAccount<TokenAccount> =
+        InterfaceAccount::try_from(&ctx.accounts.other_reserve)?;
+    let is_token_0 = reserve.mint == ctx.accounts.user.swap.token_0.mint;
+    if is_token_0 {
+        ctx.accounts
+            .user
+            .swap
+            .init_expected_reserves(reserve.amount, other_reserve.amount);
+    } else {
+        ctx.accounts
+            .user
+            .swap
+            .init_expected_reserves(other_reserve.amount, reserve.amount);
+    }
+
+    // update cumulative price info.
+    // we call this before the short circuit
+    // so the numbers are accurate.
+    ctx.accounts.update_cumulative_price_info()?;
+    price_info::record_observation(&ctx.accounts.user.swap, ctx.remaining_accounts)?;
+
+    invariant!(
+        ctx.accounts.user.swap.curve.is_constant_product(),
+        UnsupportedCurve
+    );
+
+    let pool_mint_supply = ctx.accounts.pool_mint.supply;
+    invariant!(pool_mint_supply > 0, "pool_mint.supply cannot be 0");
+    let source_amount = args.source_amount;
+    if source_amount == 0 {
+        return Ok(());
+    }
+
+    // the deposit is computed on the amount received after the transfer fee
+    let mint: InterfaceAccount<Mint> = InterfaceAccount::try_from(&ctx.accounts.input.mint)?;
+    let source_amount = mint.amount_after_transfer_fee(source_amount)?;
+
+    // half of the deposit is implicitly swapped into the other token,
+    // so it must pay the trade fee.
+    let half_source_amount = unwrap_int!(source_amount
+        .checked_div(2)
+        .and_then(|v| v.checked_add(source_amount.checked_rem(2)?)));
+
+    // the trade fee depends on the price after the implied swap.
+    // a swap too small to receive any tokens leaves the price unchanged.
+    let reserves = ctx.accounts.user.swap.expected_reserves;
+    let reserves_after = xyk::swap(
+        half_source_amount,
+        reserves.get(is_token_0),
+        reserves.get(!is_token_0),
+    )
+    .and_then(|swap_result| reserves.after_swap(is_token_0, &swap_result))
+    .unwrap_or(reserves);
+    let swap_fees = ctx.accounts.user.swap.update_trade_fees(reserves_after)?;
+    let (trade_fee, admin_trade_fee) =
+        unwrap_int!(swap_fees.compute_trade_fees(half_source_amount));
+
+    let pool_token_amount = unwrap_int!(xyk::deposit_single_token_type(
+        unwrap_int!(source_amount.checked_sub(trade_fee)),
+        ctx.accounts.user.swap.expected_reserves.get(is_token_0),
+        pool_mint_supply,
+    ));
+    invariant!(pool_token_amount > 0, InsufficientLiquidity);
+    invariant!(
+        pool_token_amount >= args.minimum_pool_token_amount,
+        ExceededSlippage
+    );
+
+    // Transfer user's tokens to the reserve, and the admin's share of the fee to the fees account.
+    ctx.accounts
+        .transfer_user_to_reserve(&mint, args.source_amount)?;
+    if admin_trade_fee > 0 {
+        ctx.accounts
+            .transfer_reserve_to_fees(&mint, admin_trade_fee)?;
+    }
+
+    unwrap_int!(ctx.accounts.user.swap.expected_reserves.credit(
+        is_token_0,
+        unwrap_int!(source_amount.checked_sub(admin_trade_fee))
+    ));
+
+    // Mint lp tokens to user
+    ctx.accounts.mint_lp_to_user(pool_token_amount)?;
+
+    let (token_0_amount, token_1_amount) = if is_token_0 {
+        (source_amount, 0)
+    } else {
+        (0, source_amount)
+    };
+
+    let reserves = ctx.accounts.user.swap.expected_reserves;
+    emit!(DepositEvent {
+        lp_mint: ctx.accounts.user.swap.pool_mint,
+        pool_token_amount,
+        token_0_amount,
+        token_1_amount,
+        user_authority: ctx.accounts.user.user_authority.key(),
+        trade_fee,
+        admin_trade_fee,
+        reserve_0: reserves.amount_0,
+        reserve_1: reserves.amount_1,
+        pool_token_supply: unwrap_int!(pool_mint_supply.checked_add(pool_token_amount)),
+    });
+
+    ctx.accounts.track_cumulative_deposit_volume(
+        is_token_0,
+        pool_token_amount,
+        source_amount,
+        trade_fee,
+    )?;
+
+    Ok(())
+}
+
+pub struct DepositSingleArgs {
+    pub source_amount: u64,
+    pub minimum_pool_token_amount: u64,
+}
+
 impl<'info> Deposit<'info> {
     /// Transfers the user's swap tokens to the reserve.
-    fn transfer_user_to_reserve(&self, input: &SwapToken<'info>, amount: u64) -> Result<()> {
-        let token_program = &self.user.token_program;
-        let cpi_ctx = CpiContext::new(
-            token_program.to_account_info().clone(),
-            token::Transfer {
-                from: input.user.to_account_info(),
-                to: input.reserve.to_account_info(),
-                authority: self.user.user_authority.to_account_info().clone(),
-            },
-        );
-        token::transfer(cpi_ctx, amount)
+    fn transfer_user_to_reserve(
+        &self,
+        input: &SwapToken<'info>,
+        mint: &InterfaceAccount<'info, Mint>,
+        amount: u64,
+    ) -> Result<()> {
+        token_interface::transfer_checked(
+            &input.token_program,
+            mint,
+            &input.user,
+            &input.reserve,
+            &self.user.user_authority,
+            amount,
+            &[],
+        )
     }
 
     /// Mints the LP tokens to the user.
@@ -106,9 +260,7 @@ impl<'info> Deposit<'info> {
 
     fn update_cumulative_price_info(&mut self) -> Result<()> {
         // update price info
-        let price_info = &mut self.user.swap.price_info;
-        price_info
-            .update_cumulative_price_info(self.input_0.reserve.amount, self.input_1.reserve.amount)
+        self.user.swap.update_cumulative_price_info()
     }
 
     /// Tracks and saves the cumulative deposit volume.
@@ -134,3 +286,92 @@ impl<'info> Deposit<'info> {
         Ok(())
     }
 }
+
+impl<'info> DepositSingle<'info> {
+    /// Transfers the user's input tokens to the reserve.
+    fn transfer_user_to_reserve(
+        &self,
+        mint: &InterfaceAccount<'info, Mint>,
+        amount: u64,
+    ) -> Result<()> {
+        token_interface::transfer_checked(
+            &self.input.token_program,
+            mint,
+            &self.input.user,
+            &self.input.reserve,
+            &self.user.user_authority,
+            amount,
+            &[],
+        )
+    }
+
+    /// Transfers the admin's share of the trade fee from the reserve to the fees account.
+    fn transfer_reserve_to_fees(
+        &self,
+        mint: &InterfaceAccount<'info, Mint>,
+        amount: u64,
+    ) -> Result<()> {
+        let token_swap = &self.user.swap;
+        let seeds = gen_swap_signer_seeds!(token_swap);
+        token_interface::transfer_checked(
+            &self.input.token_program,
+            mint,
+            &self.input.reserve,
+            &self.input.fees,
+            &token_swap.to_account_info(),
+            amount,
+            &[&seeds[..]],
+        )
+    }
+
+    /// Mints the LP tokens to the user.
+    fn mint_lp_to_user(&self, amount: u64) -> Result<()> {
+        let token_swap = &self.user.swap;
+        let seeds = gen_swap_signer_seeds!(token_swap);
+        let signer_seeds = &[&seeds[..]];
+        token::mint_to(
+            CpiContext::new_with_signer(
+                self.user.token_program.to_account_info(),
+                token::MintTo {
+                    mint: self.pool_mint.to_account_info(),
+                    to: self.output_lp.to_account_info(),
+                    authority: token_swap.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            amount,
+        )
+    }
+
+    fn update_cumulative_price_info(&mut self) -> Result<()> {
+        // update price info
+        self.user.swap.update_cumulative_price_info()
+    }
+
+    /// Tracks and saves the cumulative deposit volume.
+    fn track_cumulative_deposit_volume(
+        &mut self,
+        is_token_0: bool,
+        pool_token_amount: u64,
+        source_amount: u64,
+        trade_fee: u64,
+    ) -> Result<()> {
+        // record cumulative volume numbers
+        let cumulative_stats = &mut self.user.swap.cumulative_stats;
+        cumulative_stats.total_lp_minted = unwrap_int!(cumulative_stats
+            .total_lp_minted
+            .checked_add(pool_token_amount.into()));
+
+        let cum_input_token = if is_token_0 {
+            &mut cumulative_stats.token_0
+        } else {
+            &mut cumulative_stats.token_1
+        };
+        cum_input_token.total_deposit_volume = unwrap_int!(cum_input_token
+            .total_deposit_volume
+            .checked_add(source_amount.into()));
+        cum_input_token.total_trade_fees =
+            unwrap_int!(cum_input_token.total_trade_fees.checked_add(trade_fee));
+        Ok(())
+    }
+}
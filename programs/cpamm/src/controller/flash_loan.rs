@@ -0,0 +1,207 @@
+//! [crate::cpamm::flash_loan] instruction processor.
+
+use crate::*;
+use anchor_lang::solana_program::sysvar::instructions::{
+    load_current_index_checked, load_instruction_at_checked,
+};
+use anchor_lang::InstructionData;
+use token_interface::{InterfaceAccount, Mint, TokenAccount};
+
+/// Index of the [SwapInfo] in the [FlashLoan] accounts.
+const SWAP_ACCOUNT_INDEX: usize = 1;
+/// Index of the reserve in the [FlashLoan] accounts.
+const RESERVE_ACCOUNT_INDEX: usize = 4;
+
+/// Flash loan
+pub fn flash_loan(ctx: Context<FlashLoan>, amount: u64) -> Result<()> {
+    invariant!(!ctx.accounts.user.swap.is_paused, Paused);
+    invariant!(
+        !ctx.accounts.user.swap.flash_loan.is_active(),
+        FlashLoanInProgress
+    );
+    invariant!(amount > 0, "amount must be positive");
+    let is_token_0 = ctx.accounts.is_token_0();
+    let reserve: InterfaceAccount<TokenAccount> =
+        InterfaceAccount::try_from(&ctx.accounts.token.reserve)?;
+    let other_reserve: InterfaceAccount<TokenAccount> =
+        InterfaceAccount::try_from(&ctx.accounts.other_reserve)?;
+    if is_token_0 {
+        ctx.accounts
+            .user
+            .swap
+            .init_expected_reserves(reserve.amount, other_reserve.amount);
+    } else {
+        ctx.accounts
+            .user
+            .swap
+            .init_expected_reserves(other_reserve.amount, reserve.amount);
+    }
+    invariant!(
+        amount < ctx.accounts.user.swap.expected_reserves.get(is_token_0),
+        InsufficientLiquidity
+    );
+
+    ctx.accounts.check_repayment()?;
+
+    // update cumulative price info.
+    // must be called BEFORE mutation.
+    ctx.accounts.update_cumulative_price_info()?;
+    let flash_loan = &mut ctx.accounts.user.swap.flash_loan;
+    if is_token_0 {
+        flash_loan.amount_0 = amount;
+    } else {
+        flash_loan.amount_1 = amount;
+    }
+
+    // Transfer pool's tokens to the user.
+    let mint: InterfaceAccount<Mint> = InterfaceAccount::try_from(&ctx.accounts.token.mint)?;
+    let token_swap = &ctx.accounts.user.swap;
+    let seeds = gen_swap_signer_seeds!(token_swap);
+    token_interface::transfer_checked(
+        &ctx.accounts.token.token_program,
+        &mint,
+        &ctx.accounts.token.reserve,
+        &ctx.accounts.token.user,
+        &token_swap.to_account_info(),
+        amount,
+        &[&seeds[..]],
+    )
+}
+
+/// Repay flash loan
+pub fn repay_flash_loan(ctx: Context<FlashLoan>) -> Result<()> {
+    let is_token_0 = ctx.accounts.is_token_0();
+    let flash_loan = ctx.accounts.user.swap.flash_loan;
+    let amount = if is_token_0 {
+        flash_loan.amount_0
+    } else {
+        flash_loan.amount_1
+    };
+    invariant!(amount > 0, "no flash loan to repay");
+
+    let swap_fees = ctx.accounts.user.swap.fees;
+    let (fee, admin_fee) =
+        unwrap_int!(swap_fees.compute_flash_loan_fees(flash_loan.fee_kbps, amount));
+
+    // Transfer user's tokens back to the pool, and the admin's share of the fee to the fees account.
+    // The user pays the transfer fees, so that the pool receives the full amounts.
+    let mint: InterfaceAccount<Mint> = InterfaceAccount::try_from(&ctx.accounts.token.mint)?;
+    ctx.accounts.transfer_user_to(
+        &mint,
+        &ctx.accounts.token.reserve,
+        mint.amount_before_transfer_fee(unwrap_int!(amount
+            .checked_add(fee)
+            .and_then(|v| v.checked_sub(admin_fee))))?,
+    )?;
+    if admin_fee > 0 {
+        ctx.accounts.transfer_user_to(
+            &mint,
+            &ctx.accounts.token.fees,
+            mint.amount_before_transfer_fee(admin_fee)?,
+        )?;
+    }
+
+    let flash_loan = &mut ctx.accounts.user.swap.flash_loan;
+    if is_token_0 {
+        flash_loan.amount_0 = 0;
+    } else {
+        flash_loan.amount_1 = 0;
+    }
+
+    // the loan is returned to the reserves along with the LP's share of the fee
+    unwrap_int!(ctx
+        .accounts
+        .user
+        .swap
+        .expected_reserves
+        .credit(is_token_0, unwrap_int!(fee.checked_sub(admin_fee))));
+
+    emit!(FlashLoanEvent {
+        lp_mint: ctx.accounts.user.swap.pool_mint,
+        mint: ctx.accounts.token.mint.key(),
+        amount,
+        fee,
+        admin_fee,
+    });
+
+    ctx.accounts.track_cumulative_flash_loan_fees(fee)?;
+
+    Ok(())
+}
+
+impl<'info> FlashLoan<'info> {
+    fn is_token_0(&self) -> bool {
+        self.token.reserve.key() == self.user.swap.token_0.reserves
+    }
+
+    /// Ensures that this instruction is not a CPI and that a [cpamm::repay_flash_loan]
+    /// for the same swap and token is executed later in the transaction.
+    fn check_repayment(&self) -> Result<()> {
+        let instructions = &self.instructions.to_account_info();
+        let current_index = usize::from(load_current_index_checked(instructions)?);
+
+        // the current instruction must be this program, otherwise the loan
+        // could be repaid by the same top-level instruction that borrows
+        let current_ix = load_instruction_at_checked(current_index, instructions)?;
+        assert_keys_eq!(current_ix.program_id, crate::ID, FlashLoanNotRepaid);
+
+        let repay_data = crate::instruction::RepayFlashLoan.data();
+        let mut index = unwrap_int!(current_index.checked_add(1));
+        while let Ok(ix) = load_instruction_at_checked(index, instructions) {
+            if ix.program_id == crate::ID
+                && ix.data.starts_with(&repay_data)
+                && ix
+                    .accounts
+                    .get(SWAP_ACCOUNT_INDEX)
+                    .map(|meta| meta.pubkey == self.user.swap.key())
+                    .unwrap_or(false)
+                && ix
+                    .accounts
+                    .get(RESERVE_ACCOUNT_INDEX)
+                    .map(|meta| meta.pubkey == self.token.reserve.key())
+                    .unwrap_or(false)
+            {
+                return Ok(());
+            }
+            index = unwrap_int!(index.checked_add(1));
+        }
+
+        err!(FlashLoanNotRepaid)
+    }
+
+    fn update_cumulative_price_info(&mut self) -> Result<()> {
+        // update price info
+        self.user.swap.update_cumulative_price_info()
+    }
+
+    /// Transfers the user's tokens to a pool token account.
+    fn transfer_user_to(
+        &self,
+        mint: &InterfaceAccount<'info, Mint>,
+        to: &AccountInfo<'info>,
+        amount: u64,
+    ) -> Result<()> {
+        token_interface::transfer_checked(
+            &self.token.token_program,
+            mint,
+            &self.token.user,
+            to,
+            &self.user.user_authority,
+            amount,
+            &[],
+        )
+    }
+
+    /// Tracks and saves the cumulative flash loan fees as trade fees.
+    fn track_cumulative_flash_loan_fees(&mut self, fee: u64) -> Result<()> {
+        let is_token_0 = self.is_token_0();
+        let cumulative_stats = &mut self.user.swap.cumulative_stats;
+        let cum_token = if is_token_0 {
+            &mut cumulative_stats.token_0
+        } else {
+            &mut cumulative_stats.token_1
+        };
+        cum_token.total_trade_fees = unwrap_int!(cum_token.total_trade_fees.checked_add(fee));
+        Ok(())
+    }
+}
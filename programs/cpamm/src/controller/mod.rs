@@ -1,6 +1,21 @@
 //! Processes instructions.
 #![deny(clippy::integer_arithmetic, clippy::float_arithmetic)]
 
+pub(crate) mod admin;
+pub(crate) mod close_swap;
+pub(crate) mod concentrated;
 pub(crate) mod deposit;
+pub(crate) mod flash_loan;
+pub(crate) mod new_swap;
+pub(crate) mod reserves;
 pub(crate) mod swap;
+pub(crate) mod swap_route;
 pub(crate) mod withdraw;
+
+use crate::*;
+
+/// Ensures that the current time has not passed the `deadline` of an instruction.
+pub(crate) fn check_deadline(deadline: i64) -> Result<()> {
+    invariant!(Clock::get()?.unix_timestamp <= deadline, Expired);
+    Ok(())
+}
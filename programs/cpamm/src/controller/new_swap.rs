@@ -0,0 +1,90 @@
+//! [crate::cpamm::new_swap] instruction processor.
+
+use crate::token_interface::{InterfaceAccount, TokenAccount};
+use crate::*;
+use anchor_spl::token::{self, MintTo};
+
+/// Creates a new swap with the given curve in a fee tier of the factory.
+pub fn new_swap(ctx: Context<NewSwap>, fee_tier_kbps: u32, curve: SwapCurve) -> Result<()> {
+    let calculator = curve.calculator()?;
+    invariant!(
+        ctx.accounts.factory.is_fee_tier_enabled(fee_tier_kbps),
+        InvalidFeeTier
+    );
+
+    let token_0 = &ctx.accounts.token_0;
+    let token_1 = &ctx.accounts.token_1;
+    let reserve_0: InterfaceAccount<TokenAccount> = InterfaceAccount::try_from(&token_0.reserve)?;
+    let reserve_1: InterfaceAccount<TokenAccount> = InterfaceAccount::try_from(&token_1.reserve)?;
+    invariant!(reserve_0.amount != 0, NewSwapMustHaveNonZeroSupply);
+    invariant!(reserve_1.amount != 0, NewSwapMustHaveNonZeroSupply);
+
+    let initial_liquidity = unwrap_int!(
+        calculator.calculate_initial_swap_pool_amount(reserve_0.amount, reserve_1.amount)
+    );
+    invariant!(
+        initial_liquidity >= xyk::MINIMUM_LIQUIDITY,
+        InitialLiquidityTooLow
+    );
+
+    // update factory index
+    let factory = &mut ctx.accounts.factory;
+    let index = factory.num_swaps;
+    factory.num_swaps = unwrap_int!(index.checked_add(1));
+
+    // init info
+    let swap_info = &mut ctx.accounts.swap;
+    swap_info.factory = factory.key();
+    swap_info.bump = unwrap_bump!(ctx, "swap");
+
+    swap_info.index = index;
+    swap_info.admin_key = factory.admin;
+    swap_info.token_0 = SwapTokenInfo::from(token_0);
+    swap_info.token_1 = SwapTokenInfo::from(token_1);
+
+    swap_info.is_paused = false;
+    swap_info.pool_mint = ctx.accounts.pool_mint.key();
+    swap_info.fees = factory.fees_for_tier(fee_tier_kbps);
+    swap_info.fee_tier_kbps = fee_tier_kbps;
+    swap_info.curve = curve;
+    swap_info.expected_reserves = SwapReserves {
+        amount_0: reserve_0.amount,
+        amount_1: reserve_1.amount,
+    };
+
+    // Zero cumulative stats.
+    swap_info.cumulative_stats = Default::default();
+
+    // set up initial price info
+    swap_info.price_info = SwapPriceInfo {
+        last_update_ts: Clock::get()?.unix_timestamp,
+        // zero cumulative price because no time has elapsed
+        price_0_cumulative_last: 0,
+        price_1_cumulative_last: 0,
+    };
+
+    // mint initial liquidity to initial staker
+    let seeds = gen_swap_signer_seeds!(swap_info);
+    let signer_seeds = &[&seeds[..]];
+    token::mint_to(
+        CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            MintTo {
+                mint: ctx.accounts.pool_mint.to_account_info(),
+                to: ctx.accounts.output_lp.to_account_info(),
+                authority: swap_info.to_account_info(),
+            },
+            signer_seeds,
+        ),
+        initial_liquidity,
+    )?;
+
+    emit!(NewPoolEvent {
+        lp_mint: ctx.accounts.pool_mint.key(),
+        mint_0: token_0.mint.key(),
+        mint_1: token_1.mint.key(),
+        initial_liquidity
+    });
+
+    Ok(())
+}
@@ -0,0 +1,82 @@
+//! [crate::cpamm::skim] and [crate::cpamm::sync] instruction processors.
+
+use crate::token_interface::{InterfaceAccount, Mint, TokenAccount};
+use crate::*;
+
+/// Sends the balances of the reserves in excess of the expected reserves
+/// to the admin fees accounts.
+pub fn skim(ctx: Context<Skim>) -> Result<()> {
+    let reserve_0: InterfaceAccount<TokenAccount> =
+        InterfaceAccount::try_from(&ctx.accounts.token_0.reserve)?;
+    let reserve_1: InterfaceAccount<TokenAccount> =
+        InterfaceAccount::try_from(&ctx.accounts.token_1.reserve)?;
+    update_cumulative_price_info(&mut ctx.accounts.swap, reserve_0.amount, reserve_1.amount)?;
+
+    let expected_reserves = ctx.accounts.swap.expected_reserves;
+    let amount_0 = unwrap_int!(reserve_0.amount.checked_sub(expected_reserves.amount_0));
+    let amount_1 = unwrap_int!(reserve_1.amount.checked_sub(expected_reserves.amount_1));
+
+    let swap = &ctx.accounts.swap;
+    skim_token(swap, &ctx.accounts.token_0, amount_0)?;
+    skim_token(swap, &ctx.accounts.token_1, amount_1)?;
+
+    emit!(SkimEvent {
+        lp_mint: swap.pool_mint,
+        amount_0,
+        amount_1,
+    });
+
+    Ok(())
+}
+
+/// Sets the expected reserves of the swap to the balances of the reserves.
+pub fn sync(ctx: Context<SyncReserves>) -> Result<()> {
+    let reserve_0: InterfaceAccount<TokenAccount> =
+        InterfaceAccount::try_from(&ctx.accounts.reserve_0)?;
+    let reserve_1: InterfaceAccount<TokenAccount> =
+        InterfaceAccount::try_from(&ctx.accounts.reserve_1)?;
+    let swap = &mut ctx.accounts.swap;
+    update_cumulative_price_info(swap, reserve_0.amount, reserve_1.amount)?;
+
+    swap.expected_reserves = SwapReserves {
+        amount_0: reserve_0.amount,
+        amount_1: reserve_1.amount,
+    };
+
+    emit!(SyncEvent {
+        lp_mint: swap.pool_mint,
+        reserve_0: reserve_0.amount,
+        reserve_1: reserve_1.amount,
+    });
+
+    Ok(())
+}
+
+/// Updates the cumulative price info using the reserves from before the instruction.
+/// Must be called BEFORE mutation.
+fn update_cumulative_price_info(swap: &mut SwapInfo, balance_0: u64, balance_1: u64) -> Result<()> {
+    swap.init_expected_reserves(balance_0, balance_1);
+    swap.update_cumulative_price_info()
+}
+
+/// Transfers `amount` of the reserves of a token to its admin fees account.
+fn skim_token<'info>(
+    swap: &Account<'info, SwapInfo>,
+    token: &SkimToken<'info>,
+    amount: u64,
+) -> Result<()> {
+    if amount == 0 {
+        return Ok(());
+    }
+    let mint: InterfaceAccount<Mint> = InterfaceAccount::try_from(&token.mint)?;
+    let seeds = gen_swap_signer_seeds!(swap);
+    token_interface::transfer_checked(
+        &token.token_program,
+        &mint,
+        &token.reserve,
+        &token.fees,
+        &swap.to_account_info(),
+        amount,
+        &[&seeds[..]],
+    )
+}
@@ -1,7 +1,7 @@
 //! [crate::cpamm::swap] instruction controller.
 
+use crate::token_interface::{self, InterfaceAccount, Mint, TokenAccount};
 use crate::*;
-use anchor_spl::token;
 use xyk::SwapResult;
 
 pub struct SwapArgs {
@@ -9,139 +9,382 @@ pub struct SwapArgs {
     pub minimum_amount_out: u64,
 }
 
+pub struct SwapExactOutArgs {
+    pub amount_out: u64,
+    pub maximum_amount_in: u64,
+}
+
+/// Reserves and mints of a swap, loaded from either token program.
+pub(crate) struct SwapTokens<'info> {
+    pub input_reserve: InterfaceAccount<'info, TokenAccount>,
+    pub input_mint: InterfaceAccount<'info, Mint>,
+    pub output_reserve: InterfaceAccount<'info, TokenAccount>,
+    pub output_mint: InterfaceAccount<'info, Mint>,
+}
+
+/// The user's token account paying the input of a swap.
+pub(crate) struct SwapInput<'a, 'info> {
+    pub token_program: &'a AccountInfo<'info>,
+    pub source: &'a AccountInfo<'info>,
+    pub authority: &'a AccountInfo<'info>,
+}
+
+/// Accounts paying for and receiving a computed swap.
+pub(crate) struct SwapAccounts<'a, 'info> {
+    /// The user's input, or [None] if it was already transferred to the input reserves.
+    pub input: Option<SwapInput<'a, 'info>>,
+    pub output_token_program: &'a AccountInfo<'info>,
+    pub output_fees: &'a AccountInfo<'info>,
+    /// The token account receiving the output net of the trade fee.
+    pub destination: &'a AccountInfo<'info>,
+    pub referrer: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub user_authority: Pubkey,
+}
+
 /// Swap
-pub fn swap(ctx: Context<Swap>, args: SwapArgs) -> Result<()> {
+pub fn swap<'info>(ctx: Context<'_, '_, '_, 'info, Swap<'info>>, args: SwapArgs) -> Result<()> {
+    let tokens = ctx.accounts.load_tokens()?;
+    let (input_reserve, output_reserve) = ctx.accounts.expected_reserves(&tokens);
+
     // update cumulative price info.
     // must be called BEFORE mutation.
     ctx.accounts.update_cumulative_price_info()?;
+    price_info::record_observation(&ctx.accounts.user.swap, ctx.remaining_accounts)?;
 
-    let (input_reserve, output_reserve) = (
-        ctx.accounts.input.reserve.amount,
-        ctx.accounts.output.reserve.amount,
-    );
-
-    let swap_fees = ctx.accounts.user.swap.fees;
-    let token_swap = &ctx.accounts.user.swap;
+    // the pool only receives the input net of the transfer fee
+    let amount_in = tokens
+        .input_mint
+        .amount_after_transfer_fee(args.amount_in)?;
 
     // compute the swap
-    let swap_result = unwrap_int!(xyk::swap(args.amount_in, input_reserve, output_reserve));
+    let swap_info = &ctx.accounts.user.swap;
+    let is_input_token_0 = tokens.input_reserve.mint == swap_info.token_0.mint;
+    let curve = swap_info.curve.swap_calculator(is_input_token_0)?;
+    let swap_result = unwrap_int!(curve.swap(amount_in, input_reserve, output_reserve));
+    let reserves_after = unwrap_int!(swap_info
+        .expected_reserves
+        .after_swap(is_input_token_0, &swap_result));
+    let swap_fees = ctx.accounts.user.swap.update_trade_fees(reserves_after)?;
     if swap_result.destination_amount_swapped == 0 {
         // skip the transfers if nothing is being swapped
         return Ok(());
     }
 
+    // the transfer fee of the output mint is withheld from the user
+    let (trade_fee, _) =
+        unwrap_int!(swap_fees.compute_trade_fees(swap_result.destination_amount_swapped));
+    let output_transfer_fee = tokens.output_mint.transfer_fee(unwrap_int!(swap_result
+        .destination_amount_swapped
+        .checked_sub(trade_fee)))?;
     invariant!(
-        swap_result.destination_amount_swapped >= args.minimum_amount_out,
+        unwrap_int!(swap_result
+            .destination_amount_swapped
+            .checked_sub(output_transfer_fee))
+            >= args.minimum_amount_out,
         ExceededSlippage
     );
 
+    ctx.accounts
+        .execute_swap(&tokens, &swap_fees, &swap_result, ctx.remaining_accounts)
+}
+
+/// Swap exact out
+pub fn swap_exact_out<'info>(
+    ctx: Context<'_, '_, '_, 'info, Swap<'info>>,
+    args: SwapExactOutArgs,
+) -> Result<()> {
+    let tokens = ctx.accounts.load_tokens()?;
+    let (input_reserve, output_reserve) = ctx.accounts.expected_reserves(&tokens);
+
+    // update cumulative price info.
+    // must be called BEFORE mutation.
+    ctx.accounts.update_cumulative_price_info()?;
+    price_info::record_observation(&ctx.accounts.user.swap, ctx.remaining_accounts)?;
+
+    invariant!(
+        ctx.accounts.user.swap.curve.is_constant_product(),
+        UnsupportedCurve
+    );
+
+    if args.amount_out == 0 {
+        // skip the transfers if nothing is being swapped
+        return Ok(());
+    }
+
+    // the trade fee and the transfer fee are taken from the output, so more must be swapped
+    let amount_out = tokens
+        .output_mint
+        .amount_before_transfer_fee(args.amount_out)?;
+    invariant!(amount_out < output_reserve, InsufficientLiquidity);
+
+    // the trade fee depends on the price after the trade, which is estimated
+    // from the output before the trade fee
+    let swap_info = &ctx.accounts.user.swap;
+    let is_input_token_0 = tokens.input_reserve.mint == swap_info.token_0.mint;
+    let reserves_after = unwrap_int!(swap_info.expected_reserves.after_swap(
+        is_input_token_0,
+        &unwrap_int!(xyk::swap_exact_out(
+            amount_out,
+            input_reserve,
+            output_reserve
+        ))
+    ));
+    let swap_fees = ctx.accounts.user.swap.update_trade_fees(reserves_after)?;
+    let destination_amount = unwrap_int!(swap_fees.compute_amount_before_trade_fee(amount_out));
+    invariant!(destination_amount < output_reserve, InsufficientLiquidity);
+
+    // compute the swap
+    let swap_result = unwrap_int!(xyk::swap_exact_out(
+        destination_amount,
+        input_reserve,
+        output_reserve
+    ));
+
+    // the user also pays the transfer fee of the input mint
+    let amount_in = tokens
+        .input_mint
+        .amount_before_transfer_fee(swap_result.source_amount_swapped)?;
+    invariant!(amount_in <= args.maximum_amount_in, ExceededSlippage);
+
+    ctx.accounts
+        .execute_swap(&tokens, &swap_fees, &swap_result, ctx.remaining_accounts)
+}
+
+impl<'info> Swap<'info> {
+    /// Loads the reserves and mints of the swap.
+    fn load_tokens(&self) -> Result<SwapTokens<'info>> {
+        Ok(SwapTokens {
+            input_reserve: InterfaceAccount::try_from(&self.input.reserve)?,
+            input_mint: InterfaceAccount::try_from(&self.input.mint)?,
+            output_reserve: InterfaceAccount::try_from(&self.output.reserve)?,
+            output_mint: InterfaceAccount::try_from(&self.output.mint)?,
+        })
+    }
+
+    /// Transfers the tokens of a computed swap to and from the user.
+    ///
+    /// If a token account of the output mint is passed as a remaining account,
+    /// it receives [SwapInfo::referral_fee_kbps] of the admin trade fee.
+    fn execute_swap(
+        &mut self,
+        tokens: &SwapTokens<'info>,
+        swap_fees: &SwapFees,
+        swap_result: &SwapResult,
+        remaining_accounts: &[AccountInfo<'info>],
+    ) -> Result<()> {
+        let referrer = self.load_referrer(tokens, remaining_accounts)?;
+        let accounts = SwapAccounts {
+            input: Some(SwapInput {
+                token_program: &self.input.token_program,
+                source: &self.input.user,
+                authority: &self.user.user_authority,
+            }),
+            output_token_program: &self.output.token_program,
+            output_fees: &self.output.fees,
+            destination: &self.output.user,
+            referrer,
+            user_authority: self.user.user_authority.key(),
+        };
+        execute_swap(
+            &mut self.user.swap,
+            tokens,
+            accounts,
+            swap_fees,
+            swap_result,
+        )?;
+        Ok(())
+    }
+
+    /// Loads the referrer token account, which is the remaining account owned by a token program.
+    fn load_referrer(
+        &self,
+        tokens: &SwapTokens<'info>,
+        remaining_accounts: &[AccountInfo<'info>],
+    ) -> Result<Option<InterfaceAccount<'info, TokenAccount>>> {
+        let referrer_info = match remaining_accounts
+            .iter()
+            .find(|account| token_interface::is_token_program(account.owner))
+        {
+            Some(referrer_info) => referrer_info,
+            None => return Ok(None),
+        };
+        let referrer: InterfaceAccount<TokenAccount> = InterfaceAccount::try_from(referrer_info)?;
+        assert_keys_eq!(referrer.mint, tokens.output_reserve.mint);
+        assert_keys_neq!(*referrer_info.key, self.output.reserve);
+        Ok(Some(referrer))
+    }
+
+    /// Returns the expected reserves of the input and output tokens.
+    fn expected_reserves(&mut self, tokens: &SwapTokens<'info>) -> (u64, u64) {
+        let swap_info = &mut self.user.swap;
+        let is_input_token_0 = tokens.input_reserve.mint == swap_info.token_0.mint;
+        if is_input_token_0 {
+            swap_info
+                .init_expected_reserves(tokens.input_reserve.amount, tokens.output_reserve.amount);
+        } else {
+            swap_info
+                .init_expected_reserves(tokens.output_reserve.amount, tokens.input_reserve.amount);
+        }
+        let reserves = swap_info.expected_reserves;
+        (
+            reserves.get(is_input_token_0),
+            reserves.get(!is_input_token_0),
+        )
+    }
+
+    fn update_cumulative_price_info(&mut self) -> Result<()> {
+        // update price info
+        self.user.swap.update_cumulative_price_info()?;
+        Ok(())
+    }
+}
+
+/// Transfers the tokens of a computed swap, collects fees, and records statistics.
+///
+/// If [SwapAccounts::input] is set, the user sends enough input tokens for the pool to receive
+/// [SwapResult::source_amount_swapped] after the input mint's transfer fee.
+/// If [SwapAccounts::referrer] is set, it receives [SwapInfo::referral_fee_kbps]
+/// of the admin trade fee.
+///
+/// Returns the amount sent to [SwapAccounts::destination].
+pub(crate) fn execute_swap<'info>(
+    token_swap: &mut Account<'info, SwapInfo>,
+    tokens: &SwapTokens<'info>,
+    accounts: SwapAccounts<'_, 'info>,
+    swap_fees: &SwapFees,
+    swap_result: &SwapResult,
+) -> Result<u64> {
     let (trade_fee, admin_trade_fee) =
         unwrap_int!(swap_fees.compute_trade_fees(swap_result.destination_amount_swapped));
 
-    // Transfer user's tokens to the pool.
-    let token_program = &ctx.accounts.user.token_program;
-    token::transfer(
-        CpiContext::new(
-            token_program.to_account_info(),
-            token::Transfer {
-                from: ctx.accounts.input.user.to_account_info(),
-                to: ctx.accounts.input.reserve.to_account_info(),
-                authority: ctx.accounts.user.user_authority.to_account_info(),
-            },
-        ),
-        swap_result.source_amount_swapped,
-    )?;
+    let referral_fee = if accounts.referrer.is_some() {
+        unwrap_int!(token_swap.compute_referral_fee(admin_trade_fee))
+    } else {
+        0
+    };
+    let admin_trade_fee = unwrap_int!(admin_trade_fee.checked_sub(referral_fee));
+
+    if let Some(input) = &accounts.input {
+        // Transfer user's tokens to the pool.
+        token_interface::transfer_checked(
+            input.token_program,
+            &tokens.input_mint,
+            input.source,
+            tokens.input_reserve.as_ref(),
+            input.authority,
+            tokens
+                .input_mint
+                .amount_before_transfer_fee(swap_result.source_amount_swapped)?,
+            &[],
+        )?;
+    }
 
     let seeds = gen_swap_signer_seeds!(token_swap);
+    let signer_seeds = &[&seeds[..]];
+    let output_token_program = accounts.output_token_program;
 
     if admin_trade_fee > 0 {
         // Transfer user's fee to the fees account.
-        let signer_seeds = &[&seeds[..]];
-        token::transfer(
-            CpiContext::new_with_signer(
-                token_program.to_account_info(),
-                token::Transfer {
-                    from: ctx.accounts.output.reserve.to_account_info(),
-                    to: ctx.accounts.output.fees.to_account_info(),
-                    authority: token_swap.to_account_info(),
-                },
-                signer_seeds,
-            ),
+        token_interface::transfer_checked(
+            output_token_program,
+            &tokens.output_mint,
+            tokens.output_reserve.as_ref(),
+            accounts.output_fees,
+            &token_swap.to_account_info(),
             admin_trade_fee,
+            signer_seeds,
         )?;
     }
 
+    if let Some(referrer) = &accounts.referrer {
+        if referral_fee > 0 {
+            // Transfer the referrer's share of the fee to the referrer.
+            token_interface::transfer_checked(
+                output_token_program,
+                &tokens.output_mint,
+                tokens.output_reserve.as_ref(),
+                referrer.as_ref(),
+                &token_swap.to_account_info(),
+                referral_fee,
+                signer_seeds,
+            )?;
+        }
+    }
+
     // Transfer pool's tokens to the user.
-    let signer_seeds = &[&seeds[..]];
-    token::transfer(
-        CpiContext::new_with_signer(
-            token_program.to_account_info(),
-            token::Transfer {
-                from: ctx.accounts.output.reserve.to_account_info(),
-                to: ctx.accounts.output.user.to_account_info(),
-                authority: token_swap.to_account_info(),
-            },
-            signer_seeds,
-        ),
-        unwrap_int!(swap_result
-            .destination_amount_swapped
-            .checked_sub(trade_fee)),
+    let amount_out = unwrap_int!(swap_result
+        .destination_amount_swapped
+        .checked_sub(trade_fee));
+    token_interface::transfer_checked(
+        output_token_program,
+        &tokens.output_mint,
+        tokens.output_reserve.as_ref(),
+        accounts.destination,
+        &token_swap.to_account_info(),
+        amount_out,
+        signer_seeds,
     )?;
 
+    // track the amounts received and sent by the reserves
+    let is_input_token_0 = tokens.input_reserve.mint == token_swap.token_0.mint;
+    let amount_sent = unwrap_int!(amount_out
+        .checked_add(admin_trade_fee)
+        .and_then(|v| v.checked_add(referral_fee)));
+    let reserves = &mut token_swap.expected_reserves;
+    unwrap_int!(reserves.credit(is_input_token_0, swap_result.source_amount_swapped));
+    unwrap_int!(reserves.debit(!is_input_token_0, amount_sent));
+    let reserves = *reserves;
+
     emit!(SwapEvent {
-        lp_mint: ctx.accounts.user.swap.pool_mint,
-        input_mint: ctx.accounts.input.reserve.mint,
-        output_mint: ctx.accounts.output.reserve.mint,
+        lp_mint: token_swap.pool_mint,
+        input_mint: tokens.input_reserve.mint,
+        output_mint: tokens.output_reserve.mint,
         source_amount_swapped: swap_result.source_amount_swapped,
         destination_amount_swapped: swap_result.destination_amount_swapped,
+        referrer: accounts.referrer.map(|referrer| *referrer.as_ref().key),
+        referral_fee,
+        trade_fee_kbps: swap_fees.trade_fee_kbps,
+        user_authority: accounts.user_authority,
+        trade_fee,
+        admin_trade_fee,
+        reserve_0: reserves.amount_0,
+        reserve_1: reserves.amount_1,
     });
 
-    ctx.accounts
-        .track_cumulative_swap_volume(&swap_result, trade_fee)?;
+    track_cumulative_swap_volume(
+        token_swap,
+        tokens.input_reserve.mint,
+        swap_result,
+        trade_fee,
+    )?;
 
-    Ok(())
+    Ok(amount_out)
 }
 
-impl<'info> Swap<'info> {
-    fn update_cumulative_price_info(&mut self) -> Result<()> {
-        // update price info
-        let swap_info = &mut self.user.swap;
-        let (reserve_a, reserve_b) = if self.input.reserve.mint == swap_info.token_0.mint {
-            (self.input.reserve.amount, self.output.reserve.amount)
-        } else {
-            (self.output.reserve.amount, self.input.reserve.amount)
-        };
-        swap_info
-            .price_info
-            .update_cumulative_price_info(reserve_a, reserve_b)?;
-        Ok(())
-    }
-
-    /// Tracks and saves the cumulative swap volume.
-    fn track_cumulative_swap_volume(
-        &mut self,
-        swap_result: &SwapResult,
-        trade_fee: u64,
-    ) -> Result<()> {
-        // record cumulative volume numbers
-        let token_0_mint = self.user.swap.token_0.mint;
-        let swap_info = &mut self.user.swap;
-        let cumulative_stats = &mut swap_info.cumulative_stats;
-        let (cum_input_token, cum_output_token) = if self.input.reserve.mint == token_0_mint {
-            (&mut cumulative_stats.token_0, &mut cumulative_stats.token_1)
-        } else {
-            (&mut cumulative_stats.token_1, &mut cumulative_stats.token_0)
-        };
+/// Tracks and saves the cumulative swap volume of a [SwapInfo].
+fn track_cumulative_swap_volume(
+    swap_info: &mut SwapInfo,
+    input_mint: Pubkey,
+    swap_result: &SwapResult,
+    trade_fee: u64,
+) -> Result<()> {
+    // record cumulative volume numbers
+    let token_0_mint = swap_info.token_0.mint;
+    let cumulative_stats = &mut swap_info.cumulative_stats;
+    let (cum_input_token, cum_output_token) = if input_mint == token_0_mint {
+        (&mut cumulative_stats.token_0, &mut cumulative_stats.token_1)
+    } else {
+        (&mut cumulative_stats.token_1, &mut cumulative_stats.token_0)
+    };
 
-        cum_input_token.total_input_volume = unwrap_int!(cum_input_token
-            .total_input_volume
-            .checked_add(swap_result.source_amount_swapped.into()));
-        cum_output_token.total_output_volume = unwrap_int!(cum_output_token
-            .total_output_volume
-            .checked_add(swap_result.destination_amount_swapped.into()));
-        cum_output_token.total_trade_fees =
-            unwrap_int!(cum_output_token.total_trade_fees.checked_add(trade_fee));
+    cum_input_token.total_input_volume = unwrap_int!(cum_input_token
+        .total_input_volume
+        .checked_add(swap_result.source_amount_swapped.into()));
+    cum_output_token.total_output_volume = unwrap_int!(cum_output_token
+        .total_output_volume
+        .checked_add(swap_result.destination_amount_swapped.into()));
+    cum_output_token.total_trade_fees =
+        unwrap_int!(cum_output_token.total_trade_fees.checked_add(trade_fee));
 
-        Ok(())
-    }
+    Ok(())
 }
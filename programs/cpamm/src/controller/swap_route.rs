@@ -0,0 +1,195 @@
+//! [crate::cpamm::swap_route] instruction controller.
+//!
+//! Each hop of the route is passed as [ACCOUNTS_PER_HOP] remaining accounts:
+//!
+//! 0. `[writable]` The [SwapInfo] of the hop.
+//! 1. `[writable]` The pool's reserves of the input token of the hop.
+//! 2. `[writable]` The pool's reserves of the output token of the hop.
+//! 3. `[writable]` The admin fees account of the output token of the hop.
+//! 4. `[]` The mint of the output token of the hop.
+//! 5. `[]` The token program of the output mint.
+//!
+//! The output of each hop is transferred directly from its reserves into the
+//! input reserves of the next hop, so no intermediate token accounts are needed.
+//! Each hop swaps the amount its reserves receive after the transfer fee of its input mint.
+
+use crate::controller::swap::{execute_swap, SwapAccounts, SwapInput, SwapTokens};
+use crate::token_interface::{InterfaceAccount, Mint, TokenAccount};
+use crate::*;
+use xyk::SwapResult;
+
+/// Number of remaining accounts per hop of a route.
+pub const ACCOUNTS_PER_HOP: usize = 6;
+
+pub struct SwapRouteArgs {
+    pub amount_in: u64,
+    pub minimum_amount_out: u64,
+}
+
+/// Swap route
+pub fn swap_route<'info>(
+    ctx: Context<'_, '_, '_, 'info, SwapRoute<'info>>,
+    args: SwapRouteArgs,
+) -> Result<()> {
+    let remaining_accounts = ctx.remaining_accounts;
+    invariant!(
+        !remaining_accounts.is_empty()
+            && remaining_accounts.len().checked_rem(ACCOUNTS_PER_HOP) == Some(0),
+        InvalidRoute
+    );
+    invariant!(args.amount_in > 0, "amount_in must be positive");
+
+    let mut input_mint: InterfaceAccount<Mint> =
+        InterfaceAccount::try_from(&ctx.accounts.input_mint)?;
+    let mut hops: Vec<RouteHop> = vec![];
+    for hop_accounts in remaining_accounts.chunks(ACCOUNTS_PER_HOP) {
+        let hop = RouteHop::load(hop_accounts, input_mint)?;
+
+        // a pool may only appear once, since every hop is loaded before the route is executed
+        invariant!(
+            !hops.iter().any(|other| other.swap.key() == hop.swap.key()),
+            InvalidRoute
+        );
+
+        input_mint = hop.tokens.output_mint.clone();
+        hops.push(hop);
+    }
+
+    let output: InterfaceAccount<TokenAccount> = InterfaceAccount::try_from(&ctx.accounts.output)?;
+    assert_keys_eq!(output.mint, input_mint, InvalidRoute);
+
+    let mut amount = args.amount_in;
+    for index in 0..hops.len() {
+        // each hop pays its output directly into the input reserves of the next hop
+        let destination = match hops.get(index.saturating_add(1)) {
+            Some(next_hop) => next_hop.tokens.input_reserve.as_ref().clone(),
+            None => ctx.accounts.output.to_account_info(),
+        };
+        let hop = &mut hops[index];
+
+        let amount_received = hop.tokens.input_mint.amount_after_transfer_fee(amount)?;
+        let (swap_fees, swap_result) = hop.compute_swap(amount_received)?;
+
+        // only the first hop is paid by the user
+        let input = if index == 0 {
+            Some(SwapInput {
+                token_program: &ctx.accounts.token_program,
+                source: &ctx.accounts.input,
+                authority: &ctx.accounts.user_authority,
+            })
+        } else {
+            None
+        };
+        let accounts = SwapAccounts {
+            input,
+            output_token_program: &hop.output_token_program,
+            output_fees: &hop.output_fees,
+            destination: &destination,
+            referrer: None,
+            user_authority: ctx.accounts.user_authority.key(),
+        };
+        amount = execute_swap(
+            &mut hop.swap,
+            &hop.tokens,
+            accounts,
+            &swap_fees,
+            &swap_result,
+        )?;
+        hop.swap.exit(&crate::ID)?;
+    }
+
+    invariant!(
+        input_mint.amount_after_transfer_fee(amount)? >= args.minimum_amount_out,
+        ExceededSlippage
+    );
+
+    Ok(())
+}
+
+/// A single hop of a route.
+struct RouteHop<'info> {
+    swap: Box<Account<'info, SwapInfo>>,
+    tokens: SwapTokens<'info>,
+    output_fees: AccountInfo<'info>,
+    output_token_program: AccountInfo<'info>,
+}
+
+impl<'info> RouteHop<'info> {
+    /// Loads and validates the accounts of a hop swapping `input_mint`.
+    fn load(
+        accounts: &[AccountInfo<'info>],
+        input_mint: InterfaceAccount<'info, Mint>,
+    ) -> Result<Self> {
+        let hop = match accounts {
+            [swap, input_reserve, output_reserve, output_fees, output_mint, output_token_program] => {
+                RouteHop {
+                    swap: Box::new(Account::try_from(swap)?),
+                    tokens: SwapTokens {
+                        input_reserve: InterfaceAccount::try_from(input_reserve)?,
+                        input_mint,
+                        output_reserve: InterfaceAccount::try_from(output_reserve)?,
+                        output_mint: InterfaceAccount::try_from(output_mint)?,
+                    },
+                    output_fees: output_fees.clone(),
+                    output_token_program: output_token_program.clone(),
+                }
+            }
+            _ => return err!(InvalidRoute),
+        };
+
+        invariant!(!hop.swap.is_paused, Paused);
+        invariant!(!hop.swap.flash_loan.is_active(), FlashLoanInProgress);
+
+        let tokens = &hop.tokens;
+        assert_keys_eq!(tokens.input_reserve.mint, tokens.input_mint, InvalidRoute);
+        let (swap_input, swap_output) = if tokens.input_reserve.key() == hop.swap.token_0.reserves {
+            (&hop.swap.token_0, &hop.swap.token_1)
+        } else {
+            (&hop.swap.token_1, &hop.swap.token_0)
+        };
+        assert_keys_eq!(tokens.input_reserve, swap_input.reserves);
+        assert_keys_eq!(tokens.output_reserve, swap_output.reserves);
+        assert_keys_eq!(hop.output_fees, swap_output.admin_fees);
+        assert_keys_eq!(tokens.output_mint, swap_output.mint);
+        assert_keys_eq!(hop.output_token_program, *tokens.output_mint.as_ref().owner);
+
+        Ok(hop)
+    }
+
+    /// Computes the swap of the hop and updates its price and trade fees.
+    fn compute_swap(&mut self, amount_in: u64) -> Result<(SwapFees, SwapResult)> {
+        let tokens = &self.tokens;
+        let is_input_token_0 = tokens.input_reserve.mint == self.swap.token_0.mint;
+        if is_input_token_0 {
+            self.swap
+                .init_expected_reserves(tokens.input_reserve.amount, tokens.output_reserve.amount);
+        } else {
+            self.swap
+                .init_expected_reserves(tokens.output_reserve.amount, tokens.input_reserve.amount);
+        }
+        let reserves = self.swap.expected_reserves;
+        let (input_reserve, output_reserve) = (
+            reserves.get(is_input_token_0),
+            reserves.get(!is_input_token_0),
+        );
+
+        // update cumulative price info.
+        // must be called BEFORE mutation.
+        self.swap.update_cumulative_price_info()?;
+
+        // compute the swap
+        let curve = self.swap.curve.swap_calculator(is_input_token_0)?;
+        let swap_result: SwapResult =
+            unwrap_int!(curve.swap(amount_in, input_reserve, output_reserve));
+        let reserves_after = unwrap_int!(reserves.after_swap(is_input_token_0, &swap_result));
+        let swap_fees = self.swap.update_trade_fees(reserves_after)?;
+        let (trade_fee, _) =
+            unwrap_int!(swap_fees.compute_trade_fees(swap_result.destination_amount_swapped));
+        invariant!(
+            swap_result.destination_amount_swapped > trade_fee,
+            InsufficientLiquidity
+        );
+
+        Ok((swap_fees, swap_result))
+    }
+}
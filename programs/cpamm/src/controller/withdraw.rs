@@ -1,9 +1,10 @@
 //! [crate::cpamm::withdraw] instruction processor.
 
+use crate::token_interface::{self, InterfaceAccount, Mint, TokenAccount};
 use crate::*;
 use anchor_spl::token;
 
-use xyk::{pool_tokens_to_trading_tokens, RoundDirection};
+use xyk::RoundDirection;
 
 pub struct WithdrawArgs {
     pub amount_in: u64,
@@ -11,40 +12,61 @@ pub struct WithdrawArgs {
     pub minimum_amount_out_1: u64,
 }
 
+pub struct WithdrawOneArgs {
+    pub pool_token_amount: u64,
+    pub minimum_amount_out: u64,
+}
+
 /// Withdraw
 pub fn withdraw(ctx: Context<Withdraw>, args: WithdrawArgs) -> Result<()> {
+    let reserve_0: InterfaceAccount<TokenAccount> =
+        InterfaceAccount::try_from(&ctx.accounts.output_0.reserve)?;
+    let reserve_1: InterfaceAccount<TokenAccount> =
+        InterfaceAccount::try_from(&ctx.accounts.output_1.reserve)?;
+
+    ctx.accounts
+        .user
+        .swap
+        .init_expected_reserves(reserve_0.amount, reserve_1.amount);
+
     // update cumulative price info.
     // we call this before the short circuit
     // so the numbers are accurate.
     ctx.accounts.update_cumulative_price_info()?;
+    price_info::record_observation(&ctx.accounts.user.swap, ctx.remaining_accounts)?;
 
     // skip the withdrawal if nothing is being withdrawn
     if args.amount_in == 0 {
         return Ok(());
     }
 
-    let result = unwrap_int!(pool_tokens_to_trading_tokens(
+    let curve = ctx.accounts.user.swap.curve.calculator()?;
+    let reserves = ctx.accounts.user.swap.expected_reserves;
+    let result = unwrap_int!(curve.pool_tokens_to_trading_tokens(
         args.amount_in,
         ctx.accounts.pool_mint.supply,
-        ctx.accounts.output_0.reserve.amount,
-        ctx.accounts.output_1.reserve.amount,
+        reserves.amount_0,
+        reserves.amount_1,
         RoundDirection::Floor,
     ));
 
-    let token_0_amount = std::cmp::min(ctx.accounts.output_0.reserve.amount, result.token_a_amount);
-    let token_1_amount = std::cmp::min(ctx.accounts.output_1.reserve.amount, result.token_b_amount);
+    let token_0_amount = std::cmp::min(reserves.amount_0, result.token_a_amount);
+    let token_1_amount = std::cmp::min(reserves.amount_1, result.token_b_amount);
 
     // pool token output should be at least 1 for each token
     invariant!(token_0_amount > 0, InsufficientLiquidity);
     invariant!(token_1_amount > 0, InsufficientLiquidity);
 
     // ensure we are meeting the max slippage
+    let swap_fees = ctx.accounts.user.swap.fees;
+    let mint_0: InterfaceAccount<Mint> = InterfaceAccount::try_from(&ctx.accounts.output_0.mint)?;
+    let mint_1: InterfaceAccount<Mint> = InterfaceAccount::try_from(&ctx.accounts.output_1.mint)?;
     invariant!(
-        token_0_amount >= args.minimum_amount_out_0,
+        amount_less_transfer_fee(&swap_fees, &mint_0, token_0_amount)? >= args.minimum_amount_out_0,
         ExceededSlippage
     );
     invariant!(
-        token_1_amount >= args.minimum_amount_out_1,
+        amount_less_transfer_fee(&swap_fees, &mint_1, token_1_amount)? >= args.minimum_amount_out_1,
         ExceededSlippage
     );
 
@@ -63,16 +85,42 @@ pub fn withdraw(ctx: Context<Withdraw>, args: WithdrawArgs) -> Result<()> {
     )?;
 
     // Transfer tokens from reserve to user
-    ctx.accounts
-        .withdraw_token(&ctx.accounts.output_0, token_0_amount)?;
-    ctx.accounts
-        .withdraw_token(&ctx.accounts.output_1, token_1_amount)?;
+    let (fee_0, admin_fee_0) =
+        ctx.accounts
+            .withdraw_token(&ctx.accounts.output_0, &mint_0, token_0_amount)?;
+    let (fee_1, admin_fee_1) =
+        ctx.accounts
+            .withdraw_token(&ctx.accounts.output_1, &mint_1, token_1_amount)?;
+
+    // the LP share of the withdraw fee remains in the reserves
+    let expected_reserves = &mut ctx.accounts.user.swap.expected_reserves;
+    unwrap_int!(expected_reserves.debit(
+        true,
+        unwrap_int!(token_0_amount
+            .checked_sub(fee_0)
+            .and_then(|v| v.checked_add(admin_fee_0)))
+    ));
+    unwrap_int!(expected_reserves.debit(
+        false,
+        unwrap_int!(token_1_amount
+            .checked_sub(fee_1)
+            .and_then(|v| v.checked_add(admin_fee_1)))
+    ));
 
+    let reserves = *expected_reserves;
     emit!(WithdrawEvent {
         lp_mint: ctx.accounts.pool_mint.key(),
         pool_token_amount: args.amount_in,
         token_0_amount,
-        token_1_amount
+        token_1_amount,
+        user_authority: ctx.accounts.user.user_authority.key(),
+        fee_0,
+        fee_1,
+        admin_fee_0,
+        admin_fee_1,
+        reserve_0: reserves.amount_0,
+        reserve_1: reserves.amount_1,
+        pool_token_supply: unwrap_int!(ctx.accounts.pool_mint.supply.checked_sub(args.amount_in)),
     });
 
     ctx.accounts.track_cumulative_withdraw_volume(
@@ -81,6 +129,164 @@ pub fn withdraw(ctx: Context<Withdraw>, args: WithdrawArgs) -> Result<()> {
         token_1_amount,
     )?;
 
+    // a closing swap may be fully withdrawn
+    if !ctx.accounts.user.swap.is_closing() {
+        ctx.accounts.pool_mint.reload()?;
+        invariant!(
+            ctx.accounts.pool_mint.supply >= xyk::MINIMUM_LIQUIDITY,
+            InsufficientLiquidityPostWithdrawal
+        );
+    }
+
+    Ok(())
+}
+
+/// Withdraw a single token
+pub fn withdraw_one(ctx: Context<WithdrawOne>, args: WithdrawOneArgs) -> Result<()> {
+    let reserve: InterfaceAccount<TokenAccount> =
+        InterfaceAccount::try_from(&ctx.accounts.output.reserve)?;
+    let other_reserve: InterfaceAccount<TokenAccount> =
+        InterfaceAccount::try_from(&ctx.accounts.other_reserve)?;
+
+    let is_token_0 = reserve.mint == ctx.accounts.user.swap.token_0.mint;
+    if is_token_0 {
+        ctx.accounts
+            .user
+            .swap
+            .init_expected_reserves(reserve.amount, other_reserve.amount);
+    } else {
+        ctx.accounts
+            .user
+            .swap
+            .init_expected_reserves(other_reserve.amount, reserve.amount);
+    }
+
+    // update cumulative price info.
+    // we call this before the short circuit
+    // so the numbers are accurate.
+    ctx.accounts.update_cumulative_price_info()?;
+    price_info::record_observation(&ctx.accounts.user.swap, ctx.remaining_accounts)?;
+
+    invariant!(
+        ctx.accounts.user.swap.curve.is_constant_product(),
+        UnsupportedCurve
+    );
+
+    // skip the withdrawal if nothing is being withdrawn
+    if args.pool_token_amount == 0 {
+        return Ok(());
+    }
+
+    let output_reserve = ctx.accounts.user.swap.expected_reserves.get(is_token_0);
+    let destination_amount = unwrap_int!(xyk::withdraw_single_token_type(
+        args.pool_token_amount,
+        output_reserve,
+        ctx.accounts.pool_mint.supply,
+    ));
+
+    // half of the withdrawal is implicitly swapped from the other token,
+    // so it must pay the trade fee.
+    let half_destination_amount = unwrap_int!(destination_amount
+        .checked_div(2)
+        .and_then(|v| v.checked_add(destination_amount.checked_rem(2)?)));
+
+    // the trade fee depends on the price after the implied swap.
+    // an empty swap leaves the price unchanged.
+    let reserves = ctx.accounts.user.swap.expected_reserves;
+    let reserves_after = xyk::swap_exact_out(
+        half_destination_amount,
+        reserves.get(!is_token_0),
+        reserves.get(is_token_0),
+    )
+    .and_then(|swap_result| reserves.after_swap(!is_token_0, &swap_result))
+    .unwrap_or(reserves);
+    let swap_fees = ctx.accounts.user.swap.update_trade_fees(reserves_after)?;
+    let (trade_fee, admin_trade_fee) =
+        unwrap_int!(swap_fees.compute_trade_fees(half_destination_amount));
+    let token_amount = unwrap_int!(destination_amount.checked_sub(trade_fee));
+
+    let (withdraw_fee, admin_withdraw_fee) =
+        unwrap_int!(swap_fees.compute_withdraw_fees(token_amount));
+    let user_amount = unwrap_int!(token_amount.checked_sub(withdraw_fee));
+
+    // pool token output should be at least 1
+    invariant!(user_amount > 0, InsufficientLiquidity);
+
+    // ensure we are meeting the max slippage.
+    // the transfer fee of the mint is withheld from the user.
+    let mint: InterfaceAccount<Mint> = InterfaceAccount::try_from(&ctx.accounts.output.mint)?;
+    invariant!(
+        mint.amount_after_transfer_fee(user_amount)? >= args.minimum_amount_out,
+        ExceededSlippage
+    );
+
+    // Burn LP tokens
+    let token_program = &ctx.accounts.user.token_program;
+    token::burn(
+        CpiContext::new(
+            token_program.to_account_info(),
+            token::Burn {
+                mint: ctx.accounts.pool_mint.to_account_info(),
+                to: ctx.accounts.input_lp.to_account_info(),
+                authority: ctx.accounts.user.user_authority.to_account_info(),
+            },
+        ),
+        args.pool_token_amount,
+    )?;
+
+    // Transfer tokens from reserve to user and admin
+    ctx.accounts
+        .transfer_reserve_to(&mint, &ctx.accounts.output.user, user_amount)?;
+    let admin_fee = unwrap_int!(admin_trade_fee.checked_add(admin_withdraw_fee));
+    if admin_fee > 0 {
+        ctx.accounts
+            .transfer_reserve_to(&mint, &ctx.accounts.output.fees, admin_fee)?;
+    }
+    unwrap_int!(ctx
+        .accounts
+        .user
+        .swap
+        .expected_reserves
+        .debit(is_token_0, unwrap_int!(user_amount.checked_add(admin_fee))));
+    let (token_0_amount, token_1_amount) = if is_token_0 {
+        (token_amount, 0)
+    } else {
+        (0, token_amount)
+    };
+
+    let fee = unwrap_int!(trade_fee.checked_add(withdraw_fee));
+    let (fee_0, fee_1, admin_fee_0, admin_fee_1) = if is_token_0 {
+        (fee, 0, admin_fee, 0)
+    } else {
+        (0, fee, 0, admin_fee)
+    };
+    let reserves = ctx.accounts.user.swap.expected_reserves;
+    emit!(WithdrawEvent {
+        lp_mint: ctx.accounts.pool_mint.key(),
+        pool_token_amount: args.pool_token_amount,
+        token_0_amount,
+        token_1_amount,
+        user_authority: ctx.accounts.user.user_authority.key(),
+        fee_0,
+        fee_1,
+        admin_fee_0,
+        admin_fee_1,
+        reserve_0: reserves.amount_0,
+        reserve_1: reserves.amount_1,
+        pool_token_supply: unwrap_int!(ctx
+            .accounts
+            .pool_mint
+            .supply
+            .checked_sub(args.pool_token_amount)),
+    });
+
+    ctx.accounts.track_cumulative_withdraw_volume(
+        is_token_0,
+        args.pool_token_amount,
+        token_amount,
+        trade_fee,
+    )?;
+
     ctx.accounts.pool_mint.reload()?;
     invariant!(
         ctx.accounts.pool_mint.supply >= xyk::MINIMUM_LIQUIDITY,
@@ -90,14 +296,18 @@ pub fn withdraw(ctx: Context<Withdraw>, args: WithdrawArgs) -> Result<()> {
     Ok(())
 }
 
+/// Computes the amount of a withdrawal of `amount` tokens which is received by the user,
+/// before the withdraw fee.
+fn amount_less_transfer_fee(swap_fees: &SwapFees, mint: &Mint, amount: u64) -> Result<u64> {
+    let (withdraw_fee, _) = unwrap_int!(swap_fees.compute_withdraw_fees(amount));
+    let transfer_fee = mint.transfer_fee(unwrap_int!(amount.checked_sub(withdraw_fee)))?;
+    Ok(unwrap_int!(amount.checked_sub(transfer_fee)))
+}
+
 impl<'info> Withdraw<'info> {
     fn update_cumulative_price_info(&mut self) -> Result<()> {
         // update price info
-        let price_info = &mut self.user.swap.price_info;
-        price_info.update_cumulative_price_info(
-            self.output_0.reserve.amount,
-            self.output_1.reserve.amount,
-        )
+        self.user.swap.update_cumulative_price_info()
     }
 
     /// Tracks and saves the cumulative swap volume.
@@ -124,9 +334,14 @@ impl<'info> Withdraw<'info> {
     }
 
     /// Withdraws a token.
-    fn withdraw_token(&self, output: &SwapTokenWithFees<'info>, amount: u64) -> Result<()> {
+    /// Returns the withdraw fee and the admin's portion of it.
+    fn withdraw_token(
+        &self,
+        output: &SwapTokenWithFees<'info>,
+        mint: &InterfaceAccount<'info, Mint>,
+        amount: u64,
+    ) -> Result<(u64, u64)> {
         let token_swap = &self.user.swap;
-        let token_program = &self.user.token_program;
 
         let swap_fees = self.user.swap.fees;
         let (withdraw_fee, admin_withdraw_fee) =
@@ -135,36 +350,83 @@ impl<'info> Withdraw<'info> {
         // Transfer token from reserve to user
         let seeds = gen_swap_signer_seeds!(token_swap);
         let signer_seeds = &[&seeds[..]];
-        token::transfer(
-            CpiContext::new_with_signer(
-                token_program.to_account_info().clone(),
-                token::Transfer {
-                    from: output.reserve.to_account_info(),
-                    to: output.user.to_account_info(),
-                    authority: token_swap.to_account_info(),
-                },
-                signer_seeds,
-            ),
+        token_interface::transfer_checked(
+            &output.token_program,
+            mint,
+            &output.reserve,
+            &output.user,
+            &token_swap.to_account_info(),
             unwrap_int!(amount.checked_sub(withdraw_fee)),
+            signer_seeds,
         )?;
 
         if admin_withdraw_fee > 0 {
             // Transfer withdrawal fee of token to admin address
-            let signer_seeds = &[&seeds[..]];
-            token::transfer(
-                CpiContext::new_with_signer(
-                    token_program.to_account_info().clone(),
-                    token::Transfer {
-                        from: output.reserve.to_account_info(),
-                        to: output.fees.to_account_info(),
-                        authority: token_swap.to_account_info(),
-                    },
-                    signer_seeds,
-                ),
+            token_interface::transfer_checked(
+                &output.token_program,
+                mint,
+                &output.reserve,
+                &output.fees,
+                &token_swap.to_account_info(),
                 admin_withdraw_fee,
+                signer_seeds,
             )?;
         }
 
+        Ok((withdraw_fee, admin_withdraw_fee))
+    }
+}
+
+impl<'info> WithdrawOne<'info> {
+    fn update_cumulative_price_info(&mut self) -> Result<()> {
+        // update price info
+        self.user.swap.update_cumulative_price_info()
+    }
+
+    /// Tracks and saves the cumulative withdraw volume.
+    fn track_cumulative_withdraw_volume(
+        &mut self,
+        is_token_0: bool,
+        pool_token_amount: u64,
+        token_amount: u64,
+        trade_fee: u64,
+    ) -> Result<()> {
+        // record cumulative volume numbers
+        let cumulative_stats = &mut self.user.swap.cumulative_stats;
+        cumulative_stats.total_lp_redeemed = unwrap_int!(cumulative_stats
+            .total_lp_redeemed
+            .checked_add(pool_token_amount.into()));
+
+        let cum_output_token = if is_token_0 {
+            &mut cumulative_stats.token_0
+        } else {
+            &mut cumulative_stats.token_1
+        };
+        cum_output_token.total_withdraw_volume = unwrap_int!(cum_output_token
+            .total_withdraw_volume
+            .checked_add(token_amount.into()));
+        cum_output_token.total_trade_fees =
+            unwrap_int!(cum_output_token.total_trade_fees.checked_add(trade_fee));
         Ok(())
     }
+
+    /// Transfers tokens from the output reserve.
+    fn transfer_reserve_to(
+        &self,
+        mint: &InterfaceAccount<'info, Mint>,
+        to: &AccountInfo<'info>,
+        amount: u64,
+    ) -> Result<()> {
+        let token_swap = &self.user.swap;
+        let seeds = gen_swap_signer_seeds!(token_swap);
+        token_interface::transfer_checked(
+            &self.output.token_program,
+            mint,
+            &self.output.reserve,
+            to,
+            &token_swap.to_account_info(),
+            amount,
+            &[&seeds[..]],
+        )
+    }
 }
@@ -0,0 +1,97 @@
+//! Swap curves.
+#![deny(missing_docs)]
+
+use crate::*;
+use xyk::{ConstantProduct, Curve, StableSwap, WeightedProduct};
+
+impl SwapCurve {
+    /// The constant product invariant, `x * y = k`.
+    pub const CONSTANT_PRODUCT: u8 = 0;
+    /// The StableSwap invariant.
+    pub const STABLE_SWAP: u8 = 1;
+    /// The weighted product invariant, `x^w_x * y^w_y = k`.
+    pub const WEIGHTED_PRODUCT: u8 = 2;
+
+    /// A constant product curve.
+    pub fn constant_product() -> Self {
+        Self {
+            curve_type: Self::CONSTANT_PRODUCT,
+            amp_factor: 0,
+            weight_0: 0,
+        }
+    }
+
+    /// A StableSwap curve with the given amplification coefficient.
+    pub fn stable_swap(amp_factor: u64) -> Self {
+        Self {
+            curve_type: Self::STABLE_SWAP,
+            amp_factor,
+            weight_0: 0,
+        }
+    }
+
+    /// A weighted product curve where token 0 has `weight_0` of [xyk::WEIGHT_DENOMINATOR].
+    ///
+    /// Weights above [xyk::MAX_WEIGHT] are stored as [u16::MAX] and fail validation.
+    pub fn weighted_product(weight_0: u64) -> Self {
+        Self {
+            curve_type: Self::WEIGHTED_PRODUCT,
+            amp_factor: 0,
+            weight_0: u16::try_from(weight_0).unwrap_or(u16::MAX),
+        }
+    }
+
+    /// Returns true if the curve is a constant product curve.
+    pub fn is_constant_product(&self) -> bool {
+        self.curve_type == Self::CONSTANT_PRODUCT
+    }
+
+    /// Validates the curve.
+    pub fn validate(&self) -> Result<()> {
+        self.calculator()?;
+        Ok(())
+    }
+
+    /// Returns the calculator for the curve, where token A is token 0.
+    pub fn calculator(&self) -> Result<Box<dyn Curve>> {
+        match self.curve_type {
+            Self::CONSTANT_PRODUCT => Ok(Box::new(ConstantProduct)),
+            Self::STABLE_SWAP => Ok(Box::new(unwrap_opt!(
+                StableSwap::new(self.amp_factor),
+                InvalidCurve
+            ))),
+            Self::WEIGHTED_PRODUCT => Ok(Box::new(self.weighted_product_calculator()?)),
+            _ => Err(error!(crate::ErrorCode::InvalidCurve)),
+        }
+    }
+
+    /// Returns the calculator for swaps from the given token, where the
+    /// source token is token A.
+    pub fn swap_calculator(&self, is_input_token_0: bool) -> Result<Box<dyn Curve>> {
+        if self.curve_type == Self::WEIGHTED_PRODUCT && !is_input_token_0 {
+            Ok(Box::new(self.weighted_product_calculator()?.reversed()))
+        } else {
+            self.calculator()
+        }
+    }
+
+    /// Computes the marginal price of token 0 in terms of token 1 if `is_token_0`, or of
+    /// token 1 in terms of token 0 otherwise, scaled by 10^[xyk::PRICE_SCALE].
+    pub fn spot_price(&self, is_token_0: bool, reserve_0: u64, reserve_1: u64) -> Option<u128> {
+        let (reserve_a, reserve_b) = if is_token_0 {
+            (reserve_0, reserve_1)
+        } else {
+            (reserve_1, reserve_0)
+        };
+        self.swap_calculator(is_token_0)
+            .ok()?
+            .spot_price(reserve_a, reserve_b)
+    }
+
+    fn weighted_product_calculator(&self) -> Result<WeightedProduct> {
+        Ok(unwrap_opt!(
+            WeightedProduct::new(self.weight_0.into()),
+            InvalidCurve
+        ))
+    }
+}
@@ -3,6 +3,8 @@
 
 use anchor_lang::prelude::*;
 
+use crate::{FactoryPolicy, SwapFees};
+
 /// Emitted on a successful [crate::cpamm::new_swap].
 #[event]
 pub struct NewPoolEvent {
@@ -29,6 +31,19 @@ pub struct DepositEvent {
     pub token_0_amount: u64,
     /// Amount of token 1 deposited.
     pub token_1_amount: u64,
+    /// The user which deposited the tokens.
+    pub user_authority: Pubkey,
+    /// Trade fee of a [crate::cpamm::deposit_single_token_exact_in], including the admin trade fee.
+    /// Zero for other deposits.
+    pub trade_fee: u64,
+    /// Portion of the trade fee sent to the admin.
+    pub admin_trade_fee: u64,
+    /// Reserves of token 0 after the deposit.
+    pub reserve_0: u64,
+    /// Reserves of token 1 after the deposit.
+    pub reserve_1: u64,
+    /// Supply of the LP token after the deposit.
+    pub pool_token_supply: u64,
 }
 
 /// Emitted on a successful [crate::cpamm::withdraw].
@@ -43,6 +58,24 @@ pub struct WithdrawEvent {
     pub token_0_amount: u64,
     /// Amount of token 1 withdrawn.
     pub token_1_amount: u64,
+    /// The user which withdrew the tokens.
+    pub user_authority: Pubkey,
+    /// Fees charged on token 0, including the admin fees.
+    /// For a [crate::cpamm::withdraw_one], this includes the trade fee.
+    pub fee_0: u64,
+    /// Fees charged on token 1, including the admin fees.
+    /// For a [crate::cpamm::withdraw_one], this includes the trade fee.
+    pub fee_1: u64,
+    /// Portion of the fees on token 0 sent to the admin.
+    pub admin_fee_0: u64,
+    /// Portion of the fees on token 1 sent to the admin.
+    pub admin_fee_1: u64,
+    /// Reserves of token 0 after the withdrawal.
+    pub reserve_0: u64,
+    /// Reserves of token 1 after the withdrawal.
+    pub reserve_1: u64,
+    /// Supply of the LP token after the withdrawal.
+    pub pool_token_supply: u64,
 }
 
 /// Emitted on a successful [crate::cpamm::swap].
@@ -59,4 +92,343 @@ pub struct SwapEvent {
     pub source_amount_swapped: u64,
     /// Amount of destination token swapped
     pub destination_amount_swapped: u64,
+    /// Token account of the referrer of the swap, if any.
+    pub referrer: Option<Pubkey>,
+    /// Amount of destination token sent to the referrer.
+    pub referral_fee: u64,
+    /// Trade fee of the swap, in kbps.
+    /// This differs from [crate::SwapFees::trade_fee_kbps] if dynamic fees are enabled.
+    pub trade_fee_kbps: u64,
+    /// The user which swapped the tokens.
+    pub user_authority: Pubkey,
+    /// Trade fee taken from the destination amount, including the admin and referral fees.
+    pub trade_fee: u64,
+    /// Portion of the trade fee sent to the admin, excluding the referral fee.
+    pub admin_trade_fee: u64,
+    /// Reserves of token 0 after the swap.
+    pub reserve_0: u64,
+    /// Reserves of token 1 after the swap.
+    pub reserve_1: u64,
+}
+
+/// Emitted on a successful [crate::cpamm::set_fees].
+#[event]
+pub struct SetFeesEvent {
+    /// Mint of the LP token.
+    #[index]
+    pub lp_mint: Pubkey,
+    /// The new fees of the swap.
+    pub fees: SwapFees,
+}
+
+/// Emitted on a successful [crate::cpamm::set_default_fees].
+#[event]
+pub struct SetDefaultFeesEvent {
+    /// The [crate::Factory].
+    #[index]
+    pub factory: Pubkey,
+    /// The new default fees of swaps.
+    pub fees: SwapFees,
+}
+
+/// Emitted on a successful [crate::cpamm::set_fee_tiers].
+#[event]
+pub struct SetFeeTiersEvent {
+    /// The [crate::Factory].
+    #[index]
+    pub factory: Pubkey,
+    /// The trade fees of the enabled fee tiers, in kbps.
+    pub fee_tiers: Vec<u32>,
+}
+
+/// Emitted on a successful [crate::cpamm::set_factory_policy].
+#[event]
+pub struct SetFactoryPolicyEvent {
+    /// The [crate::Factory].
+    #[index]
+    pub factory: Pubkey,
+    /// The new policy of the factory.
+    pub policy: FactoryPolicy,
+}
+
+/// Emitted on a successful [crate::cpamm::add_pool_creator] or [crate::cpamm::remove_pool_creator].
+#[event]
+pub struct SetPoolCreatorEvent {
+    /// The [crate::Factory].
+    #[index]
+    pub factory: Pubkey,
+    /// The key which may or may no longer create swaps.
+    pub creator: Pubkey,
+    /// Whether the key may create swaps.
+    pub is_allowed: bool,
+}
+
+/// Emitted on a successful [crate::cpamm::deny_mint] or [crate::cpamm::allow_mint].
+#[event]
+pub struct SetMintDeniedEvent {
+    /// The [crate::Factory].
+    #[index]
+    pub factory: Pubkey,
+    /// The mint.
+    pub mint: Pubkey,
+    /// Whether the mint is denied from new swaps.
+    pub is_denied: bool,
+}
+
+/// Emitted on a successful [crate::cpamm::set_flash_loan_fee].
+#[event]
+pub struct SetFlashLoanFeeEvent {
+    /// Mint of the LP token.
+    #[index]
+    pub lp_mint: Pubkey,
+    /// The new flash loan fee, thousands of bps.
+    pub fee_kbps: u64,
+}
+
+/// Emitted on a successful [crate::cpamm::set_referral_fee].
+#[event]
+pub struct SetReferralFeeEvent {
+    /// Mint of the LP token.
+    #[index]
+    pub lp_mint: Pubkey,
+    /// The new referral fee, thousands of bps.
+    pub referral_fee_kbps: u32,
+}
+
+/// Emitted on a successful [crate::cpamm::set_dynamic_fee].
+#[event]
+pub struct SetDynamicFeeEvent {
+    /// Mint of the LP token.
+    #[index]
+    pub lp_mint: Pubkey,
+    /// Maximum trade fee, in kbps.
+    pub max_trade_fee_kbps: u32,
+    /// Portion of the price deviation added to the trade fee, in kbps.
+    pub volatility_factor_kbps: u32,
+}
+
+/// Emitted on a successful [crate::cpamm::repay_flash_loan].
+#[event]
+pub struct FlashLoanEvent {
+    /// Mint of the LP token.
+    #[index]
+    pub lp_mint: Pubkey,
+    /// Mint of the token lent.
+    pub mint: Pubkey,
+    /// Amount of tokens lent.
+    pub amount: u64,
+    /// Fee paid on top of the loan, including the admin fee.
+    pub fee: u64,
+    /// Portion of the fee sent to the admin.
+    pub admin_fee: u64,
+}
+
+/// Emitted on a successful [crate::cpamm::skim].
+#[event]
+pub struct SkimEvent {
+    /// Mint of the LP token.
+    #[index]
+    pub lp_mint: Pubkey,
+    /// Amount of token 0 sent to the admin fees account.
+    pub amount_0: u64,
+    /// Amount of token 1 sent to the admin fees account.
+    pub amount_1: u64,
+}
+
+/// Emitted on a successful [crate::cpamm::sync].
+#[event]
+pub struct SyncEvent {
+    /// Mint of the LP token.
+    #[index]
+    pub lp_mint: Pubkey,
+    /// New expected reserves of token 0.
+    pub reserve_0: u64,
+    /// New expected reserves of token 1.
+    pub reserve_1: u64,
+}
+
+/// Emitted on a successful [crate::cpamm::pause] or [crate::cpamm::unpause].
+#[event]
+pub struct SetPausedEvent {
+    /// Mint of the LP token.
+    #[index]
+    pub lp_mint: Pubkey,
+    /// Whether or not the swap is now paused.
+    pub is_paused: bool,
+}
+
+/// Emitted on a successful [crate::cpamm::begin_close_swap].
+#[event]
+pub struct BeginCloseSwapEvent {
+    /// Mint of the LP token.
+    #[index]
+    pub lp_mint: Pubkey,
+}
+
+/// Emitted on a successful [crate::cpamm::close_swap].
+#[event]
+pub struct CloseSwapEvent {
+    /// Mint of the LP token.
+    #[index]
+    pub lp_mint: Pubkey,
+    /// Destination of the remaining balances of token 0.
+    pub destination_0: Pubkey,
+    /// Remaining balance of token 0 in the reserve and admin fees accounts.
+    pub amount_0: u64,
+    /// Destination of the remaining balances of token 1.
+    pub destination_1: Pubkey,
+    /// Remaining balance of token 1 in the reserve and admin fees accounts.
+    pub amount_1: u64,
+    /// Recipient of the rent of the closed accounts.
+    pub rent_destination: Pubkey,
+}
+
+/// Emitted on a successful [crate::cpamm::claim_admin_fees].
+#[event]
+pub struct ClaimAdminFeesEvent {
+    /// Mint of the LP token.
+    #[index]
+    pub lp_mint: Pubkey,
+    /// Token account which received the fees of token 0.
+    pub destination_0: Pubkey,
+    /// Amount of token 0 claimed.
+    pub amount_0: u64,
+    /// Token account which received the fees of token 1.
+    pub destination_1: Pubkey,
+    /// Amount of token 1 claimed.
+    pub amount_1: u64,
+}
+
+/// Emitted on a successful [crate::cpamm::set_admin_fees_account].
+#[event]
+pub struct SetAdminFeesAccountEvent {
+    /// Mint of the LP token.
+    #[index]
+    pub lp_mint: Pubkey,
+    /// Mint of the token.
+    pub mint: Pubkey,
+    /// The previous admin fees account.
+    pub previous_fees: Pubkey,
+    /// The new admin fees account.
+    pub fees: Pubkey,
+}
+
+/// Emitted when a new admin is proposed for a [crate::Factory] or [crate::SwapInfo].
+#[event]
+pub struct CommitAdminEvent {
+    /// The [crate::Factory] or [crate::SwapInfo].
+    #[index]
+    pub account: Pubkey,
+    /// The current admin.
+    pub admin: Pubkey,
+    /// The proposed admin.
+    pub pending_admin: Pubkey,
+}
+
+/// Emitted when the admin of a [crate::Factory] or [crate::SwapInfo] changes.
+#[event]
+pub struct AcceptAdminEvent {
+    /// The [crate::Factory] or [crate::SwapInfo].
+    #[index]
+    pub account: Pubkey,
+    /// The previous admin.
+    pub previous_admin: Pubkey,
+    /// The new admin.
+    pub admin: Pubkey,
+}
+
+/// Emitted on a successful [crate::cpamm::new_concentrated_pool].
+#[event]
+pub struct NewConcentratedPoolEvent {
+    /// The [crate::ConcentratedPool].
+    #[index]
+    pub pool: Pubkey,
+    /// Mint of token 0.
+    pub mint_0: Pubkey,
+    /// Mint of token 1.
+    pub mint_1: Pubkey,
+    /// Tick spacing of the pool.
+    pub tick_spacing: u16,
+    /// Trade fee of the pool, thousands of bps.
+    pub trade_fee_kbps: u64,
+    /// Initial square root price of the pool, Q64.64.
+    pub sqrt_price_x64: u128,
+}
+
+/// Emitted on a successful [crate::cpamm::increase_liquidity].
+#[event]
+pub struct IncreaseLiquidityEvent {
+    /// The [crate::ConcentratedPool].
+    #[index]
+    pub pool: Pubkey,
+    /// The [crate::Position].
+    pub position: Pubkey,
+    /// Owner of the position.
+    pub owner: Pubkey,
+    /// Liquidity added to the position.
+    pub liquidity: u128,
+    /// Amount of token 0 deposited.
+    pub token_0_amount: u64,
+    /// Amount of token 1 deposited.
+    pub token_1_amount: u64,
+}
+
+/// Emitted on a successful [crate::cpamm::decrease_liquidity].
+#[event]
+pub struct DecreaseLiquidityEvent {
+    /// The [crate::ConcentratedPool].
+    #[index]
+    pub pool: Pubkey,
+    /// The [crate::Position].
+    pub position: Pubkey,
+    /// Owner of the position.
+    pub owner: Pubkey,
+    /// Liquidity removed from the position.
+    pub liquidity: u128,
+    /// Amount of token 0 withdrawn.
+    pub token_0_amount: u64,
+    /// Amount of token 1 withdrawn.
+    pub token_1_amount: u64,
+}
+
+/// Emitted on a successful [crate::cpamm::collect_fees].
+#[event]
+pub struct CollectFeesEvent {
+    /// The [crate::ConcentratedPool].
+    #[index]
+    pub pool: Pubkey,
+    /// The [crate::Position].
+    pub position: Pubkey,
+    /// Owner of the position.
+    pub owner: Pubkey,
+    /// Fees of token 0 collected.
+    pub fee_0: u64,
+    /// Fees of token 1 collected.
+    pub fee_1: u64,
+}
+
+/// Emitted on a successful [crate::cpamm::swap_concentrated].
+#[event]
+pub struct ConcentratedSwapEvent {
+    /// The [crate::ConcentratedPool].
+    #[index]
+    pub pool: Pubkey,
+    /// The user which performed the swap.
+    pub user_authority: Pubkey,
+    /// Mint of the input token.
+    pub input_mint: Pubkey,
+    /// Mint of the output token.
+    pub output_mint: Pubkey,
+    /// Amount of input tokens received by the pool, including the trade fee.
+    pub source_amount_swapped: u64,
+    /// Amount of output tokens sent by the pool.
+    pub destination_amount_swapped: u64,
+    /// Trade fee taken from the input tokens.
+    pub trade_fee: u64,
+    /// Square root price of the pool after the swap, Q64.64.
+    pub sqrt_price_x64: u128,
+    /// Tick of the pool after the swap.
+    pub tick_current: i32,
+    /// Liquidity in range after the swap.
+    pub liquidity: u128,
 }
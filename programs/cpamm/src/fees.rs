@@ -3,11 +3,12 @@
 
 use crate::*;
 use ::u128::mul_div_u64;
+use num_traits::ToPrimitive;
 
 /// Thousands of BPS in 100%.
-pub const KBPS_PER_WHOLE: u64 = 10_000_000;
+pub const KBPS_PER_WHOLE: u64 = xyk::KBPS_PER_WHOLE;
 
-/// Initial [SwapFees] for new pools.
+/// Initial [SwapFees] for new pools, and the initial [Factory::default_fees].
 pub const INITIAL: SwapFees = SwapFees {
     trade_fee_kbps: 30_000,
     withdraw_fee_kbps: 0,
@@ -15,6 +16,61 @@ pub const INITIAL: SwapFees = SwapFees {
     admin_withdraw_fee_kbps: 10_000_000,
 };
 
+/// Initial [Factory::fee_tiers]: 0.01%, 0.05%, 0.3%, and 1%.
+pub const INITIAL_FEE_TIERS: [u32; MAX_FEE_TIERS] = [1_000, 5_000, 30_000, 100_000, 0, 0, 0, 0];
+
+impl Factory {
+    /// Returns true if new [SwapInfo]s may be created with the fee tier.
+    pub fn is_fee_tier_enabled(&self, fee_tier_kbps: u32) -> bool {
+        fee_tier_kbps != 0 && self.current_fee_tiers().contains(&fee_tier_kbps)
+    }
+
+    /// The [SwapFees] of a new [SwapInfo] in the fee tier.
+    pub fn fees_for_tier(&self, fee_tier_kbps: u32) -> SwapFees {
+        SwapFees {
+            trade_fee_kbps: fee_tier_kbps.into(),
+            ..self.current_default_fees()
+        }
+    }
+
+    /// The [Factory::fee_tiers], or [INITIAL_FEE_TIERS] if none are stored.
+    pub fn current_fee_tiers(&self) -> [u32; MAX_FEE_TIERS] {
+        if self.fee_tiers == [0; MAX_FEE_TIERS] {
+            INITIAL_FEE_TIERS
+        } else {
+            self.fee_tiers
+        }
+    }
+
+    /// The [Factory::default_fees], or [INITIAL] if they are all zero.
+    pub fn current_default_fees(&self) -> SwapFees {
+        if self.default_fees == SwapFees::default() {
+            INITIAL
+        } else {
+            self.default_fees
+        }
+    }
+}
+
+/// Validates a list of fee tiers and pads it to [MAX_FEE_TIERS] with unused tiers.
+/// At least one tier must be enabled, since a factory without tiers uses [INITIAL_FEE_TIERS].
+pub fn validate_fee_tiers(fee_tiers: &[u32]) -> Result<[u32; MAX_FEE_TIERS]> {
+    invariant!(
+        !fee_tiers.is_empty() && fee_tiers.len() <= MAX_FEE_TIERS,
+        InvalidFeeTier
+    );
+    let mut padded = [0; MAX_FEE_TIERS];
+    for (i, &fee_tier_kbps) in fee_tiers.iter().enumerate() {
+        invariant!(
+            fee_tier_kbps != 0 && u64::from(fee_tier_kbps) <= KBPS_PER_WHOLE,
+            InvalidFeeTier
+        );
+        invariant!(!fee_tiers[..i].contains(&fee_tier_kbps), InvalidFeeTier);
+        padded[i] = fee_tier_kbps;
+    }
+    Ok(padded)
+}
+
 impl SwapFees {
     /// Validates the fees.
     pub fn validate(&self) -> Result<()> {
@@ -30,24 +86,168 @@ impl SwapFees {
 
     /// Compute trade and admin trade fee from the trade amount
     pub fn compute_trade_fees(&self, destination_amount_swapped: u64) -> Option<(u64, u64)> {
-        let trade_fee = mul_div_u64(
-            destination_amount_swapped,
-            self.trade_fee_kbps,
-            KBPS_PER_WHOLE,
-        )?;
+        xyk::Fees::from(*self).trade_fees(destination_amount_swapped)
+    }
+
+    /// Compute the amount that must be swapped so that `amount_out` remains after the trade fee
+    pub fn compute_amount_before_trade_fee(&self, amount_out: u64) -> Option<u64> {
+        xyk::Fees::from(*self).amount_before_trade_fee(amount_out)
+    }
+
+    /// Compute flash loan and admin flash loan fees from the loan amount.
+    /// The admin's share of the fee is the same as its share of the trade fee.
+    pub fn compute_flash_loan_fees(
+        &self,
+        flash_loan_fee_kbps: u64,
+        loan_amount: u64,
+    ) -> Option<(u64, u64)> {
+        let flash_loan_fee = mul_div_u64(loan_amount, flash_loan_fee_kbps, KBPS_PER_WHOLE)?;
 
-        let admin_trade_fee = mul_div_u64(trade_fee, self.admin_trade_fee_kbps, KBPS_PER_WHOLE)?;
+        let admin_flash_loan_fee =
+            mul_div_u64(flash_loan_fee, self.admin_trade_fee_kbps, KBPS_PER_WHOLE)?;
 
-        Some((trade_fee, admin_trade_fee))
+        Some((flash_loan_fee, admin_flash_loan_fee))
     }
 
     /// Compute withdraw and admin withdraw fees from the withdrawal amount
     pub fn compute_withdraw_fees(&self, withdrawal_amount: u64) -> Option<(u64, u64)> {
-        let withdraw_fee = mul_div_u64(withdrawal_amount, self.withdraw_fee_kbps, KBPS_PER_WHOLE)?;
+        xyk::Fees::from(*self).withdraw_fees(withdrawal_amount)
+    }
+}
+
+/// Number of seconds after which the [SwapDynamicFee::price_ema] catches up to the spot price.
+pub const PRICE_EMA_PERIOD_SECONDS: u64 = 600;
 
-        let admin_withdraw_fee =
-            mul_div_u64(withdraw_fee, self.admin_withdraw_fee_kbps, KBPS_PER_WHOLE)?;
+impl SwapDynamicFee {
+    /// Returns true if dynamic fees are enabled.
+    pub fn is_enabled(&self) -> bool {
+        self.max_trade_fee_kbps != 0
+    }
+
+    /// Validates the dynamic fee configuration.
+    pub fn validate(&self) -> Result<()> {
+        invariant!(
+            u64::from(self.max_trade_fee_kbps) <= KBPS_PER_WHOLE,
+            InvalidFee
+        );
+        Ok(())
+    }
+
+    /// Computes the trade fee at `now` of a trade which leaves the `reserves` at
+    /// `reserves_after`, without updating the price average.
+    pub fn trade_fee_kbps_at(
+        &self,
+        curve: &SwapCurve,
+        base_fee_kbps: u64,
+        reserves: SwapReserves,
+        reserves_after: SwapReserves,
+        now: i64,
+    ) -> Option<u64> {
+        let mut next = *self;
+        next.update(curve, base_fee_kbps, reserves, reserves_after, now)
+    }
+
+    /// Updates the price average with the spot price of the `reserves` on the `curve`,
+    /// returning the trade fee at `now` of a trade which leaves them at `reserves_after`.
+    ///
+    /// The fee rises with the larger of the deviations of the prices before and
+    /// after the trade from the average, so that a single large trade pays for
+    /// the volatility it causes.
+    pub fn update(
+        &mut self,
+        curve: &SwapCurve,
+        base_fee_kbps: u64,
+        reserves: SwapReserves,
+        reserves_after: SwapReserves,
+        now: i64,
+    ) -> Option<u64> {
+        if !self.is_enabled() || reserves.amount_0 == 0 || reserves.amount_1 == 0 {
+            return Some(base_fee_kbps);
+        }
+
+        let price = curve.spot_price(true, reserves.amount_0, reserves.amount_1)?;
+        let time_elapsed = now.checked_sub(self.last_update_ts)?.max(0).to_u64()?;
+        self.price_ema = xyk::update_price_ema(
+            self.price_ema,
+            price,
+            time_elapsed,
+            PRICE_EMA_PERIOD_SECONDS,
+        )?;
+        self.last_update_ts = now;
+
+        let deviation_before_kbps = xyk::price_deviation_kbps(self.price_ema, price)?;
+        // a trade which empties the reserves of a token moves the price without bound
+        let deviation_after_kbps = if reserves_after.amount_0 == 0 || reserves_after.amount_1 == 0 {
+            KBPS_PER_WHOLE
+        } else {
+            let price_after =
+                curve.spot_price(true, reserves_after.amount_0, reserves_after.amount_1)?;
+            xyk::price_deviation_kbps(self.price_ema, price_after)?
+        };
+        xyk::dynamic_trade_fee_kbps(
+            base_fee_kbps,
+            self.max_trade_fee_kbps.into(),
+            self.volatility_factor_kbps.into(),
+            deviation_before_kbps.max(deviation_after_kbps),
+        )
+    }
+}
+
+impl SwapInfo {
+    /// Updates the [SwapInfo::dynamic_fee] and returns the [SwapFees] of a trade
+    /// which leaves the [SwapInfo::expected_reserves] at `reserves_after`.
+    /// This should be called before the pool is mutated.
+    pub fn update_trade_fees(&mut self, reserves_after: SwapReserves) -> Result<SwapFees> {
+        if !self.dynamic_fee.is_enabled() {
+            return Ok(self.fees);
+        }
+        let trade_fee_kbps = unwrap_int!(self.dynamic_fee.update(
+            &self.curve,
+            self.fees.trade_fee_kbps,
+            self.expected_reserves,
+            reserves_after,
+            Clock::get()?.unix_timestamp,
+        ));
+        Ok(SwapFees {
+            trade_fee_kbps,
+            ..self.fees
+        })
+    }
+
+    /// Compute the portion of the admin trade fee sent to the referrer
+    pub fn compute_referral_fee(&self, admin_trade_fee: u64) -> Option<u64> {
+        mul_div_u64(
+            admin_trade_fee,
+            self.referral_fee_kbps.into(),
+            KBPS_PER_WHOLE,
+        )
+    }
+
+    /// Computes the [SwapFees] at `now` of a trade which leaves the
+    /// [SwapInfo::expected_reserves] at `reserves_after`.
+    pub fn trade_fees_at(&self, reserves_after: SwapReserves, now: i64) -> Option<SwapFees> {
+        let trade_fee_kbps = self.dynamic_fee.trade_fee_kbps_at(
+            &self.curve,
+            self.fees.trade_fee_kbps,
+            self.expected_reserves,
+            reserves_after,
+            now,
+        )?;
+        Some(SwapFees {
+            trade_fee_kbps,
+            ..self.fees
+        })
+    }
+}
 
-        Some((withdraw_fee, admin_withdraw_fee))
+/// The fee math is shared with the [xyk] quotes, so that quotes match on-chain results.
+impl From<SwapFees> for xyk::Fees {
+    fn from(fees: SwapFees) -> Self {
+        xyk::Fees {
+            trade_fee_kbps: fees.trade_fee_kbps,
+            withdraw_fee_kbps: fees.withdraw_fee_kbps,
+            admin_trade_fee_kbps: fees.admin_trade_fee_kbps,
+            admin_withdraw_fee_kbps: fees.admin_withdraw_fee_kbps,
+        }
     }
 }
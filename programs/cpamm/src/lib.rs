@@ -6,7 +6,7 @@
 mod macros;
 
 use anchor_lang::prelude::*;
-use anchor_spl::token::MintTo;
+use token_interface::{InterfaceAccount, TokenAccount};
 use vipers::prelude::*;
 
 mod account_structs;
@@ -16,8 +16,11 @@ mod controller;
 mod events;
 mod state;
 
+pub mod concentrated;
+pub mod curve;
 pub mod fees;
 pub mod price_info;
+pub mod token_interface;
 
 pub use account_structs::*;
 pub use events::*;
@@ -28,8 +31,6 @@ declare_id!("SCHAtsf8mbjyjiv4LkhLKutTf6JnZAbdJKFkXQNMFHZ");
 /// Constant product AMM.
 #[program]
 pub mod cpamm {
-    use anchor_spl::token;
-
     use super::*;
 
     /// Creates a new [Factory].
@@ -41,81 +42,40 @@ pub mod cpamm {
         factory.bump = unwrap_bump!(ctx, "factory");
         factory.num_swaps = 0;
         factory.admin = addresses::ADMIN_ACCOUNT;
+        factory.default_fees = fees::INITIAL;
+        factory.fee_tiers = fees::INITIAL_FEE_TIERS;
+        factory.policy = FactoryPolicy::default();
 
         Ok(())
     }
 
-    /// Creates a new [SwapInfo].
+    /// Creates a new constant product [SwapInfo] in one of the [Factory::fee_tiers].
     #[access_control(ctx.accounts.validate())]
-    pub fn new_swap(ctx: Context<NewSwap>, _bump: u8) -> Result<()> {
-        let token_0 = &ctx.accounts.token_0;
-        let token_1 = &ctx.accounts.token_1;
-        invariant!(token_0.reserve.amount != 0, NewSwapMustHaveNonZeroSupply);
-        invariant!(token_1.reserve.amount != 0, NewSwapMustHaveNonZeroSupply);
-
-        let initial_liquidity = unwrap_int!(xyk::calculate_initial_swap_pool_amount(
-            token_0.reserve.amount,
-            token_1.reserve.amount
-        ));
-        invariant!(
-            initial_liquidity >= xyk::MINIMUM_LIQUIDITY,
-            InitialLiquidityTooLow
-        );
-
-        // update factory index
-        let factory = &mut ctx.accounts.factory;
-        let index = factory.num_swaps;
-        factory.num_swaps = unwrap_int!(index.checked_add(1));
-
-        // init info
-        let swap_info = &mut ctx.accounts.swap;
-        swap_info.factory = factory.key();
-        swap_info.bump = unwrap_bump!(ctx, "swap");
-
-        swap_info.index = index;
-        swap_info.admin_key = factory.admin;
-        swap_info.token_0 = SwapTokenInfo::from(token_0);
-        swap_info.token_1 = SwapTokenInfo::from(token_1);
-
-        swap_info.is_paused = false;
-        swap_info.pool_mint = ctx.accounts.pool_mint.key();
-        swap_info.fees = fees::INITIAL;
-
-        // Zero cumulative stats.
-        swap_info.cumulative_stats = Default::default();
-
-        // set up initial price info
-        swap_info.price_info = SwapPriceInfo {
-            last_update_ts: Clock::get()?.unix_timestamp,
-            // zero cumulative price because no time has elapsed
-            price_0_cumulative_last: 0,
-            price_1_cumulative_last: 0,
-        };
-
-        // mint initial liquidity to initial staker
-        let seeds = gen_swap_signer_seeds!(swap_info);
-        let signer_seeds = &[&seeds[..]];
-        token::mint_to(
-            CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                MintTo {
-                    mint: ctx.accounts.pool_mint.to_account_info(),
-                    to: ctx.accounts.output_lp.to_account_info(),
-                    authority: swap_info.to_account_info(),
-                },
-                signer_seeds,
-            ),
-            initial_liquidity,
-        )?;
+    pub fn new_swap(ctx: Context<NewSwap>, _bump: u8, fee_tier_kbps: u32) -> Result<()> {
+        controller::new_swap::new_swap(ctx, fee_tier_kbps, SwapCurve::constant_product())
+    }
 
-        emit!(NewPoolEvent {
-            lp_mint: ctx.accounts.pool_mint.key(),
-            mint_0: token_0.mint.key(),
-            mint_1: token_1.mint.key(),
-            initial_liquidity
-        });
+    /// Creates a new StableSwap [SwapInfo] with the given amplification coefficient.
+    #[access_control(ctx.accounts.validate())]
+    pub fn new_stable_swap(
+        ctx: Context<NewSwap>,
+        _bump: u8,
+        fee_tier_kbps: u32,
+        amp_factor: u64,
+    ) -> Result<()> {
+        controller::new_swap::new_swap(ctx, fee_tier_kbps, SwapCurve::stable_swap(amp_factor))
+    }
 
-        Ok(())
+    /// Creates a new weighted product [SwapInfo] where token 0 has `weight_0`
+    /// of [xyk::WEIGHT_DENOMINATOR], e.g. 8,000 for an 80/20 pool.
+    #[access_control(ctx.accounts.validate())]
+    pub fn new_weighted_swap(
+        ctx: Context<NewSwap>,
+        _bump: u8,
+        fee_tier_kbps: u32,
+        weight_0: u64,
+    ) -> Result<()> {
+        controller::new_swap::new_swap(ctx, fee_tier_kbps, SwapCurve::weighted_product(weight_0))
     }
 
     /// Creates a new [SwapMeta].
@@ -136,8 +96,15 @@ pub mod cpamm {
     }
 
     /// Performs a swap.
+    ///
+    /// An [Observations] account and a referrer token account of the output mint
+    /// may optionally be passed as remaining accounts.
     #[access_control(ctx.accounts.validate())]
-    pub fn swap(ctx: Context<Swap>, amount_in: u64, minimum_amount_out: u64) -> Result<()> {
+    pub fn swap<'info>(
+        ctx: Context<'_, '_, '_, 'info, Swap<'info>>,
+        amount_in: u64,
+        minimum_amount_out: u64,
+    ) -> Result<()> {
         controller::swap::swap(
             ctx,
             controller::swap::SwapArgs {
@@ -150,8 +117,48 @@ pub mod cpamm {
     /// Performs a swap of the maximum amount possible.
     /// This is useful for order routers.
     #[access_control(ctx.accounts.validate())]
-    pub fn swap_max(ctx: Context<Swap>, minimum_amount_out: u64) -> Result<()> {
-        let amount_in = ctx.accounts.input.user.amount;
+    pub fn swap_max<'info>(
+        ctx: Context<'_, '_, '_, 'info, Swap<'info>>,
+        minimum_amount_out: u64,
+    ) -> Result<()> {
+        let input: InterfaceAccount<TokenAccount> =
+            InterfaceAccount::try_from(&ctx.accounts.input.user)?;
+        let amount_in = input.amount;
+        controller::swap::swap(
+            ctx,
+            controller::swap::SwapArgs {
+                amount_in,
+                minimum_amount_out,
+            },
+        )
+    }
+
+    /// Performs a swap that receives exactly `amount_out` of the output token,
+    /// spending at most `maximum_amount_in` of the input token.
+    #[access_control(ctx.accounts.validate())]
+    pub fn swap_exact_out<'info>(
+        ctx: Context<'_, '_, '_, 'info, Swap<'info>>,
+        amount_out: u64,
+        maximum_amount_in: u64,
+    ) -> Result<()> {
+        controller::swap::swap_exact_out(
+            ctx,
+            controller::swap::SwapExactOutArgs {
+                amount_out,
+                maximum_amount_in,
+            },
+        )
+    }
+
+    /// Performs a swap, failing if the current time is past `deadline`.
+    #[access_control(ctx.accounts.validate())]
+    pub fn swap_with_deadline<'info>(
+        ctx: Context<'_, '_, '_, 'info, Swap<'info>>,
+        amount_in: u64,
+        minimum_amount_out: u64,
+        deadline: i64,
+    ) -> Result<()> {
+        controller::check_deadline(deadline)?;
         controller::swap::swap(
             ctx,
             controller::swap::SwapArgs {
@@ -161,6 +168,23 @@ pub mod cpamm {
         )
     }
 
+    /// Swaps through a route of [SwapInfo]s passed as remaining accounts,
+    /// enforcing `minimum_amount_out` only on the final output.
+    #[access_control(ctx.accounts.validate())]
+    pub fn swap_route<'info>(
+        ctx: Context<'_, '_, '_, 'info, SwapRoute<'info>>,
+        amount_in: u64,
+        minimum_amount_out: u64,
+    ) -> Result<()> {
+        controller::swap_route::swap_route(
+            ctx,
+            controller::swap_route::SwapRouteArgs {
+                amount_in,
+                minimum_amount_out,
+            },
+        )
+    }
+
     /// Performs a withdraw.
     #[access_control(ctx.accounts.validate())]
     pub fn withdraw(
@@ -179,6 +203,43 @@ pub mod cpamm {
         )
     }
 
+    /// Performs a withdraw, failing if the current time is past `deadline`.
+    #[access_control(ctx.accounts.validate())]
+    pub fn withdraw_with_deadline(
+        ctx: Context<Withdraw>,
+        amount_in: u64,
+        minimum_amount_out_0: u64,
+        minimum_amount_out_1: u64,
+        deadline: i64,
+    ) -> Result<()> {
+        controller::check_deadline(deadline)?;
+        controller::withdraw::withdraw(
+            ctx,
+            controller::withdraw::WithdrawArgs {
+                amount_in,
+                minimum_amount_out_0,
+                minimum_amount_out_1,
+            },
+        )
+    }
+
+    /// Withdraws only one of the tokens of the swap.
+    /// Half of the withdrawal is treated as a swap from the other token and pays the trade fee.
+    #[access_control(ctx.accounts.validate())]
+    pub fn withdraw_one(
+        ctx: Context<WithdrawOne>,
+        pool_token_amount: u64,
+        minimum_amount_out: u64,
+    ) -> Result<()> {
+        controller::withdraw::withdraw_one(
+            ctx,
+            controller::withdraw::WithdrawOneArgs {
+                pool_token_amount,
+                minimum_amount_out,
+            },
+        )
+    }
+
     /// Performs a deposit.
     #[access_control(ctx.accounts.validate())]
     pub fn deposit(
@@ -187,13 +248,26 @@ pub mod cpamm {
         maximum_amount_in_0: u64,
         maximum_amount_in_1: u64,
     ) -> Result<()> {
-        // update price info
-        let price_info = &mut ctx.accounts.user.swap.price_info;
-        price_info.update_cumulative_price_info(
-            ctx.accounts.input_0.reserve.amount,
-            ctx.accounts.input_1.reserve.amount,
-        )?;
+        controller::deposit::deposit(
+            ctx,
+            controller::deposit::DepositArgs {
+                pool_token_amount,
+                maximum_amount_in_0,
+                maximum_amount_in_1,
+            },
+        )
+    }
 
+    /// Performs a deposit, failing if the current time is past `deadline`.
+    #[access_control(ctx.accounts.validate())]
+    pub fn deposit_with_deadline(
+        ctx: Context<Deposit>,
+        pool_token_amount: u64,
+        maximum_amount_in_0: u64,
+        maximum_amount_in_1: u64,
+        deadline: i64,
+    ) -> Result<()> {
+        controller::check_deadline(deadline)?;
         controller::deposit::deposit(
             ctx,
             controller::deposit::DepositArgs {
@@ -201,9 +275,574 @@ pub mod cpamm {
                 maximum_amount_in_0,
                 maximum_amount_in_1,
             },
+        )
+    }
+
+    /// Deposits only one of the tokens of the swap.
+    /// Half of the deposit is treated as a swap into the other token and pays the trade fee.
+    #[access_control(ctx.accounts.validate())]
+    pub fn deposit_single_token_exact_in(
+        ctx: Context<DepositSingle>,
+        source_amount: u64,
+        minimum_pool_token_amount: u64,
+    ) -> Result<()> {
+        controller::deposit::deposit_single_token_exact_in(
+            ctx,
+            controller::deposit::DepositSingleArgs {
+                source_amount,
+                minimum_pool_token_amount,
+            },
+        )
+    }
+
+    /// Creates a new [Observations] account for a [SwapInfo]. Must be signed by the swap admin.
+    /// `min_interval_seconds` may be at most [price_info::MAX_OBSERVATION_INTERVAL_SECONDS].
+    #[access_control(ctx.accounts.validate())]
+    pub fn new_observations(
+        ctx: Context<NewObservations>,
+        min_interval_seconds: u32,
+    ) -> Result<()> {
+        invariant!(
+            min_interval_seconds <= price_info::MAX_OBSERVATION_INTERVAL_SECONDS,
+            InvalidObservationInterval
+        );
+        let observations = &mut ctx.accounts.observations;
+        observations.swap = ctx.accounts.swap.key();
+        observations.bump = unwrap_bump!(ctx, "observations");
+        observations.min_interval_seconds = min_interval_seconds;
+        observations.index = 0;
+        observations.count = 0;
+        observations.observations = [Observation::default(); MAX_OBSERVATIONS];
+
+        Ok(())
+    }
+
+    /// Updates the cumulative prices of a [SwapInfo] and records them into its [Observations].
+    #[access_control(ctx.accounts.validate())]
+    pub fn record_observation(ctx: Context<RecordObservation>) -> Result<()> {
+        let reserve_0: InterfaceAccount<TokenAccount> =
+            InterfaceAccount::try_from(&ctx.accounts.reserve_0)?;
+        let reserve_1: InterfaceAccount<TokenAccount> =
+            InterfaceAccount::try_from(&ctx.accounts.reserve_1)?;
+        let swap = &mut ctx.accounts.swap;
+        swap.init_expected_reserves(reserve_0.amount, reserve_1.amount);
+        swap.update_cumulative_price_info()?;
+        ctx.accounts.observations.record(&swap.price_info)
+    }
+
+    /// Computes the time-weighted average prices of a [SwapInfo] over at least the
+    /// last `window_seconds`. The [price_info::TwapResult] is set as the return data.
+    #[access_control(ctx.accounts.validate())]
+    pub fn consult(ctx: Context<Consult>, window_seconds: u32) -> Result<()> {
+        let reserve_0: InterfaceAccount<TokenAccount> =
+            InterfaceAccount::try_from(&ctx.accounts.reserve_0)?;
+        let reserve_1: InterfaceAccount<TokenAccount> =
+            InterfaceAccount::try_from(&ctx.accounts.reserve_1)?;
+        let swap = &ctx.accounts.swap;
+        let reserves = if swap.expected_reserves.is_initialized() {
+            swap.expected_reserves
+        } else {
+            SwapReserves {
+                amount_0: reserve_0.amount,
+                amount_1: reserve_1.amount,
+            }
+        };
+        let result = ctx.accounts.observations.consult(
+            &swap.price_info,
+            &swap.curve,
+            reserves.amount_0,
+            reserves.amount_1,
+            Clock::get()?.unix_timestamp,
+            window_seconds,
         )?;
+        anchor_lang::solana_program::program::set_return_data(&result.try_to_vec()?);
+        Ok(())
+    }
+
+    /// Sends any tokens in the reserves of a [SwapInfo] in excess of its
+    /// [SwapInfo::expected_reserves] to the admin fees accounts.
+    ///
+    /// This is permissionless, since the tokens may only go to the admin.
+    #[access_control(ctx.accounts.validate())]
+    pub fn skim(ctx: Context<Skim>) -> Result<()> {
+        controller::reserves::skim(ctx)
+    }
+
+    /// Sets the [SwapInfo::expected_reserves] of a [SwapInfo] to the balances of its reserves.
+    ///
+    /// Tokens sent directly to the reserves are not used for pricing until this is called.
+    #[access_control(ctx.accounts.validate())]
+    pub fn sync(ctx: Context<SyncReserves>) -> Result<()> {
+        controller::reserves::sync(ctx)
+    }
+
+    /// Lends `amount` of a token from the reserves of a [SwapInfo].
+    ///
+    /// A [cpamm::repay_flash_loan] instruction for the same swap and token must
+    /// come after this instruction in the same transaction. Swaps, deposits, and
+    /// withdrawals are disabled until the loan is repaid.
+    #[access_control(ctx.accounts.validate())]
+    pub fn flash_loan(ctx: Context<FlashLoan>, amount: u64) -> Result<()> {
+        controller::flash_loan::flash_loan(ctx, amount)
+    }
+
+    /// Repays a flash loan taken out via [cpamm::flash_loan], plus the flash loan fee.
+    #[access_control(ctx.accounts.validate())]
+    pub fn repay_flash_loan(ctx: Context<FlashLoan>) -> Result<()> {
+        controller::flash_loan::repay_flash_loan(ctx)
+    }
+
+    /// Sets the flash loan fee of a [SwapInfo].
+    /// Only the [SwapInfo::admin_key] may call this.
+    #[access_control(ctx.accounts.validate())]
+    pub fn set_flash_loan_fee(ctx: Context<SwapAdmin>, fee_kbps: u64) -> Result<()> {
+        invariant!(fee_kbps <= fees::KBPS_PER_WHOLE, InvalidFee);
+
+        let swap = &mut ctx.accounts.swap;
+        swap.flash_loan.fee_kbps = fee_kbps;
+
+        emit!(SetFlashLoanFeeEvent {
+            lp_mint: swap.pool_mint,
+            fee_kbps,
+        });
+
+        Ok(())
+    }
+
+    /// Sets the portion of the admin trade fee of a [SwapInfo] which is sent to the
+    /// referrer of a swap, in thousands of bps.
+    /// Only the [SwapInfo::admin_key] may call this.
+    #[access_control(ctx.accounts.validate())]
+    pub fn set_referral_fee(ctx: Context<SwapAdmin>, referral_fee_kbps: u32) -> Result<()> {
+        invariant!(
+            u64::from(referral_fee_kbps) <= fees::KBPS_PER_WHOLE,
+            InvalidFee
+        );
+
+        let swap = &mut ctx.accounts.swap;
+        swap.referral_fee_kbps = referral_fee_kbps;
+
+        emit!(SetReferralFeeEvent {
+            lp_mint: swap.pool_mint,
+            referral_fee_kbps,
+        });
+
+        Ok(())
+    }
+
+    /// Configures the dynamic trade fee of a [SwapInfo], which raises the trade fee
+    /// above [SwapFees::trade_fee_kbps] when the spot price moves quickly, up to
+    /// `max_trade_fee_kbps`. Setting `max_trade_fee_kbps` to zero disables dynamic fees.
+    ///
+    /// The trade fee rises by `volatility_factor_kbps` of the deviation of the spot price
+    /// from its moving average over the last [fees::PRICE_EMA_PERIOD_SECONDS].
+    /// Only the [SwapInfo::admin_key] may call this.
+    #[access_control(ctx.accounts.validate())]
+    pub fn set_dynamic_fee(
+        ctx: Context<SwapAdmin>,
+        max_trade_fee_kbps: u32,
+        volatility_factor_kbps: u32,
+    ) -> Result<()> {
+        let dynamic_fee = SwapDynamicFee {
+            max_trade_fee_kbps,
+            volatility_factor_kbps,
+            // the moving average restarts from the next spot price
+            price_ema: 0,
+            last_update_ts: 0,
+        };
+        dynamic_fee.validate()?;
+
+        let swap = &mut ctx.accounts.swap;
+        swap.dynamic_fee = dynamic_fee;
+
+        emit!(SetDynamicFeeEvent {
+            lp_mint: swap.pool_mint,
+            max_trade_fee_kbps,
+            volatility_factor_kbps,
+        });
+
         Ok(())
     }
+
+    /// Sets the [SwapFees] of a [SwapInfo].
+    /// Only the [SwapInfo::admin_key] may call this.
+    #[access_control(ctx.accounts.validate())]
+    pub fn set_fees(ctx: Context<SwapAdmin>, fees: SwapFees) -> Result<()> {
+        fees.validate()?;
+
+        let swap = &mut ctx.accounts.swap;
+        swap.fees = fees;
+
+        emit!(SetFeesEvent {
+            lp_mint: swap.pool_mint,
+            fees,
+        });
+
+        Ok(())
+    }
+
+    /// Pauses a [SwapInfo], disabling swaps, deposits, and withdrawals.
+    /// Only the [SwapInfo::admin_key] may call this.
+    #[access_control(ctx.accounts.validate())]
+    pub fn pause(ctx: Context<SwapAdmin>) -> Result<()> {
+        ctx.accounts.set_paused(true)
+    }
+
+    /// Unpauses a [SwapInfo].
+    /// Only the [SwapInfo::admin_key] may call this.
+    #[access_control(ctx.accounts.validate())]
+    pub fn unpause(ctx: Context<SwapAdmin>) -> Result<()> {
+        ctx.accounts.set_paused(false)
+    }
+
+    /// Begins closing a [SwapInfo]. The swap is paused permanently, except for
+    /// [cpamm::withdraw]s, which may then withdraw all of its liquidity.
+    /// Once the pool mint supply is zero, the swap may be closed via [cpamm::close_swap].
+    /// Only the [SwapInfo::admin_key] may call this.
+    #[access_control(ctx.accounts.validate())]
+    pub fn begin_close_swap(ctx: Context<SwapAdmin>) -> Result<()> {
+        ctx.accounts.begin_close_swap()
+    }
+
+    /// Closes a [SwapInfo] which has been fully withdrawn after [cpamm::begin_close_swap].
+    ///
+    /// The remaining balances of the reserve and admin fees accounts of each token are
+    /// transferred to the destinations, and the token accounts, the [SwapInfo], and its
+    /// [SwapMeta] and [Observations] if they exist are closed, returning their rent to the
+    /// rent destination. Withheld Token-2022 transfer fees of the token accounts must be
+    /// harvested to their mints beforehand.
+    /// Only the [SwapInfo::admin_key] may call this.
+    #[access_control(ctx.accounts.validate())]
+    pub fn close_swap(ctx: Context<CloseSwap>) -> Result<()> {
+        ctx.accounts.close_swap()
+    }
+
+    /// Transfers the accumulated admin fees of both tokens of a [SwapInfo] to the given destinations.
+    /// Only the [SwapInfo::admin_key] may call this.
+    #[access_control(ctx.accounts.validate())]
+    pub fn claim_admin_fees(ctx: Context<ClaimAdminFees>) -> Result<()> {
+        ctx.accounts.claim_admin_fees()
+    }
+
+    /// Replaces the [SwapTokenInfo::admin_fees] account of a token of a [SwapInfo].
+    /// The balance of the previous account is moved to the new account.
+    /// Only the [SwapInfo::admin_key] may call this.
+    #[access_control(ctx.accounts.validate())]
+    pub fn set_admin_fees_account(ctx: Context<SetAdminFeesAccount>) -> Result<()> {
+        ctx.accounts.set_admin_fees_account()
+    }
+
+    /// Proposes a new admin for a [SwapInfo].
+    /// The new admin must call [cpamm::accept_swap_admin] to take effect.
+    /// Committing [Pubkey::default] cancels the pending transfer.
+    #[access_control(ctx.accounts.validate())]
+    pub fn commit_swap_admin(ctx: Context<SwapAdmin>, new_admin: Pubkey) -> Result<()> {
+        let swap = &mut ctx.accounts.swap;
+        swap.pending_admin_key = new_admin;
+
+        emit!(CommitAdminEvent {
+            account: swap.key(),
+            admin: swap.admin_key,
+            pending_admin: new_admin,
+        });
+
+        Ok(())
+    }
+
+    /// Accepts the admin role of a [SwapInfo].
+    #[access_control(ctx.accounts.validate())]
+    pub fn accept_swap_admin(ctx: Context<AcceptSwapAdmin>) -> Result<()> {
+        let swap = &mut ctx.accounts.swap;
+        let previous_admin = swap.admin_key;
+        swap.admin_key = swap.pending_admin_key;
+        swap.pending_admin_key = Pubkey::default();
+
+        emit!(AcceptAdminEvent {
+            account: swap.key(),
+            previous_admin,
+            admin: swap.admin_key,
+        });
+
+        Ok(())
+    }
+
+    /// Proposes a new admin for a [Factory].
+    /// The new admin must call [cpamm::accept_factory_admin] to take effect.
+    /// Committing [Pubkey::default] cancels the pending transfer.
+    #[access_control(ctx.accounts.validate())]
+    pub fn commit_factory_admin(ctx: Context<FactoryAdmin>, new_admin: Pubkey) -> Result<()> {
+        let factory = &mut ctx.accounts.factory;
+        factory.pending_admin = new_admin;
+
+        emit!(CommitAdminEvent {
+            account: factory.key(),
+            admin: factory.admin,
+            pending_admin: new_admin,
+        });
+
+        Ok(())
+    }
+
+    /// Sets the [Factory::default_fees] of new [SwapInfo]s.
+    /// Fees which are all zero are treated as unset, so new swaps use [fees::INITIAL] instead.
+    /// Existing swaps are not affected.
+    /// Only the [Factory::admin] may call this.
+    #[access_control(ctx.accounts.validate())]
+    pub fn set_default_fees(ctx: Context<FactoryAdmin>, fees: SwapFees) -> Result<()> {
+        fees.validate()?;
+
+        let factory = &mut ctx.accounts.factory;
+        factory.default_fees = fees;
+
+        emit!(SetDefaultFeesEvent {
+            factory: factory.key(),
+            fees,
+        });
+
+        Ok(())
+    }
+
+    /// Sets the [Factory::fee_tiers] that new [SwapInfo]s may be created with,
+    /// as trade fees in kbps. Between one and [MAX_FEE_TIERS] tiers may be enabled.
+    /// Existing swaps in a tier which is removed are not affected.
+    /// Only the [Factory::admin] may call this.
+    #[access_control(ctx.accounts.validate())]
+    pub fn set_fee_tiers(ctx: Context<FactoryAdmin>, fee_tiers: Vec<u32>) -> Result<()> {
+        let factory = &mut ctx.accounts.factory;
+        factory.fee_tiers = fees::validate_fee_tiers(&fee_tiers)?;
+
+        emit!(SetFeeTiersEvent {
+            factory: factory.key(),
+            fee_tiers,
+        });
+
+        Ok(())
+    }
+
+    /// Sets the [FactoryPolicy] of a [Factory], which restricts the creation of new [SwapInfo]s.
+    /// Existing swaps are not affected.
+    /// Only the [Factory::admin] may call this.
+    #[access_control(ctx.accounts.validate())]
+    pub fn set_factory_policy(ctx: Context<FactoryAdmin>, policy: FactoryPolicy) -> Result<()> {
+        let factory = &mut ctx.accounts.factory;
+        factory.policy = policy;
+
+        emit!(SetFactoryPolicyEvent {
+            factory: factory.key(),
+            policy,
+        });
+
+        Ok(())
+    }
+
+    /// Allows a key to create [SwapInfo]s on a [Factory] which restricts pool creators.
+    /// Only the [Factory::admin] may call this.
+    #[access_control(ctx.accounts.validate())]
+    pub fn add_pool_creator(ctx: Context<AddPoolCreator>, _bump: u8) -> Result<()> {
+        let pool_creator = &mut ctx.accounts.pool_creator;
+        pool_creator.factory = ctx.accounts.factory_admin.factory.key();
+        pool_creator.creator = ctx.accounts.creator.key();
+        pool_creator.bump = unwrap_bump!(ctx, "pool_creator");
+
+        emit!(SetPoolCreatorEvent {
+            factory: pool_creator.factory,
+            creator: pool_creator.creator,
+            is_allowed: true,
+        });
+
+        Ok(())
+    }
+
+    /// Removes a [PoolCreator], returning its rent to the recipient.
+    /// Existing swaps of the creator are not affected.
+    /// Only the [Factory::admin] may call this.
+    #[access_control(ctx.accounts.validate())]
+    pub fn remove_pool_creator(ctx: Context<RemovePoolCreator>) -> Result<()> {
+        // the rent of the pool creator is returned by the `close` constraint
+        emit!(SetPoolCreatorEvent {
+            factory: ctx.accounts.pool_creator.factory,
+            creator: ctx.accounts.pool_creator.creator,
+            is_allowed: false,
+        });
+
+        Ok(())
+    }
+
+    /// Denies a mint from being used by new [SwapInfo]s of a [Factory].
+    /// Existing swaps of the mint are not affected.
+    /// Only the [Factory::admin] may call this.
+    #[access_control(ctx.accounts.validate())]
+    pub fn deny_mint(ctx: Context<DenyMint>, _bump: u8) -> Result<()> {
+        let denied_mint = &mut ctx.accounts.denied_mint;
+        denied_mint.factory = ctx.accounts.factory_admin.factory.key();
+        denied_mint.mint = ctx.accounts.mint.key();
+        denied_mint.bump = unwrap_bump!(ctx, "denied_mint");
+
+        emit!(SetMintDeniedEvent {
+            factory: denied_mint.factory,
+            mint: denied_mint.mint,
+            is_denied: true,
+        });
+
+        Ok(())
+    }
+
+    /// Removes a [DeniedMint], returning its rent to the recipient.
+    /// Only the [Factory::admin] may call this.
+    #[access_control(ctx.accounts.validate())]
+    pub fn allow_mint(ctx: Context<AllowMint>) -> Result<()> {
+        // the rent of the denied mint is returned by the `close` constraint
+        emit!(SetMintDeniedEvent {
+            factory: ctx.accounts.denied_mint.factory,
+            mint: ctx.accounts.denied_mint.mint,
+            is_denied: false,
+        });
+
+        Ok(())
+    }
+
+    /// Accepts the admin role of a [Factory].
+    #[access_control(ctx.accounts.validate())]
+    pub fn accept_factory_admin(ctx: Context<AcceptFactoryAdmin>) -> Result<()> {
+        let factory = &mut ctx.accounts.factory;
+        let previous_admin = factory.admin;
+        factory.admin = factory.pending_admin;
+        factory.pending_admin = Pubkey::default();
+
+        emit!(AcceptAdminEvent {
+            account: factory.key(),
+            previous_admin,
+            admin: factory.admin,
+        });
+
+        Ok(())
+    }
+
+    /// Sets the admin of a [SwapInfo] to the admin of its [Factory].
+    /// Only the [Factory::admin] may call this.
+    #[access_control(ctx.accounts.validate())]
+    pub fn sync_swap_admin(ctx: Context<SyncSwapAdmin>) -> Result<()> {
+        let swap = &mut ctx.accounts.swap;
+        let previous_admin = swap.admin_key;
+        swap.admin_key = ctx.accounts.factory_admin.factory.admin;
+        swap.pending_admin_key = Pubkey::default();
+
+        emit!(AcceptAdminEvent {
+            account: swap.key(),
+            previous_admin,
+            admin: swap.admin_key,
+        });
+
+        Ok(())
+    }
+
+    /// Creates a new [ConcentratedPool], along with the [Tick]s at either end of its price range.
+    ///
+    /// Pools are unique per pair of mints and tick spacing. The trade fee of the pool is one of
+    /// the [Factory::fee_tiers], and is earned entirely by the [Position]s in range.
+    #[access_control(ctx.accounts.validate())]
+    pub fn new_concentrated_pool(
+        ctx: Context<NewConcentratedPool>,
+        _bump: u8,
+        tick_spacing: u16,
+        fee_tier_kbps: u32,
+        initial_sqrt_price_x64: u128,
+    ) -> Result<()> {
+        controller::concentrated::new_concentrated_pool(
+            ctx,
+            tick_spacing,
+            fee_tier_kbps,
+            initial_sqrt_price_x64,
+        )
+    }
+
+    /// Opens an empty [Position] of a [ConcentratedPool] between two ticks.
+    #[access_control(ctx.accounts.validate())]
+    pub fn open_position(
+        ctx: Context<OpenPosition>,
+        _bump: u8,
+        tick_lower: i32,
+        tick_upper: i32,
+    ) -> Result<()> {
+        controller::concentrated::open_position(ctx, tick_lower, tick_upper)
+    }
+
+    /// Adds `liquidity` to a [Position], depositing at most `maximum_amount_in_0`
+    /// of token 0 and `maximum_amount_in_1` of token 1.
+    ///
+    /// The [Tick]s of the position are created if needed, and linked into the list of
+    /// ticks of the pool if they did not bound any liquidity.
+    #[access_control(ctx.accounts.validate())]
+    pub fn increase_liquidity<'info>(
+        ctx: Context<'_, '_, '_, 'info, IncreaseLiquidity<'info>>,
+        liquidity: u128,
+        maximum_amount_in_0: u64,
+        maximum_amount_in_1: u64,
+    ) -> Result<()> {
+        controller::concentrated::increase_liquidity(
+            ctx,
+            controller::concentrated::ModifyLiquidityArgs {
+                liquidity,
+                amount_limit_0: maximum_amount_in_0,
+                amount_limit_1: maximum_amount_in_1,
+            },
+        )
+    }
+
+    /// Removes `liquidity` from a [Position], withdrawing at least `minimum_amount_out_0`
+    /// of token 0 and `minimum_amount_out_1` of token 1.
+    ///
+    /// The fees earned by the position are not withdrawn. View [cpamm::collect_fees].
+    /// [Tick]s of the position which no longer bound any liquidity are unlinked.
+    #[access_control(ctx.accounts.validate())]
+    pub fn decrease_liquidity<'info>(
+        ctx: Context<'_, '_, '_, 'info, ModifyLiquidity<'info>>,
+        liquidity: u128,
+        minimum_amount_out_0: u64,
+        minimum_amount_out_1: u64,
+    ) -> Result<()> {
+        controller::concentrated::decrease_liquidity(
+            ctx,
+            controller::concentrated::ModifyLiquidityArgs {
+                liquidity,
+                amount_limit_0: minimum_amount_out_0,
+                amount_limit_1: minimum_amount_out_1,
+            },
+        )
+    }
+
+    /// Withdraws the trade fees earned by a [Position].
+    #[access_control(ctx.accounts.validate())]
+    pub fn collect_fees(ctx: Context<ModifyLiquidity>) -> Result<()> {
+        controller::concentrated::collect_fees(ctx)
+    }
+
+    /// Closes a [Position] with no liquidity and no uncollected fees.
+    #[access_control(ctx.accounts.validate())]
+    pub fn close_position(ctx: Context<ClosePosition>) -> Result<()> {
+        // the rent of the position is returned to the owner by the `close` constraint
+        Ok(())
+    }
+
+    /// Performs a swap on a [ConcentratedPool].
+    ///
+    /// The initialized [Tick]s which the price crosses must be passed as remaining
+    /// accounts, in order. The swap stops at the first tick which is not passed,
+    /// in which case only part of `amount_in` is swapped.
+    #[access_control(ctx.accounts.validate())]
+    pub fn swap_concentrated<'info>(
+        ctx: Context<'_, '_, '_, 'info, SwapConcentrated<'info>>,
+        amount_in: u64,
+        minimum_amount_out: u64,
+    ) -> Result<()> {
+        controller::concentrated::swap_concentrated(
+            ctx,
+            controller::swap::SwapArgs {
+                amount_in,
+                minimum_amount_out,
+            },
+        )
+    }
 }
 
 // Error codes
@@ -220,6 +859,10 @@ pub enum ErrorCode {
         "The withdrawal will result in the pool having too little liquidity. Withdraw less tokens."
     )]
     InsufficientLiquidityPostWithdrawal,
+    #[msg("Transaction deadline has passed")]
+    Expired,
+    #[msg("Invalid swap route")]
+    InvalidRoute,
 
     #[msg("New swap must have non-zero supply on its reserves", offset = 10)]
     NewSwapMustHaveNonZeroSupply,
@@ -231,6 +874,51 @@ pub enum ErrorCode {
     SwapTokensCannotBeEqual,
     #[msg("Swap's pool mint supply must be zero")]
     SwapPoolMintSupply,
+    #[msg("Payer is not allowed to create swaps on the factory")]
+    PoolCreatorNotAllowed,
+    #[msg("Mint is denied by the factory")]
+    MintDenied,
+    #[msg("Mint has a freeze authority")]
+    MintHasFreezeAuthority,
     #[msg("Invalid fee", offset = 20)]
     InvalidFee,
+    #[msg("Invalid curve")]
+    InvalidCurve,
+    #[msg("Instruction is not supported by the swap's curve")]
+    UnsupportedCurve,
+    #[msg("Fee tier is not enabled or is invalid")]
+    InvalidFeeTier,
+
+    #[msg("Signer is not the admin", offset = 30)]
+    Unauthorized,
+    #[msg("Swap is closing")]
+    SwapClosing,
+    #[msg("Swap must begin closing first")]
+    SwapNotClosing,
+
+    #[msg("Not enough observations to cover the window", offset = 40)]
+    InsufficientObservations,
+    #[msg("Observation interval is too long")]
+    InvalidObservationInterval,
+
+    #[msg("A flash loan is in progress", offset = 50)]
+    FlashLoanInProgress,
+    #[msg("Flash loan must be repaid later in the same transaction")]
+    FlashLoanNotRepaid,
+
+    #[msg("Mint has an extension which is not supported by swaps", offset = 60)]
+    UnsupportedMintExtension,
+
+    #[msg("Invalid tick spacing", offset = 70)]
+    InvalidTickSpacing,
+    #[msg("Tick is out of range or not a multiple of the tick spacing")]
+    InvalidTick,
+    #[msg("Lower tick must be below the upper tick")]
+    InvalidTickRange,
+    #[msg("Ticks adjacent to a tick being linked or unlinked are missing")]
+    TicksNotAdjacent,
+    #[msg("Price is out of the range of the pool")]
+    InvalidSqrtPrice,
+    #[msg("Position has liquidity or uncollected fees")]
+    PositionNotEmpty,
 }
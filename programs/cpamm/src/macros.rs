@@ -1,4 +1,7 @@
 /// Generates [crate::state::SwapInfo] signer seeds,
+///
+/// Swaps created before fee tiers have no fee tier seed.
+/// For more information, view [crate::state::SwapInfo::fee_tier_seed_len].
 macro_rules! gen_swap_signer_seeds {
     ($swap:expr) => {
         &[
@@ -6,7 +9,22 @@ macro_rules! gen_swap_signer_seeds {
             &$swap.factory.to_bytes(),
             &$swap.token_0.mint.to_bytes(),
             &$swap.token_1.mint.to_bytes(),
+            &$swap.fee_tier_kbps.to_le_bytes()[..$swap.fee_tier_seed_len()],
             &[$swap.bump],
         ]
     };
 }
+
+/// Generates [crate::state::ConcentratedPool] signer seeds.
+macro_rules! gen_concentrated_pool_signer_seeds {
+    ($pool:expr) => {
+        &[
+            b"ConcentratedPool" as &[u8],
+            &$pool.factory.to_bytes(),
+            &$pool.token_0.mint.to_bytes(),
+            &$pool.token_1.mint.to_bytes(),
+            &$pool.tick_spacing.to_le_bytes(),
+            &[$pool.bump],
+        ]
+    };
+}
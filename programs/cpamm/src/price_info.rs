@@ -1,63 +1,190 @@
 //! Swap historical price information.
 //! This is based on the Uniswap V2 TWAP oracle solution.
+//!
+//! Swaps may optionally have an [Observations] account, which stores a ring buffer of
+//! cumulative price samples so that TWAPs can be computed on-chain via [crate::cpamm::consult].
 #![deny(missing_docs)]
 
 use crate::*;
 use num_traits::ToPrimitive;
 
 /// Scale of a price.
-pub const PRICE_SCALE: u8 = 18;
+pub const PRICE_SCALE: u8 = xyk::PRICE_SCALE;
 
-fn to_precise(num: u64) -> Option<u128> {
-    (num as u128).checked_mul(10u128.pow(PRICE_SCALE.into()))
-}
-
-fn add_cumulative_price_info(
-    other_reserve: u64,
-    this_reserve: u64,
-    time_elapsed: u64,
-) -> Option<u128> {
-    to_precise(other_reserve)?
-        .checked_div(this_reserve.into())?
-        .checked_mul(time_elapsed.into())
-}
+/// Maximum [Observations::min_interval_seconds], so that the [Observations] of a swap
+/// always cover recent prices.
+pub const MAX_OBSERVATION_INTERVAL_SECONDS: u32 = 86_400;
 
 impl SwapPriceInfo {
-    /// Updates the cumulative price information.
+    /// Updates the cumulative price information with the spot prices of the `curve`.
     /// This should be called before the pool is mutated.
     /// Taken from <https://github.com/Uniswap/v2-core/blob/master/contracts/UniswapV2Pair.sol>.
-    pub fn update_cumulative_price_info(&mut self, reserve_0: u64, reserve_1: u64) -> Result<()> {
+    pub fn update_cumulative_price_info(
+        &mut self,
+        curve: &SwapCurve,
+        reserve_0: u64,
+        reserve_1: u64,
+    ) -> Result<()> {
         let now = Clock::get()?.unix_timestamp;
         if self.last_update_ts > now || reserve_0 == 0 || reserve_1 == 0 {
             return Ok(());
         }
 
-        let time_elapsed: u64 = unwrap_int!(now
-            .checked_sub(self.last_update_ts)
-            .and_then(|v| v.to_u64()));
+        let (next_price_0_cumulative, next_price_1_cumulative) =
+            unwrap_int!(self.cumulative_prices_at(curve, reserve_0, reserve_1, now));
+        self.price_0_cumulative_last = next_price_0_cumulative;
+        self.price_1_cumulative_last = next_price_1_cumulative;
+
+        self.last_update_ts = now;
+
+        Ok(())
+    }
+
+    /// Computes the cumulative prices at `now`, assuming the reserves have not
+    /// changed since the last update.
+    pub fn cumulative_prices_at(
+        &self,
+        curve: &SwapCurve,
+        reserve_0: u64,
+        reserve_1: u64,
+        now: i64,
+    ) -> Option<(u128, u128)> {
+        if self.last_update_ts > now || reserve_0 == 0 || reserve_1 == 0 {
+            return Some((self.price_0_cumulative_last, self.price_1_cumulative_last));
+        }
+
+        let time_elapsed: u64 = now.checked_sub(self.last_update_ts)?.to_u64()?;
 
         // * never overflows, and + overflow is desired
-        let price_0_add: u128 = unwrap_int!(add_cumulative_price_info(
-            reserve_1,
-            reserve_0,
-            time_elapsed
-        ));
-        let price_1_add: u128 = unwrap_int!(add_cumulative_price_info(
-            reserve_0,
-            reserve_1,
-            time_elapsed
-        ));
+        let price_0_add: u128 = curve
+            .spot_price(true, reserve_0, reserve_1)?
+            .checked_mul(time_elapsed.into())?;
+        let price_1_add: u128 = curve
+            .spot_price(false, reserve_0, reserve_1)?
+            .checked_mul(time_elapsed.into())?;
 
         let (next_price_0_cumulative, _) =
             self.price_0_cumulative_last.overflowing_add(price_0_add);
-        self.price_0_cumulative_last = next_price_0_cumulative;
-
         let (next_price_1_cumulative, _) =
             self.price_1_cumulative_last.overflowing_add(price_1_add);
-        self.price_1_cumulative_last = next_price_1_cumulative;
 
-        self.last_update_ts = now;
+        Some((next_price_0_cumulative, next_price_1_cumulative))
+    }
+}
+
+impl SwapInfo {
+    /// Updates the cumulative price information with the [SwapInfo::expected_reserves].
+    /// This should be called before the pool is mutated.
+    pub fn update_cumulative_price_info(&mut self) -> Result<()> {
+        let reserves = self.expected_reserves;
+        self.price_info.update_cumulative_price_info(
+            &self.curve,
+            reserves.amount_0,
+            reserves.amount_1,
+        )
+    }
+}
+
+/// Time-weighted average prices of a [SwapInfo], returned by [crate::cpamm::consult].
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TwapResult {
+    /// Start of the window the prices were averaged over.
+    pub window_start_ts: i64,
+    /// End of the window the prices were averaged over.
+    pub window_end_ts: i64,
+    /// Average price of token 0 in terms of token 1, scaled by 10^[PRICE_SCALE].
+    pub price_0_average: u128,
+    /// Average price of token 1 in terms of token 0, scaled by 10^[PRICE_SCALE].
+    pub price_1_average: u128,
+}
+
+impl Observations {
+    /// Records the latest cumulative prices of the [SwapPriceInfo].
+    /// Samples taken less than [Observations::min_interval_seconds] after the
+    /// most recent [Observation] are skipped.
+    pub fn record(&mut self, price_info: &SwapPriceInfo) -> Result<()> {
+        let next_index = if self.count == 0 {
+            0
+        } else {
+            let latest = &self.observations[usize::from(self.index)];
+            let time_elapsed = unwrap_int!(price_info.last_update_ts.checked_sub(latest.timestamp));
+            if time_elapsed <= 0 || time_elapsed < self.min_interval_seconds.into() {
+                return Ok(());
+            }
+            unwrap_int!(usize::from(self.index)
+                .checked_add(1)
+                .and_then(|v| v.checked_rem(MAX_OBSERVATIONS)))
+        };
+
+        self.observations[next_index] = Observation {
+            timestamp: price_info.last_update_ts,
+            price_0_cumulative: price_info.price_0_cumulative_last,
+            price_1_cumulative: price_info.price_1_cumulative_last,
+        };
+        self.index = unwrap_int!(next_index.to_u16());
+        if usize::from(self.count) < MAX_OBSERVATIONS {
+            self.count = unwrap_int!(self.count.checked_add(1));
+        }
 
         Ok(())
     }
+
+    /// Computes the time-weighted average prices over at least the last `window_seconds`.
+    ///
+    /// The window starts at the most recent [Observation] that is at least
+    /// `window_seconds` old, and ends at `now`.
+    pub fn consult(
+        &self,
+        price_info: &SwapPriceInfo,
+        curve: &SwapCurve,
+        reserve_0: u64,
+        reserve_1: u64,
+        now: i64,
+        window_seconds: u32,
+    ) -> Result<TwapResult> {
+        invariant!(window_seconds > 0, "window_seconds must be positive");
+        let target_ts = unwrap_int!(now.checked_sub(window_seconds.into()));
+
+        let start = self.observations[..usize::from(self.count)]
+            .iter()
+            .filter(|observation| observation.timestamp <= target_ts)
+            .max_by_key(|observation| observation.timestamp);
+        let start = unwrap_opt!(start, InsufficientObservations);
+
+        let (price_0_cumulative, price_1_cumulative) =
+            unwrap_int!(price_info.cumulative_prices_at(curve, reserve_0, reserve_1, now));
+        let time_elapsed: u128 =
+            unwrap_int!(now.checked_sub(start.timestamp).and_then(|v| v.to_u128()));
+
+        // cumulative prices are allowed to overflow
+        Ok(TwapResult {
+            window_start_ts: start.timestamp,
+            window_end_ts: now,
+            price_0_average: unwrap_int!(price_0_cumulative
+                .wrapping_sub(start.price_0_cumulative)
+                .checked_div(time_elapsed)),
+            price_1_average: unwrap_int!(price_1_cumulative
+                .wrapping_sub(start.price_1_cumulative)
+                .checked_div(time_elapsed)),
+        })
+    }
+}
+
+/// Records an [Observation] into the [Observations] account passed as a
+/// remaining account of an instruction, if one was provided.
+pub(crate) fn record_observation(
+    swap: &Account<SwapInfo>,
+    remaining_accounts: &[AccountInfo],
+) -> Result<()> {
+    // the observations account is the only remaining account owned by this program
+    if let Some(observations_info) = remaining_accounts
+        .iter()
+        .find(|account| *account.owner == crate::ID)
+    {
+        let mut observations: Account<Observations> = Account::try_from(observations_info)?;
+        assert_keys_eq!(observations.swap, swap.key());
+        observations.record(&swap.price_info)?;
+        observations.exit(&crate::ID)?;
+    }
+    Ok(())
 }
@@ -5,7 +5,7 @@ use anchor_lang::prelude::*;
 
 use crate::InitSwapToken;
 
-/// Keeps track of [SwapInfo]s.
+/// Keeps track of [SwapInfo]s and [ConcentratedPool]s.
 #[account]
 #[derive(Copy, Debug, Default, PartialEq, Eq)]
 pub struct Factory {
@@ -15,10 +15,70 @@ pub struct Factory {
     pub bump: u8,
     /// Total number of swaps that have been created.
     pub num_swaps: u64,
-    /// Admin of the [Factory]. Currently unused.
+    /// Admin of the [Factory].
+    /// New [SwapInfo]s are created with this admin.
     pub admin: Pubkey,
+    /// Admin that may accept the admin role via [crate::cpamm::accept_factory_admin].
+    pub pending_admin: Pubkey,
+    /// Total number of [ConcentratedPool]s that have been created.
+    pub num_concentrated_pools: u64,
+    /// Fees of new [SwapInfo]s. Their trade fee is that of their fee tier instead.
+    /// Zero on factories created before default fees, which use [crate::fees::INITIAL].
+    /// For more information, view [crate::cpamm::set_default_fees].
+    pub default_fees: SwapFees,
+    /// Trade fees, in kbps, which new [SwapInfo]s may be created with. Unused tiers are zero.
+    /// Zero on factories created before fee tiers, which use [crate::fees::INITIAL_FEE_TIERS].
+    /// For more information, view [crate::cpamm::set_fee_tiers].
+    pub fee_tiers: [u32; MAX_FEE_TIERS],
+    /// Restrictions on the creation of new [SwapInfo]s.
+    /// For more information, view [crate::cpamm::set_factory_policy].
+    pub policy: FactoryPolicy,
     /// Reserved for future program upgrades.
-    pub reserved: [u64; 16],
+    /// [Factory::pending_admin] and the fields after it take 32 + 8 + 32 + 32 + 2 = 106
+    /// of the 128 bytes originally reserved, so the [Factory] stays the size of
+    /// factories created before them.
+    pub reserved: [u8; 22],
+}
+
+/// Maximum number of [Factory::fee_tiers].
+pub const MAX_FEE_TIERS: usize = 8;
+
+/// Restrictions on the creation of new [SwapInfo]s by a [Factory].
+///
+/// Mints with a [DeniedMint] may never be used by new swaps, regardless of the policy.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FactoryPolicy {
+    /// If true, only keys with a [PoolCreator] may create swaps.
+    pub restrict_creators: bool,
+    /// If true, swaps may not be created with a mint that has a freeze authority,
+    /// since freezing a reserve would halt the swap.
+    pub require_no_freeze_authority: bool,
+}
+
+/// A key which may create [SwapInfo]s on a [Factory] that restricts pool creators.
+/// For more information, view [crate::cpamm::add_pool_creator].
+#[account]
+#[derive(Copy, Debug, Default, PartialEq, Eq)]
+pub struct PoolCreator {
+    /// The [Factory].
+    pub factory: Pubkey,
+    /// The key which may create swaps.
+    pub creator: Pubkey,
+    /// The bump seed.
+    pub bump: u8,
+}
+
+/// A mint which may not be used by new [SwapInfo]s of a [Factory].
+/// For more information, view [crate::cpamm::deny_mint].
+#[account]
+#[derive(Copy, Debug, Default, PartialEq, Eq)]
+pub struct DeniedMint {
+    /// The [Factory].
+    pub factory: Pubkey,
+    /// The denied mint.
+    pub mint: Pubkey,
+    /// The bump seed.
+    pub bump: u8,
 }
 
 /// A swap with an index. Used by the [Factory].
@@ -51,7 +111,6 @@ pub struct SwapInfo {
     /// Index
     pub index: u64,
     /// Public key of admin account to execute admin instructions.
-    /// This is immutable.
     pub admin_key: Pubkey,
     /// Token 0
     pub token_0: SwapTokenInfo,
@@ -72,8 +131,111 @@ pub struct SwapInfo {
     /// For more information, view [crate::price_info].
     pub price_info: SwapPriceInfo,
 
+    /// Admin that may accept the admin role via [crate::cpamm::accept_swap_admin].
+    pub pending_admin_key: Pubkey,
+    /// The invariant of the swap.
+    /// For more information, view [crate::curve].
+    pub curve: SwapCurve,
+    /// Flash loan information.
+    /// For more information, view [crate::cpamm::flash_loan].
+    pub flash_loan: SwapFlashLoanInfo,
+    /// Reserves accounted for by the instructions of the swap.
+    /// For more information, view [crate::cpamm::sync].
+    pub expected_reserves: SwapReserves,
+    /// Dynamic trade fee configuration.
+    /// For more information, view [crate::cpamm::set_dynamic_fee].
+    pub dynamic_fee: SwapDynamicFee,
+    /// Trade fee of the [Factory::fee_tiers] the swap was created in, in kbps.
+    /// The same pair of mints may have a swap in each fee tier.
+    pub fee_tier_kbps: u32,
+    /// Bit flags of the swap, e.g. [SwapInfo::FLAG_CLOSING].
+    pub flags: u8,
+    /// Proportion of the admin trade fee sent to the referrer of a swap, thousands of bps.
+    /// For more information, view [crate::cpamm::set_referral_fee].
+    pub referral_fee_kbps: u32,
+
     /// Data reserved for future program upgrades.
-    pub reserved: [u64; 16],
+    /// The [SwapInfo] must stay within the size of swaps created before the fields above.
+    pub reserved: [u8; 4],
+}
+
+impl SwapInfo {
+    /// If set in [SwapInfo::flags], the swap is paused and may only be fully withdrawn
+    /// from before it is closed.
+    /// For more information, view [crate::cpamm::begin_close_swap].
+    pub const FLAG_CLOSING: u8 = 1;
+
+    /// Returns true if [SwapInfo::FLAG_CLOSING] is set.
+    pub fn is_closing(&self) -> bool {
+        self.flags & Self::FLAG_CLOSING != 0
+    }
+
+    /// Number of bytes of [SwapInfo::fee_tier_kbps] in the seeds of the swap.
+    ///
+    /// Swaps created before fee tiers have no fee tier, and their address was derived
+    /// without it. Seeds are hashed as one concatenated byte string, so an empty seed
+    /// derives the same address as a missing one.
+    pub fn fee_tier_seed_len(&self) -> usize {
+        if self.fee_tier_kbps == 0 {
+            0
+        } else {
+            std::mem::size_of::<u32>()
+        }
+    }
+
+    /// Initializes the [SwapInfo::expected_reserves] from the balances of the reserves,
+    /// if the swap was created before its reserves were tracked.
+    pub fn init_expected_reserves(&mut self, balance_0: u64, balance_1: u64) {
+        if !self.expected_reserves.is_initialized() {
+            self.expected_reserves = SwapReserves {
+                amount_0: balance_0,
+                amount_1: balance_1,
+            };
+        }
+    }
+}
+
+/// Number of [Observation]s stored in an [Observations] account.
+pub const MAX_OBSERVATIONS: usize = 64;
+
+/// A ring buffer of cumulative price samples of a [SwapInfo].
+/// For more information, view [crate::price_info].
+#[account]
+#[derive(Debug, PartialEq, Eq)]
+pub struct Observations {
+    /// The [SwapInfo].
+    pub swap: Pubkey,
+    /// The bump seed.
+    pub bump: u8,
+    /// Minimum number of seconds between two [Observation]s.
+    pub min_interval_seconds: u32,
+    /// Index of the most recent [Observation].
+    pub index: u16,
+    /// Number of [Observation]s written, up to [MAX_OBSERVATIONS].
+    pub count: u16,
+    /// The [Observation]s.
+    pub observations: [Observation; MAX_OBSERVATIONS],
+}
+
+impl Observations {
+    /// Number of bytes in a serialized [Observations], including the discriminator.
+    pub const LEN: usize = 8 + 32 + 1 + 4 + 2 + 2 + MAX_OBSERVATIONS * Observation::LEN;
+}
+
+/// A sample of the cumulative prices of a [SwapInfo].
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Observation {
+    /// When the sample was taken.
+    pub timestamp: i64,
+    /// Cumulative price of token 0 at the time of the sample.
+    pub price_0_cumulative: u128,
+    /// Cumulative price of token 1 at the time of the sample.
+    pub price_1_cumulative: u128,
+}
+
+impl Observation {
+    /// Number of bytes in a serialized [Observation].
+    pub const LEN: usize = 8 + 16 + 16;
 }
 
 /// Cumulative statistics about the swap.
@@ -143,6 +305,114 @@ impl<'info> From<&InitSwapToken<'info>> for SwapTokenInfo {
     }
 }
 
+/// The invariant of a [SwapInfo].
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SwapCurve {
+    /// Type of the curve, e.g. [SwapCurve::CONSTANT_PRODUCT].
+    pub curve_type: u8,
+    /// Amplification coefficient, if the curve is [SwapCurve::STABLE_SWAP].
+    pub amp_factor: u64,
+    /// Weight of token 0, in units of [xyk::WEIGHT_DENOMINATOR], if the curve is
+    /// [SwapCurve::WEIGHTED_PRODUCT]. Token 1 has the rest of the weight.
+    pub weight_0: u16,
+}
+
+/// Dynamic trade fee of a [SwapInfo], which rises with short-term price volatility.
+///
+/// Volatility is measured as the deviation of the spot price of token 0 from
+/// [SwapDynamicFee::price_ema].
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SwapDynamicFee {
+    /// Maximum trade fee, in kbps. Dynamic fees are disabled if this is zero.
+    pub max_trade_fee_kbps: u32,
+    /// Portion of the price deviation added to [SwapFees::trade_fee_kbps], in kbps.
+    pub volatility_factor_kbps: u32,
+    /// Moving average of the spot price of token 0 in terms of token 1 on the
+    /// [SwapInfo::curve], scaled by 10^[crate::price_info::PRICE_SCALE].
+    pub price_ema: u128,
+    /// When [SwapDynamicFee::price_ema] was last updated.
+    pub last_update_ts: i64,
+}
+
+/// Reserves of a [SwapInfo].
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SwapReserves {
+    /// Amount of token 0.
+    pub amount_0: u64,
+    /// Amount of token 1.
+    pub amount_1: u64,
+}
+
+impl SwapReserves {
+    /// Returns true if the reserves have been initialized.
+    /// The reserves of a swap are never both empty once it has been created.
+    pub fn is_initialized(&self) -> bool {
+        self.amount_0 != 0 || self.amount_1 != 0
+    }
+
+    /// Returns the reserves of token 0 or token 1.
+    pub fn get(&self, is_token_0: bool) -> u64 {
+        if is_token_0 {
+            self.amount_0
+        } else {
+            self.amount_1
+        }
+    }
+
+    /// Adds `amount` to the reserves of token 0 or token 1.
+    pub fn credit(&mut self, is_token_0: bool, amount: u64) -> Option<()> {
+        let reserve = self.get_mut(is_token_0);
+        *reserve = reserve.checked_add(amount)?;
+        Some(())
+    }
+
+    /// Subtracts `amount` from the reserves of token 0 or token 1.
+    pub fn debit(&mut self, is_token_0: bool, amount: u64) -> Option<()> {
+        let reserve = self.get_mut(is_token_0);
+        *reserve = reserve.checked_sub(amount)?;
+        Some(())
+    }
+
+    /// Returns the reserves after a swap from token 0 or token 1, before fees.
+    pub fn after_swap(
+        &self,
+        is_input_token_0: bool,
+        swap_result: &xyk::SwapResult,
+    ) -> Option<SwapReserves> {
+        let mut reserves = *self;
+        reserves.credit(is_input_token_0, swap_result.source_amount_swapped)?;
+        reserves.debit(!is_input_token_0, swap_result.destination_amount_swapped)?;
+        Some(reserves)
+    }
+
+    fn get_mut(&mut self, is_token_0: bool) -> &mut u64 {
+        if is_token_0 {
+            &mut self.amount_0
+        } else {
+            &mut self.amount_1
+        }
+    }
+}
+
+/// Flash loan information of a [SwapInfo].
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SwapFlashLoanInfo {
+    /// Flash loan fee, thousands of bps.
+    /// The admin receives [SwapFees::admin_trade_fee_kbps] of this fee.
+    pub fee_kbps: u64,
+    /// Amount of token 0 currently lent out.
+    pub amount_0: u64,
+    /// Amount of token 1 currently lent out.
+    pub amount_1: u64,
+}
+
+impl SwapFlashLoanInfo {
+    /// Returns true if a flash loan is in progress.
+    pub fn is_active(&self) -> bool {
+        self.amount_0 != 0 || self.amount_1 != 0
+    }
+}
+
 /// Fees associated with a [SwapInfo].
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
 pub struct SwapFees {
@@ -155,3 +425,109 @@ pub struct SwapFees {
     /// Proportion of withdraw fee sent to the admin, thousands of bps
     pub admin_withdraw_fee_kbps: u64,
 }
+
+/// A pool whose liquidity is provided over price ranges bounded by [Tick]s.
+///
+/// Liquidity is owned by [Position]s instead of a pool mint, and each position
+/// accrues the trade fees earned while the price is within its range.
+/// For more information, view [crate::concentrated].
+#[account]
+#[derive(Copy, Debug, Default, PartialEq, Eq)]
+pub struct ConcentratedPool {
+    /// The [Factory].
+    pub factory: Pubkey,
+    /// The bump seed.
+    pub bump: u8,
+    /// Index of the pool among the [Factory::num_concentrated_pools].
+    pub index: u64,
+
+    /// Token 0
+    pub token_0: ConcentratedTokenInfo,
+    /// Token 1
+    pub token_1: ConcentratedTokenInfo,
+    /// Ticks must be multiples of the tick spacing.
+    pub tick_spacing: u16,
+    /// Trade fee, thousands of bps. The entire fee is earned by the [Position]s.
+    pub trade_fee_kbps: u64,
+
+    /// Square root of the price of token 0 in terms of token 1, Q64.64.
+    pub sqrt_price_x64: u128,
+    /// The tick of the current price.
+    pub tick_current: i32,
+    /// Liquidity of the [Position]s whose range contains [ConcentratedPool::tick_current].
+    pub liquidity: u128,
+    /// Index of the highest initialized [Tick] at or below [ConcentratedPool::tick_current].
+    pub tick_below: i32,
+    /// Index of the lowest initialized [Tick] above [ConcentratedPool::tick_current].
+    pub tick_above: i32,
+
+    /// Fees of token 0 earned per unit of liquidity over the life of the pool, Q64.64.
+    pub fee_growth_global_0_x64: u128,
+    /// Fees of token 1 earned per unit of liquidity over the life of the pool, Q64.64.
+    pub fee_growth_global_1_x64: u128,
+
+    /// Reserved for future program upgrades.
+    pub reserved: [u64; 8],
+}
+
+/// Information about one of the tokens of a [ConcentratedPool].
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ConcentratedTokenInfo {
+    /// Token account for pool reserves
+    pub reserves: Pubkey,
+    /// Mint information for the token
+    pub mint: Pubkey,
+}
+
+/// A boundary of the price ranges of a [ConcentratedPool].
+///
+/// The initialized ticks of a pool form a doubly linked list, which starts and ends
+/// at the ticks created with the pool at [xyk::min_tick] and [xyk::max_tick].
+#[account]
+#[derive(Copy, Debug, Default, PartialEq, Eq)]
+pub struct Tick {
+    /// The [ConcentratedPool].
+    pub pool: Pubkey,
+    /// The bump seed.
+    pub bump: u8,
+    /// Index of the tick. The price at the tick is `1.0001^index`.
+    pub index: i32,
+    /// Index of the previous initialized tick, or of this tick if it is the lowest.
+    pub prev: i32,
+    /// Index of the next initialized tick, or of this tick if it is the highest.
+    pub next: i32,
+    /// Total liquidity of the [Position]s bounded by this tick.
+    pub liquidity_gross: u128,
+    /// Liquidity added to the pool when the price crosses this tick upwards.
+    pub liquidity_net: i128,
+    /// Fee growth of token 0 on the other side of this tick from the current price, Q64.64.
+    pub fee_growth_outside_0_x64: u128,
+    /// Fee growth of token 1 on the other side of this tick from the current price, Q64.64.
+    pub fee_growth_outside_1_x64: u128,
+}
+
+/// Liquidity provided to a [ConcentratedPool] between two [Tick]s.
+#[account]
+#[derive(Copy, Debug, Default, PartialEq, Eq)]
+pub struct Position {
+    /// The [ConcentratedPool].
+    pub pool: Pubkey,
+    /// Owner of the position.
+    pub owner: Pubkey,
+    /// The bump seed.
+    pub bump: u8,
+    /// Index of the lower [Tick] of the range.
+    pub tick_lower: i32,
+    /// Index of the upper [Tick] of the range.
+    pub tick_upper: i32,
+    /// Liquidity of the position.
+    pub liquidity: u128,
+    /// Fee growth of token 0 inside the range when the fees were last updated, Q64.64.
+    pub fee_growth_inside_0_last_x64: u128,
+    /// Fee growth of token 1 inside the range when the fees were last updated, Q64.64.
+    pub fee_growth_inside_1_last_x64: u128,
+    /// Fees of token 0 earned but not yet collected.
+    pub fees_owed_0: u64,
+    /// Fees of token 1 earned but not yet collected.
+    pub fees_owed_1: u64,
+}
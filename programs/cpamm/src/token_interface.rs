@@ -0,0 +1,307 @@
+//! Token accounts and mints of either the SPL Token or the Token-2022 program.
+//!
+//! Anchor's [anchor_spl::token] accounts may only be owned by the SPL Token program,
+//! so the accounts of a swap's tokens are passed as [UncheckedAccount]s and loaded
+//! as [InterfaceAccount]s, which accept accounts of either program.
+//!
+//! Token-2022 mints may withhold a fee on every transfer. Instructions therefore use
+//! [transfer_checked], and compute swaps on the amounts actually received by the pool.
+#![deny(missing_docs)]
+
+use crate::*;
+use anchor_lang::solana_program::program::invoke_signed;
+use spl_token_2022::extension::default_account_state::DefaultAccountState;
+use spl_token_2022::extension::transfer_fee::{
+    TransferFee, TransferFeeConfig, MAX_FEE_BASIS_POINTS,
+};
+use spl_token_2022::extension::{ExtensionType, StateWithExtensions};
+use spl_token_2022::state::AccountState;
+use std::ops::Deref;
+
+/// Mint extensions which do not break the assumptions of the AMM.
+///
+/// Mints with any other extension are rejected by [crate::cpamm::new_swap].
+/// This includes non-transferable mints, and extensions unknown to this program
+/// such as the permanent delegate, which could move tokens out of the reserves.
+/// A [ExtensionType::DefaultAccountState] which freezes new accounts is rejected as well.
+pub const SUPPORTED_MINT_EXTENSIONS: &[ExtensionType] = &[
+    ExtensionType::TransferFeeConfig,
+    ExtensionType::MintCloseAuthority,
+    ExtensionType::DefaultAccountState,
+    ExtensionType::InterestBearingConfig,
+];
+
+/// Returns true if the key is the SPL Token or the Token-2022 program.
+pub fn is_token_program(key: &Pubkey) -> bool {
+    *key == anchor_spl::token::ID || *key == spl_token_2022::ID
+}
+
+/// A token account or mint owned by either token program.
+#[derive(Clone)]
+pub struct InterfaceAccount<'info, T: AccountDeserialize + Clone> {
+    account: T,
+    info: AccountInfo<'info>,
+}
+
+impl<'info, T: AccountDeserialize + Clone> InterfaceAccount<'info, T> {
+    /// Deserializes the account, checking that it is owned by a token program.
+    pub fn try_from(info: &AccountInfo<'info>) -> Result<Self> {
+        if !is_token_program(info.owner) {
+            return Err(anchor_lang::error::ErrorCode::AccountOwnedByWrongProgram.into());
+        }
+        let mut data: &[u8] = &info.try_borrow_data()?;
+        Ok(InterfaceAccount {
+            account: T::try_deserialize(&mut data)?,
+            info: info.clone(),
+        })
+    }
+
+    /// Reloads the account from its data.
+    pub fn reload(&mut self) -> Result<()> {
+        let mut data: &[u8] = &self.info.try_borrow_data()?;
+        self.account = T::try_deserialize(&mut data)?;
+        Ok(())
+    }
+}
+
+impl<'info, T: AccountDeserialize + Clone> AsRef<AccountInfo<'info>>
+    for InterfaceAccount<'info, T>
+{
+    fn as_ref(&self) -> &AccountInfo<'info> {
+        &self.info
+    }
+}
+
+impl<'info, T: AccountDeserialize + Clone> AsKeyRef for InterfaceAccount<'info, T> {
+    fn as_key_ref(&self) -> &Pubkey {
+        self.info.key
+    }
+}
+
+impl<'info, T: AccountDeserialize + Clone> Deref for InterfaceAccount<'info, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.account
+    }
+}
+
+/// A token account of either token program.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TokenAccount(spl_token_2022::state::Account);
+
+impl AccountDeserialize for TokenAccount {
+    fn try_deserialize_unchecked(buf: &mut &[u8]) -> Result<Self> {
+        let account = StateWithExtensions::<spl_token_2022::state::Account>::unpack(buf)?;
+        Ok(TokenAccount(account.base))
+    }
+}
+
+impl Deref for TokenAccount {
+    type Target = spl_token_2022::state::Account;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// A mint of either token program.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mint {
+    base: spl_token_2022::state::Mint,
+    /// Extensions of the mint, or [None] if the mint has an unknown extension.
+    extensions: Option<Vec<ExtensionType>>,
+    /// Transfer fees of the mint.
+    transfer_fee_config: Option<TransferFeeConfig>,
+    /// True if new token accounts of the mint are frozen.
+    frozen_by_default: bool,
+}
+
+impl AccountDeserialize for Mint {
+    fn try_deserialize_unchecked(buf: &mut &[u8]) -> Result<Self> {
+        let mint = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(buf)?;
+        Ok(Mint {
+            base: mint.base,
+            extensions: mint.get_extension_types().ok(),
+            transfer_fee_config: mint.get_extension::<TransferFeeConfig>().ok().copied(),
+            frozen_by_default: mint
+                .get_extension::<DefaultAccountState>()
+                .map_or(false, |default_state| {
+                    default_state.state == AccountState::Frozen as u8
+                }),
+        })
+    }
+}
+
+impl Deref for Mint {
+    type Target = spl_token_2022::state::Mint;
+
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
+
+impl Mint {
+    /// Returns true if all extensions of the mint are [SUPPORTED_MINT_EXTENSIONS].
+    ///
+    /// Mints whose new token accounts are frozen by default are not supported either,
+    /// since the reserves and admin fees accounts of the swap could not be transferred from.
+    pub fn has_supported_extensions(&self) -> bool {
+        if self.frozen_by_default {
+            return false;
+        }
+        match &self.extensions {
+            Some(extensions) => extensions
+                .iter()
+                .all(|extension| SUPPORTED_MINT_EXTENSIONS.contains(extension)),
+            None => false,
+        }
+    }
+
+    /// Returns the [TransferFee] in effect, if any.
+    fn epoch_transfer_fee(&self) -> Result<Option<TransferFee>> {
+        Ok(match &self.transfer_fee_config {
+            Some(config) => Some(*config.get_epoch_fee(Clock::get()?.epoch)),
+            None => None,
+        })
+    }
+
+    /// Computes the fee withheld from a transfer of `amount` tokens.
+    pub fn transfer_fee(&self, amount: u64) -> Result<u64> {
+        Ok(match self.epoch_transfer_fee()? {
+            Some(transfer_fee) => unwrap_int!(transfer_fee.calculate(amount)),
+            None => 0,
+        })
+    }
+
+    /// Computes the amount received by the destination of a transfer of `amount` tokens.
+    pub fn amount_after_transfer_fee(&self, amount: u64) -> Result<u64> {
+        Ok(unwrap_int!(amount.checked_sub(self.transfer_fee(amount)?)))
+    }
+
+    /// Computes the amount which must be transferred for the destination to receive `amount` tokens.
+    pub fn amount_before_transfer_fee(&self, amount: u64) -> Result<u64> {
+        Ok(match self.epoch_transfer_fee()? {
+            Some(transfer_fee) => unwrap_int!(amount_before_transfer_fee(&transfer_fee, amount)),
+            None => amount,
+        })
+    }
+}
+
+/// Computes the smallest transfer from which `amount` tokens remain after the [TransferFee].
+///
+/// The fee is `ceil(transfer * bps / 10_000)`, capped at the maximum fee, so `transfer` must be
+/// at least `ceil(amount * 10_000 / (10_000 - bps))`, or `amount` plus the maximum fee.
+fn amount_before_transfer_fee(transfer_fee: &TransferFee, amount: u64) -> Option<u64> {
+    let basis_points = u64::from(u16::from(transfer_fee.transfer_fee_basis_points));
+    if basis_points == 0 || amount == 0 {
+        return Some(amount);
+    }
+    let with_maximum_fee = amount.checked_add(u64::from(transfer_fee.maximum_fee));
+    let remaining_basis_points = u64::from(MAX_FEE_BASIS_POINTS).checked_sub(basis_points)?;
+    if remaining_basis_points == 0 {
+        return with_maximum_fee;
+    }
+    let with_proportional_fee = u64::try_from(
+        u128::from(amount)
+            .checked_mul(MAX_FEE_BASIS_POINTS.into())?
+            .checked_add(u128::from(remaining_basis_points).checked_sub(1)?)?
+            .checked_div(remaining_basis_points.into())?,
+    )
+    .ok();
+    match (with_proportional_fee, with_maximum_fee) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Transfers tokens of either token program.
+///
+/// `transfer_checked` is used since Token-2022 requires the mint for transfers of mints with a transfer fee.
+pub fn transfer_checked<'info>(
+    token_program: &AccountInfo<'info>,
+    mint: &InterfaceAccount<'info, Mint>,
+    from: &AccountInfo<'info>,
+    to: &AccountInfo<'info>,
+    authority: &AccountInfo<'info>,
+    amount: u64,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let ix = spl_token_2022::instruction::transfer_checked(
+        token_program.key,
+        from.key,
+        mint.as_ref().key,
+        to.key,
+        authority.key,
+        &[],
+        amount,
+        mint.decimals,
+    )?;
+    invoke_signed(
+        &ix,
+        &[
+            from.clone(),
+            mint.as_ref().clone(),
+            to.clone(),
+            authority.clone(),
+            token_program.clone(),
+        ],
+        signer_seeds,
+    )?;
+    Ok(())
+}
+
+/// Moves the transfer fees withheld in the `sources` token accounts to the mint.
+///
+/// Does nothing if the mint has no transfer fee. The mint must be writable.
+pub fn harvest_withheld_tokens_to_mint<'info>(
+    token_program: &AccountInfo<'info>,
+    mint: &InterfaceAccount<'info, Mint>,
+    sources: &[&AccountInfo<'info>],
+) -> Result<()> {
+    if mint.transfer_fee_config.is_none() {
+        return Ok(());
+    }
+    let source_keys: Vec<&Pubkey> = sources.iter().map(|source| source.key).collect();
+    let ix = spl_token_2022::extension::transfer_fee::instruction::harvest_withheld_tokens_to_mint(
+        token_program.key,
+        mint.as_ref().key,
+        &source_keys,
+    )?;
+    let mut account_infos = vec![mint.as_ref().clone()];
+    account_infos.extend(sources.iter().map(|source| (*source).clone()));
+    account_infos.push(token_program.clone());
+    invoke_signed(&ix, &account_infos, &[])?;
+    Ok(())
+}
+
+/// Closes a token account of either token program, sending its rent to the destination.
+///
+/// Token-2022 accounts may only be closed once their withheld transfer fees are harvested to the mint
+/// with [harvest_withheld_tokens_to_mint].
+pub fn close_account<'info>(
+    token_program: &AccountInfo<'info>,
+    account: &AccountInfo<'info>,
+    destination: &AccountInfo<'info>,
+    authority: &AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let ix = spl_token_2022::instruction::close_account(
+        token_program.key,
+        account.key,
+        destination.key,
+        authority.key,
+        &[],
+    )?;
+    invoke_signed(
+        &ix,
+        &[
+            account.clone(),
+            destination.clone(),
+            authority.clone(),
+            token_program.clone(),
+        ],
+        signer_seeds,
+    )?;
+    Ok(())
+}
@@ -0,0 +1,4484 @@
+//! Integration tests of the CP-AMM program, run against [solana_program_test].
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::{Instruction, InstructionError};
+use anchor_lang::solana_program::program_pack::Pack;
+use anchor_lang::solana_program::system_instruction;
+use anchor_lang::Discriminator;
+use cpamm::{ErrorCode, SwapCurve, SwapInfo};
+use cpamm_client::{accounts as client_accounts, instructions, pda};
+use solana_program_test::*;
+use solana_sdk::account::AccountSharedData;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::{Transaction, TransactionError};
+use spl_token_2022::extension::interest_bearing_mint::InterestBearingConfig;
+use spl_token_2022::extension::transfer_fee::TransferFeeConfig;
+use spl_token_2022::extension::{ExtensionType, StateWithExtensions};
+use spl_token_2022::state::AccountState;
+use xyk::Curve;
+
+/// Reserve amounts of the default test swap.
+const INITIAL_RESERVE: u64 = 1_000_000_000;
+
+/// Fee tier of the default test swap, which has the initial trade fee.
+const FEE_TIER_KBPS: u32 = 30_000;
+
+/// Length of the data of a [cpamm::Factory] created by the first release of the program.
+/// Fields added since then are carved out of its reserved bytes.
+const BASELINE_FACTORY_LEN: usize = 209;
+
+/// Length of the data of a [SwapInfo] created by the first release of the program.
+/// Fields added since then are carved out of its reserved bytes.
+const BASELINE_SWAP_INFO_LEN: usize = 682;
+
+struct TestEnv {
+    ctx: ProgramTestContext,
+    /// Admin of the factory and its swaps.
+    admin: Keypair,
+}
+
+/// A Token-2022 mint extension, initialized by [TestEnv::create_mint_with_extensions].
+enum MintExtension {
+    TransferFee { basis_points: u16, maximum_fee: u64 },
+    InterestBearing { rate: i16 },
+    NonTransferable,
+    DefaultAccountState { state: AccountState },
+}
+
+impl MintExtension {
+    fn extension_type(&self) -> ExtensionType {
+        match self {
+            MintExtension::TransferFee { .. } => ExtensionType::TransferFeeConfig,
+            MintExtension::InterestBearing { .. } => ExtensionType::InterestBearingConfig,
+            MintExtension::NonTransferable => ExtensionType::NonTransferable,
+            MintExtension::DefaultAccountState { .. } => ExtensionType::DefaultAccountState,
+        }
+    }
+
+    fn initialize(&self, mint: &Pubkey, authority: &Pubkey) -> Instruction {
+        let program_id = &spl_token_2022::ID;
+        match *self {
+            MintExtension::TransferFee {
+                basis_points,
+                maximum_fee,
+            } => spl_token_2022::extension::transfer_fee::instruction::initialize_transfer_fee_config(
+                program_id,
+                mint,
+                Some(authority),
+                Some(authority),
+                basis_points,
+                maximum_fee,
+            ),
+            MintExtension::InterestBearing { rate } => {
+                spl_token_2022::extension::interest_bearing_mint::instruction::initialize(
+                    program_id,
+                    mint,
+                    Some(*authority),
+                    rate,
+                )
+            }
+            MintExtension::NonTransferable => {
+                spl_token_2022::instruction::initialize_non_transferable_mint(program_id, mint)
+            }
+            MintExtension::DefaultAccountState { state } => {
+                spl_token_2022::extension::default_account_state::instruction::initialize_default_account_state(
+                    program_id,
+                    mint,
+                    &state,
+                )
+            }
+        }
+        .unwrap()
+    }
+}
+
+/// A swap between two fresh mints, with a funded user.
+struct TestSwap {
+    swap: Pubkey,
+    factory: Pubkey,
+    mint_0: Pubkey,
+    mint_1: Pubkey,
+    /// Token programs of `mint_0` and `mint_1`.
+    token_programs: [Pubkey; 2],
+    pool_mint: Pubkey,
+    reserve_0: Pubkey,
+    reserve_1: Pubkey,
+    fees_0: Pubkey,
+    fees_1: Pubkey,
+    user_0: Pubkey,
+    user_1: Pubkey,
+    user_lp: Pubkey,
+}
+
+/// Accounts required by `new_swap`, before the swap is created.
+struct NewSwapSetup {
+    factory: Pubkey,
+    swap: Pubkey,
+    mint_0: Pubkey,
+    mint_1: Pubkey,
+    pool_mint: Pubkey,
+    reserve_0: Pubkey,
+    reserve_1: Pubkey,
+    fees_0: Pubkey,
+    fees_1: Pubkey,
+    user_lp: Pubkey,
+}
+
+impl NewSwapSetup {
+    fn accounts(&self, payer: Pubkey) -> cpamm::accounts::NewSwap {
+        cpamm::accounts::NewSwap {
+            factory: self.factory,
+            swap: self.swap,
+            pool_mint: self.pool_mint,
+            token_0: cpamm::accounts::InitSwapToken {
+                mint: self.mint_0,
+                reserve: self.reserve_0,
+                fees: self.fees_0,
+                denied_mint: pda::find_denied_mint_address(&self.factory, &self.mint_0).0,
+            },
+            token_1: cpamm::accounts::InitSwapToken {
+                mint: self.mint_1,
+                reserve: self.reserve_1,
+                fees: self.fees_1,
+                denied_mint: pda::find_denied_mint_address(&self.factory, &self.mint_1).0,
+            },
+            output_lp: self.user_lp,
+            payer,
+            pool_creator: pda::find_pool_creator_address(&self.factory, &payer).0,
+            token_program: anchor_spl::token::ID,
+            system_program: anchor_lang::solana_program::system_program::ID,
+        }
+    }
+}
+
+impl TestEnv {
+    async fn new() -> Self {
+        let mut program_test = ProgramTest::new("cpamm", cpamm::ID, processor!(cpamm::entry));
+        // run the token program natively rather than loading its BPF build
+        program_test.add_program(
+            "spl_token",
+            spl_token::ID,
+            processor!(spl_token::processor::Processor::process),
+        );
+        program_test.add_program(
+            "spl_token_2022",
+            spl_token_2022::ID,
+            processor!(spl_token_2022::processor::Processor::process),
+        );
+        TestEnv {
+            ctx: program_test.start_with_context().await,
+            admin: Keypair::new(),
+        }
+    }
+
+    fn payer(&self) -> Pubkey {
+        self.ctx.payer.pubkey()
+    }
+
+    /// Builds a transaction signed by the payer and the `signers`, with a new blockhash.
+    async fn transaction(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Transaction {
+        let blockhash = self
+            .ctx
+            .banks_client
+            .get_new_latest_blockhash(&self.ctx.last_blockhash)
+            .await
+            .unwrap();
+        self.ctx.last_blockhash = blockhash;
+
+        let mut all_signers = vec![&self.ctx.payer];
+        all_signers.extend_from_slice(signers);
+        Transaction::new_signed_with_payer(
+            instructions,
+            Some(&self.ctx.payer.pubkey()),
+            &all_signers,
+            blockhash,
+        )
+    }
+
+    async fn process(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> std::result::Result<(), TransactionError> {
+        let tx = self.transaction(instructions, signers).await;
+        self.ctx
+            .banks_client
+            .process_transaction(tx)
+            .await
+            .map_err(|err| err.unwrap())
+    }
+
+    /// Simulates a transaction, returning the events of type `E` which it emits.
+    async fn simulate_events<E: anchor_lang::Event>(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Vec<E> {
+        let tx = self.transaction(instructions, signers).await;
+        let simulation = self
+            .ctx
+            .banks_client
+            .simulate_transaction(tx)
+            .await
+            .unwrap();
+        assert!(matches!(simulation.result, Some(Ok(()))));
+        simulation
+            .simulation_details
+            .unwrap()
+            .logs
+            .iter()
+            .filter_map(|log| log.strip_prefix("Program log: "))
+            .filter_map(|data| base64::decode(data).ok())
+            .filter(|data| data.starts_with(&E::discriminator()))
+            .map(|data| E::deserialize(&mut &data[8..]).unwrap())
+            .collect()
+    }
+
+    async fn get_account(&mut self, address: Pubkey) -> solana_sdk::account::Account {
+        self.ctx
+            .banks_client
+            .get_account(address)
+            .await
+            .unwrap()
+            .unwrap()
+    }
+
+    async fn get_account_data(&mut self, address: Pubkey) -> Vec<u8> {
+        self.get_account(address).await.data
+    }
+
+    /// Returns the token program which owns the mint.
+    async fn token_program(&mut self, mint: Pubkey) -> Pubkey {
+        self.get_account(mint).await.owner
+    }
+
+    async fn swap_info(&mut self, swap: Pubkey) -> SwapInfo {
+        let data = self.get_account_data(swap).await;
+        cpamm_client::accounts::decode_swap_info(&data).unwrap()
+    }
+
+    async fn token_balance(&mut self, account: Pubkey) -> u64 {
+        let data = self.get_account_data(account).await;
+        StateWithExtensions::<spl_token_2022::state::Account>::unpack(&data)
+            .unwrap()
+            .base
+            .amount
+    }
+
+    async fn mint_supply(&mut self, mint: Pubkey) -> u64 {
+        let data = self.get_account_data(mint).await;
+        StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)
+            .unwrap()
+            .base
+            .supply
+    }
+
+    /// Returns the transfer fees harvested to a mint with a transfer fee.
+    async fn mint_withheld_amount(&mut self, mint: Pubkey) -> u64 {
+        let data = self.get_account_data(mint).await;
+        StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)
+            .unwrap()
+            .get_extension::<TransferFeeConfig>()
+            .unwrap()
+            .withheld_amount
+            .into()
+    }
+
+    async fn unix_timestamp(&mut self) -> i64 {
+        self.ctx
+            .banks_client
+            .get_sysvar::<Clock>()
+            .await
+            .unwrap()
+            .unix_timestamp
+    }
+
+    async fn advance_clock(&mut self, seconds: i64) {
+        let mut clock = self.ctx.banks_client.get_sysvar::<Clock>().await.unwrap();
+        clock.unix_timestamp += seconds;
+        self.ctx.set_sysvar(&clock);
+    }
+
+    async fn create_mint(&mut self, authority: Pubkey, decimals: u8) -> Pubkey {
+        let mint = Keypair::new();
+        let rent = self.ctx.banks_client.get_rent().await.unwrap();
+        let payer = self.payer();
+        self.process(
+            &[
+                system_instruction::create_account(
+                    &payer,
+                    &mint.pubkey(),
+                    rent.minimum_balance(spl_token::state::Mint::LEN),
+                    spl_token::state::Mint::LEN as u64,
+                    &spl_token::ID,
+                ),
+                spl_token::instruction::initialize_mint(
+                    &spl_token::ID,
+                    &mint.pubkey(),
+                    &authority,
+                    Some(&authority),
+                    decimals,
+                )
+                .unwrap(),
+            ],
+            &[&mint],
+        )
+        .await
+        .unwrap();
+        mint.pubkey()
+    }
+
+    /// Creates a Token-2022 mint with the given extensions.
+    async fn create_mint_with_extensions(
+        &mut self,
+        authority: Pubkey,
+        decimals: u8,
+        extensions: &[MintExtension],
+    ) -> Pubkey {
+        let mint = Keypair::new();
+        let extension_types: Vec<ExtensionType> = extensions
+            .iter()
+            .map(MintExtension::extension_type)
+            .collect();
+        let len = ExtensionType::get_account_len::<spl_token_2022::state::Mint>(&extension_types);
+        let rent = self.ctx.banks_client.get_rent().await.unwrap();
+        let payer = self.payer();
+
+        let mut ixs = vec![system_instruction::create_account(
+            &payer,
+            &mint.pubkey(),
+            rent.minimum_balance(len),
+            len as u64,
+            &spl_token_2022::ID,
+        )];
+        ixs.extend(
+            extensions
+                .iter()
+                .map(|extension| extension.initialize(&mint.pubkey(), &authority)),
+        );
+        ixs.push(
+            spl_token_2022::instruction::initialize_mint(
+                &spl_token_2022::ID,
+                &mint.pubkey(),
+                &authority,
+                Some(&authority),
+                decimals,
+            )
+            .unwrap(),
+        );
+        self.process(&ixs, &[&mint]).await.unwrap();
+        mint.pubkey()
+    }
+
+    /// Creates a mint whose key sorts after `other`.
+    async fn create_mint_after(&mut self, other: Pubkey, decimals: u8) -> Pubkey {
+        loop {
+            let payer = self.payer();
+            let mint = self.create_mint(payer, decimals).await;
+            if mint > other {
+                return mint;
+            }
+        }
+    }
+
+    async fn create_token_account(&mut self, mint: Pubkey, owner: Pubkey) -> Pubkey {
+        let mint_account = self.get_account(mint).await;
+        let token_program = mint_account.owner;
+        let mut account_extensions = vec![];
+        if token_program == spl_token_2022::ID {
+            let mint_extensions =
+                StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&mint_account.data)
+                    .unwrap()
+                    .get_extension_types()
+                    .unwrap();
+            account_extensions =
+                ExtensionType::get_required_init_account_extensions(&mint_extensions);
+            // accounts of non-transferable mints must have an immutable owner
+            account_extensions.push(ExtensionType::ImmutableOwner);
+        }
+        let len =
+            ExtensionType::get_account_len::<spl_token_2022::state::Account>(&account_extensions);
+
+        let account = Keypair::new();
+        let rent = self.ctx.banks_client.get_rent().await.unwrap();
+        let payer = self.payer();
+        let mut ixs = vec![system_instruction::create_account(
+            &payer,
+            &account.pubkey(),
+            rent.minimum_balance(len),
+            len as u64,
+            &token_program,
+        )];
+        if token_program == spl_token_2022::ID {
+            ixs.push(
+                spl_token_2022::instruction::initialize_immutable_owner(
+                    &token_program,
+                    &account.pubkey(),
+                )
+                .unwrap(),
+            );
+        }
+        ixs.push(
+            spl_token_2022::instruction::initialize_account(
+                &token_program,
+                &account.pubkey(),
+                &mint,
+                &owner,
+            )
+            .unwrap(),
+        );
+        self.process(&ixs, &[&account]).await.unwrap();
+        account.pubkey()
+    }
+
+    async fn mint_to(&mut self, mint: Pubkey, account: Pubkey, amount: u64) {
+        let payer = self.payer();
+        let token_program = self.token_program(mint).await;
+        self.process(
+            &[spl_token_2022::instruction::mint_to(
+                &token_program,
+                &mint,
+                &account,
+                &payer,
+                &[],
+                amount,
+            )
+            .unwrap()],
+            &[],
+        )
+        .await
+        .unwrap();
+    }
+
+    /// Creates a factory, and makes [TestEnv::admin] its admin.
+    async fn new_factory(&mut self) -> Pubkey {
+        let base = Keypair::new();
+        let (factory, bump) = pda::find_factory_address(&base.pubkey());
+        let payer = self.payer();
+        self.process(
+            &[instructions::new_factory(
+                cpamm::accounts::NewFactory {
+                    base: base.pubkey(),
+                    factory,
+                    payer,
+                    system_program: anchor_lang::solana_program::system_program::ID,
+                },
+                bump,
+            )],
+            &[&base],
+        )
+        .await
+        .unwrap();
+
+        // the factory admin is hardcoded, so replace it with a key we control
+        let mut account = self
+            .ctx
+            .banks_client
+            .get_account(factory)
+            .await
+            .unwrap()
+            .unwrap();
+        let mut factory_data = cpamm_client::accounts::decode_factory(&account.data).unwrap();
+        assert_eq!(factory_data.base, base.pubkey());
+        assert_eq!(factory_data.num_swaps, 0);
+        factory_data.admin = self.admin.pubkey();
+        let mut data: Vec<u8> = vec![];
+        factory_data.try_serialize(&mut data).unwrap();
+        account.data = data;
+        self.ctx
+            .set_account(&factory, &AccountSharedData::from(account));
+
+        factory
+    }
+
+    /// Creates the mints and token accounts of a swap with the given reserves.
+    async fn setup_new_swap(
+        &mut self,
+        mint_0: Pubkey,
+        mint_1: Pubkey,
+        amount_0: u64,
+        amount_1: u64,
+    ) -> NewSwapSetup {
+        let factory = self.new_factory().await;
+        self.setup_new_swap_in_tier(factory, mint_0, mint_1, amount_0, amount_1, FEE_TIER_KBPS)
+            .await
+    }
+
+    /// Creates the token accounts of a swap in a fee tier of an existing factory.
+    async fn setup_new_swap_in_tier(
+        &mut self,
+        factory: Pubkey,
+        mint_0: Pubkey,
+        mint_1: Pubkey,
+        amount_0: u64,
+        amount_1: u64,
+        fee_tier_kbps: u32,
+    ) -> NewSwapSetup {
+        let (swap, _) = pda::find_swap_address(&factory, &mint_0, &mint_1, fee_tier_kbps);
+
+        let pool_mint = self.create_mint(swap, 6).await;
+        let reserve_0 = self.create_token_account(mint_0, swap).await;
+        let reserve_1 = self.create_token_account(mint_1, swap).await;
+        let fees_0 = self.create_token_account(mint_0, swap).await;
+        let fees_1 = self.create_token_account(mint_1, swap).await;
+        if amount_0 > 0 {
+            self.mint_to(mint_0, reserve_0, amount_0).await;
+        }
+        if amount_1 > 0 {
+            self.mint_to(mint_1, reserve_1, amount_1).await;
+        }
+        let payer = self.payer();
+        let user_lp = self.create_token_account(pool_mint, payer).await;
+
+        NewSwapSetup {
+            factory,
+            swap,
+            mint_0,
+            mint_1,
+            pool_mint,
+            reserve_0,
+            reserve_1,
+            fees_0,
+            fees_1,
+            user_lp,
+        }
+    }
+
+    async fn new_sorted_mints(&mut self) -> (Pubkey, Pubkey) {
+        let payer = self.payer();
+        let mint_0 = self.create_mint(payer, 6).await;
+        let mint_1 = self.create_mint_after(mint_0, 6).await;
+        (mint_0, mint_1)
+    }
+
+    /// Creates a swap with the given reserves, and funds the user with tokens of both mints.
+    async fn new_swap_with_reserves(&mut self, amount_0: u64, amount_1: u64) -> TestSwap {
+        let (mint_0, mint_1) = self.new_sorted_mints().await;
+        self.new_swap_of_mints(mint_0, mint_1, amount_0, amount_1)
+            .await
+    }
+
+    /// Creates a swap of the given mints, and funds the user with tokens of both mints.
+    async fn new_swap_of_mints(
+        &mut self,
+        mint_0: Pubkey,
+        mint_1: Pubkey,
+        amount_0: u64,
+        amount_1: u64,
+    ) -> TestSwap {
+        let setup = self
+            .setup_new_swap(mint_0, mint_1, amount_0, amount_1)
+            .await;
+        let (_, bump) = pda::find_swap_address(&setup.factory, &mint_0, &mint_1, FEE_TIER_KBPS);
+        let payer = self.payer();
+        self.process(
+            &[instructions::new_swap(
+                setup.accounts(payer),
+                bump,
+                FEE_TIER_KBPS,
+            )],
+            &[],
+        )
+        .await
+        .unwrap();
+
+        let user_0 = self.create_token_account(mint_0, payer).await;
+        let user_1 = self.create_token_account(mint_1, payer).await;
+        self.mint_to(mint_0, user_0, INITIAL_RESERVE).await;
+        self.mint_to(mint_1, user_1, INITIAL_RESERVE).await;
+
+        let token_programs = [
+            self.token_program(mint_0).await,
+            self.token_program(mint_1).await,
+        ];
+
+        TestSwap {
+            swap: setup.swap,
+            factory: setup.factory,
+            mint_0,
+            mint_1,
+            token_programs,
+            pool_mint: setup.pool_mint,
+            reserve_0: setup.reserve_0,
+            reserve_1: setup.reserve_1,
+            fees_0: setup.fees_0,
+            fees_1: setup.fees_1,
+            user_0,
+            user_1,
+            user_lp: setup.user_lp,
+        }
+    }
+
+    async fn new_swap(&mut self) -> TestSwap {
+        self.new_swap_with_reserves(INITIAL_RESERVE, INITIAL_RESERVE)
+            .await
+    }
+
+    async fn factory(&mut self, factory: Pubkey) -> cpamm::Factory {
+        let data = self.get_account_data(factory).await;
+        cpamm_client::accounts::decode_factory(&data).unwrap()
+    }
+
+    /// Processes admin instructions of a factory, signed by the admin.
+    async fn process_factory_admin(
+        &mut self,
+        factory: Pubkey,
+        ix: impl FnOnce(cpamm::accounts::FactoryAdmin) -> Instruction,
+    ) -> std::result::Result<(), TransactionError> {
+        let accounts = cpamm::accounts::FactoryAdmin {
+            admin: self.admin.pubkey(),
+            factory,
+        };
+        let admin = Keypair::from_bytes(&self.admin.to_bytes()).unwrap();
+        self.process(&[ix(accounts)], &[&admin]).await
+    }
+
+    /// Creates the [cpamm::Observations] of the swap as the swap admin.
+    async fn new_observations(
+        &mut self,
+        swap: Pubkey,
+        min_interval_seconds: u32,
+    ) -> std::result::Result<(), TransactionError> {
+        let accounts = cpamm::accounts::NewObservations {
+            swap,
+            admin: self.admin.pubkey(),
+            observations: pda::find_observations_address(&swap).0,
+            payer: self.payer(),
+            system_program: anchor_lang::solana_program::system_program::ID,
+        };
+        let admin = Keypair::from_bytes(&self.admin.to_bytes()).unwrap();
+        self.process(
+            &[instructions::new_observations(
+                accounts,
+                min_interval_seconds,
+            )],
+            &[&admin],
+        )
+        .await
+    }
+
+    async fn set_paused(&mut self, test_swap: &TestSwap, paused: bool) {
+        let accounts = cpamm::accounts::SwapAdmin {
+            admin: self.admin.pubkey(),
+            swap: test_swap.swap,
+        };
+        let ix = if paused {
+            instructions::pause(accounts)
+        } else {
+            instructions::unpause(accounts)
+        };
+        let admin = Keypair::from_bytes(&self.admin.to_bytes()).unwrap();
+        self.process(&[ix], &[&admin]).await.unwrap();
+    }
+}
+
+impl TestSwap {
+    /// Accounts of a swap from token 0 to token 1.
+    fn swap_0_to_1(&self, swap_info: &SwapInfo, user_authority: Pubkey) -> cpamm::accounts::Swap {
+        client_accounts::swap_accounts(
+            self.swap,
+            swap_info,
+            self.token_programs,
+            user_authority,
+            self.mint_0,
+            self.user_0,
+            self.user_1,
+        )
+    }
+
+    fn deposit(&self, swap_info: &SwapInfo, user_authority: Pubkey) -> cpamm::accounts::Deposit {
+        client_accounts::deposit_accounts(
+            self.swap,
+            swap_info,
+            self.token_programs,
+            user_authority,
+            self.user_0,
+            self.user_1,
+            self.user_lp,
+        )
+    }
+
+    fn withdraw(&self, swap_info: &SwapInfo, user_authority: Pubkey) -> cpamm::accounts::Withdraw {
+        client_accounts::withdraw_accounts(
+            self.swap,
+            swap_info,
+            self.token_programs,
+            user_authority,
+            self.user_lp,
+            self.user_0,
+            self.user_1,
+        )
+    }
+}
+
+fn assert_error<E: Copy + std::fmt::Debug + Into<u32>>(
+    result: std::result::Result<(), TransactionError>,
+    error: E,
+) {
+    match result {
+        Err(TransactionError::InstructionError(_, InstructionError::Custom(code))) => {
+            assert_eq!(code, error.into(), "expected {:?}", error)
+        }
+        other => panic!("expected {:?}, got {:?}", error, other),
+    }
+}
+
+// --------------------------------
+// Factory and swap creation
+// --------------------------------
+
+#[test]
+fn test_swap_info_len() {
+    let mut data = vec![];
+    SwapInfo::default().try_serialize(&mut data).unwrap();
+    assert_eq!(data.len(), BASELINE_SWAP_INFO_LEN);
+}
+
+#[test]
+fn test_factory_len() {
+    let mut data = vec![];
+    cpamm::Factory::default().try_serialize(&mut data).unwrap();
+    assert_eq!(data.len(), BASELINE_FACTORY_LEN);
+}
+
+#[tokio::test]
+async fn test_new_swap() {
+    let mut env = TestEnv::new().await;
+    let test_swap = env.new_swap().await;
+
+    let swap_info = env.swap_info(test_swap.swap).await;
+    assert_eq!(swap_info.factory, test_swap.factory);
+    assert_eq!(swap_info.index, 0);
+    assert_eq!(swap_info.admin_key, env.admin.pubkey());
+    assert_eq!(swap_info.token_0.mint, test_swap.mint_0);
+    assert_eq!(swap_info.token_0.reserves, test_swap.reserve_0);
+    assert_eq!(swap_info.token_0.admin_fees, test_swap.fees_0);
+    assert_eq!(swap_info.token_1.mint, test_swap.mint_1);
+    assert_eq!(swap_info.token_1.reserves, test_swap.reserve_1);
+    assert_eq!(swap_info.token_1.admin_fees, test_swap.fees_1);
+    assert_eq!(swap_info.pool_mint, test_swap.pool_mint);
+    assert_eq!(swap_info.fees, cpamm::fees::INITIAL);
+    assert_eq!(swap_info.fee_tier_kbps, FEE_TIER_KBPS);
+    assert!(!swap_info.is_paused);
+    assert_eq!(
+        env.get_account_data(test_swap.swap).await.len(),
+        BASELINE_SWAP_INFO_LEN
+    );
+
+    // initial liquidity is the geometric mean of the reserves
+    assert_eq!(env.token_balance(test_swap.user_lp).await, INITIAL_RESERVE);
+
+    let factory = env.factory(test_swap.factory).await;
+    assert_eq!(factory.num_swaps, 1);
+    assert_eq!(factory.default_fees, cpamm::fees::INITIAL);
+    assert_eq!(factory.fee_tiers, cpamm::fees::INITIAL_FEE_TIERS);
+}
+
+#[tokio::test]
+async fn test_new_swap_meta() {
+    let mut env = TestEnv::new().await;
+    let test_swap = env.new_swap().await;
+
+    let (swap_meta, bump) = pda::find_swap_meta_address(&test_swap.factory, 0);
+    let payer = env.payer();
+    env.process(
+        &[instructions::new_swap_meta(
+            cpamm::accounts::NewSwapMeta {
+                swap: test_swap.swap,
+                swap_meta,
+                payer,
+                system_program: anchor_lang::solana_program::system_program::ID,
+            },
+            bump,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    let data = env.get_account_data(swap_meta).await;
+    let meta = cpamm_client::accounts::decode_swap_meta(&data).unwrap();
+    assert_eq!(meta.factory, test_swap.factory);
+    assert_eq!(meta.index, 0);
+    assert_eq!(meta.bump, bump);
+    assert_eq!(meta.swap, test_swap.swap);
+    assert_eq!(meta.created_by, payer);
+}
+
+#[tokio::test]
+async fn test_new_swap_zero_reserve() {
+    let mut env = TestEnv::new().await;
+    let (mint_0, mint_1) = env.new_sorted_mints().await;
+    let setup = env.setup_new_swap(mint_0, mint_1, INITIAL_RESERVE, 0).await;
+    let payer = env.payer();
+    let result = env
+        .process(
+            &[instructions::new_swap(
+                setup.accounts(payer),
+                0,
+                FEE_TIER_KBPS,
+            )],
+            &[],
+        )
+        .await;
+    assert_error(result, ErrorCode::NewSwapMustHaveNonZeroSupply);
+}
+
+#[tokio::test]
+async fn test_new_swap_initial_liquidity_too_low() {
+    let mut env = TestEnv::new().await;
+    let (mint_0, mint_1) = env.new_sorted_mints().await;
+    let setup = env.setup_new_swap(mint_0, mint_1, 10, 10).await;
+    let payer = env.payer();
+    let result = env
+        .process(
+            &[instructions::new_swap(
+                setup.accounts(payer),
+                0,
+                FEE_TIER_KBPS,
+            )],
+            &[],
+        )
+        .await;
+    assert_error(result, ErrorCode::InitialLiquidityTooLow);
+}
+
+#[tokio::test]
+async fn test_new_swap_tokens_not_sorted() {
+    let mut env = TestEnv::new().await;
+    let (mint_0, mint_1) = env.new_sorted_mints().await;
+    let setup = env
+        .setup_new_swap(mint_1, mint_0, INITIAL_RESERVE, INITIAL_RESERVE)
+        .await;
+    let payer = env.payer();
+    let result = env
+        .process(
+            &[instructions::new_swap(
+                setup.accounts(payer),
+                0,
+                FEE_TIER_KBPS,
+            )],
+            &[],
+        )
+        .await;
+    assert_error(result, ErrorCode::SwapTokensNotSorted);
+}
+
+#[tokio::test]
+async fn test_new_swap_tokens_equal() {
+    let mut env = TestEnv::new().await;
+    let payer = env.payer();
+    let mint = env.create_mint(payer, 6).await;
+    let setup = env
+        .setup_new_swap(mint, mint, INITIAL_RESERVE, INITIAL_RESERVE)
+        .await;
+    let result = env
+        .process(
+            &[instructions::new_swap(
+                setup.accounts(payer),
+                0,
+                FEE_TIER_KBPS,
+            )],
+            &[],
+        )
+        .await;
+    assert_error(result, ErrorCode::SwapTokensCannotBeEqual);
+}
+
+#[tokio::test]
+async fn test_new_swap_pool_mint_supply() {
+    let mut env = TestEnv::new().await;
+    let (mint_0, mint_1) = env.new_sorted_mints().await;
+    let mut setup = env
+        .setup_new_swap(mint_0, mint_1, INITIAL_RESERVE, INITIAL_RESERVE)
+        .await;
+
+    // replace the pool mint with one that already has supply
+    let payer = env.payer();
+    let pool_mint = env.create_mint(payer, 6).await;
+    let user_lp = env.create_token_account(pool_mint, payer).await;
+    env.mint_to(pool_mint, user_lp, 1).await;
+    let set_authority = |authority_type| {
+        spl_token::instruction::set_authority(
+            &spl_token::ID,
+            &pool_mint,
+            Some(&setup.swap),
+            authority_type,
+            &payer,
+            &[],
+        )
+        .unwrap()
+    };
+    env.process(
+        &[
+            set_authority(spl_token::instruction::AuthorityType::MintTokens),
+            set_authority(spl_token::instruction::AuthorityType::FreezeAccount),
+        ],
+        &[],
+    )
+    .await
+    .unwrap();
+    setup.pool_mint = pool_mint;
+    setup.user_lp = user_lp;
+
+    let result = env
+        .process(
+            &[instructions::new_swap(
+                setup.accounts(payer),
+                0,
+                FEE_TIER_KBPS,
+            )],
+            &[],
+        )
+        .await;
+    assert_error(result, ErrorCode::SwapPoolMintSupply);
+}
+
+#[tokio::test]
+async fn test_new_stable_swap_invalid_curve() {
+    let mut env = TestEnv::new().await;
+    let (mint_0, mint_1) = env.new_sorted_mints().await;
+    let setup = env
+        .setup_new_swap(mint_0, mint_1, INITIAL_RESERVE, INITIAL_RESERVE)
+        .await;
+    let payer = env.payer();
+    let result = env
+        .process(
+            &[instructions::new_stable_swap(
+                setup.accounts(payer),
+                0,
+                FEE_TIER_KBPS,
+                0,
+            )],
+            &[],
+        )
+        .await;
+    assert_error(result, ErrorCode::InvalidCurve);
+}
+
+#[tokio::test]
+async fn test_new_weighted_swap() {
+    let mut env = TestEnv::new().await;
+    let (mint_0, mint_1) = env.new_sorted_mints().await;
+    // an 80/20 pool where both tokens are worth the same
+    let reserve_0 = 4 * INITIAL_RESERVE;
+    let setup = env
+        .setup_new_swap(mint_0, mint_1, reserve_0, INITIAL_RESERVE)
+        .await;
+    let payer = env.payer();
+    env.process(
+        &[instructions::new_weighted_swap(
+            setup.accounts(payer),
+            0,
+            FEE_TIER_KBPS,
+            8_000,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    let swap_info = env.swap_info(setup.swap).await;
+    assert_eq!(swap_info.curve, SwapCurve::weighted_product(8_000));
+    let curve = xyk::WeightedProduct::new(8_000).unwrap();
+    assert_eq!(
+        env.token_balance(setup.user_lp).await,
+        curve
+            .calculate_initial_swap_pool_amount(reserve_0, INITIAL_RESERVE)
+            .unwrap()
+    );
+
+    let user_0 = env.create_token_account(mint_0, payer).await;
+    let user_1 = env.create_token_account(mint_1, payer).await;
+    env.mint_to(mint_0, user_0, INITIAL_RESERVE).await;
+    env.mint_to(mint_1, user_1, INITIAL_RESERVE).await;
+
+    // the weights apply in both directions
+    let amount_in = 1_000_000;
+    let quote_0 = cpamm_client::quote::quote_swap(&swap_info, mint_0, amount_in).unwrap();
+    let quote_1 = cpamm_client::quote::quote_swap(&swap_info, mint_1, amount_in).unwrap();
+    assert!(quote_0.amount_out > 990_000 && quote_0.amount_out < amount_in);
+    assert!(quote_1.amount_out > 990_000 && quote_1.amount_out < amount_in);
+
+    let accounts = client_accounts::swap_accounts(
+        setup.swap,
+        &swap_info,
+        [anchor_spl::token::ID; 2],
+        payer,
+        mint_0,
+        user_0,
+        user_1,
+    );
+    env.process(
+        &[instructions::swap(accounts, amount_in, quote_0.amount_out)],
+        &[],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        env.token_balance(user_1).await,
+        INITIAL_RESERVE + quote_0.amount_out
+    );
+    assert_eq!(
+        env.token_balance(setup.reserve_0).await,
+        reserve_0 + amount_in
+    );
+
+    let swap_info = env.swap_info(setup.swap).await;
+    let quote = cpamm_client::quote::quote_swap(&swap_info, mint_1, amount_in).unwrap();
+    let accounts = client_accounts::swap_accounts(
+        setup.swap,
+        &swap_info,
+        [anchor_spl::token::ID; 2],
+        payer,
+        mint_1,
+        user_1,
+        user_0,
+    );
+    env.process(
+        &[instructions::swap(accounts, amount_in, quote.amount_out)],
+        &[],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        env.token_balance(user_0).await,
+        INITIAL_RESERVE - amount_in + quote.amount_out
+    );
+}
+
+#[tokio::test]
+async fn test_new_weighted_swap_invalid_curve() {
+    let mut env = TestEnv::new().await;
+    let (mint_0, mint_1) = env.new_sorted_mints().await;
+    let setup = env
+        .setup_new_swap(mint_0, mint_1, INITIAL_RESERVE, INITIAL_RESERVE)
+        .await;
+    let payer = env.payer();
+    for weight_0 in [0, xyk::MIN_WEIGHT - 1, xyk::MAX_WEIGHT + 1] {
+        let result = env
+            .process(
+                &[instructions::new_weighted_swap(
+                    setup.accounts(payer),
+                    0,
+                    FEE_TIER_KBPS,
+                    weight_0,
+                )],
+                &[],
+            )
+            .await;
+        assert_error(result, ErrorCode::InvalidCurve);
+    }
+}
+
+#[tokio::test]
+async fn test_new_swap_fee_tiers() {
+    let mut env = TestEnv::new().await;
+    let factory = env.new_factory().await;
+    let (mint_0, mint_1) = env.new_sorted_mints().await;
+    let payer = env.payer();
+
+    // the same pair may have a swap in each fee tier
+    let mut swaps = vec![];
+    for fee_tier_kbps in [1_000, 100_000] {
+        let setup = env
+            .setup_new_swap_in_tier(
+                factory,
+                mint_0,
+                mint_1,
+                INITIAL_RESERVE,
+                INITIAL_RESERVE,
+                fee_tier_kbps,
+            )
+            .await;
+        env.process(
+            &[instructions::new_swap(
+                setup.accounts(payer),
+                0,
+                fee_tier_kbps,
+            )],
+            &[],
+        )
+        .await
+        .unwrap();
+
+        let swap_info = env.swap_info(setup.swap).await;
+        assert_eq!(swap_info.fee_tier_kbps, fee_tier_kbps);
+        assert_eq!(
+            swap_info.fees,
+            cpamm::SwapFees {
+                trade_fee_kbps: fee_tier_kbps.into(),
+                ..cpamm::fees::INITIAL
+            }
+        );
+        swaps.push(setup.swap);
+    }
+    assert_ne!(swaps[0], swaps[1]);
+    assert_eq!(env.factory(factory).await.num_swaps, 2);
+
+    // swaps sign with the fee tier in their seeds
+    let swap_info = env.swap_info(swaps[1]).await;
+    let user_0 = env.create_token_account(mint_0, payer).await;
+    let user_1 = env.create_token_account(mint_1, payer).await;
+    env.mint_to(mint_0, user_0, INITIAL_RESERVE).await;
+    let amount_in = 1_000_000;
+    let quote = cpamm_client::quote::quote_swap(&swap_info, mint_0, amount_in).unwrap();
+    let accounts = client_accounts::swap_accounts(
+        swaps[1],
+        &swap_info,
+        [anchor_spl::token::ID; 2],
+        payer,
+        mint_0,
+        user_0,
+        user_1,
+    );
+    env.process(
+        &[instructions::swap(accounts, amount_in, quote.amount_out)],
+        &[],
+    )
+    .await
+    .unwrap();
+    assert_eq!(env.token_balance(user_1).await, quote.amount_out);
+    // 1% of the output
+    assert_eq!(
+        quote.trade_fee(),
+        (quote.amount_out + quote.trade_fee()) / 100
+    );
+}
+
+#[tokio::test]
+async fn test_swap_without_fee_tier() {
+    let mut env = TestEnv::new().await;
+    let factory = env.new_factory().await;
+    let (mint_0, mint_1) = env.new_sorted_mints().await;
+    let payer = env.payer();
+
+    // swaps created before fee tiers were derived without a fee tier seed
+    let setup = env
+        .setup_new_swap_in_tier(factory, mint_0, mint_1, INITIAL_RESERVE, INITIAL_RESERVE, 0)
+        .await;
+    let (swap, bump) = pda::find_swap_address(&factory, &mint_0, &mint_1, 0);
+    assert_eq!(setup.swap, swap);
+    assert_eq!(
+        swap,
+        Pubkey::create_program_address(
+            &[
+                b"SwapInfo",
+                factory.as_ref(),
+                mint_0.as_ref(),
+                mint_1.as_ref(),
+                &[bump],
+            ],
+            &cpamm::ID,
+        )
+        .unwrap()
+    );
+
+    let swap_info = SwapInfo {
+        factory,
+        bump,
+        admin_key: env.admin.pubkey(),
+        token_0: cpamm::SwapTokenInfo {
+            reserves: setup.reserve_0,
+            mint: mint_0,
+            admin_fees: setup.fees_0,
+        },
+        token_1: cpamm::SwapTokenInfo {
+            reserves: setup.reserve_1,
+            mint: mint_1,
+            admin_fees: setup.fees_1,
+        },
+        pool_mint: setup.pool_mint,
+        fees: cpamm::fees::INITIAL,
+        price_info: cpamm::SwapPriceInfo {
+            last_update_ts: env.unix_timestamp().await,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut data: Vec<u8> = vec![];
+    swap_info.try_serialize(&mut data).unwrap();
+    let rent = env.ctx.banks_client.get_rent().await.unwrap();
+    env.ctx.set_account(
+        &swap,
+        &AccountSharedData::from(solana_sdk::account::Account {
+            lamports: rent.minimum_balance(data.len()),
+            data,
+            owner: cpamm::ID,
+            executable: false,
+            rent_epoch: 0,
+        }),
+    );
+
+    // the swap signs for transfers out of its reserves and fees
+    let user_0 = env.create_token_account(mint_0, payer).await;
+    let user_1 = env.create_token_account(mint_1, payer).await;
+    env.mint_to(mint_0, user_0, INITIAL_RESERVE).await;
+    let token_programs = [anchor_spl::token::ID; 2];
+    let accounts = client_accounts::swap_accounts(
+        swap,
+        &swap_info,
+        token_programs,
+        payer,
+        mint_0,
+        user_0,
+        user_1,
+    );
+    env.process(&[instructions::swap(accounts, 1_000_000, 1)], &[])
+        .await
+        .unwrap();
+    let amount_out = env.token_balance(user_1).await;
+    assert!(amount_out > 0);
+    let admin_fees = env.token_balance(setup.fees_1).await;
+    assert!(admin_fees > 0);
+
+    let admin = Keypair::from_bytes(&env.admin.to_bytes()).unwrap();
+    env.process(
+        &[instructions::claim_admin_fees(
+            client_accounts::claim_admin_fees_accounts(
+                swap,
+                &swap_info,
+                token_programs,
+                admin.pubkey(),
+                [user_0, user_1],
+            ),
+        )],
+        &[&admin],
+    )
+    .await
+    .unwrap();
+    assert_eq!(env.token_balance(setup.fees_1).await, 0);
+    assert_eq!(env.token_balance(user_1).await, amount_out + admin_fees);
+}
+
+#[tokio::test]
+async fn test_new_swap_invalid_fee_tier() {
+    let mut env = TestEnv::new().await;
+    let factory = env.new_factory().await;
+    let (mint_0, mint_1) = env.new_sorted_mints().await;
+    let setup = env
+        .setup_new_swap_in_tier(
+            factory,
+            mint_0,
+            mint_1,
+            INITIAL_RESERVE,
+            INITIAL_RESERVE,
+            2_500,
+        )
+        .await;
+    let payer = env.payer();
+    let result = env
+        .process(
+            &[instructions::new_swap(setup.accounts(payer), 0, 2_500)],
+            &[],
+        )
+        .await;
+    assert_error(result, ErrorCode::InvalidFeeTier);
+}
+
+#[tokio::test]
+async fn test_set_fee_tiers_and_default_fees() {
+    let mut env = TestEnv::new().await;
+    let factory = env.new_factory().await;
+
+    let fees = cpamm::SwapFees {
+        trade_fee_kbps: 0,
+        withdraw_fee_kbps: 1_000,
+        admin_trade_fee_kbps: 0,
+        admin_withdraw_fee_kbps: 0,
+    };
+    env.process_factory_admin(factory, |accounts| {
+        instructions::set_default_fees(accounts, fees)
+    })
+    .await
+    .unwrap();
+    env.process_factory_admin(factory, |accounts| {
+        instructions::set_fee_tiers(accounts, vec![2_500, 40_000])
+    })
+    .await
+    .unwrap();
+    let factory_data = env.factory(factory).await;
+    assert_eq!(factory_data.default_fees, fees);
+    assert_eq!(factory_data.fee_tiers, [2_500, 40_000, 0, 0, 0, 0, 0, 0]);
+
+    // new swaps use the default fees and the trade fee of their tier
+    let (mint_0, mint_1) = env.new_sorted_mints().await;
+    let setup = env
+        .setup_new_swap_in_tier(
+            factory,
+            mint_0,
+            mint_1,
+            INITIAL_RESERVE,
+            INITIAL_RESERVE,
+            2_500,
+        )
+        .await;
+    let payer = env.payer();
+    env.process(
+        &[instructions::new_swap(setup.accounts(payer), 0, 2_500)],
+        &[],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        env.swap_info(setup.swap).await.fees,
+        cpamm::SwapFees {
+            trade_fee_kbps: 2_500,
+            ..fees
+        }
+    );
+
+    // removed tiers may no longer be used
+    let setup = env
+        .setup_new_swap_in_tier(
+            factory,
+            mint_0,
+            mint_1,
+            INITIAL_RESERVE,
+            INITIAL_RESERVE,
+            FEE_TIER_KBPS,
+        )
+        .await;
+    let result = env
+        .process(
+            &[instructions::new_swap(
+                setup.accounts(payer),
+                0,
+                FEE_TIER_KBPS,
+            )],
+            &[],
+        )
+        .await;
+    assert_error(result, ErrorCode::InvalidFeeTier);
+}
+
+#[tokio::test]
+async fn test_new_swap_legacy_factory() {
+    let mut env = TestEnv::new().await;
+    let factory = env.new_factory().await;
+
+    // a factory created before fee tiers has zeros where they are now stored
+    let mut account = env
+        .ctx
+        .banks_client
+        .get_account(factory)
+        .await
+        .unwrap()
+        .unwrap();
+    let factory_data = env.factory(factory).await;
+    let mut data = cpamm::Factory::discriminator().to_vec();
+    data.extend(factory_data.base.as_ref());
+    data.push(factory_data.bump);
+    data.extend(factory_data.num_swaps.to_le_bytes());
+    data.extend(factory_data.admin.as_ref());
+    data.extend([0; 128]);
+    assert_eq!(data.len(), BASELINE_FACTORY_LEN);
+    account.data = data;
+    env.ctx
+        .set_account(&factory, &AccountSharedData::from(account));
+    let factory_data = env.factory(factory).await;
+    assert_eq!(factory_data.fee_tiers, [0; cpamm::MAX_FEE_TIERS]);
+    assert_eq!(factory_data.default_fees, cpamm::SwapFees::default());
+
+    // so it uses the initial fee tiers and fees
+    let (mint_0, mint_1) = env.new_sorted_mints().await;
+    let setup = env
+        .setup_new_swap_in_tier(
+            factory,
+            mint_0,
+            mint_1,
+            INITIAL_RESERVE,
+            INITIAL_RESERVE,
+            FEE_TIER_KBPS,
+        )
+        .await;
+    let payer = env.payer();
+    env.process(
+        &[instructions::new_swap(
+            setup.accounts(payer),
+            0,
+            FEE_TIER_KBPS,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+    assert_eq!(env.swap_info(setup.swap).await.fees, cpamm::fees::INITIAL);
+
+    // fee tiers which were never enabled on the factory are still rejected
+    let setup = env
+        .setup_new_swap_in_tier(
+            factory,
+            mint_0,
+            mint_1,
+            INITIAL_RESERVE,
+            INITIAL_RESERVE,
+            2_500,
+        )
+        .await;
+    let result = env
+        .process(
+            &[instructions::new_swap(setup.accounts(payer), 0, 2_500)],
+            &[],
+        )
+        .await;
+    assert_error(result, ErrorCode::InvalidFeeTier);
+}
+
+#[tokio::test]
+async fn test_set_fee_tiers_invalid() {
+    let mut env = TestEnv::new().await;
+    let factory = env.new_factory().await;
+
+    for fee_tiers in [
+        vec![],
+        vec![0],
+        vec![1_000, 1_000],
+        vec![cpamm::fees::KBPS_PER_WHOLE as u32 + 1],
+        (1..=cpamm::MAX_FEE_TIERS as u32 + 1).collect(),
+    ] {
+        let result = env
+            .process_factory_admin(factory, |accounts| {
+                instructions::set_fee_tiers(accounts, fee_tiers)
+            })
+            .await;
+        assert_error(result, ErrorCode::InvalidFeeTier);
+    }
+
+    let result = env
+        .process_factory_admin(factory, |accounts| {
+            instructions::set_default_fees(
+                accounts,
+                cpamm::SwapFees {
+                    admin_trade_fee_kbps: cpamm::fees::KBPS_PER_WHOLE + 1,
+                    ..cpamm::fees::INITIAL
+                },
+            )
+        })
+        .await;
+    assert_error(result, ErrorCode::InvalidFee);
+
+    // only the admin may configure the factory
+    let payer = env.payer();
+    let result = env
+        .process(
+            &[instructions::set_fee_tiers(
+                cpamm::accounts::FactoryAdmin {
+                    admin: payer,
+                    factory,
+                },
+                vec![1_000],
+            )],
+            &[],
+        )
+        .await;
+    assert_error(result, ErrorCode::Unauthorized);
+}
+
+#[tokio::test]
+async fn test_new_swap_restricted_creators() {
+    let mut env = TestEnv::new().await;
+    let factory = env.new_factory().await;
+    let (mint_0, mint_1) = env.new_sorted_mints().await;
+    let payer = env.payer();
+    let policy = cpamm::FactoryPolicy {
+        restrict_creators: true,
+        require_no_freeze_authority: false,
+    };
+    env.process_factory_admin(factory, |accounts| {
+        instructions::set_factory_policy(accounts, policy)
+    })
+    .await
+    .unwrap();
+    assert_eq!(env.factory(factory).await.policy, policy);
+
+    let setup = env
+        .setup_new_swap_in_tier(
+            factory,
+            mint_0,
+            mint_1,
+            INITIAL_RESERVE,
+            INITIAL_RESERVE,
+            FEE_TIER_KBPS,
+        )
+        .await;
+    let result = env
+        .process(
+            &[instructions::new_swap(
+                setup.accounts(payer),
+                0,
+                FEE_TIER_KBPS,
+            )],
+            &[],
+        )
+        .await;
+    assert_error(result, ErrorCode::PoolCreatorNotAllowed);
+
+    // only the admin may allow pool creators
+    let (pool_creator, bump) = pda::find_pool_creator_address(&factory, &payer);
+    let add_pool_creator = |factory_admin| cpamm::accounts::AddPoolCreator {
+        factory_admin,
+        creator: payer,
+        pool_creator,
+        payer,
+        system_program: anchor_lang::solana_program::system_program::ID,
+    };
+    let result = env
+        .process(
+            &[instructions::add_pool_creator(
+                add_pool_creator(cpamm::accounts::FactoryAdmin {
+                    admin: payer,
+                    factory,
+                }),
+                bump,
+            )],
+            &[],
+        )
+        .await;
+    assert_error(result, ErrorCode::Unauthorized);
+
+    env.process_factory_admin(factory, |accounts| {
+        instructions::add_pool_creator(add_pool_creator(accounts), bump)
+    })
+    .await
+    .unwrap();
+    let data = env.get_account_data(pool_creator).await;
+    let pool_creator_info = cpamm_client::accounts::decode_pool_creator(&data).unwrap();
+    assert_eq!(pool_creator_info.factory, factory);
+    assert_eq!(pool_creator_info.creator, payer);
+    assert_eq!(pool_creator_info.bump, bump);
+
+    env.process(
+        &[instructions::new_swap(
+            setup.accounts(payer),
+            0,
+            FEE_TIER_KBPS,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    // removed creators may no longer create swaps
+    env.process_factory_admin(factory, |factory_admin| {
+        instructions::remove_pool_creator(cpamm::accounts::RemovePoolCreator {
+            factory_admin,
+            pool_creator,
+            recipient: payer,
+        })
+    })
+    .await
+    .unwrap();
+    assert!(env
+        .ctx
+        .banks_client
+        .get_account(pool_creator)
+        .await
+        .unwrap()
+        .is_none());
+
+    let setup = env
+        .setup_new_swap_in_tier(
+            factory,
+            mint_0,
+            mint_1,
+            INITIAL_RESERVE,
+            INITIAL_RESERVE,
+            1_000,
+        )
+        .await;
+    let result = env
+        .process(
+            &[instructions::new_swap(setup.accounts(payer), 0, 1_000)],
+            &[],
+        )
+        .await;
+    assert_error(result, ErrorCode::PoolCreatorNotAllowed);
+}
+
+#[tokio::test]
+async fn test_new_swap_denied_mint() {
+    let mut env = TestEnv::new().await;
+    let factory = env.new_factory().await;
+    let (mint_0, mint_1) = env.new_sorted_mints().await;
+    let payer = env.payer();
+
+    let (denied_mint, bump) = pda::find_denied_mint_address(&factory, &mint_1);
+    env.process_factory_admin(factory, |factory_admin| {
+        instructions::deny_mint(
+            cpamm::accounts::DenyMint {
+                factory_admin,
+                mint: mint_1,
+                denied_mint,
+                payer,
+                system_program: anchor_lang::solana_program::system_program::ID,
+            },
+            bump,
+        )
+    })
+    .await
+    .unwrap();
+    let data = env.get_account_data(denied_mint).await;
+    let denied_mint_info = cpamm_client::accounts::decode_denied_mint(&data).unwrap();
+    assert_eq!(denied_mint_info.factory, factory);
+    assert_eq!(denied_mint_info.mint, mint_1);
+
+    let setup = env
+        .setup_new_swap_in_tier(
+            factory,
+            mint_0,
+            mint_1,
+            INITIAL_RESERVE,
+            INITIAL_RESERVE,
+            FEE_TIER_KBPS,
+        )
+        .await;
+    let result = env
+        .process(
+            &[instructions::new_swap(
+                setup.accounts(payer),
+                0,
+                FEE_TIER_KBPS,
+            )],
+            &[],
+        )
+        .await;
+    assert_error(result, ErrorCode::MintDenied);
+
+    // the denied mint account cannot be substituted
+    let mut accounts = setup.accounts(payer);
+    accounts.token_1.denied_mint = Pubkey::new_unique();
+    let result = env
+        .process(&[instructions::new_swap(accounts, 0, FEE_TIER_KBPS)], &[])
+        .await;
+    assert!(result.is_err());
+
+    env.process_factory_admin(factory, |factory_admin| {
+        instructions::allow_mint(cpamm::accounts::AllowMint {
+            factory_admin,
+            denied_mint,
+            recipient: payer,
+        })
+    })
+    .await
+    .unwrap();
+    env.process(
+        &[instructions::new_swap(
+            setup.accounts(payer),
+            0,
+            FEE_TIER_KBPS,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn test_new_swap_freeze_authority() {
+    let mut env = TestEnv::new().await;
+    let factory = env.new_factory().await;
+    let (mint_0, mint_1) = env.new_sorted_mints().await;
+    let payer = env.payer();
+    env.process_factory_admin(factory, |accounts| {
+        instructions::set_factory_policy(
+            accounts,
+            cpamm::FactoryPolicy {
+                restrict_creators: false,
+                require_no_freeze_authority: true,
+            },
+        )
+    })
+    .await
+    .unwrap();
+
+    let setup = env
+        .setup_new_swap_in_tier(
+            factory,
+            mint_0,
+            mint_1,
+            INITIAL_RESERVE,
+            INITIAL_RESERVE,
+            FEE_TIER_KBPS,
+        )
+        .await;
+    let result = env
+        .process(
+            &[instructions::new_swap(
+                setup.accounts(payer),
+                0,
+                FEE_TIER_KBPS,
+            )],
+            &[],
+        )
+        .await;
+    assert_error(result, ErrorCode::MintHasFreezeAuthority);
+
+    for mint in [mint_0, mint_1] {
+        env.process(
+            &[spl_token::instruction::set_authority(
+                &spl_token::ID,
+                &mint,
+                None,
+                spl_token::instruction::AuthorityType::FreezeAccount,
+                &payer,
+                &[],
+            )
+            .unwrap()],
+            &[],
+        )
+        .await
+        .unwrap();
+    }
+    env.process(
+        &[instructions::new_swap(
+            setup.accounts(payer),
+            0,
+            FEE_TIER_KBPS,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+}
+
+// --------------------------------
+// Swaps
+// --------------------------------
+
+#[tokio::test]
+async fn test_swap() {
+    let mut env = TestEnv::new().await;
+    let test_swap = env.new_swap().await;
+    let swap_info = env.swap_info(test_swap.swap).await;
+    let payer = env.payer();
+
+    let amount_in = 1_000_000;
+    let quote =
+        cpamm_client::quote::quote_swap(&swap_info, swap_info.token_0.mint, amount_in).unwrap();
+    env.process(
+        &[instructions::swap(
+            test_swap.swap_0_to_1(&swap_info, payer),
+            amount_in,
+            quote.amount_out,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(
+        env.token_balance(test_swap.user_0).await,
+        INITIAL_RESERVE - quote.amount_in
+    );
+    assert_eq!(
+        env.token_balance(test_swap.user_1).await,
+        INITIAL_RESERVE + quote.amount_out
+    );
+    assert_eq!(env.token_balance(test_swap.fees_1).await, quote.admin_fee);
+    assert_eq!(
+        env.token_balance(test_swap.reserve_1).await,
+        INITIAL_RESERVE - quote.amount_out - quote.admin_fee
+    );
+
+    let swap_info = env.swap_info(test_swap.swap).await;
+    assert_eq!(
+        swap_info.cumulative_stats.token_0.total_input_volume,
+        u128::from(quote.amount_in)
+    );
+    assert_eq!(
+        swap_info.cumulative_stats.token_1.total_trade_fees,
+        quote.trade_fee()
+    );
+}
+
+#[tokio::test]
+async fn test_swap_with_referrer() {
+    let mut env = TestEnv::new().await;
+    let test_swap = env.new_swap().await;
+    let admin = Keypair::from_bytes(&env.admin.to_bytes()).unwrap();
+    let payer = env.payer();
+
+    // only the admin may set the referral fee
+    let set_referral_fee = |admin, referral_fee_kbps| {
+        instructions::set_referral_fee(
+            cpamm::accounts::SwapAdmin {
+                admin,
+                swap: test_swap.swap,
+            },
+            referral_fee_kbps,
+        )
+    };
+    let result = env
+        .process(&[set_referral_fee(payer, 2_500_000)], &[])
+        .await;
+    assert_error(result, ErrorCode::Unauthorized);
+    let result = env
+        .process(
+            &[set_referral_fee(
+                admin.pubkey(),
+                cpamm::fees::KBPS_PER_WHOLE as u32 + 1,
+            )],
+            &[&admin],
+        )
+        .await;
+    assert_error(result, ErrorCode::InvalidFee);
+    env.process(&[set_referral_fee(admin.pubkey(), 2_500_000)], &[&admin])
+        .await
+        .unwrap();
+
+    let swap_info = env.swap_info(test_swap.swap).await;
+    assert_eq!(swap_info.referral_fee_kbps, 2_500_000);
+    // the fees of the swap are unchanged
+    assert_eq!(swap_info.fees, cpamm::fees::INITIAL);
+
+    let amount_in = 1_000_000;
+    let quote =
+        cpamm_client::quote::quote_swap(&swap_info, swap_info.token_0.mint, amount_in).unwrap();
+    let referrer = env
+        .create_token_account(test_swap.mint_1, Pubkey::new_unique())
+        .await;
+    let mut ix = instructions::swap(
+        test_swap.swap_0_to_1(&swap_info, payer),
+        amount_in,
+        quote.amount_out,
+    );
+    ix.accounts.push(AccountMeta::new(referrer, false));
+    env.process(&[ix], &[]).await.unwrap();
+
+    // the referrer receives a quarter of the admin trade fee
+    let referral_fee = quote.admin_fee / 4;
+    assert!(referral_fee > 0);
+    assert_eq!(env.token_balance(referrer).await, referral_fee);
+    assert_eq!(
+        env.token_balance(test_swap.fees_1).await,
+        quote.admin_fee - referral_fee
+    );
+    assert_eq!(
+        env.token_balance(test_swap.user_1).await,
+        INITIAL_RESERVE + quote.amount_out
+    );
+}
+
+#[tokio::test]
+async fn test_swap_max() {
+    let mut env = TestEnv::new().await;
+    let test_swap = env.new_swap().await;
+    let swap_info = env.swap_info(test_swap.swap).await;
+    let payer = env.payer();
+
+    env.process(
+        &[instructions::swap_max(
+            test_swap.swap_0_to_1(&swap_info, payer),
+            0,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    // the entire balance is swapped
+    assert_eq!(env.token_balance(test_swap.user_0).await, 0);
+    assert_eq!(
+        env.token_balance(test_swap.reserve_0).await,
+        2 * INITIAL_RESERVE
+    );
+}
+
+#[tokio::test]
+async fn test_swap_exceeded_slippage() {
+    let mut env = TestEnv::new().await;
+    let test_swap = env.new_swap().await;
+    let swap_info = env.swap_info(test_swap.swap).await;
+    let payer = env.payer();
+
+    let result = env
+        .process(
+            &[instructions::swap(
+                test_swap.swap_0_to_1(&swap_info, payer),
+                1_000_000,
+                1_000_000,
+            )],
+            &[],
+        )
+        .await;
+    assert_error(result, ErrorCode::ExceededSlippage);
+
+    let result = env
+        .process(
+            &[instructions::swap_max(
+                test_swap.swap_0_to_1(&swap_info, payer),
+                INITIAL_RESERVE,
+            )],
+            &[],
+        )
+        .await;
+    assert_error(result, ErrorCode::ExceededSlippage);
+}
+
+#[tokio::test]
+async fn test_swap_dynamic_fee() {
+    let mut env = TestEnv::new().await;
+    let test_swap = env.new_swap().await;
+    let admin = Keypair::from_bytes(&env.admin.to_bytes()).unwrap();
+    let payer = env.payer();
+
+    let max_trade_fee_kbps = 1_000_000;
+    env.process(
+        &[instructions::set_dynamic_fee(
+            cpamm::accounts::SwapAdmin {
+                admin: admin.pubkey(),
+                swap: test_swap.swap,
+            },
+            max_trade_fee_kbps,
+            5_000_000,
+        )],
+        &[&admin],
+    )
+    .await
+    .unwrap();
+
+    // a large swap moves the price away from its average
+    let swap_info = env.swap_info(test_swap.swap).await;
+    env.process(
+        &[instructions::swap(
+            test_swap.swap_0_to_1(&swap_info, payer),
+            INITIAL_RESERVE / 10,
+            0,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    let swap_info = env.swap_info(test_swap.swap).await;
+    let now = env.unix_timestamp().await;
+    let amount_in = 1_000_000;
+    let reserves = swap_info.expected_reserves;
+    let swap_result = xyk::swap(amount_in, reserves.amount_0, reserves.amount_1).unwrap();
+    let trade_fees = swap_info
+        .trade_fees_at(reserves.after_swap(true, &swap_result).unwrap(), now)
+        .unwrap();
+    assert!(trade_fees.trade_fee_kbps > swap_info.fees.trade_fee_kbps);
+    assert!(trade_fees.trade_fee_kbps <= max_trade_fee_kbps.into());
+
+    // so the next swap pays more than the base trade fee
+    let base_quote =
+        cpamm_client::quote::quote_swap(&swap_info, swap_info.token_0.mint, amount_in).unwrap();
+    let max_fee_quote = cpamm_client::quote::quote_swap(
+        &SwapInfo {
+            fees: cpamm::SwapFees {
+                trade_fee_kbps: max_trade_fee_kbps.into(),
+                ..swap_info.fees
+            },
+            ..swap_info
+        },
+        swap_info.token_0.mint,
+        amount_in,
+    )
+    .unwrap();
+    let balance_before = env.token_balance(test_swap.user_1).await;
+    env.process(
+        &[instructions::swap(
+            test_swap.swap_0_to_1(&swap_info, payer),
+            amount_in,
+            0,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+    let amount_out = env.token_balance(test_swap.user_1).await - balance_before;
+    assert!(amount_out < base_quote.amount_out);
+    assert!(amount_out > max_fee_quote.amount_out);
+}
+
+#[tokio::test]
+async fn test_swap_dynamic_fee_single_large_swap() {
+    let mut env = TestEnv::new().await;
+    let test_swap = env.new_swap().await;
+    let admin = Keypair::from_bytes(&env.admin.to_bytes()).unwrap();
+    let payer = env.payer();
+
+    env.process(
+        &[instructions::set_dynamic_fee(
+            cpamm::accounts::SwapAdmin {
+                admin: admin.pubkey(),
+                swap: test_swap.swap,
+            },
+            1_000_000,
+            5_000_000,
+        )],
+        &[&admin],
+    )
+    .await
+    .unwrap();
+
+    // the price has not moved before the swap, but the swap itself moves it
+    let swap_info = env.swap_info(test_swap.swap).await;
+    let amount_in = INITIAL_RESERVE / 10;
+    let base_quote =
+        cpamm_client::quote::quote_swap(&swap_info, swap_info.token_0.mint, amount_in).unwrap();
+    let balance_before = env.token_balance(test_swap.user_1).await;
+    env.process(
+        &[instructions::swap(
+            test_swap.swap_0_to_1(&swap_info, payer),
+            amount_in,
+            0,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+    let amount_out = env.token_balance(test_swap.user_1).await - balance_before;
+    assert!(amount_out < base_quote.amount_out);
+}
+
+#[tokio::test]
+async fn test_deposit_single_dynamic_fee() {
+    let mut env = TestEnv::new().await;
+    let test_swap = env.new_swap().await;
+    let admin = Keypair::from_bytes(&env.admin.to_bytes()).unwrap();
+    let payer = env.payer();
+
+    let max_trade_fee_kbps = 1_000_000;
+    env.process(
+        &[instructions::set_dynamic_fee(
+            cpamm::accounts::SwapAdmin {
+                admin: admin.pubkey(),
+                swap: test_swap.swap,
+            },
+            max_trade_fee_kbps,
+            5_000_000,
+        )],
+        &[&admin],
+    )
+    .await
+    .unwrap();
+
+    // a large swap moves the price away from its average
+    let swap_info = env.swap_info(test_swap.swap).await;
+    env.process(
+        &[instructions::swap(
+            test_swap.swap_0_to_1(&swap_info, payer),
+            INITIAL_RESERVE / 10,
+            0,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    // so the implied swap of a single token deposit pays more than the base trade fee
+    let swap_info = env.swap_info(test_swap.swap).await;
+    let source_amount = 1_000_000;
+    let half_source_amount = source_amount / 2;
+    let pool_mint_supply = env.mint_supply(test_swap.pool_mint).await;
+    let pool_token_amount = |trade_fee_kbps: u64| {
+        let fees = cpamm::SwapFees {
+            trade_fee_kbps,
+            ..swap_info.fees
+        };
+        let (trade_fee, _) = fees.compute_trade_fees(half_source_amount).unwrap();
+        xyk::deposit_single_token_type(
+            source_amount - trade_fee,
+            swap_info.expected_reserves.amount_0,
+            pool_mint_supply,
+        )
+        .unwrap()
+    };
+    let base_amount = pool_token_amount(swap_info.fees.trade_fee_kbps);
+    let max_fee_amount = pool_token_amount(max_trade_fee_kbps.into());
+
+    let balance_before = env.token_balance(test_swap.user_lp).await;
+    env.process(
+        &[instructions::deposit_single_token_exact_in(
+            cpamm::accounts::DepositSingle {
+                user: client_accounts::user_context(test_swap.swap, payer),
+                input: cpamm::accounts::SwapTokenWithFees {
+                    user: test_swap.user_0,
+                    reserve: test_swap.reserve_0,
+                    fees: test_swap.fees_0,
+                    mint: test_swap.mint_0,
+                    token_program: test_swap.token_programs[0],
+                },
+                other_reserve: test_swap.reserve_1,
+                pool_mint: test_swap.pool_mint,
+                output_lp: test_swap.user_lp,
+            },
+            source_amount,
+            0,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+    let amount_minted = env.token_balance(test_swap.user_lp).await - balance_before;
+    assert!(amount_minted < base_amount);
+    assert!(amount_minted > max_fee_amount);
+}
+
+#[tokio::test]
+async fn test_weighted_swap_prices() {
+    let mut env = TestEnv::new().await;
+    let (mint_0, mint_1) = env.new_sorted_mints().await;
+    // an 80/20 pool where both tokens are worth the same
+    let reserve_0 = 4 * INITIAL_RESERVE;
+    let setup = env
+        .setup_new_swap(mint_0, mint_1, reserve_0, INITIAL_RESERVE)
+        .await;
+    let admin = Keypair::from_bytes(&env.admin.to_bytes()).unwrap();
+    let payer = env.payer();
+    env.process(
+        &[
+            instructions::new_weighted_swap(setup.accounts(payer), 0, FEE_TIER_KBPS, 8_000),
+            instructions::set_dynamic_fee(
+                cpamm::accounts::SwapAdmin {
+                    admin: admin.pubkey(),
+                    swap: setup.swap,
+                },
+                1_000_000,
+                5_000_000,
+            ),
+        ],
+        &[&admin],
+    )
+    .await
+    .unwrap();
+
+    let user_0 = env.create_token_account(mint_0, payer).await;
+    let user_1 = env.create_token_account(mint_1, payer).await;
+    env.mint_to(mint_0, user_0, INITIAL_RESERVE).await;
+    let swap_info = env.swap_info(setup.swap).await;
+    let accounts = client_accounts::swap_accounts(
+        setup.swap,
+        &swap_info,
+        [anchor_spl::token::ID; 2],
+        payer,
+        mint_0,
+        user_0,
+        user_1,
+    );
+    env.process(&[instructions::swap(accounts, 1_000, 0)], &[])
+        .await
+        .unwrap();
+
+    // prices follow the curve rather than the ratio of the reserves
+    let one = 10u128.pow(cpamm::price_info::PRICE_SCALE.into());
+    let swap_info = env.swap_info(setup.swap).await;
+    let price_ema = swap_info.dynamic_fee.price_ema;
+    assert!(price_ema > one * 999 / 1_000 && price_ema < one * 1_001 / 1_000);
+
+    let reserves = swap_info.expected_reserves;
+    let price_info = &swap_info.price_info;
+    let (price_0_cumulative, price_1_cumulative) = price_info
+        .cumulative_prices_at(
+            &swap_info.curve,
+            reserves.amount_0,
+            reserves.amount_1,
+            price_info.last_update_ts + 1,
+        )
+        .unwrap();
+    let price_0 = price_0_cumulative.wrapping_sub(price_info.price_0_cumulative_last);
+    let price_1 = price_1_cumulative.wrapping_sub(price_info.price_1_cumulative_last);
+    assert!(price_0 > one * 999 / 1_000 && price_0 < one * 1_001 / 1_000);
+    assert!(price_1 > one * 999 / 1_000 && price_1 < one * 1_001 / 1_000);
+}
+
+#[tokio::test]
+async fn test_swap_paused() {
+    let mut env = TestEnv::new().await;
+    let test_swap = env.new_swap().await;
+    env.set_paused(&test_swap, true).await;
+    let swap_info = env.swap_info(test_swap.swap).await;
+    assert!(swap_info.is_paused);
+    let payer = env.payer();
+
+    let result = env
+        .process(
+            &[instructions::swap(
+                test_swap.swap_0_to_1(&swap_info, payer),
+                1_000_000,
+                0,
+            )],
+            &[],
+        )
+        .await;
+    assert_error(result, ErrorCode::Paused);
+
+    let result = env
+        .process(
+            &[instructions::deposit(
+                test_swap.deposit(&swap_info, payer),
+                1_000,
+                u64::MAX,
+                u64::MAX,
+            )],
+            &[],
+        )
+        .await;
+    assert_error(result, ErrorCode::Paused);
+
+    // swaps work again once unpaused
+    env.set_paused(&test_swap, false).await;
+    env.process(
+        &[instructions::swap(
+            test_swap.swap_0_to_1(&swap_info, payer),
+            1_000_000,
+            0,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn test_swap_with_deadline_expired() {
+    let mut env = TestEnv::new().await;
+    let test_swap = env.new_swap().await;
+    let swap_info = env.swap_info(test_swap.swap).await;
+    let payer = env.payer();
+    let now = env.unix_timestamp().await;
+
+    let result = env
+        .process(
+            &[instructions::swap_with_deadline(
+                test_swap.swap_0_to_1(&swap_info, payer),
+                1_000_000,
+                0,
+                now - 1,
+            )],
+            &[],
+        )
+        .await;
+    assert_error(result, ErrorCode::Expired);
+
+    env.process(
+        &[instructions::swap_with_deadline(
+            test_swap.swap_0_to_1(&swap_info, payer),
+            1_000_000,
+            0,
+            now + 60,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn test_swap_route_invalid() {
+    let mut env = TestEnv::new().await;
+    let test_swap = env.new_swap().await;
+    let payer = env.payer();
+
+    let result = env
+        .process(
+            &[instructions::swap_route(
+                cpamm::accounts::SwapRoute {
+                    token_program: anchor_spl::token::ID,
+                    user_authority: payer,
+                    input: test_swap.user_0,
+                    output: test_swap.user_1,
+                    input_mint: test_swap.mint_0,
+                },
+                &[],
+                1_000_000,
+                0,
+            )],
+            &[],
+        )
+        .await;
+    assert_error(result, ErrorCode::InvalidRoute);
+}
+
+#[tokio::test]
+async fn test_swap_exact_out_unsupported_curve() {
+    let mut env = TestEnv::new().await;
+    let (mint_0, mint_1) = env.new_sorted_mints().await;
+    let setup = env
+        .setup_new_swap(mint_0, mint_1, INITIAL_RESERVE, INITIAL_RESERVE)
+        .await;
+    let payer = env.payer();
+    env.process(
+        &[instructions::new_stable_swap(
+            setup.accounts(payer),
+            0,
+            FEE_TIER_KBPS,
+            100,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+    let user_0 = env.create_token_account(mint_0, payer).await;
+    let user_1 = env.create_token_account(mint_1, payer).await;
+    env.mint_to(mint_0, user_0, INITIAL_RESERVE).await;
+
+    let swap_info = env.swap_info(setup.swap).await;
+    let accounts = client_accounts::swap_accounts(
+        setup.swap,
+        &swap_info,
+        [anchor_spl::token::ID; 2],
+        payer,
+        mint_0,
+        user_0,
+        user_1,
+    );
+    let result = env
+        .process(
+            &[instructions::swap_exact_out(accounts, 1_000, u64::MAX)],
+            &[],
+        )
+        .await;
+    assert_error(result, ErrorCode::UnsupportedCurve);
+}
+
+// --------------------------------
+// Deposits and withdrawals
+// --------------------------------
+
+#[tokio::test]
+async fn test_deposit_and_withdraw() {
+    let mut env = TestEnv::new().await;
+    let test_swap = env.new_swap().await;
+    let swap_info = env.swap_info(test_swap.swap).await;
+    let payer = env.payer();
+
+    env.process(
+        &[instructions::deposit(
+            test_swap.deposit(&swap_info, payer),
+            1_000_000,
+            1_000_000,
+            1_000_000,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        env.token_balance(test_swap.user_lp).await,
+        INITIAL_RESERVE + 1_000_000
+    );
+    assert_eq!(
+        env.token_balance(test_swap.user_0).await,
+        INITIAL_RESERVE - 1_000_000
+    );
+    assert_eq!(
+        env.token_balance(test_swap.reserve_1).await,
+        INITIAL_RESERVE + 1_000_000
+    );
+
+    env.process(
+        &[instructions::withdraw(
+            test_swap.withdraw(&swap_info, payer),
+            2_000_000,
+            2_000_000,
+            2_000_000,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        env.token_balance(test_swap.user_lp).await,
+        INITIAL_RESERVE - 1_000_000
+    );
+    assert_eq!(
+        env.token_balance(test_swap.user_0).await,
+        INITIAL_RESERVE + 1_000_000
+    );
+    assert_eq!(
+        env.mint_supply(test_swap.pool_mint).await,
+        INITIAL_RESERVE - 1_000_000
+    );
+}
+
+#[tokio::test]
+async fn test_deposit_exceeded_slippage() {
+    let mut env = TestEnv::new().await;
+    let test_swap = env.new_swap().await;
+    let swap_info = env.swap_info(test_swap.swap).await;
+    let payer = env.payer();
+
+    let result = env
+        .process(
+            &[instructions::deposit(
+                test_swap.deposit(&swap_info, payer),
+                1_000_000,
+                999_999,
+                1_000_000,
+            )],
+            &[],
+        )
+        .await;
+    assert_error(result, ErrorCode::ExceededSlippage);
+}
+
+#[tokio::test]
+async fn test_withdraw_exceeded_slippage() {
+    let mut env = TestEnv::new().await;
+    let test_swap = env.new_swap().await;
+    let swap_info = env.swap_info(test_swap.swap).await;
+    let payer = env.payer();
+
+    let result = env
+        .process(
+            &[instructions::withdraw(
+                test_swap.withdraw(&swap_info, payer),
+                1_000_000,
+                1_000_000,
+                1_000_001,
+            )],
+            &[],
+        )
+        .await;
+    assert_error(result, ErrorCode::ExceededSlippage);
+}
+
+#[tokio::test]
+async fn test_withdraw_insufficient_liquidity() {
+    let mut env = TestEnv::new().await;
+    // the LP supply is 1_000_000, so one LP token is worth less than one token 0
+    let test_swap = env.new_swap_with_reserves(10_000, 100_000_000).await;
+    let swap_info = env.swap_info(test_swap.swap).await;
+    let payer = env.payer();
+
+    let result = env
+        .process(
+            &[instructions::withdraw(
+                test_swap.withdraw(&swap_info, payer),
+                1,
+                0,
+                0,
+            )],
+            &[],
+        )
+        .await;
+    assert_error(result, ErrorCode::InsufficientLiquidity);
+}
+
+#[tokio::test]
+async fn test_withdraw_insufficient_liquidity_post_withdrawal() {
+    let mut env = TestEnv::new().await;
+    let test_swap = env.new_swap().await;
+    let swap_info = env.swap_info(test_swap.swap).await;
+    let payer = env.payer();
+
+    let result = env
+        .process(
+            &[instructions::withdraw(
+                test_swap.withdraw(&swap_info, payer),
+                INITIAL_RESERVE - xyk::MINIMUM_LIQUIDITY + 1,
+                0,
+                0,
+            )],
+            &[],
+        )
+        .await;
+    assert_error(result, ErrorCode::InsufficientLiquidityPostWithdrawal);
+
+    // withdrawing down to exactly the minimum liquidity is allowed
+    env.process(
+        &[instructions::withdraw(
+            test_swap.withdraw(&swap_info, payer),
+            INITIAL_RESERVE - xyk::MINIMUM_LIQUIDITY,
+            0,
+            0,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn test_deposit_and_withdraw_with_deadline_expired() {
+    let mut env = TestEnv::new().await;
+    let test_swap = env.new_swap().await;
+    let swap_info = env.swap_info(test_swap.swap).await;
+    let payer = env.payer();
+    let now = env.unix_timestamp().await;
+
+    let result = env
+        .process(
+            &[instructions::deposit_with_deadline(
+                test_swap.deposit(&swap_info, payer),
+                1_000_000,
+                u64::MAX,
+                u64::MAX,
+                now - 1,
+            )],
+            &[],
+        )
+        .await;
+    assert_error(result, ErrorCode::Expired);
+
+    let result = env
+        .process(
+            &[instructions::withdraw_with_deadline(
+                test_swap.withdraw(&swap_info, payer),
+                1_000_000,
+                0,
+                0,
+                now - 1,
+            )],
+            &[],
+        )
+        .await;
+    assert_error(result, ErrorCode::Expired);
+}
+
+// --------------------------------
+// Admin
+// --------------------------------
+
+#[tokio::test]
+async fn test_admin_unauthorized() {
+    let mut env = TestEnv::new().await;
+    let test_swap = env.new_swap().await;
+    let payer = env.payer();
+
+    let result = env
+        .process(
+            &[instructions::pause(cpamm::accounts::SwapAdmin {
+                admin: payer,
+                swap: test_swap.swap,
+            })],
+            &[],
+        )
+        .await;
+    assert_error(result, ErrorCode::Unauthorized);
+}
+
+#[tokio::test]
+async fn test_set_fees_invalid_fee() {
+    let mut env = TestEnv::new().await;
+    let test_swap = env.new_swap().await;
+    let admin = Keypair::from_bytes(&env.admin.to_bytes()).unwrap();
+
+    let result = env
+        .process(
+            &[instructions::set_fees(
+                cpamm::accounts::SwapAdmin {
+                    admin: admin.pubkey(),
+                    swap: test_swap.swap,
+                },
+                cpamm::SwapFees {
+                    trade_fee_kbps: cpamm::fees::KBPS_PER_WHOLE + 1,
+                    ..cpamm::fees::INITIAL
+                },
+            )],
+            &[&admin],
+        )
+        .await;
+    assert_error(result, ErrorCode::InvalidFee);
+}
+
+#[tokio::test]
+async fn test_claim_admin_fees() {
+    let mut env = TestEnv::new().await;
+    let test_swap = env.new_swap().await;
+    let swap_info = env.swap_info(test_swap.swap).await;
+    let admin = Keypair::from_bytes(&env.admin.to_bytes()).unwrap();
+    let payer = env.payer();
+
+    let fees_amount = 1_000_000;
+    env.mint_to(test_swap.mint_0, test_swap.fees_0, fees_amount)
+        .await;
+
+    let result = env
+        .process(
+            &[instructions::claim_admin_fees(
+                client_accounts::claim_admin_fees_accounts(
+                    test_swap.swap,
+                    &swap_info,
+                    test_swap.token_programs,
+                    payer,
+                    [test_swap.user_0, test_swap.user_1],
+                ),
+            )],
+            &[],
+        )
+        .await;
+    assert_error(result, ErrorCode::Unauthorized);
+
+    env.process(
+        &[instructions::claim_admin_fees(
+            client_accounts::claim_admin_fees_accounts(
+                test_swap.swap,
+                &swap_info,
+                test_swap.token_programs,
+                admin.pubkey(),
+                [test_swap.user_0, test_swap.user_1],
+            ),
+        )],
+        &[&admin],
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(env.token_balance(test_swap.fees_0).await, 0);
+    assert_eq!(
+        env.token_balance(test_swap.user_0).await,
+        INITIAL_RESERVE + fees_amount
+    );
+    assert_eq!(env.token_balance(test_swap.user_1).await, INITIAL_RESERVE);
+}
+
+#[tokio::test]
+async fn test_set_admin_fees_account() {
+    let mut env = TestEnv::new().await;
+    let test_swap = env.new_swap().await;
+    let swap_info = env.swap_info(test_swap.swap).await;
+    let admin = Keypair::from_bytes(&env.admin.to_bytes()).unwrap();
+
+    let fees_amount = 1_000_000;
+    env.mint_to(test_swap.mint_0, test_swap.fees_0, fees_amount)
+        .await;
+
+    // the new account must be owned by the swap
+    let payer = env.payer();
+    let result = env
+        .process(
+            &[instructions::set_admin_fees_account(
+                client_accounts::set_admin_fees_account_accounts(
+                    test_swap.swap,
+                    &swap_info,
+                    test_swap.token_programs[0],
+                    admin.pubkey(),
+                    test_swap.mint_0,
+                    test_swap.user_0,
+                ),
+            )],
+            &[&admin],
+        )
+        .await;
+    assert_error(result, vipers::VipersError::KeyMismatch);
+
+    let new_fees = env
+        .create_token_account(test_swap.mint_0, test_swap.swap)
+        .await;
+    env.process(
+        &[instructions::set_admin_fees_account(
+            client_accounts::set_admin_fees_account_accounts(
+                test_swap.swap,
+                &swap_info,
+                test_swap.token_programs[0],
+                admin.pubkey(),
+                test_swap.mint_0,
+                new_fees,
+            ),
+        )],
+        &[&admin],
+    )
+    .await
+    .unwrap();
+
+    let swap_info = env.swap_info(test_swap.swap).await;
+    assert_eq!(swap_info.token_0.admin_fees, new_fees);
+    assert_eq!(swap_info.token_1.admin_fees, test_swap.fees_1);
+    assert_eq!(env.token_balance(test_swap.fees_0).await, 0);
+    assert_eq!(env.token_balance(new_fees).await, fees_amount);
+
+    // fees now accrue to the new account
+    let amount_in = 1_000_000;
+    let swap_1_to_0 = client_accounts::swap_accounts(
+        test_swap.swap,
+        &swap_info,
+        test_swap.token_programs,
+        payer,
+        test_swap.mint_1,
+        test_swap.user_1,
+        test_swap.user_0,
+    );
+    env.process(&[instructions::swap(swap_1_to_0, amount_in, 0)], &[])
+        .await
+        .unwrap();
+    assert_eq!(env.token_balance(test_swap.fees_0).await, 0);
+    assert!(env.token_balance(new_fees).await > fees_amount);
+}
+
+#[tokio::test]
+async fn test_close_swap() {
+    let mut env = TestEnv::new().await;
+    let test_swap = env.new_swap().await;
+    let admin = Keypair::from_bytes(&env.admin.to_bytes()).unwrap();
+    let payer = env.payer();
+    let rent_destination = Pubkey::new_unique();
+
+    let (swap_meta, bump) = pda::find_swap_meta_address(&test_swap.factory, 0);
+    env.process(
+        &[instructions::new_swap_meta(
+            cpamm::accounts::NewSwapMeta {
+                swap: test_swap.swap,
+                swap_meta,
+                payer,
+                system_program: anchor_lang::solana_program::system_program::ID,
+            },
+            bump,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+    let (observations, _) = pda::find_observations_address(&test_swap.swap);
+    env.new_observations(test_swap.swap, 0).await.unwrap();
+
+    let swap_info = env.swap_info(test_swap.swap).await;
+    let close_swap = |admin| {
+        instructions::close_swap(client_accounts::close_swap_accounts(
+            test_swap.swap,
+            &swap_info,
+            test_swap.token_programs,
+            admin,
+            [test_swap.user_0, test_swap.user_1],
+            rent_destination,
+        ))
+    };
+    let result = env.process(&[close_swap(admin.pubkey())], &[&admin]).await;
+    assert_error(result, ErrorCode::SwapNotClosing);
+
+    // only the admin may close the swap
+    let result = env
+        .process(
+            &[instructions::begin_close_swap(cpamm::accounts::SwapAdmin {
+                admin: payer,
+                swap: test_swap.swap,
+            })],
+            &[],
+        )
+        .await;
+    assert_error(result, ErrorCode::Unauthorized);
+
+    let swap_admin = || cpamm::accounts::SwapAdmin {
+        admin: admin.pubkey(),
+        swap: test_swap.swap,
+    };
+    env.process(&[instructions::begin_close_swap(swap_admin())], &[&admin])
+        .await
+        .unwrap();
+    let swap_info = env.swap_info(test_swap.swap).await;
+    assert!(swap_info.is_paused);
+    assert!(swap_info.is_closing());
+
+    // a closing swap cannot be unpaused
+    let result = env
+        .process(&[instructions::unpause(swap_admin())], &[&admin])
+        .await;
+    assert_error(result, ErrorCode::SwapClosing);
+    let result = env
+        .process(
+            &[instructions::swap(
+                test_swap.swap_0_to_1(&swap_info, payer),
+                1_000_000,
+                0,
+            )],
+            &[],
+        )
+        .await;
+    assert_error(result, ErrorCode::Paused);
+
+    // the swap cannot be closed while it has liquidity
+    let result = env.process(&[close_swap(admin.pubkey())], &[&admin]).await;
+    assert_error(result, ErrorCode::SwapPoolMintSupply);
+
+    // the minimum liquidity may be withdrawn
+    let donation = 1_000;
+    let fees_amount = 2_000;
+    env.mint_to(test_swap.mint_0, test_swap.reserve_0, donation)
+        .await;
+    env.mint_to(test_swap.mint_1, test_swap.fees_1, fees_amount)
+        .await;
+    env.process(
+        &[instructions::withdraw(
+            test_swap.withdraw(&swap_info, payer),
+            INITIAL_RESERVE,
+            INITIAL_RESERVE,
+            INITIAL_RESERVE,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+    assert_eq!(env.mint_supply(test_swap.pool_mint).await, 0);
+    assert_eq!(
+        env.token_balance(test_swap.user_0).await,
+        2 * INITIAL_RESERVE
+    );
+    assert_eq!(
+        env.token_balance(test_swap.user_1).await,
+        2 * INITIAL_RESERVE
+    );
+
+    let closed_accounts = [
+        test_swap.swap,
+        swap_meta,
+        observations,
+        test_swap.reserve_0,
+        test_swap.reserve_1,
+        test_swap.fees_0,
+        test_swap.fees_1,
+    ];
+    let mut rent = 0;
+    for account in closed_accounts {
+        rent += env.get_account(account).await.lamports;
+    }
+    env.process(&[close_swap(admin.pubkey())], &[&admin])
+        .await
+        .unwrap();
+
+    // the remaining balances are swept to the destinations
+    assert_eq!(
+        env.token_balance(test_swap.user_0).await,
+        2 * INITIAL_RESERVE + donation
+    );
+    assert_eq!(
+        env.token_balance(test_swap.user_1).await,
+        2 * INITIAL_RESERVE + fees_amount
+    );
+    for account in closed_accounts {
+        assert!(env
+            .ctx
+            .banks_client
+            .get_account(account)
+            .await
+            .unwrap()
+            .is_none());
+    }
+    assert_eq!(env.get_account(rent_destination).await.lamports, rent);
+}
+
+// --------------------------------
+// Events
+// --------------------------------
+
+#[tokio::test]
+async fn test_swap_event() {
+    let mut env = TestEnv::new().await;
+    let test_swap = env.new_swap().await;
+    let swap_info = env.swap_info(test_swap.swap).await;
+    let payer = env.payer();
+
+    let amount_in = 1_000_000;
+    let quote =
+        cpamm_client::quote::quote_swap(&swap_info, swap_info.token_0.mint, amount_in).unwrap();
+    let events: Vec<cpamm::SwapEvent> = env
+        .simulate_events(
+            &[instructions::swap(
+                test_swap.swap_0_to_1(&swap_info, payer),
+                amount_in,
+                quote.amount_out,
+            )],
+            &[],
+        )
+        .await;
+    assert_eq!(events.len(), 1);
+    let event = &events[0];
+    assert_eq!(event.user_authority, payer);
+    assert_eq!(event.trade_fee, quote.trade_fee());
+    assert_eq!(event.admin_trade_fee, quote.admin_fee);
+    assert_eq!(event.trade_fee_kbps, swap_info.fees.trade_fee_kbps);
+    assert_eq!(event.reserve_0, INITIAL_RESERVE + quote.amount_in);
+    assert_eq!(
+        event.reserve_1,
+        INITIAL_RESERVE - quote.amount_out - quote.admin_fee
+    );
+}
+
+#[tokio::test]
+async fn test_deposit_and_withdraw_events() {
+    let mut env = TestEnv::new().await;
+    let test_swap = env.new_swap().await;
+    let swap_info = env.swap_info(test_swap.swap).await;
+    let admin = Keypair::from_bytes(&env.admin.to_bytes()).unwrap();
+    let payer = env.payer();
+
+    let amount = 1_000_000;
+    let events: Vec<cpamm::DepositEvent> = env
+        .simulate_events(
+            &[instructions::deposit(
+                test_swap.deposit(&swap_info, payer),
+                amount,
+                amount,
+                amount,
+            )],
+            &[],
+        )
+        .await;
+    assert_eq!(events.len(), 1);
+    let event = &events[0];
+    assert_eq!(event.user_authority, payer);
+    assert_eq!(event.token_0_amount, amount);
+    assert_eq!(event.trade_fee, 0);
+    assert_eq!(event.reserve_0, INITIAL_RESERVE + amount);
+    assert_eq!(event.reserve_1, INITIAL_RESERVE + amount);
+    assert_eq!(event.pool_token_supply, INITIAL_RESERVE + amount);
+
+    let fees = cpamm::SwapFees {
+        withdraw_fee_kbps: 10_000,
+        ..swap_info.fees
+    };
+    env.process(
+        &[instructions::set_fees(
+            cpamm::accounts::SwapAdmin {
+                admin: admin.pubkey(),
+                swap: test_swap.swap,
+            },
+            fees,
+        )],
+        &[&admin],
+    )
+    .await
+    .unwrap();
+
+    let events: Vec<cpamm::WithdrawEvent> = env
+        .simulate_events(
+            &[instructions::withdraw(
+                test_swap.withdraw(&swap_info, payer),
+                amount,
+                0,
+                0,
+            )],
+            &[],
+        )
+        .await;
+    assert_eq!(events.len(), 1);
+    let event = &events[0];
+    let (withdraw_fee, admin_withdraw_fee) = fees.compute_withdraw_fees(amount).unwrap();
+    assert!(withdraw_fee > 0);
+    assert_eq!(event.user_authority, payer);
+    assert_eq!(event.fee_0, withdraw_fee);
+    assert_eq!(event.fee_1, withdraw_fee);
+    assert_eq!(event.admin_fee_0, admin_withdraw_fee);
+    assert_eq!(event.admin_fee_1, admin_withdraw_fee);
+    assert_eq!(
+        event.reserve_0,
+        INITIAL_RESERVE - amount + withdraw_fee - admin_withdraw_fee
+    );
+    assert_eq!(event.pool_token_supply, INITIAL_RESERVE - amount);
+}
+
+// --------------------------------
+// Oracle and flash loans
+// --------------------------------
+
+#[tokio::test]
+async fn test_new_observations() {
+    let mut env = TestEnv::new().await;
+    let test_swap = env.new_swap().await;
+    let (observations, _) = pda::find_observations_address(&test_swap.swap);
+    let payer = env.payer();
+
+    // only the swap admin may create the observations
+    let result = env
+        .process(
+            &[instructions::new_observations(
+                cpamm::accounts::NewObservations {
+                    swap: test_swap.swap,
+                    admin: payer,
+                    observations,
+                    payer,
+                    system_program: anchor_lang::solana_program::system_program::ID,
+                },
+                0,
+            )],
+            &[],
+        )
+        .await;
+    assert_error(result, ErrorCode::Unauthorized);
+
+    let result = env
+        .new_observations(
+            test_swap.swap,
+            cpamm::price_info::MAX_OBSERVATION_INTERVAL_SECONDS + 1,
+        )
+        .await;
+    assert_error(result, ErrorCode::InvalidObservationInterval);
+
+    env.new_observations(
+        test_swap.swap,
+        cpamm::price_info::MAX_OBSERVATION_INTERVAL_SECONDS,
+    )
+    .await
+    .unwrap();
+    let data = env.get_account_data(observations).await;
+    let observations_info = client_accounts::decode_observations(&data).unwrap();
+    assert_eq!(
+        observations_info.min_interval_seconds,
+        cpamm::price_info::MAX_OBSERVATION_INTERVAL_SECONDS
+    );
+}
+
+#[tokio::test]
+async fn test_consult_insufficient_observations() {
+    let mut env = TestEnv::new().await;
+    let test_swap = env.new_swap().await;
+    let (observations, _) = pda::find_observations_address(&test_swap.swap);
+
+    env.new_observations(test_swap.swap, 0).await.unwrap();
+
+    let result = env
+        .process(
+            &[instructions::consult(
+                cpamm::accounts::Consult {
+                    swap: test_swap.swap,
+                    reserve_0: test_swap.reserve_0,
+                    reserve_1: test_swap.reserve_1,
+                    observations,
+                },
+                60,
+            )],
+            &[],
+        )
+        .await;
+    assert_error(result, ErrorCode::InsufficientObservations);
+}
+
+fn flash_loan_accounts(test_swap: &TestSwap, user_authority: Pubkey) -> cpamm::accounts::FlashLoan {
+    cpamm::accounts::FlashLoan {
+        user: client_accounts::user_context(test_swap.swap, user_authority),
+        token: cpamm::accounts::SwapTokenWithFees {
+            user: test_swap.user_0,
+            reserve: test_swap.reserve_0,
+            fees: test_swap.fees_0,
+            mint: test_swap.mint_0,
+            token_program: test_swap.token_programs[0],
+        },
+        other_reserve: test_swap.reserve_1,
+        instructions: anchor_lang::solana_program::sysvar::instructions::ID,
+    }
+}
+
+#[tokio::test]
+async fn test_flash_loan() {
+    let mut env = TestEnv::new().await;
+    let test_swap = env.new_swap().await;
+    let swap_info = env.swap_info(test_swap.swap).await;
+    let payer = env.payer();
+
+    // must be repaid
+    let result = env
+        .process(
+            &[instructions::flash_loan(
+                flash_loan_accounts(&test_swap, payer),
+                1_000_000,
+            )],
+            &[],
+        )
+        .await;
+    assert_error(result, ErrorCode::FlashLoanNotRepaid);
+
+    // the pool cannot be used while the loan is outstanding
+    let result = env
+        .process(
+            &[
+                instructions::flash_loan(flash_loan_accounts(&test_swap, payer), 1_000_000),
+                instructions::swap(test_swap.swap_0_to_1(&swap_info, payer), 1_000_000, 0),
+                instructions::repay_flash_loan(flash_loan_accounts(&test_swap, payer)),
+            ],
+            &[],
+        )
+        .await;
+    assert_error(result, ErrorCode::FlashLoanInProgress);
+
+    env.process(
+        &[
+            instructions::flash_loan(flash_loan_accounts(&test_swap, payer), 1_000_000),
+            instructions::repay_flash_loan(flash_loan_accounts(&test_swap, payer)),
+        ],
+        &[],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        env.token_balance(test_swap.reserve_0).await,
+        INITIAL_RESERVE
+    );
+    let swap_info = env.swap_info(test_swap.swap).await;
+    assert!(!swap_info.flash_loan.is_active());
+}
+
+// --------------------------------
+// Skim and sync
+// --------------------------------
+
+/// Amount sent directly to the reserves of token 0 in the skim and sync tests.
+const DONATION: u64 = 1_000_000;
+
+/// Sends [DONATION] tokens from the user directly to the reserves of token 0.
+async fn donate(env: &mut TestEnv, test_swap: &TestSwap) {
+    let payer = env.payer();
+    env.process(
+        &[spl_token::instruction::transfer(
+            &spl_token::ID,
+            &test_swap.user_0,
+            &test_swap.reserve_0,
+            &payer,
+            &[],
+            DONATION,
+        )
+        .unwrap()],
+        &[],
+    )
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn test_sync() {
+    let mut env = TestEnv::new().await;
+    let test_swap = env.new_swap().await;
+    let payer = env.payer();
+    donate(&mut env, &test_swap).await;
+
+    // the donation is not used for pricing
+    let swap_info = env.swap_info(test_swap.swap).await;
+    let amount_in = 1_000_000;
+    let quote =
+        cpamm_client::quote::quote_swap(&swap_info, swap_info.token_0.mint, amount_in).unwrap();
+    env.process(
+        &[instructions::swap(
+            test_swap.swap_0_to_1(&swap_info, payer),
+            amount_in,
+            quote.amount_out,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        env.token_balance(test_swap.user_1).await,
+        INITIAL_RESERVE + quote.amount_out
+    );
+
+    let swap_info = env.swap_info(test_swap.swap).await;
+    assert_eq!(
+        swap_info.expected_reserves.amount_0,
+        INITIAL_RESERVE + quote.amount_in
+    );
+    assert_eq!(
+        swap_info.expected_reserves.amount_1,
+        env.token_balance(test_swap.reserve_1).await
+    );
+
+    env.process(
+        &[instructions::sync(client_accounts::sync_accounts(
+            test_swap.swap,
+            &swap_info,
+        ))],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    let swap_info = env.swap_info(test_swap.swap).await;
+    assert_eq!(
+        swap_info.expected_reserves.amount_0,
+        INITIAL_RESERVE + quote.amount_in + DONATION
+    );
+    assert_eq!(
+        swap_info.expected_reserves.amount_0,
+        env.token_balance(test_swap.reserve_0).await
+    );
+}
+
+#[tokio::test]
+async fn test_skim() {
+    let mut env = TestEnv::new().await;
+    let test_swap = env.new_swap().await;
+    let swap_info = env.swap_info(test_swap.swap).await;
+    donate(&mut env, &test_swap).await;
+
+    env.process(
+        &[instructions::skim(client_accounts::skim_accounts(
+            test_swap.swap,
+            &swap_info,
+            test_swap.token_programs,
+        ))],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(env.token_balance(test_swap.fees_0).await, DONATION);
+    assert_eq!(env.token_balance(test_swap.fees_1).await, 0);
+    assert_eq!(
+        env.token_balance(test_swap.reserve_0).await,
+        INITIAL_RESERVE
+    );
+    let swap_info = env.swap_info(test_swap.swap).await;
+    assert_eq!(swap_info.expected_reserves.amount_0, INITIAL_RESERVE);
+    assert_eq!(swap_info.expected_reserves.amount_1, INITIAL_RESERVE);
+}
+
+#[tokio::test]
+async fn test_skim_paused() {
+    let mut env = TestEnv::new().await;
+    let test_swap = env.new_swap().await;
+    let swap_info = env.swap_info(test_swap.swap).await;
+    env.set_paused(&test_swap, true).await;
+
+    let result = env
+        .process(
+            &[instructions::skim(client_accounts::skim_accounts(
+                test_swap.swap,
+                &swap_info,
+                test_swap.token_programs,
+            ))],
+            &[],
+        )
+        .await;
+    assert_error(result, ErrorCode::Paused);
+}
+
+// --------------------------------
+// Token-2022
+// --------------------------------
+
+/// Transfer fee of the Token-2022 test mints, in basis points.
+const TRANSFER_FEE_BPS: u16 = 100;
+
+/// Creates a swap whose token 0 is a Token-2022 mint with a [TRANSFER_FEE_BPS] transfer fee.
+async fn new_transfer_fee_swap(env: &mut TestEnv) -> TestSwap {
+    let payer = env.payer();
+    let mint_0 = env
+        .create_mint_with_extensions(
+            payer,
+            6,
+            &[MintExtension::TransferFee {
+                basis_points: TRANSFER_FEE_BPS,
+                maximum_fee: u64::MAX,
+            }],
+        )
+        .await;
+    let mint_1 = env.create_mint_after(mint_0, 6).await;
+    env.new_swap_of_mints(mint_0, mint_1, INITIAL_RESERVE, INITIAL_RESERVE)
+        .await
+}
+
+#[tokio::test]
+async fn test_swap_transfer_fee() {
+    let mut env = TestEnv::new().await;
+    let test_swap = new_transfer_fee_swap(&mut env).await;
+    let swap_info = env.swap_info(test_swap.swap).await;
+    let payer = env.payer();
+    assert_eq!(test_swap.token_programs[0], spl_token_2022::ID);
+
+    // the pool swaps the input net of the transfer fee
+    let amount_in = 1_000_000;
+    let amount_received = 990_000;
+    let quote =
+        cpamm_client::quote::quote_swap(&swap_info, swap_info.token_0.mint, amount_received)
+            .unwrap();
+    env.process(
+        &[instructions::swap(
+            test_swap.swap_0_to_1(&swap_info, payer),
+            amount_in,
+            quote.amount_out,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        env.token_balance(test_swap.user_0).await,
+        INITIAL_RESERVE - amount_in
+    );
+    assert_eq!(
+        env.token_balance(test_swap.reserve_0).await,
+        INITIAL_RESERVE + amount_received
+    );
+    assert_eq!(
+        env.token_balance(test_swap.user_1).await,
+        INITIAL_RESERVE + quote.amount_out
+    );
+
+    // the user receives the output net of the transfer fee
+    let reserve_0 = env.token_balance(test_swap.reserve_0).await;
+    let swap_info = env.swap_info(test_swap.swap).await;
+    let quote =
+        cpamm_client::quote::quote_swap(&swap_info, swap_info.token_1.mint, 1_000_000).unwrap();
+    let transfer_fee = (quote.amount_out + 99) / 100;
+    let accounts = client_accounts::swap_accounts(
+        test_swap.swap,
+        &swap_info,
+        test_swap.token_programs,
+        payer,
+        test_swap.mint_1,
+        test_swap.user_1,
+        test_swap.user_0,
+    );
+    env.process(
+        &[instructions::swap(
+            accounts,
+            1_000_000,
+            quote.amount_out - transfer_fee,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        env.token_balance(test_swap.user_0).await,
+        INITIAL_RESERVE - amount_in + quote.amount_out - transfer_fee
+    );
+    assert_eq!(
+        env.token_balance(test_swap.reserve_0).await,
+        reserve_0 - quote.amount_out - quote.admin_fee
+    );
+}
+
+#[tokio::test]
+async fn test_deposit_and_withdraw_transfer_fee() {
+    let mut env = TestEnv::new().await;
+    let test_swap = new_transfer_fee_swap(&mut env).await;
+    let swap_info = env.swap_info(test_swap.swap).await;
+    let payer = env.payer();
+
+    // the user pays the transfer fee on top of the deposit
+    let amount_in_0 = 1_010_102;
+    let result = env
+        .process(
+            &[instructions::deposit(
+                test_swap.deposit(&swap_info, payer),
+                1_000_000,
+                amount_in_0 - 1,
+                1_000_000,
+            )],
+            &[],
+        )
+        .await;
+    assert_error(result, ErrorCode::ExceededSlippage);
+    env.process(
+        &[instructions::deposit(
+            test_swap.deposit(&swap_info, payer),
+            1_000_000,
+            amount_in_0,
+            1_000_000,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        env.token_balance(test_swap.user_0).await,
+        INITIAL_RESERVE - amount_in_0
+    );
+    assert_eq!(
+        env.token_balance(test_swap.reserve_0).await,
+        INITIAL_RESERVE + 1_000_000
+    );
+
+    // the transfer fee is withheld from the withdrawal
+    let amount_out_0 = 990_000;
+    let result = env
+        .process(
+            &[instructions::withdraw(
+                test_swap.withdraw(&swap_info, payer),
+                1_000_000,
+                amount_out_0 + 1,
+                1_000_000,
+            )],
+            &[],
+        )
+        .await;
+    assert_error(result, ErrorCode::ExceededSlippage);
+    env.process(
+        &[instructions::withdraw(
+            test_swap.withdraw(&swap_info, payer),
+            1_000_000,
+            amount_out_0,
+            1_000_000,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        env.token_balance(test_swap.user_0).await,
+        INITIAL_RESERVE - amount_in_0 + amount_out_0
+    );
+    assert_eq!(
+        env.token_balance(test_swap.reserve_0).await,
+        INITIAL_RESERVE
+    );
+}
+
+#[tokio::test]
+async fn test_close_swap_transfer_fee() {
+    let mut env = TestEnv::new().await;
+    let test_swap = new_transfer_fee_swap(&mut env).await;
+    let admin = Keypair::from_bytes(&env.admin.to_bytes()).unwrap();
+    let payer = env.payer();
+    let rent_destination = Pubkey::new_unique();
+
+    // the transfer fee of the swap is withheld in the reserve
+    let swap_info = env.swap_info(test_swap.swap).await;
+    env.process(
+        &[instructions::swap(
+            test_swap.swap_0_to_1(&swap_info, payer),
+            1_000_000,
+            0,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+    let withheld_amount = 10_000;
+
+    env.process(
+        &[instructions::begin_close_swap(cpamm::accounts::SwapAdmin {
+            admin: admin.pubkey(),
+            swap: test_swap.swap,
+        })],
+        &[&admin],
+    )
+    .await
+    .unwrap();
+    env.process(
+        &[instructions::withdraw(
+            test_swap.withdraw(&swap_info, payer),
+            INITIAL_RESERVE,
+            0,
+            0,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+    assert_eq!(env.mint_supply(test_swap.pool_mint).await, 0);
+
+    // the withheld fees are harvested to the mint, so the reserve can be closed
+    env.process(
+        &[instructions::close_swap(
+            client_accounts::close_swap_accounts(
+                test_swap.swap,
+                &swap_info,
+                test_swap.token_programs,
+                admin.pubkey(),
+                [test_swap.user_0, test_swap.user_1],
+                rent_destination,
+            ),
+        )],
+        &[&admin],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        env.mint_withheld_amount(test_swap.mint_0).await,
+        withheld_amount
+    );
+    for account in [
+        test_swap.swap,
+        test_swap.reserve_0,
+        test_swap.reserve_1,
+        test_swap.fees_0,
+        test_swap.fees_1,
+    ] {
+        assert!(env
+            .ctx
+            .banks_client
+            .get_account(account)
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
+
+#[tokio::test]
+async fn test_swap_interest_bearing_mint() {
+    let mut env = TestEnv::new().await;
+    let payer = env.payer();
+    let mint_0 = env
+        .create_mint_with_extensions(payer, 6, &[MintExtension::InterestBearing { rate: 500 }])
+        .await;
+    let mint_1 = env.create_mint_after(mint_0, 6).await;
+    let test_swap = env
+        .new_swap_of_mints(mint_0, mint_1, INITIAL_RESERVE, INITIAL_RESERVE)
+        .await;
+    let swap_info = env.swap_info(test_swap.swap).await;
+
+    let quote =
+        cpamm_client::quote::quote_swap(&swap_info, swap_info.token_0.mint, 1_000_000).unwrap();
+    env.process(
+        &[instructions::swap(
+            test_swap.swap_0_to_1(&swap_info, payer),
+            1_000_000,
+            quote.amount_out,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        env.token_balance(test_swap.user_1).await,
+        INITIAL_RESERVE + quote.amount_out
+    );
+}
+
+#[tokio::test]
+async fn test_deposit_and_withdraw_interest_bearing_mint() {
+    let mut env = TestEnv::new().await;
+    let payer = env.payer();
+    let mint_0 = env
+        .create_mint_with_extensions(payer, 6, &[MintExtension::InterestBearing { rate: 500 }])
+        .await;
+    let mint_1 = env.create_mint_after(mint_0, 6).await;
+    let test_swap = env
+        .new_swap_of_mints(mint_0, mint_1, INITIAL_RESERVE, INITIAL_RESERVE)
+        .await;
+    let swap_info = env.swap_info(test_swap.swap).await;
+
+    // a year of interest changes the displayed amounts, but not the raw
+    // amounts the swap prices with.
+    env.advance_clock(365 * 24 * 60 * 60).await;
+    let now = env.unix_timestamp().await;
+    let data = env.get_account_data(mint_0).await;
+    let ui_amount: f64 = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)
+        .unwrap()
+        .get_extension::<InterestBearingConfig>()
+        .unwrap()
+        .amount_to_ui_amount(INITIAL_RESERVE, 6, now)
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(ui_amount > 1_050.0);
+
+    env.process(
+        &[instructions::deposit(
+            test_swap.deposit(&swap_info, payer),
+            1_000_000,
+            1_000_000,
+            1_000_000,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        env.token_balance(test_swap.user_0).await,
+        INITIAL_RESERVE - 1_000_000
+    );
+    assert_eq!(
+        env.token_balance(test_swap.reserve_0).await,
+        INITIAL_RESERVE + 1_000_000
+    );
+
+    let swap_info = env.swap_info(test_swap.swap).await;
+    let quote =
+        cpamm_client::quote::quote_swap(&swap_info, swap_info.token_0.mint, 1_000_000).unwrap();
+    env.process(
+        &[instructions::swap(
+            test_swap.swap_0_to_1(&swap_info, payer),
+            1_000_000,
+            quote.amount_out,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        env.token_balance(test_swap.user_1).await,
+        INITIAL_RESERVE - 1_000_000 + quote.amount_out
+    );
+
+    let swap_info = env.swap_info(test_swap.swap).await;
+    assert_eq!(
+        swap_info.expected_reserves.get(true),
+        env.token_balance(test_swap.reserve_0).await
+    );
+    assert_eq!(
+        swap_info.expected_reserves.get(false),
+        env.token_balance(test_swap.reserve_1).await
+    );
+
+    env.process(
+        &[instructions::withdraw(
+            test_swap.withdraw(&swap_info, payer),
+            1_000_000,
+            0,
+            0,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+    assert_eq!(env.token_balance(test_swap.user_lp).await, INITIAL_RESERVE);
+    let swap_info = env.swap_info(test_swap.swap).await;
+    assert_eq!(
+        swap_info.expected_reserves.get(true),
+        env.token_balance(test_swap.reserve_0).await
+    );
+}
+
+#[tokio::test]
+async fn test_new_swap_default_account_state_initialized() {
+    let mut env = TestEnv::new().await;
+    let payer = env.payer();
+    let mint_0 = env
+        .create_mint_with_extensions(
+            payer,
+            6,
+            &[MintExtension::DefaultAccountState {
+                state: AccountState::Initialized,
+            }],
+        )
+        .await;
+    let mint_1 = env.create_mint_after(mint_0, 6).await;
+    let test_swap = env
+        .new_swap_of_mints(mint_0, mint_1, INITIAL_RESERVE, INITIAL_RESERVE)
+        .await;
+    assert_eq!(
+        env.token_balance(test_swap.reserve_0).await,
+        INITIAL_RESERVE
+    );
+}
+
+#[tokio::test]
+async fn test_new_swap_default_account_state_frozen() {
+    let mut env = TestEnv::new().await;
+    let payer = env.payer();
+    let mint_0 = env
+        .create_mint_with_extensions(
+            payer,
+            6,
+            &[MintExtension::DefaultAccountState {
+                state: AccountState::Frozen,
+            }],
+        )
+        .await;
+    let mint_1 = env.create_mint_after(mint_0, 6).await;
+    // new accounts of `mint_0` are frozen, so its reserve cannot be funded.
+    let setup = env.setup_new_swap(mint_0, mint_1, 0, INITIAL_RESERVE).await;
+    let (_, bump) = pda::find_swap_address(&setup.factory, &mint_0, &mint_1, FEE_TIER_KBPS);
+    let result = env
+        .process(
+            &[instructions::new_swap(
+                setup.accounts(payer),
+                bump,
+                FEE_TIER_KBPS,
+            )],
+            &[],
+        )
+        .await;
+    assert_error(result, ErrorCode::UnsupportedMintExtension);
+}
+
+#[tokio::test]
+async fn test_new_swap_unsupported_mint_extension() {
+    let mut env = TestEnv::new().await;
+    let payer = env.payer();
+    let mint_0 = env
+        .create_mint_with_extensions(payer, 6, &[MintExtension::NonTransferable])
+        .await;
+    let mint_1 = env.create_mint_after(mint_0, 6).await;
+    let setup = env
+        .setup_new_swap(mint_0, mint_1, INITIAL_RESERVE, INITIAL_RESERVE)
+        .await;
+    let (_, bump) = pda::find_swap_address(&setup.factory, &mint_0, &mint_1, FEE_TIER_KBPS);
+    let result = env
+        .process(
+            &[instructions::new_swap(
+                setup.accounts(payer),
+                bump,
+                FEE_TIER_KBPS,
+            )],
+            &[],
+        )
+        .await;
+    assert_error(result, ErrorCode::UnsupportedMintExtension);
+}
+
+// --------------------------------
+// Concentrated liquidity
+// --------------------------------
+
+/// Tick spacing of the default test pool.
+const TICK_SPACING: u16 = 10;
+
+/// Square root of a price of 1, Q64.64.
+const SQRT_PRICE_ONE_X64: u128 = 1 << 64;
+
+/// A concentrated liquidity pool between two fresh mints, with a funded user.
+struct TestConcentratedPool {
+    factory: Pubkey,
+    pool: Pubkey,
+    mint_0: Pubkey,
+    mint_1: Pubkey,
+    /// Token programs of `mint_0` and `mint_1`.
+    token_programs: [Pubkey; 2],
+    reserve_0: Pubkey,
+    reserve_1: Pubkey,
+    user_0: Pubkey,
+    user_1: Pubkey,
+}
+
+impl TestEnv {
+    async fn concentrated_pool(&mut self, pool: Pubkey) -> cpamm::ConcentratedPool {
+        let data = self.get_account_data(pool).await;
+        client_accounts::decode_concentrated_pool(&data).unwrap()
+    }
+
+    async fn tick(&mut self, pool: Pubkey, index: i32) -> cpamm::Tick {
+        let data = self
+            .get_account_data(pda::find_tick_address(&pool, index).0)
+            .await;
+        client_accounts::decode_tick(&data).unwrap()
+    }
+
+    async fn position(&mut self, position: Pubkey) -> cpamm::Position {
+        let data = self.get_account_data(position).await;
+        client_accounts::decode_position(&data).unwrap()
+    }
+
+    /// Creates the mints and token accounts of a concentrated pool,
+    /// returning the instruction which creates the pool.
+    async fn setup_new_concentrated_pool(
+        &mut self,
+        tick_spacing: u16,
+        fee_tier_kbps: u32,
+        initial_sqrt_price_x64: u128,
+    ) -> (TestConcentratedPool, Instruction) {
+        let factory = self.new_factory().await;
+        let (mint_0, mint_1) = self.new_sorted_mints().await;
+        let (pool, bump) =
+            pda::find_concentrated_pool_address(&factory, &mint_0, &mint_1, tick_spacing);
+        let reserve_0 = self.create_token_account(mint_0, pool).await;
+        let reserve_1 = self.create_token_account(mint_1, pool).await;
+
+        let payer = self.payer();
+        let user_0 = self.create_token_account(mint_0, payer).await;
+        let user_1 = self.create_token_account(mint_1, payer).await;
+        self.mint_to(mint_0, user_0, INITIAL_RESERVE).await;
+        self.mint_to(mint_1, user_1, INITIAL_RESERVE).await;
+
+        let ix = instructions::new_concentrated_pool(
+            cpamm::accounts::NewConcentratedPool {
+                factory,
+                pool,
+                tick_min: pda::find_tick_address(&pool, xyk::min_tick(tick_spacing)).0,
+                tick_max: pda::find_tick_address(&pool, xyk::max_tick(tick_spacing)).0,
+                token_0: cpamm::accounts::InitConcentratedToken {
+                    mint: mint_0,
+                    reserve: reserve_0,
+                    denied_mint: pda::find_denied_mint_address(&factory, &mint_0).0,
+                },
+                token_1: cpamm::accounts::InitConcentratedToken {
+                    mint: mint_1,
+                    reserve: reserve_1,
+                    denied_mint: pda::find_denied_mint_address(&factory, &mint_1).0,
+                },
+                payer,
+                pool_creator: pda::find_pool_creator_address(&factory, &payer).0,
+                system_program: anchor_lang::solana_program::system_program::ID,
+            },
+            bump,
+            tick_spacing,
+            fee_tier_kbps,
+            initial_sqrt_price_x64,
+        );
+        let token_programs = [
+            self.token_program(mint_0).await,
+            self.token_program(mint_1).await,
+        ];
+        let test_pool = TestConcentratedPool {
+            factory,
+            pool,
+            mint_0,
+            mint_1,
+            token_programs,
+            reserve_0,
+            reserve_1,
+            user_0,
+            user_1,
+        };
+        (test_pool, ix)
+    }
+
+    /// Creates a concentrated pool at a price of 1.
+    async fn new_concentrated_pool(&mut self) -> TestConcentratedPool {
+        let (test_pool, ix) = self
+            .setup_new_concentrated_pool(TICK_SPACING, FEE_TIER_KBPS, SQRT_PRICE_ONE_X64)
+            .await;
+        self.process(&[ix], &[]).await.unwrap();
+        test_pool
+    }
+
+    /// Linked ticks adjacent to the ticks of a position, which are passed when the ticks of
+    /// the position are linked or unlinked.
+    async fn adjacent_ticks(
+        &mut self,
+        test_pool: &TestConcentratedPool,
+        position: Pubkey,
+    ) -> Vec<Pubkey> {
+        let pool = test_pool.pool;
+        let pool_info = self.concentrated_pool(pool).await;
+        let position_info = self.position(position).await;
+        let indices = [position_info.tick_lower, position_info.tick_upper];
+        let mut adjacent = vec![];
+        for index in indices {
+            let tick = self
+                .ctx
+                .banks_client
+                .get_account(pda::find_tick_address(&pool, index).0)
+                .await
+                .unwrap()
+                .map(|account| client_accounts::decode_tick(&account.data).unwrap())
+                .filter(cpamm::Tick::is_linked);
+            let (prev, next) = match tick {
+                Some(tick) => (tick.prev, tick.next),
+                None => {
+                    let mut prev = self.tick(pool, pool_info.min_tick()).await;
+                    while prev.next < index {
+                        prev = self.tick(pool, prev.next).await;
+                    }
+                    (prev.index, prev.next)
+                }
+            };
+            for tick in [prev, next] {
+                let address = pda::find_tick_address(&pool, tick).0;
+                if !indices.contains(&tick) && !adjacent.contains(&address) {
+                    adjacent.push(address);
+                }
+            }
+        }
+        adjacent
+    }
+
+    /// Opens a position of the payer between two ticks.
+    async fn open_position(
+        &mut self,
+        test_pool: &TestConcentratedPool,
+        tick_lower: i32,
+        tick_upper: i32,
+    ) -> std::result::Result<Pubkey, TransactionError> {
+        let pool = test_pool.pool;
+        let payer = self.payer();
+        let (position, bump) = pda::find_position_address(&pool, &payer, tick_lower, tick_upper);
+        self.process(
+            &[instructions::open_position(
+                cpamm::accounts::OpenPosition {
+                    pool,
+                    owner: payer,
+                    position,
+                    payer,
+                    system_program: anchor_lang::solana_program::system_program::ID,
+                },
+                bump,
+                tick_lower,
+                tick_upper,
+            )],
+            &[],
+        )
+        .await?;
+        Ok(position)
+    }
+
+    /// Accounts of an instruction which modifies the liquidity of a position of the payer.
+    async fn modify_liquidity_accounts(
+        &mut self,
+        test_pool: &TestConcentratedPool,
+        position: Pubkey,
+    ) -> cpamm::accounts::ModifyLiquidity {
+        let pool_info = self.concentrated_pool(test_pool.pool).await;
+        let position_info = self.position(position).await;
+        client_accounts::modify_liquidity_accounts(
+            &pool_info,
+            position,
+            &position_info,
+            test_pool.token_programs,
+            [test_pool.user_0, test_pool.user_1],
+        )
+    }
+
+    async fn increase_liquidity(
+        &mut self,
+        test_pool: &TestConcentratedPool,
+        position: Pubkey,
+        liquidity: u128,
+    ) {
+        let pool_info = self.concentrated_pool(test_pool.pool).await;
+        let position_info = self.position(position).await;
+        let payer = self.payer();
+        let accounts = client_accounts::increase_liquidity_accounts(
+            &pool_info,
+            position,
+            &position_info,
+            test_pool.token_programs,
+            [test_pool.user_0, test_pool.user_1],
+            payer,
+        );
+        let adjacent_ticks = self.adjacent_ticks(test_pool, position).await;
+        self.process(
+            &[instructions::increase_liquidity(
+                accounts,
+                &adjacent_ticks,
+                liquidity,
+                u64::MAX,
+                u64::MAX,
+            )],
+            &[],
+        )
+        .await
+        .unwrap();
+    }
+
+    async fn decrease_liquidity(
+        &mut self,
+        test_pool: &TestConcentratedPool,
+        position: Pubkey,
+        liquidity: u128,
+        minimum_amount_out_0: u64,
+    ) -> std::result::Result<(), TransactionError> {
+        let accounts = self.modify_liquidity_accounts(test_pool, position).await;
+        let adjacent_ticks = self.adjacent_ticks(test_pool, position).await;
+        self.process(
+            &[instructions::decrease_liquidity(
+                accounts,
+                &adjacent_ticks,
+                liquidity,
+                minimum_amount_out_0,
+                0,
+            )],
+            &[],
+        )
+        .await
+    }
+
+    /// Opens a position of the payer between two ticks, and adds `liquidity` to it.
+    async fn new_position(
+        &mut self,
+        test_pool: &TestConcentratedPool,
+        tick_lower: i32,
+        tick_upper: i32,
+        liquidity: u128,
+    ) -> Pubkey {
+        let position = self
+            .open_position(test_pool, tick_lower, tick_upper)
+            .await
+            .unwrap();
+        self.increase_liquidity(test_pool, position, liquidity)
+            .await;
+        position
+    }
+
+    /// Swaps `amount_in` of one token of a concentrated pool for the other, crossing `ticks`.
+    async fn swap_concentrated(
+        &mut self,
+        test_pool: &TestConcentratedPool,
+        zero_for_one: bool,
+        ticks: &[i32],
+        amount_in: u64,
+    ) -> std::result::Result<(), TransactionError> {
+        let pool_info = self.concentrated_pool(test_pool.pool).await;
+        let (input_mint, user_input, user_output) = if zero_for_one {
+            (test_pool.mint_0, test_pool.user_0, test_pool.user_1)
+        } else {
+            (test_pool.mint_1, test_pool.user_1, test_pool.user_0)
+        };
+        let payer = self.payer();
+        let accounts = client_accounts::swap_concentrated_accounts(
+            test_pool.pool,
+            &pool_info,
+            test_pool.token_programs,
+            payer,
+            input_mint,
+            user_input,
+            user_output,
+        );
+        let ticks: Vec<Pubkey> = ticks
+            .iter()
+            .map(|index| pda::find_tick_address(&test_pool.pool, *index).0)
+            .collect();
+        self.process(
+            &[instructions::swap_concentrated(
+                accounts, &ticks, amount_in, 0,
+            )],
+            &[],
+        )
+        .await
+    }
+}
+
+fn sqrt_price(tick: i32) -> u128 {
+    xyk::tick_to_sqrt_price(tick).unwrap()
+}
+
+#[tokio::test]
+async fn test_new_concentrated_pool() {
+    let mut env = TestEnv::new().await;
+    let test_pool = env.new_concentrated_pool().await;
+
+    let pool_info = env.concentrated_pool(test_pool.pool).await;
+    assert_eq!(pool_info.index, 0);
+    assert_eq!(pool_info.token_0.mint, test_pool.mint_0);
+    assert_eq!(pool_info.token_1.reserves, test_pool.reserve_1);
+    assert_eq!(pool_info.tick_current, 0);
+    assert_eq!(pool_info.liquidity, 0);
+    assert_eq!(pool_info.trade_fee_kbps, u64::from(FEE_TIER_KBPS));
+    assert_eq!(pool_info.tick_below, xyk::min_tick(TICK_SPACING));
+    assert_eq!(pool_info.tick_above, xyk::max_tick(TICK_SPACING));
+
+    let tick_min = env.tick(test_pool.pool, pool_info.min_tick()).await;
+    assert_eq!(tick_min.prev, tick_min.index);
+    assert_eq!(tick_min.next, pool_info.max_tick());
+    let factory = env.concentrated_pool(test_pool.pool).await.factory;
+    let data = env.get_account_data(factory).await;
+    let factory_info = client_accounts::decode_factory(&data).unwrap();
+    assert_eq!(factory_info.num_concentrated_pools, 1);
+    assert_eq!(factory_info.num_swaps, 0);
+}
+
+#[tokio::test]
+async fn test_new_concentrated_pool_invalid() {
+    let mut env = TestEnv::new().await;
+    let (_, ix) = env
+        .setup_new_concentrated_pool(0, FEE_TIER_KBPS, SQRT_PRICE_ONE_X64)
+        .await;
+    assert_error(env.process(&[ix], &[]).await, ErrorCode::InvalidTickSpacing);
+
+    let max_sqrt_price = sqrt_price(xyk::max_tick(TICK_SPACING));
+    let (_, ix) = env
+        .setup_new_concentrated_pool(TICK_SPACING, FEE_TIER_KBPS, max_sqrt_price)
+        .await;
+    assert_error(env.process(&[ix], &[]).await, ErrorCode::InvalidSqrtPrice);
+
+    let (_, ix) = env
+        .setup_new_concentrated_pool(TICK_SPACING, FEE_TIER_KBPS + 1, SQRT_PRICE_ONE_X64)
+        .await;
+    assert_error(env.process(&[ix], &[]).await, ErrorCode::InvalidFeeTier);
+}
+
+#[tokio::test]
+async fn test_new_concentrated_pool_restricted_creators() {
+    let mut env = TestEnv::new().await;
+    let (test_pool, ix) = env
+        .setup_new_concentrated_pool(TICK_SPACING, FEE_TIER_KBPS, SQRT_PRICE_ONE_X64)
+        .await;
+    let factory = test_pool.factory;
+    let payer = env.payer();
+    env.process_factory_admin(factory, |accounts| {
+        instructions::set_factory_policy(
+            accounts,
+            cpamm::FactoryPolicy {
+                restrict_creators: true,
+                require_no_freeze_authority: false,
+            },
+        )
+    })
+    .await
+    .unwrap();
+    assert_error(
+        env.process(&[ix.clone()], &[]).await,
+        ErrorCode::PoolCreatorNotAllowed,
+    );
+
+    let (pool_creator, bump) = pda::find_pool_creator_address(&factory, &payer);
+    env.process_factory_admin(factory, |factory_admin| {
+        instructions::add_pool_creator(
+            cpamm::accounts::AddPoolCreator {
+                factory_admin,
+                creator: payer,
+                pool_creator,
+                payer,
+                system_program: anchor_lang::solana_program::system_program::ID,
+            },
+            bump,
+        )
+    })
+    .await
+    .unwrap();
+    env.process(&[ix], &[]).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_new_concentrated_pool_denied_mint() {
+    let mut env = TestEnv::new().await;
+    let (test_pool, ix) = env
+        .setup_new_concentrated_pool(TICK_SPACING, FEE_TIER_KBPS, SQRT_PRICE_ONE_X64)
+        .await;
+    let factory = test_pool.factory;
+    let payer = env.payer();
+    let (denied_mint, bump) = pda::find_denied_mint_address(&factory, &test_pool.mint_0);
+    env.process_factory_admin(factory, |factory_admin| {
+        instructions::deny_mint(
+            cpamm::accounts::DenyMint {
+                factory_admin,
+                mint: test_pool.mint_0,
+                denied_mint,
+                payer,
+                system_program: anchor_lang::solana_program::system_program::ID,
+            },
+            bump,
+        )
+    })
+    .await
+    .unwrap();
+    assert_error(env.process(&[ix.clone()], &[]).await, ErrorCode::MintDenied);
+
+    env.process_factory_admin(factory, |factory_admin| {
+        instructions::allow_mint(cpamm::accounts::AllowMint {
+            factory_admin,
+            denied_mint,
+            recipient: payer,
+        })
+    })
+    .await
+    .unwrap();
+    env.process(&[ix], &[]).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_new_concentrated_pool_freeze_authority() {
+    let mut env = TestEnv::new().await;
+    let (test_pool, ix) = env
+        .setup_new_concentrated_pool(TICK_SPACING, FEE_TIER_KBPS, SQRT_PRICE_ONE_X64)
+        .await;
+    let payer = env.payer();
+    env.process_factory_admin(test_pool.factory, |accounts| {
+        instructions::set_factory_policy(
+            accounts,
+            cpamm::FactoryPolicy {
+                restrict_creators: false,
+                require_no_freeze_authority: true,
+            },
+        )
+    })
+    .await
+    .unwrap();
+    assert_error(
+        env.process(&[ix.clone()], &[]).await,
+        ErrorCode::MintHasFreezeAuthority,
+    );
+
+    for mint in [test_pool.mint_0, test_pool.mint_1] {
+        env.process(
+            &[spl_token::instruction::set_authority(
+                &spl_token::ID,
+                &mint,
+                None,
+                spl_token::instruction::AuthorityType::FreezeAccount,
+                &payer,
+                &[],
+            )
+            .unwrap()],
+            &[],
+        )
+        .await
+        .unwrap();
+    }
+    env.process(&[ix], &[]).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_ticks_linked_with_liquidity() {
+    let mut env = TestEnv::new().await;
+    let test_pool = env.new_concentrated_pool().await;
+    let pool = test_pool.pool;
+    let max_tick = xyk::max_tick(TICK_SPACING);
+
+    assert_error(
+        env.open_position(&test_pool, -105, 100).await.map(|_| ()),
+        ErrorCode::InvalidTick,
+    );
+    assert_error(
+        env.open_position(&test_pool, 100, -100).await.map(|_| ()),
+        ErrorCode::InvalidTickRange,
+    );
+
+    // the ticks of a position are only created once it has liquidity
+    let narrow = env.open_position(&test_pool, -100, 100).await.unwrap();
+    assert!(env
+        .ctx
+        .banks_client
+        .get_account(pda::find_tick_address(&pool, 100).0)
+        .await
+        .unwrap()
+        .is_none());
+    env.increase_liquidity(&test_pool, narrow, 1_000_000).await;
+    env.new_position(&test_pool, -200, 200, 1_000_000).await;
+    let pool_info = env.concentrated_pool(pool).await;
+    assert_eq!((pool_info.tick_below, pool_info.tick_above), (-100, 100));
+    let tick = env.tick(pool, 100).await;
+    assert_eq!((tick.prev, tick.next), (-100, 200));
+    let tick = env.tick(pool, 200).await;
+    assert_eq!((tick.prev, tick.next), (100, max_tick));
+
+    // the linked ticks adjacent to new ticks must be passed
+    let position = env.open_position(&test_pool, 300, 400).await.unwrap();
+    let pool_info = env.concentrated_pool(pool).await;
+    let position_info = env.position(position).await;
+    let payer = env.payer();
+    let accounts = client_accounts::increase_liquidity_accounts(
+        &pool_info,
+        position,
+        &position_info,
+        test_pool.token_programs,
+        [test_pool.user_0, test_pool.user_1],
+        payer,
+    );
+    let result = env
+        .process(
+            &[instructions::increase_liquidity(
+                accounts,
+                &[],
+                1_000_000,
+                u64::MAX,
+                u64::MAX,
+            )],
+            &[],
+        )
+        .await;
+    assert_error(result, ErrorCode::TicksNotAdjacent);
+
+    // ticks are unlinked once they no longer bound any liquidity
+    env.decrease_liquidity(&test_pool, narrow, 1_000_000, 0)
+        .await
+        .unwrap();
+    let pool_info = env.concentrated_pool(pool).await;
+    assert_eq!((pool_info.tick_below, pool_info.tick_above), (-200, 200));
+    assert!(!env.tick(pool, -100).await.is_linked());
+    assert!(!env.tick(pool, 100).await.is_linked());
+    let tick = env.tick(pool, 200).await;
+    assert_eq!((tick.prev, tick.next), (-200, max_tick));
+
+    // and linked again when liquidity is added back
+    env.increase_liquidity(&test_pool, narrow, 1_000_000).await;
+    let pool_info = env.concentrated_pool(pool).await;
+    assert_eq!((pool_info.tick_below, pool_info.tick_above), (-100, 100));
+    let tick = env.tick(pool, -100).await;
+    assert_eq!((tick.prev, tick.next), (-200, 100));
+}
+
+#[tokio::test]
+async fn test_concentrated_position_lifecycle() {
+    let mut env = TestEnv::new().await;
+    let test_pool = env.new_concentrated_pool().await;
+
+    let liquidity = 100_000_000_000;
+    let position = env.new_position(&test_pool, -100, 100, liquidity).await;
+    let (amount_0, amount_1) = xyk::amounts_for_liquidity(
+        SQRT_PRICE_ONE_X64,
+        sqrt_price(-100),
+        sqrt_price(100),
+        liquidity,
+        true,
+    )
+    .unwrap();
+    assert_eq!(env.token_balance(test_pool.reserve_0).await, amount_0);
+    assert_eq!(env.token_balance(test_pool.reserve_1).await, amount_1);
+    assert_eq!(
+        env.concentrated_pool(test_pool.pool).await.liquidity,
+        liquidity
+    );
+
+    // swap within the range of the position
+    let amount_in = 1_000_000;
+    let step = xyk::compute_swap_step(
+        SQRT_PRICE_ONE_X64,
+        sqrt_price(-100),
+        liquidity,
+        amount_in,
+        cpamm::fees::INITIAL.trade_fee_kbps,
+    )
+    .unwrap();
+    env.swap_concentrated(&test_pool, true, &[], amount_in)
+        .await
+        .unwrap();
+    assert_eq!(
+        env.token_balance(test_pool.user_1).await,
+        INITIAL_RESERVE - amount_1 + step.amount_out
+    );
+    let pool_info = env.concentrated_pool(test_pool.pool).await;
+    assert_eq!(pool_info.sqrt_price_x64, step.sqrt_price_x64);
+    assert_eq!(
+        pool_info.tick_current,
+        xyk::sqrt_price_to_tick(step.sqrt_price_x64).unwrap()
+    );
+
+    // the position earns the entire trade fee, less rounding
+    let accounts = env.modify_liquidity_accounts(&test_pool, position).await;
+    let collect = instructions::collect_fees(accounts);
+    let events: Vec<cpamm::CollectFeesEvent> = env.simulate_events(&[collect.clone()], &[]).await;
+    assert_eq!(events.len(), 1);
+    assert!(events[0].fee_0 <= step.fee_amount && events[0].fee_0 + 1 >= step.fee_amount);
+    assert_eq!(events[0].fee_1, 0);
+    env.process(&[collect], &[]).await.unwrap();
+    assert_eq!(env.position(position).await.fees_owed_0, 0);
+
+    // the position may not be closed until it is empty
+    let payer = env.payer();
+    let close = instructions::close_position(cpamm::accounts::ClosePosition {
+        owner: payer,
+        position,
+    });
+    assert_error(
+        env.process(&[close.clone()], &[]).await,
+        ErrorCode::PositionNotEmpty,
+    );
+
+    env.decrease_liquidity(&test_pool, position, liquidity, 0)
+        .await
+        .unwrap();
+    assert_eq!(env.concentrated_pool(test_pool.pool).await.liquidity, 0);
+    // only rounding remains in the reserves: deposits and fee growth round in
+    // favor of the pool, withdrawals and fees owed round against the position
+    assert!(env.token_balance(test_pool.reserve_0).await <= 3);
+    assert!(env.token_balance(test_pool.reserve_1).await <= 2);
+
+    env.process(&[close], &[]).await.unwrap();
+    assert!(env
+        .ctx
+        .banks_client
+        .get_account(position)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn test_decrease_liquidity_exceeded_slippage() {
+    let mut env = TestEnv::new().await;
+    let test_pool = env.new_concentrated_pool().await;
+    let liquidity = 100_000_000_000;
+    let position = env.new_position(&test_pool, -100, 100, liquidity).await;
+
+    let result = env
+        .decrease_liquidity(&test_pool, position, liquidity, u64::MAX)
+        .await;
+    assert_error(result, ErrorCode::ExceededSlippage);
+
+    let result = env
+        .decrease_liquidity(&test_pool, position, liquidity + 1, 0)
+        .await;
+    assert_error(result, ErrorCode::InsufficientLiquidity);
+}
+
+/// Creates a pool with positions over [-100, 100] and [-200, 200].
+async fn new_concentrated_pool_with_positions(
+    env: &mut TestEnv,
+) -> (TestConcentratedPool, Pubkey, Pubkey) {
+    let test_pool = env.new_concentrated_pool().await;
+    env.mint_to(test_pool.mint_0, test_pool.user_0, INITIAL_RESERVE)
+        .await;
+    env.mint_to(test_pool.mint_1, test_pool.user_1, INITIAL_RESERVE)
+        .await;
+    let narrow = env
+        .new_position(&test_pool, -100, 100, 50_000_000_000)
+        .await;
+    let wide = env
+        .new_position(&test_pool, -200, 200, 50_000_000_000)
+        .await;
+    (test_pool, narrow, wide)
+}
+
+#[tokio::test]
+async fn test_swap_concentrated_crosses_ticks() {
+    let mut env = TestEnv::new().await;
+    let (test_pool, narrow, wide) = new_concentrated_pool_with_positions(&mut env).await;
+    assert_eq!(
+        env.concentrated_pool(test_pool.pool).await.liquidity,
+        100_000_000_000
+    );
+
+    // swap down past the lower tick of the narrow position
+    let events: Vec<cpamm::ConcentratedSwapEvent> = {
+        let pool_info = env.concentrated_pool(test_pool.pool).await;
+        let payer = env.payer();
+        let accounts = client_accounts::swap_concentrated_accounts(
+            test_pool.pool,
+            &pool_info,
+            test_pool.token_programs,
+            payer,
+            test_pool.mint_0,
+            test_pool.user_0,
+            test_pool.user_1,
+        );
+        let ticks = [pda::find_tick_address(&test_pool.pool, -100).0];
+        env.simulate_events(
+            &[instructions::swap_concentrated(
+                accounts,
+                &ticks,
+                600_000_000,
+                0,
+            )],
+            &[],
+        )
+        .await
+    };
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].source_amount_swapped, 600_000_000);
+    assert_eq!(events[0].liquidity, 50_000_000_000);
+
+    env.swap_concentrated(&test_pool, true, &[-100], 600_000_000)
+        .await
+        .unwrap();
+    let pool_info = env.concentrated_pool(test_pool.pool).await;
+    assert_eq!(pool_info.liquidity, 50_000_000_000);
+    assert!(pool_info.tick_current < -100 && pool_info.tick_current > -200);
+    assert_eq!(pool_info.tick_below, -200);
+    assert_eq!(pool_info.tick_above, -100);
+
+    // fees earned below the narrow position only go to the wide position
+    let fee_growth_0 = pool_info.fee_growth_global_0_x64;
+    let narrow_accounts = env.modify_liquidity_accounts(&test_pool, narrow).await;
+    let wide_accounts = env.modify_liquidity_accounts(&test_pool, wide).await;
+    let events: Vec<cpamm::CollectFeesEvent> = env
+        .simulate_events(
+            &[
+                instructions::collect_fees(narrow_accounts),
+                instructions::collect_fees(wide_accounts),
+            ],
+            &[],
+        )
+        .await;
+    assert_eq!(events.len(), 2);
+    assert!(events[0].fee_0 < events[1].fee_0);
+    assert_eq!(
+        events[1].fee_0,
+        xyk::fees_earned(fee_growth_0, 50_000_000_000).unwrap()
+    );
+
+    // swap back up across the tick
+    env.swap_concentrated(&test_pool, false, &[-100], 300_000_000)
+        .await
+        .unwrap();
+    let pool_info = env.concentrated_pool(test_pool.pool).await;
+    assert_eq!(pool_info.liquidity, 100_000_000_000);
+    assert!(pool_info.tick_current >= -100 && pool_info.tick_current < 100);
+    assert_eq!(pool_info.tick_below, -100);
+    assert_eq!(pool_info.tick_above, 100);
+}
+
+#[tokio::test]
+async fn test_swap_concentrated_stops_at_missing_tick() {
+    let mut env = TestEnv::new().await;
+    let (test_pool, _, _) = new_concentrated_pool_with_positions(&mut env).await;
+    let balance_0 = env.token_balance(test_pool.user_0).await;
+
+    // the tick at -100 is not passed, so the swap stops there
+    env.swap_concentrated(&test_pool, true, &[], 600_000_000)
+        .await
+        .unwrap();
+    let pool_info = env.concentrated_pool(test_pool.pool).await;
+    assert_eq!(pool_info.sqrt_price_x64, sqrt_price(-100));
+    assert_eq!(pool_info.tick_current, -100);
+    assert_eq!(pool_info.liquidity, 100_000_000_000);
+
+    let amount_spent = balance_0 - env.token_balance(test_pool.user_0).await;
+    assert!(amount_spent > 0 && amount_spent < 600_000_000);
+
+    // the next swap crosses the tick without moving the price
+    env.swap_concentrated(&test_pool, true, &[-100], 1_000)
+        .await
+        .unwrap();
+    let pool_info = env.concentrated_pool(test_pool.pool).await;
+    assert_eq!(pool_info.liquidity, 50_000_000_000);
+    assert!(pool_info.tick_current < -100);
+}